@@ -0,0 +1,339 @@
+use futures_util::future::join_all;
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::watch;
+
+use crate::proxy::{session_lan_url, ProxyAccessPolicy};
+
+mod renderer;
+mod ssdp;
+
+use renderer::{DlnaRendererInfo, Renderer, TransportInfo};
+
+pub const DLNA_STATE_EVENT: &str = "dlna-renderer-state";
+
+const DEFAULT_DISCOVERY_TIMEOUT_MS: u64 = 3000;
+const STATE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// 连续失败次数达到上限视为渲染器离线，停止轮询
+const MAX_POLL_FAILURES: u32 = 5;
+
+struct CastMonitor {
+    session_id: String,
+    stop_tx: watch::Sender<bool>,
+}
+
+/// 局域网渲染器与投屏状态；渲染器按 UDN 索引
+pub struct DlnaManager {
+    // 局域网内直连，不走出站代理
+    client: Client,
+    renderers: StdMutex<HashMap<String, Arc<Renderer>>>,
+    monitors: StdMutex<HashMap<String, CastMonitor>>,
+}
+
+impl Default for DlnaManager {
+    fn default() -> Self {
+        let client = Client::builder()
+            .no_proxy()
+            .connect_timeout(Duration::from_secs(3))
+            .timeout(Duration::from_secs(8))
+            .build()
+            .expect("Failed to create DLNA http client");
+        Self {
+            client,
+            renderers: StdMutex::new(HashMap::new()),
+            monitors: StdMutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl DlnaManager {
+    fn renderer(&self, renderer_id: &str) -> Result<Arc<Renderer>, String> {
+        self.renderers
+            .lock()
+            .unwrap()
+            .get(renderer_id)
+            .cloned()
+            .ok_or_else(|| format!("DLNA renderer {} not found", renderer_id))
+    }
+
+    fn insert(&self, renderer: Renderer) -> DlnaRendererInfo {
+        let info = renderer.info();
+        self.renderers
+            .lock()
+            .unwrap()
+            .insert(renderer.id.clone(), Arc::new(renderer));
+        info
+    }
+
+    fn stop_monitor(&self, renderer_id: &str) {
+        if let Some(monitor) = self.monitors.lock().unwrap().remove(renderer_id) {
+            let _ = monitor.stop_tx.send(true);
+        }
+    }
+
+    /// 代理会话停止时结束投放该会话的渲染器的状态轮询
+    pub(crate) fn stop_session_monitors(&self, session_id: &str) {
+        self.monitors
+            .lock()
+            .unwrap()
+            .retain(|renderer_id, monitor| {
+                if monitor.session_id != session_id {
+                    return true;
+                }
+                println!(
+                    "[Rust/dlna.rs] session {} stopped, stop monitoring {}",
+                    session_id, renderer_id
+                );
+                let _ = monitor.stop_tx.send(true);
+                false
+            });
+    }
+
+    fn cast_session(&self, renderer_id: &str) -> Option<String> {
+        self.monitors
+            .lock()
+            .unwrap()
+            .get(renderer_id)
+            .map(|m| m.session_id.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DlnaRendererState {
+    renderer_id: String,
+    session_id: Option<String>,
+    transport_state: String,
+    transport_status: String,
+    volume: Option<u32>,
+    error: Option<String>,
+}
+
+async fn query_state(
+    client: &Client,
+    renderer: &Renderer,
+    session_id: Option<String>,
+) -> Result<DlnaRendererState, String> {
+    let TransportInfo {
+        transport_state,
+        transport_status,
+    } = renderer.transport_info(client).await?;
+    // 部分渲染器没有实现 GetVolume，不影响播放状态
+    let volume = renderer.volume(client).await.unwrap_or(None);
+    Ok(DlnaRendererState {
+        renderer_id: renderer.id.clone(),
+        session_id,
+        transport_state,
+        transport_status,
+        volume,
+        error: None,
+    })
+}
+
+async fn run_state_monitor(
+    app_handle: AppHandle,
+    client: Client,
+    renderer: Arc<Renderer>,
+    session_id: String,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let mut last: Option<DlnaRendererState> = None;
+    let mut failures = 0u32;
+    loop {
+        tokio::select! {
+            _ = stop_rx.changed() => break,
+            _ = tokio::time::sleep(STATE_POLL_INTERVAL) => {}
+        }
+        if *stop_rx.borrow() {
+            break;
+        }
+        match query_state(&client, &renderer, Some(session_id.clone())).await {
+            Ok(state) => {
+                failures = 0;
+                if last.as_ref() != Some(&state) {
+                    let _ = app_handle.emit(DLNA_STATE_EVENT, state.clone());
+                    last = Some(state);
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!(
+                    "[Rust/dlna.rs] state poll failed for {} ({}/{}): {}",
+                    renderer.name, failures, MAX_POLL_FAILURES, e
+                );
+                if failures >= MAX_POLL_FAILURES {
+                    let _ = app_handle.emit(
+                        DLNA_STATE_EVENT,
+                        DlnaRendererState {
+                            renderer_id: renderer.id.clone(),
+                            session_id: Some(session_id.clone()),
+                            transport_state: "UNREACHABLE".to_string(),
+                            transport_status: String::new(),
+                            volume: None,
+                            error: Some(e),
+                        },
+                    );
+                    break;
+                }
+            }
+        }
+    }
+    println!(
+        "[Rust/dlna.rs] state monitor for {} (session {}) stopped",
+        renderer.name, session_id
+    );
+}
+
+/// SSDP 搜索局域网内的媒体渲染器；已开启局域网共享时从共享网卡发出搜索
+#[tauri::command]
+pub async fn discover_dlna_renderers(
+    app_handle: AppHandle,
+    dlna: State<'_, DlnaManager>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<DlnaRendererInfo>, String> {
+    let bind_ip = match app_handle.state::<ProxyAccessPolicy>().0.lan_bind_ip() {
+        Some(IpAddr::V4(ip)) => Some(ip),
+        _ => None,
+    };
+    let timeout =
+        Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_DISCOVERY_TIMEOUT_MS).max(500));
+    let responses = ssdp::search(bind_ip, timeout).await?;
+
+    let fetched = join_all(
+        responses
+            .iter()
+            .map(|response| Renderer::fetch(&dlna.client, &response.location)),
+    )
+    .await;
+    let mut renderers = Vec::new();
+    for result in fetched {
+        match result {
+            Ok(renderer) => renderers.push(dlna.insert(renderer)),
+            Err(e) => eprintln!("[Rust/dlna.rs] skipping device: {}", e),
+        }
+    }
+    renderers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(renderers)
+}
+
+/// 手动添加渲染器（设备描述地址），用于不响应组播搜索的设备或本机的软件渲染器
+#[tauri::command]
+pub async fn add_dlna_renderer(
+    dlna: State<'_, DlnaManager>,
+    location: String,
+) -> Result<DlnaRendererInfo, String> {
+    let renderer = Renderer::fetch(&dlna.client, location.trim()).await?;
+    Ok(dlna.insert(renderer))
+}
+
+#[tauri::command]
+pub async fn list_dlna_renderers(
+    dlna: State<'_, DlnaManager>,
+) -> Result<Vec<DlnaRendererInfo>, String> {
+    let mut renderers: Vec<DlnaRendererInfo> = dlna
+        .renderers
+        .lock()
+        .unwrap()
+        .values()
+        .map(|r| r.info())
+        .collect();
+    renderers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(renderers)
+}
+
+/// 把代理会话的局域网地址投到渲染器并开始播放，之后通过 `dlna-renderer-state` 事件推送状态
+#[tauri::command]
+pub async fn dlna_cast_session(
+    app_handle: AppHandle,
+    dlna: State<'_, DlnaManager>,
+    renderer_id: String,
+    session_id: String,
+    title: Option<String>,
+) -> Result<DlnaRendererState, String> {
+    let renderer = dlna.renderer(&renderer_id)?;
+    let uri = session_lan_url(&app_handle, &session_id)?;
+    let title = title
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| "DTV Live".to_string());
+    println!(
+        "[Rust/dlna.rs] casting session {} to {} ({})",
+        session_id, renderer.name, renderer.location
+    );
+
+    dlna.stop_monitor(&renderer_id);
+    // 换台前先停止，部分电视在播放中拒绝 SetAVTransportURI
+    let _ = renderer.stop(&dlna.client).await;
+    renderer
+        .set_uri(&dlna.client, &uri, &title, "video/x-flv")
+        .await?;
+    renderer.play(&dlna.client).await?;
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    dlna.monitors.lock().unwrap().insert(
+        renderer_id.clone(),
+        CastMonitor {
+            session_id: session_id.clone(),
+            stop_tx,
+        },
+    );
+    tauri::async_runtime::spawn(run_state_monitor(
+        app_handle.clone(),
+        dlna.client.clone(),
+        renderer.clone(),
+        session_id.clone(),
+        stop_rx,
+    ));
+
+    let state = query_state(&dlna.client, &renderer, Some(session_id)).await?;
+    let _ = app_handle.emit(DLNA_STATE_EVENT, state.clone());
+    Ok(state)
+}
+
+#[tauri::command]
+pub async fn dlna_play(
+    dlna: State<'_, DlnaManager>,
+    renderer_id: String,
+) -> Result<DlnaRendererState, String> {
+    let renderer = dlna.renderer(&renderer_id)?;
+    renderer.play(&dlna.client).await?;
+    query_state(&dlna.client, &renderer, dlna.cast_session(&renderer_id)).await
+}
+
+#[tauri::command]
+pub async fn dlna_stop(
+    app_handle: AppHandle,
+    dlna: State<'_, DlnaManager>,
+    renderer_id: String,
+) -> Result<DlnaRendererState, String> {
+    let renderer = dlna.renderer(&renderer_id)?;
+    dlna.stop_monitor(&renderer_id);
+    renderer.stop(&dlna.client).await?;
+    let state = query_state(&dlna.client, &renderer, None).await?;
+    let _ = app_handle.emit(DLNA_STATE_EVENT, state.clone());
+    Ok(state)
+}
+
+#[tauri::command]
+pub async fn dlna_set_volume(
+    dlna: State<'_, DlnaManager>,
+    renderer_id: String,
+    volume: u32,
+) -> Result<DlnaRendererState, String> {
+    let renderer = dlna.renderer(&renderer_id)?;
+    renderer.set_volume(&dlna.client, volume).await?;
+    query_state(&dlna.client, &renderer, dlna.cast_session(&renderer_id)).await
+}
+
+#[tauri::command]
+pub async fn dlna_get_state(
+    dlna: State<'_, DlnaManager>,
+    renderer_id: String,
+) -> Result<DlnaRendererState, String> {
+    let renderer = dlna.renderer(&renderer_id)?;
+    query_state(&dlna.client, &renderer, dlna.cast_session(&renderer_id)).await
+}
@@ -0,0 +1,533 @@
+use reqwest::Client;
+use serde::Serialize;
+
+const AV_TRANSPORT: &str = "AVTransport";
+const RENDERING_CONTROL: &str = "RenderingControl";
+
+#[derive(Debug, Clone)]
+struct ServiceEndpoint {
+    service_type: String,
+    control_url: String,
+}
+
+/// 从设备描述文档解析出的渲染器
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub id: String,
+    pub name: String,
+    pub location: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    av_transport: ServiceEndpoint,
+    rendering_control: Option<ServiceEndpoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DlnaRendererInfo {
+    pub id: String,
+    pub name: String,
+    pub location: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub supports_volume: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportInfo {
+    // PLAYING / STOPPED / PAUSED_PLAYBACK / TRANSITIONING / NO_MEDIA_PRESENT
+    pub transport_state: String,
+    pub transport_status: String,
+}
+
+pub fn xml_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, 'a>, name: &str) -> Option<String> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().name() == name)
+        .and_then(|c| c.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn descendant_text(doc: &roxmltree::Document, name: &str) -> Option<String> {
+    doc.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
+impl Renderer {
+    /// 拉取并解析设备描述；找不到 AVTransport 服务的设备不是可投屏的渲染器
+    pub async fn fetch(client: &Client, location: &str) -> Result<Self, String> {
+        let base = reqwest::Url::parse(location)
+            .map_err(|e| format!("Invalid renderer location {}: {}", location, e))?;
+        let body = client
+            .get(base.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to fetch renderer description {}: {}", location, e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read renderer description {}: {}", location, e))?;
+        Self::parse(location, &base, &body)
+    }
+
+    fn parse(location: &str, base: &reqwest::Url, body: &str) -> Result<Self, String> {
+        let doc = roxmltree::Document::parse(body)
+            .map_err(|e| format!("Invalid renderer description {}: {}", location, e))?;
+        let base = descendant_text(&doc, "URLBase")
+            .and_then(|u| reqwest::Url::parse(&u).ok())
+            .unwrap_or_else(|| base.clone());
+
+        // 根设备或其嵌套子设备中带 AVTransport 的那个
+        for device in doc
+            .descendants()
+            .filter(|n| n.is_element() && n.tag_name().name() == "device")
+        {
+            let services: Vec<ServiceEndpoint> = device
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "serviceList")
+                .flat_map(|list| list.children())
+                .filter(|n| n.is_element() && n.tag_name().name() == "service")
+                .filter_map(|service| {
+                    let service_type = child_text(service, "serviceType")?;
+                    let control_url = base.join(&child_text(service, "controlURL")?).ok()?;
+                    Some(ServiceEndpoint {
+                        service_type,
+                        control_url: control_url.to_string(),
+                    })
+                })
+                .collect();
+            let find = |kind: &str| {
+                services
+                    .iter()
+                    .find(|s| s.service_type.contains(&format!(":service:{}:", kind)))
+                    .cloned()
+            };
+            let Some(av_transport) = find(AV_TRANSPORT) else {
+                continue;
+            };
+            let name = child_text(device, "friendlyName").unwrap_or_else(|| location.to_string());
+            return Ok(Self {
+                id: child_text(device, "UDN").unwrap_or_else(|| location.to_string()),
+                name,
+                location: location.to_string(),
+                manufacturer: child_text(device, "manufacturer"),
+                model: child_text(device, "modelName"),
+                av_transport,
+                rendering_control: find(RENDERING_CONTROL),
+            });
+        }
+        Err(format!(
+            "{} does not expose an AVTransport service",
+            location
+        ))
+    }
+
+    pub fn info(&self) -> DlnaRendererInfo {
+        DlnaRendererInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            location: self.location.clone(),
+            manufacturer: self.manufacturer.clone(),
+            model: self.model.clone(),
+            supports_volume: self.rendering_control.is_some(),
+        }
+    }
+
+    async fn soap(
+        &self,
+        client: &Client,
+        service: &ServiceEndpoint,
+        action: &str,
+        args: &[(&str, &str)],
+    ) -> Result<String, String> {
+        let args_xml: String = args
+            .iter()
+            .map(|(k, v)| format!("<{0}>{1}</{0}>", k, xml_escape(v)))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{action} xmlns:u=\"{service_type}\">{args}</u:{action}></s:Body>\
+             </s:Envelope>",
+            action = action,
+            service_type = service.service_type,
+            args = args_xml
+        );
+        let response = client
+            .post(&service.control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header(
+                "SOAPAction",
+                format!("\"{}#{}\"", service.service_type, action),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("{} {} failed: {}", self.name, action, e))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("{} {} response unreadable: {}", self.name, action, e))?;
+        if !status.is_success() {
+            // UPnP 错误放在 SOAP Fault 的 UPnPError 里
+            let detail = roxmltree::Document::parse(&text).ok().map(|doc| {
+                format!(
+                    "{} {}",
+                    descendant_text(&doc, "errorCode").unwrap_or_default(),
+                    descendant_text(&doc, "errorDescription").unwrap_or_default()
+                )
+            });
+            return Err(format!(
+                "{} {} failed with status {}: {}",
+                self.name,
+                action,
+                status,
+                detail.unwrap_or_default().trim()
+            ));
+        }
+        Ok(text)
+    }
+
+    fn volume_service(&self) -> Result<&ServiceEndpoint, String> {
+        self.rendering_control
+            .as_ref()
+            .ok_or_else(|| format!("{} does not support volume control", self.name))
+    }
+
+    pub async fn set_uri(
+        &self,
+        client: &Client,
+        uri: &str,
+        title: &str,
+        mime: &str,
+    ) -> Result<(), String> {
+        let metadata = didl_metadata(uri, title, mime);
+        self.soap(
+            client,
+            &self.av_transport,
+            "SetAVTransportURI",
+            &[
+                ("InstanceID", "0"),
+                ("CurrentURI", uri),
+                ("CurrentURIMetaData", &metadata),
+            ],
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn play(&self, client: &Client) -> Result<(), String> {
+        self.soap(
+            client,
+            &self.av_transport,
+            "Play",
+            &[("InstanceID", "0"), ("Speed", "1")],
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn stop(&self, client: &Client) -> Result<(), String> {
+        self.soap(client, &self.av_transport, "Stop", &[("InstanceID", "0")])
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn transport_info(&self, client: &Client) -> Result<TransportInfo, String> {
+        let text = self
+            .soap(
+                client,
+                &self.av_transport,
+                "GetTransportInfo",
+                &[("InstanceID", "0")],
+            )
+            .await?;
+        let doc = roxmltree::Document::parse(&text)
+            .map_err(|e| format!("{} GetTransportInfo: {}", self.name, e))?;
+        Ok(TransportInfo {
+            transport_state: descendant_text(&doc, "CurrentTransportState").unwrap_or_default(),
+            transport_status: descendant_text(&doc, "CurrentTransportStatus").unwrap_or_default(),
+        })
+    }
+
+    pub async fn volume(&self, client: &Client) -> Result<Option<u32>, String> {
+        let Some(service) = self.rendering_control.as_ref() else {
+            return Ok(None);
+        };
+        let text = self
+            .soap(
+                client,
+                service,
+                "GetVolume",
+                &[("InstanceID", "0"), ("Channel", "Master")],
+            )
+            .await?;
+        let doc = roxmltree::Document::parse(&text)
+            .map_err(|e| format!("{} GetVolume: {}", self.name, e))?;
+        Ok(descendant_text(&doc, "CurrentVolume").and_then(|v| v.parse().ok()))
+    }
+
+    pub async fn set_volume(&self, client: &Client, volume: u32) -> Result<(), String> {
+        let volume = volume.min(100).to_string();
+        self.soap(
+            client,
+            self.volume_service()?,
+            "SetVolume",
+            &[
+                ("InstanceID", "0"),
+                ("Channel", "Master"),
+                ("DesiredVolume", &volume),
+            ],
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+// 多数电视要求 CurrentURIMetaData 中带 protocolInfo，否则拒绝播放
+fn didl_metadata(uri: &str, title: &str, mime: &str) -> String {
+    format!(
+        "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">\
+         <item id=\"0\" parentID=\"-1\" restricted=\"1\">\
+         <dc:title>{title}</dc:title>\
+         <upnp:class>object.item.videoItem.videoBroadcast</upnp:class>\
+         <res protocolInfo=\"http-get:*:{mime}:*\">{uri}</res>\
+         </item></DIDL-Lite>",
+        title = xml_escape(title),
+        mime = mime,
+        uri = xml_escape(uri)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+    <friendlyName>Stub TV</friendlyName>
+    <manufacturer>Stub</manufacturer>
+    <UDN>uuid:stub-renderer</UDN>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+        <controlURL>/upnp/control/AVTransport1</controlURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+        <controlURL>upnp/control/RenderingControl1</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+    const FAULT: &str = r#"<?xml version="1.0"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body><s:Fault>
+<faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring>
+<detail><UPnPError xmlns="urn:schemas-upnp-org:control-1-0">
+<errorCode>714</errorCode><errorDescription>Illegal MIME-type</errorDescription>
+</UPnPError></detail></s:Fault></s:Body></s:Envelope>"#;
+
+    fn soap_response(action: &str, body: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\">\
+             <s:Body><u:{0}Response xmlns:u=\"urn:schemas-upnp-org:service:AVTransport:1\">{1}\
+             </u:{0}Response></s:Body></s:Envelope>",
+            action, body
+        )
+    }
+
+    /// 本地软件渲染器：返回设备描述并应答 SOAP 请求，记录收到的 (SOAPAction, body)
+    async fn spawn_stub_renderer() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let location = format!("http://{}/description.xml", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut raw = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    let (head, body) = loop {
+                        let n = stream.read(&mut buf).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        raw.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&raw).to_string();
+                        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                            continue;
+                        };
+                        let content_length = head
+                            .lines()
+                            .find_map(|l| {
+                                let (k, v) = l.split_once(':')?;
+                                k.eq_ignore_ascii_case("content-length")
+                                    .then(|| v.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= content_length {
+                            break (head.to_string(), body.to_string());
+                        }
+                    };
+                    let action = head
+                        .lines()
+                        .find_map(|l| {
+                            let (k, v) = l.split_once(':')?;
+                            k.eq_ignore_ascii_case("soapaction")
+                                .then(|| v.trim().trim_matches('"').to_string())
+                        })
+                        .unwrap_or_default();
+                    let (status, reply) = if head.starts_with("GET /description.xml") {
+                        ("200 OK", DESCRIPTION.to_string())
+                    } else if action.ends_with("#SetAVTransportURI") && body.contains("video/x-flv")
+                    {
+                        ("500 Internal Server Error", FAULT.to_string())
+                    } else if action.ends_with("#GetTransportInfo") {
+                        (
+                            "200 OK",
+                            soap_response(
+                                "GetTransportInfo",
+                                "<CurrentTransportState>PLAYING</CurrentTransportState>\
+                                 <CurrentTransportStatus>OK</CurrentTransportStatus>",
+                            ),
+                        )
+                    } else if action.ends_with("#GetVolume") {
+                        (
+                            "200 OK",
+                            soap_response("GetVolume", "<CurrentVolume>35</CurrentVolume>"),
+                        )
+                    } else {
+                        let name = action.rsplit('#').next().unwrap_or_default().to_string();
+                        ("200 OK", soap_response(&name, ""))
+                    };
+                    if !action.is_empty() {
+                        recorded.lock().unwrap().push((action, body));
+                    }
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        reply.len(),
+                        reply
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        (location, requests)
+    }
+
+    #[tokio::test]
+    async fn drives_a_local_stub_renderer() {
+        let (location, requests) = spawn_stub_renderer().await;
+        let client = Client::new();
+
+        let renderer = Renderer::fetch(&client, &location).await.unwrap();
+        assert_eq!(renderer.id, "uuid:stub-renderer");
+        assert_eq!(renderer.name, "Stub TV");
+        assert!(renderer.info().supports_volume);
+        assert!(renderer
+            .av_transport
+            .control_url
+            .ends_with("/upnp/control/AVTransport1"));
+
+        let uri = "http://192.168.1.2:4000/cast/live.flv?a=1&b=2";
+        renderer
+            .set_uri(&client, uri, "Room <1>", "video/mp2t")
+            .await
+            .unwrap();
+        renderer.play(&client).await.unwrap();
+        assert_eq!(
+            renderer.transport_info(&client).await.unwrap(),
+            TransportInfo {
+                transport_state: "PLAYING".to_string(),
+                transport_status: "OK".to_string(),
+            }
+        );
+        assert_eq!(renderer.volume(&client).await.unwrap(), Some(35));
+        renderer.set_volume(&client, 180).await.unwrap();
+
+        let requests = requests.lock().unwrap().clone();
+        let actions: Vec<&str> = requests
+            .iter()
+            .map(|(action, _)| action.rsplit('#').next().unwrap())
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "SetAVTransportURI",
+                "Play",
+                "GetTransportInfo",
+                "GetVolume",
+                "SetVolume"
+            ]
+        );
+        assert!(requests[0]
+            .0
+            .starts_with("urn:schemas-upnp-org:service:AVTransport:1#"));
+        // URI 与 DIDL 元数据都经过 XML 转义
+        assert!(requests[0].1.contains(
+            "<CurrentURI>http://192.168.1.2:4000/cast/live.flv?a=1&amp;b=2</CurrentURI>"
+        ));
+        assert!(requests[0].1.contains("Room &amp;lt;1&amp;gt;"));
+        assert!(requests[4].1.contains("<DesiredVolume>100</DesiredVolume>"));
+    }
+
+    #[tokio::test]
+    async fn surfaces_upnp_soap_faults() {
+        let (location, _) = spawn_stub_renderer().await;
+        let client = Client::new();
+        let renderer = Renderer::fetch(&client, &location).await.unwrap();
+
+        let err = renderer
+            .set_uri(
+                &client,
+                "http://192.168.1.2/live.flv",
+                "Room",
+                "video/x-flv",
+            )
+            .await
+            .unwrap_err();
+        assert!(err.contains("500"), "{}", err);
+        assert!(err.contains("714 Illegal MIME-type"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_devices_without_av_transport() {
+        let base = reqwest::Url::parse("http://127.0.0.1/desc.xml").unwrap();
+        let body = DESCRIPTION.replace("AVTransport", "ConnectionManager");
+        assert!(Renderer::parse("http://127.0.0.1/desc.xml", &base, &body).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+const SSDP_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+const MEDIA_RENDERER_ST: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+// 部分电视只响应服务类型的搜索
+const AV_TRANSPORT_ST: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+const RECV_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RECV_ERRORS: u32 = 5;
+
+/// 一条 M-SEARCH 应答
+#[derive(Debug, Clone)]
+pub struct SsdpResponse {
+    pub location: String,
+    pub server: Option<String>,
+}
+
+fn m_search(st: &str, mx: u64) -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+        mx, st
+    )
+}
+
+fn parse_response(raw: &str) -> Option<SsdpResponse> {
+    let mut lines = raw.split("\r\n");
+    let status = lines.next()?;
+    if !status.starts_with("HTTP/1.1 200") && !status.starts_with("NOTIFY") {
+        return None;
+    }
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_uppercase(), v.trim().to_string()))
+        .collect();
+    Some(SsdpResponse {
+        location: headers.get("LOCATION")?.clone(),
+        server: headers.get("SERVER").cloned(),
+    })
+}
+
+/// 在局域网内搜索媒体渲染器，按 LOCATION 去重；`bind_ip` 指定发送搜索的网卡
+pub async fn search(
+    bind_ip: Option<Ipv4Addr>,
+    timeout: Duration,
+) -> Result<Vec<SsdpResponse>, String> {
+    let bind_addr = SocketAddr::from((bind_ip.unwrap_or(Ipv4Addr::UNSPECIFIED), 0));
+    let socket = UdpSocket::bind(bind_addr)
+        .await
+        .map_err(|e| format!("Failed to bind SSDP socket on {}: {}", bind_addr, e))?;
+    socket
+        .set_multicast_ttl_v4(2)
+        .map_err(|e| format!("Failed to set SSDP multicast ttl: {}", e))?;
+    // 本机的软件渲染器也要能收到
+    let _ = socket.set_multicast_loop_v4(true);
+
+    let mx = timeout.as_secs().clamp(1, 5);
+    for st in [MEDIA_RENDERER_ST, AV_TRANSPORT_ST] {
+        // UDP 可能丢包，每种搜索发两次
+        for _ in 0..2 {
+            socket
+                .send_to(m_search(st, mx).as_bytes(), SSDP_ADDR)
+                .await
+                .map_err(|e| format!("Failed to send SSDP M-SEARCH: {}", e))?;
+        }
+    }
+
+    Ok(collect_responses(&socket, timeout).await)
+}
+
+// Windows 上 UDP 收到 ICMP 端口不可达会报 ConnectionReset，可以继续收
+fn is_transient_recv_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::TimedOut
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionRefused
+    )
+}
+
+/// 在超时前收集应答，按 LOCATION 去重
+async fn collect_responses(socket: &UdpSocket, timeout: Duration) -> Vec<SsdpResponse> {
+    let deadline = Instant::now() + timeout;
+    let mut found: Vec<SsdpResponse> = Vec::new();
+    let mut buf = [0u8; 2048];
+    let mut consecutive_errors = 0u32;
+    loop {
+        let received = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await;
+        let (len, from) = match received {
+            Ok(Ok(result)) => {
+                consecutive_errors = 0;
+                result
+            }
+            Ok(Err(e)) => {
+                eprintln!("[Rust/dlna.rs ssdp] recv error: {}", e);
+                consecutive_errors += 1;
+                // 非瞬时错误或持续报错时结束搜索，返回已发现的设备，避免空转
+                if !is_transient_recv_error(&e) || consecutive_errors >= MAX_RECV_ERRORS {
+                    break;
+                }
+                tokio::time::sleep(RECV_ERROR_BACKOFF).await;
+                continue;
+            }
+            Err(_) => break,
+        };
+        let raw = String::from_utf8_lossy(&buf[..len]);
+        let Some(response) = parse_response(&raw) else {
+            continue;
+        };
+        if found.iter().any(|r| r.location == response.location) {
+            continue;
+        }
+        println!(
+            "[Rust/dlna.rs ssdp] {} -> {} ({})",
+            from,
+            response.location,
+            response.server.as_deref().unwrap_or("-")
+        );
+        found.push(response);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_m_search_request() {
+        let request = m_search(MEDIA_RENDERER_ST, 3);
+        assert!(request.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+        assert!(request.contains("MAN: \"ssdp:discover\"\r\n"));
+        assert!(request.contains("MX: 3\r\n"));
+        assert!(request.contains(&format!("ST: {}\r\n", MEDIA_RENDERER_ST)));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn parses_responses_case_insensitively() {
+        let response = parse_response(
+            "HTTP/1.1 200 OK\r\nCache-Control: max-age=1800\r\nlocation: http://192.168.1.20:49152/desc.xml\r\nServer: Linux/4.9 UPnP/1.0 Stub/1.0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.location, "http://192.168.1.20:49152/desc.xml");
+        assert_eq!(
+            response.server.as_deref(),
+            Some("Linux/4.9 UPnP/1.0 Stub/1.0")
+        );
+
+        assert!(parse_response("HTTP/1.1 404 Not Found\r\nLOCATION: http://x/\r\n\r\n").is_none());
+        assert!(parse_response("HTTP/1.1 200 OK\r\nSERVER: x\r\n\r\n").is_none());
+    }
+
+    #[tokio::test]
+    async fn collects_loopback_responses_without_duplicates() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let target = socket.local_addr().unwrap();
+        let renderer = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let reply = "HTTP/1.1 200 OK\r\nLOCATION: http://127.0.0.1:1/desc.xml\r\nST: urn:schemas-upnp-org:device:MediaRenderer:1\r\n\r\n";
+        for payload in [reply, "garbage", reply] {
+            renderer.send_to(payload.as_bytes(), target).await.unwrap();
+        }
+
+        let found = collect_responses(&socket, Duration::from_millis(300)).await;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].location, "http://127.0.0.1:1/desc.xml");
+    }
+}
@@ -1,35 +1,28 @@
 // 在开发模式下允许控制台窗口
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use reqwest;
-use std::collections::HashMap;
 use std::panic;
-use std::sync::{Arc, Mutex};
 use tauri::Manager;
-use tokio::sync::oneshot;
+mod dlna;
 mod platforms;
+mod player;
 mod proxy;
 mod recording;
-use platforms::common::{DouyinDanmakuState, FollowHttpClient, HuyaDanmakuState};
+mod shutdown;
+mod streams;
+use platforms::common::FollowHttpClient;
 use platforms::douyin::danmu::signature::generate_douyin_ms_token;
 use platforms::douyin::fetch_douyin_partition_rooms;
 use platforms::douyin::fetch_douyin_room_info;
 use platforms::douyin::fetch_douyin_streamer_info;
-use platforms::douyin::start_douyin_danmu_listener;
-use platforms::douyin::stop_douyin_danmu_listener;
 use platforms::douyin::{get_douyin_live_stream_url, get_douyin_live_stream_url_with_quality};
 use platforms::douyu::fetch_categories;
 use platforms::douyu::fetch_douyu_room_info;
 use platforms::douyu::fetch_three_cate;
 use platforms::douyu::{fetch_live_list, fetch_live_list_for_cate3};
-use platforms::huya::stop_huya_danmaku_listener;
-use platforms::huya::{fetch_huya_live_list, start_huya_danmaku_listener};
+use platforms::huya::fetch_huya_live_list;
 // use platforms::huya::get_huya_stream_url_with_quality; // removed in favor of unified cmd
 
-// State for managing Douyu danmaku listener handles (stop signals)
-#[derive(Default, Clone)]
-pub struct DouyuDanmakuHandles(Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>);
-
 #[tauri::command]
 async fn get_stream_url_cmd(room_id: String) -> Result<String, String> {
     // Call the actual function to fetch the stream URL from the new location
@@ -66,63 +59,6 @@ async fn get_stream_url_with_quality_cmd(
 
 // Legacy Huya stream URL command removed in favor of unified command
 
-// Command to start Douyu danmaku listener
-#[tauri::command]
-async fn start_danmaku_listener(
-    room_id: String,
-    window: tauri::Window,
-    danmaku_handles: tauri::State<'_, DouyuDanmakuHandles>,
-) -> Result<(), String> {
-    // If a listener for this room_id already exists, stop it first.
-    if let Some(existing_sender) = danmaku_handles.0.lock().unwrap().remove(&room_id) {
-        let _ = existing_sender.send(());
-    }
-
-    let (stop_tx, stop_rx) = oneshot::channel();
-    danmaku_handles
-        .0
-        .lock()
-        .unwrap()
-        .insert(room_id.clone(), stop_tx);
-
-    let window_clone = window.clone();
-    let room_id_clone = room_id.clone();
-    tokio::spawn(async move {
-        let mut client = platforms::douyu::danmu_start::DanmakuClient::new(
-            &room_id_clone,
-            window_clone,
-            stop_rx, // Pass the receiver part of the oneshot channel
-        );
-        if let Err(e) = client.start().await {
-            eprintln!(
-                "[Rust Main] Douyu danmaku client for room {} failed: {}",
-                room_id_clone, e
-            );
-        }
-    });
-
-    Ok(())
-}
-
-// Command to stop Douyu danmaku listener
-#[tauri::command]
-async fn stop_danmaku_listener(
-    room_id: String,
-    danmaku_handles: tauri::State<'_, DouyuDanmakuHandles>,
-) -> Result<(), String> {
-    if let Some(sender) = danmaku_handles.0.lock().unwrap().remove(&room_id) {
-        match sender.send(()) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(format!(
-                "Failed to stop Douyu danmaku listener for room {}: receiver dropped.",
-                room_id
-            )),
-        }
-    } else {
-        Ok(())
-    }
-}
-
 // search_anchor seems fine, assuming douyu::search_anchor is correct
 #[tauri::command]
 async fn search_anchor(keyword: String) -> Result<String, String> {
@@ -136,14 +72,17 @@ fn main() {
     panic::set_hook(Box::new(|info| {
         eprintln!("[panic] {}", info);
     }));
-    // Create a new HTTP client instance to be managed by Tauri
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-        .no_proxy()
-        .build()
-        .expect("Failed to create reqwest client");
+    // 平台请求按平台取用客户端，出站代理设置变更后自动重建
     let follow_http_client = FollowHttpClient::new().expect("Failed to create follow http client");
 
+    // 开发构建启动时重新生成前端的弹幕事件类型
+    #[cfg(debug_assertions)]
+    if let Err(e) = platforms::common::danmaku::export_typescript_bindings(std::path::Path::new(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../src/platforms/common/danmakuBindings.ts"),
+    )) {
+        eprintln!("[Rust/main.rs] {}", e);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_opener::init())
@@ -166,34 +105,75 @@ fn main() {
             }
             Ok(())
         })
-        .manage(client) // Manage the reqwest client
         .manage(follow_http_client) // 专用关注刷新客户端，避免占用默认连接池
-        .manage(DouyuDanmakuHandles::default()) // Manage new DouyuDanmakuHandles
-        .manage(DouyinDanmakuState::default()) // Manage DouyinDanmakuState
-        .manage(HuyaDanmakuState::default()) // Manage HuyaDanmakuState
-        .manage(platforms::common::BilibiliDanmakuState::default()) // Manage BilibiliDanmakuState
+        .manage(platforms::common::danmaku_listener::DanmakuRegistry::default()) // 各平台弹幕连接
+        .manage(platforms::common::danmaku_replay::DanmakuReplayManager::default())
         .manage(proxy::ProxyServerHandle::default())
+        .manage(proxy::ImageProxyCache::default())
+        .manage(proxy::ProxyAccessPolicy::default())
+        .manage(proxy::ProxyMetricsRegistry::default())
+        .manage(proxy::RecordingFileLibrary::default())
         .manage(proxy::FlvProxySessionManager::default())
+        .manage(streams::StreamManager::default())
+        .manage(shutdown::ShutdownState::default())
+        .manage(dlna::DlnaManager::default())
         .manage(recording::RecordingManager::default())
         .manage(platforms::bilibili::state::BilibiliState::default())
         .invoke_handler(tauri::generate_handler![
             get_stream_url_cmd,
             get_stream_url_with_quality_cmd,
             search_anchor,
-            start_danmaku_listener,      // Douyu danmaku start
-            stop_danmaku_listener,       // Douyu danmaku stop
-            start_douyin_danmu_listener, // Added Douyin danmaku listener command
-            stop_douyin_danmu_listener,  // Added Douyin danmaku stop command
-            start_huya_danmaku_listener, // Added Huya danmaku listener command
-            stop_huya_danmaku_listener,  // Added Huya danmaku stop command
-            platforms::bilibili::danmaku::start_bilibili_danmaku_listener,
-            platforms::bilibili::danmaku::stop_bilibili_danmaku_listener,
+            platforms::common::danmaku_listener::start_danmaku,
+            platforms::common::danmaku_listener::stop_danmaku,
+            platforms::common::danmaku_listener::list_danmaku_listeners,
+            platforms::common::danmaku_filter::get_danmaku_filter_settings,
+            platforms::common::danmaku_filter::set_danmaku_filter_settings,
+            platforms::common::danmaku_filter::set_room_danmaku_filter,
+            platforms::common::danmaku_archive::list_danmaku_archives,
+            platforms::common::danmaku_archive::delete_danmaku_archive,
+            platforms::common::danmaku_archive::get_danmaku_archive_settings,
+            platforms::common::danmaku_archive::set_danmaku_archive_settings,
+            platforms::common::danmaku_export::export_danmaku_archive,
+            platforms::common::danmaku_replay::start_danmaku_replay,
+            platforms::common::danmaku_replay::pause_danmaku_replay,
+            platforms::common::danmaku_replay::resume_danmaku_replay,
+            platforms::common::danmaku_replay::seek_danmaku_replay,
+            platforms::common::danmaku_replay::set_danmaku_replay_speed,
+            platforms::common::danmaku_replay::stop_danmaku_replay,
             proxy::start_proxy,
             proxy::stop_proxy,
             proxy::start_flv_proxy_session,
             proxy::stop_flv_proxy_session,
             proxy::stop_all_flv_proxy_sessions,
+            proxy::get_flv_proxy_time_shift_status,
+            proxy::list_flv_proxy_sessions,
             proxy::start_static_proxy_server,
+            proxy::get_image_cache_stats,
+            proxy::clear_image_cache,
+            proxy::get_recording_playback_url,
+            proxy::get_proxy_access_info,
+            proxy::set_proxy_allowed_hosts,
+            proxy::set_proxy_lan_sharing,
+            proxy::set_proxy_block_fake_ip_range,
+            platforms::common::network_proxy::get_network_proxy_settings,
+            platforms::common::network_proxy::set_network_proxy_settings,
+            platforms::common::header_rules::get_upstream_header_rules,
+            platforms::common::header_rules::reload_upstream_header_rules,
+            player::launch_external_player,
+            player::export_stream_playlist,
+            dlna::discover_dlna_renderers,
+            dlna::add_dlna_renderer,
+            dlna::list_dlna_renderers,
+            dlna::dlna_cast_session,
+            dlna::dlna_play,
+            dlna::dlna_stop,
+            dlna::dlna_set_volume,
+            dlna::dlna_get_state,
+            streams::list_active_streams,
+            streams::get_stream_limits,
+            streams::set_stream_limits,
+            shutdown::confirm_app_exit,
+            shutdown::take_last_session_state,
             recording::start_live_recording,
             recording::stop_live_recording,
             recording::stop_all_live_recordings,
@@ -222,6 +202,8 @@ fn main() {
             platforms::bilibili::search::search_bilibili_rooms,
             platforms::huya::search::search_huya_anchors,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(shutdown::handle_window_event)
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(shutdown::handle_run_event);
 }
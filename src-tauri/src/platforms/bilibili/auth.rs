@@ -1,10 +1,13 @@
 // src/auth.rs
+use crate::platforms::common::header_rules::upstream_headers;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use md5::{Digest, Md5};
 use reqwest::header::HeaderMap;
-#[allow(unused_imports)]
-use reqwest::StatusCode;
 use serde::Deserialize;
-use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::models::AuthMessage;
 
 // WBI signing constants and functions (extracted)
 const MIXIN_KEY_ENC_TAB: [usize; 64] = [
@@ -83,23 +86,26 @@ fn _encode_wbi(
     query + &format!("&w_rid={}", web_sign)
 }
 
-fn get_wbi_keys(headers: HeaderMap) -> Result<(String, String), reqwest::Error> {
-    let client = reqwest::blocking::Client::builder()
-        .https_only(true)
-        .no_proxy()
-        .build()
-        .unwrap();
-
-    let mut request_headers = headers;
-    request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
-
-    let response = client.get(UID_INIT_URL).headers(request_headers).send()?;
-
-    let res_wbi: ResWbi = response.json()?;
-    Ok((
-        take_filename(res_wbi.data.wbi_img.img_url).unwrap(),
-        take_filename(res_wbi.data.wbi_img.sub_url).unwrap(),
-    ))
+async fn get_wbi_keys(
+    client: &reqwest::Client,
+    headers: HeaderMap,
+) -> Result<(String, String), String> {
+    let res_wbi: ResWbi = client
+        .get(UID_INIT_URL)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get WBI keys: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse WBI keys: {}", e))?;
+    match (
+        take_filename(res_wbi.data.wbi_img.img_url),
+        take_filename(res_wbi.data.wbi_img.sub_url),
+    ) {
+        (Some(img_key), Some(sub_key)) => Ok((img_key, sub_key)),
+        _ => Err("Failed to parse WBI keys: unexpected wbi_img url".to_string()),
+    }
 }
 
 fn take_filename(url: String) -> Option<String> {
@@ -111,143 +117,83 @@ fn take_filename(url: String) -> Option<String> {
 pub const UID_INIT_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
 pub const DANMAKU_SERVER_CONF_URL: &str =
     "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo";
-pub const USER_AGENT: &str =
-    "Mozilla/5.0 (X11; Linux x86_64; rv:138.0) Gecko/20100101 Firefox/138.0";
 
-/// Get UID using cookie (optional). If request fails or no cookie, returns (status, body).
-pub fn init_uid(headers: HeaderMap) -> (reqwest::StatusCode, String) {
-    let client = reqwest::blocking::Client::builder()
+fn build_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
         .https_only(true)
-        .no_proxy()
+        .outbound_proxy(Some("BILIBILI"))
+        .timeout(Duration::from_secs(15))
         .build()
-        .unwrap();
-
-    let mut request_headers = headers;
-    request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
-
-    let response = client.get(UID_INIT_URL).headers(request_headers).send();
-    let stat: reqwest::StatusCode;
-    let body: String;
-    match response {
-        Ok(resp) => {
-            let s: reqwest::StatusCode = resp.status();
-            stat = s;
-            match resp.text() {
-                Ok(b) => body = b,
-                Err(e) => body = format!("{{\"error\":\"{}\"}}", e),
-            }
-        }
-        Err(err) => {
-            stat = reqwest::StatusCode::INTERNAL_SERVER_ERROR;
-            body = format!("{{\"error\":\"{}\"}}", err);
-        }
-    }
-    (stat, body)
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
 }
 
-/// Query danmaku server host list and token via signed URL, with given headers
-pub fn init_host_server(headers: HeaderMap, room_id: u64) -> (reqwest::StatusCode, String) {
-    let client = reqwest::blocking::Client::builder()
-        .https_only(true)
-        .no_proxy()
-        .build()
-        .unwrap();
-
-    let mut request_headers = headers.clone();
-    request_headers.insert("user-agent", USER_AGENT.parse().unwrap());
-
-    let wbi_keys = match get_wbi_keys(request_headers.clone()) {
-        Ok(keys) => keys,
-        Err(e) => {
-            log::error!("Failed to get WBI keys: {:?}", e);
-            panic!("Failed to get WBI keys");
-        }
+/// Get UID using cookie. Returns 0 (guest) when the cookie is missing or invalid.
+async fn init_uid(client: &reqwest::Client, headers: HeaderMap) -> u64 {
+    let body: Value = match client.get(UID_INIT_URL).headers(headers).send().await {
+        Ok(resp) => resp.json().await.unwrap_or(Value::Null),
+        Err(_) => Value::Null,
     };
+    body["data"]["mid"].as_u64().unwrap_or(0)
+}
 
+/// Query danmaku server host list and token via signed URL, with given headers
+async fn init_host_server(
+    client: &reqwest::Client,
+    headers: HeaderMap,
+    room_id: u64,
+) -> Result<Value, String> {
+    let wbi_keys = get_wbi_keys(client, headers.clone()).await?;
     let params = vec![
         ("id", room_id.to_string()),
         ("type", "0".to_string()),
         ("web_location", "444.8".to_string()),
     ];
-
     let signed_query = encode_wbi(params, wbi_keys);
     let url = format!("{}?{}", DANMAKU_SERVER_CONF_URL, signed_query);
 
-    let response = client.get(url).headers(request_headers).send();
-    let stat: reqwest::StatusCode;
-    let body: String;
-    match response {
-        Ok(resp) => {
-            let s: reqwest::StatusCode = resp.status();
-            stat = s;
-            body = resp.text().unwrap_or_default();
-        }
-        Err(_) => {
-            stat = reqwest::StatusCode::INTERNAL_SERVER_ERROR;
-            body = String::new();
-        }
+    let body: Value = client
+        .get(url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get danmaku server info: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse danmaku server info: {}", e))?;
+    if body["code"].as_i64().unwrap_or(-1) != 0 {
+        return Err(format!(
+            "getDanmuInfo returned code={} message={}",
+            body["code"],
+            body["message"].as_str().unwrap_or("")
+        ));
     }
-    (stat, body)
+    Ok(body["data"].clone())
 }
 
-use super::models::AuthMessage;
-use serde_json::Value;
-use std::collections::HashMap;
-
-/// Initialize server info and auth message using cookie
-pub fn init_server_with_cookie(cookies: &str, room_id: &str) -> (Value, AuthMessage) {
-    let mut auth_map = HashMap::new();
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::COOKIE,
-        reqwest::header::HeaderValue::from_str(cookies).unwrap(),
-    );
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static(USER_AGENT),
-    );
-
-    // Try get uid via cookie (optional)
-    let (_, body1) = init_uid(headers.clone());
-    let body1_v: Value = serde_json::from_str(body1.as_str()).unwrap_or(Value::Null);
-    if let Some(mid) = body1_v["data"]["mid"].as_i64() {
-        auth_map.insert("uid".to_string(), mid.to_string());
-    } else {
-        auth_map.insert("uid".to_string(), "0".to_string());
+/// Fetch danmaku server info (host_list/token) and build the auth message.
+/// Without cookie the connection authenticates as guest (uid=0).
+pub async fn init_server(
+    cookie: Option<&str>,
+    room_id: &str,
+) -> Result<(Value, AuthMessage), String> {
+    let room_id_num = room_id
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid Bilibili room id: {}", room_id))?;
+    let client = build_client()?;
+    let mut headers = upstream_headers(Some("BILIBILI"), DANMAKU_SERVER_CONF_URL);
+
+    let mut uid = 0;
+    if let Some(cookie) = cookie.filter(|c| !c.trim().is_empty()) {
+        let value = reqwest::header::HeaderValue::from_str(cookie)
+            .map_err(|e| format!("Invalid cookie: {}", e))?;
+        headers.insert(reqwest::header::COOKIE, value);
+        uid = init_uid(&client, headers.clone()).await;
     }
 
-    auth_map.insert("room_id".to_string(), room_id.to_string());
-
-    let room_id_num = room_id.parse::<u64>().expect("room_id must be a valid u64");
-    let (_, body4) = init_host_server(headers.clone(), room_id_num);
-    let body4_res: Value = serde_json::from_str(body4.as_str()).unwrap();
-    let server_info = &body4_res["data"];
-    let token = &body4_res["data"]["token"].as_str().unwrap();
-    auth_map.insert("token".to_string(), token.to_string());
-
-    let auth_msg = AuthMessage::from(&auth_map);
-    (server_info.clone(), auth_msg)
-}
-
-/// Initialize server info and auth message without cookie (uid=0)
-pub fn init_server_no_cookie(room_id: &str) -> (Value, AuthMessage) {
-    let mut auth_map = HashMap::new();
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static(USER_AGENT),
-    );
-
-    auth_map.insert("uid".to_string(), "0".to_string());
-    auth_map.insert("room_id".to_string(), room_id.to_string());
-
-    let room_id_num = room_id.parse::<u64>().expect("room_id must be a valid u64");
-    let (_, body4) = init_host_server(headers.clone(), room_id_num);
-    let body4_res: Value = serde_json::from_str(body4.as_str()).unwrap();
-    let server_info = &body4_res["data"];
-    let token = &body4_res["data"]["token"].as_str().unwrap();
-    auth_map.insert("token".to_string(), token.to_string());
-
-    let auth_msg = AuthMessage::from(&auth_map);
-    (server_info.clone(), auth_msg)
+    let server_info = init_host_server(&client, headers, room_id_num).await?;
+    let token = server_info["token"]
+        .as_str()
+        .ok_or_else(|| "Danmaku server info has no token".to_string())?;
+    let auth_msg = AuthMessage::new(uid, room_id_num, token);
+    Ok((server_info, auth_msg))
 }
@@ -1,117 +1,220 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tauri::Emitter;
-use tokio::sync::mpsc as tokio_mpsc;
+use futures_util::future::BoxFuture;
+use tokio::sync::mpsc;
 
-use crate::platforms::bilibili::models::BiliMessage;
-use crate::platforms::bilibili::websocket::BiliLiveClient;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
-#[tauri::command]
-pub async fn start_bilibili_danmaku_listener(
-    payload: crate::platforms::common::GetStreamUrlPayload,
-    cookie: Option<String>,
-    app_handle: tauri::AppHandle,
-    state: tauri::State<'_, crate::platforms::common::BilibiliDanmakuState>,
-) -> Result<(), String> {
-    let room_id = payload.args.room_id_str.clone();
+use crate::platforms::bilibili::models::{BiliMedal, BiliMessage, BiliUser, InteractKind};
+use crate::platforms::bilibili::websocket::{BiliLiveClient, ClientEvent};
+use crate::platforms::common::danmaku::{rgb_color, DanmakuMedal};
+use crate::platforms::common::danmaku_listener::{DanmakuListener, ListenerContext};
+use crate::platforms::common::{
+    emit_danmaku_error, DanmakuEvent, DanmakuEventKind, DanmakuPlatform, DanmakuUser,
+};
 
-    // stop previous listener for this specific room if exists
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&room_id)
-    };
-    if let Some(tx) = previous_tx {
-        if tx.send(()).await.is_err() {
-            eprintln!("[Bilibili Danmaku] 旧任务关闭失败，可能已退出。");
+// GUARD_BUY 与 USER_TOAST_MSG 间隔通常不超过几秒
+const GUARD_DEDUP_WINDOW: Duration = Duration::from_secs(10);
+
+/// 同一次上舰只发出一条 Guard 事件
+#[derive(Default)]
+struct GuardDedup {
+    recent: VecDeque<(u64, u32, Instant)>,
+}
+
+impl GuardDedup {
+    fn is_duplicate(&mut self, uid: u64, guard_level: u32) -> bool {
+        let now = Instant::now();
+        self.recent
+            .retain(|(_, _, at)| now.duration_since(*at) < GUARD_DEDUP_WINDOW);
+        if self
+            .recent
+            .iter()
+            .any(|(u, level, _)| *u == uid && *level == guard_level)
+        {
+            return true;
         }
+        self.recent.push_back((uid, guard_level, now));
+        false
     }
+}
 
-    let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
-    {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.insert(room_id.clone(), tx_shutdown);
+fn to_danmaku_medal(medal: BiliMedal) -> DanmakuMedal {
+    DanmakuMedal {
+        name: medal.name,
+        level: medal.level,
+        anchor_name: medal.anchor_name,
+        anchor_room_id: medal.anchor_room_id.map(|id| id.to_string()),
     }
+}
 
-    let app_handle_clone = app_handle.clone();
-    let room_id_clone = room_id.clone();
-    let cookie_clone = cookie.clone();
-
-    // Use atomic flag to signal std::thread to stop
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    let stop_flag_for_thread = stop_flag.clone();
-
-    // Spawn std thread to run sync BiliLiveClient loop
-    std::thread::spawn(move || {
-        let mut client = match cookie_clone.as_ref() {
-            Some(c) => BiliLiveClient::new_with_cookie(c.as_str(), room_id_clone.as_str()),
-            None => BiliLiveClient::new_without_cookie(room_id_clone.as_str()),
-        };
-        client.send_auth();
+fn to_danmaku_user(user: BiliUser) -> DanmakuUser {
+    DanmakuUser::named(user.uname)
+        .id(user.uid)
+        .level(user.level)
+        .medal_info(user.medal.map(to_danmaku_medal))
+}
 
-        loop {
-            if stop_flag_for_thread.load(Ordering::Relaxed) {
-                break;
+fn to_danmaku_event(
+    room_id: &str,
+    msg: BiliMessage,
+    guards: &mut GuardDedup,
+) -> Option<DanmakuEvent> {
+    let event =
+        |kind: DanmakuEventKind| DanmakuEvent::new(DanmakuPlatform::Bilibili, room_id, kind);
+    let live_status =
+        |live: bool, reason: Option<String>| event(DanmakuEventKind::LiveStatus { live, reason });
+    let event = match msg {
+        BiliMessage::Danmu {
+            user,
+            text,
+            color,
+            timestamp_ms,
+        } => DanmakuEvent::chat(DanmakuPlatform::Bilibili, room_id, text)
+            .user(to_danmaku_user(user))
+            .color(rgb_color(i64::from(color)))
+            .timestamp(timestamp_ms),
+        BiliMessage::SuperChat {
+            user,
+            message,
+            price_cny,
+            duration_secs,
+        } => event(DanmakuEventKind::SuperChat {
+            content: message,
+            value_cny: price_cny,
+            duration_seconds: Some(duration_secs).filter(|d| *d > 0),
+        })
+        .user(to_danmaku_user(user)),
+        BiliMessage::Guard {
+            user,
+            guard_level,
+            role_name,
+            months,
+            price_cny: _,
+        } => {
+            if guards.is_duplicate(user.uid, guard_level) {
+                return None;
             }
-            if let Some(msg) = client.read_once() {
-                match msg {
-                    BiliMessage::Danmu { user, text } => {
-                        let _ = app_handle_clone.emit(
-                            "danmaku-message",
-                            crate::platforms::common::DanmakuFrontendPayload {
-                                room_id: room_id_clone.clone(),
-                                user,
-                                content: text,
-                                user_level: 0,
-                                fans_club_level: 0,
-                            },
-                        );
-                    }
-                    BiliMessage::Gift { user, gift } => {
-                        let _ = app_handle_clone.emit(
-                            "danmaku-message",
-                            crate::platforms::common::DanmakuFrontendPayload {
-                                room_id: room_id_clone.clone(),
-                                user,
-                                content: format!("[礼物] {}", gift),
-                                user_level: 0,
-                                fans_club_level: 0,
-                            },
-                        );
-                    }
-                    BiliMessage::Unsupported { .. } => {
-                        // ignore
-                    }
-                }
-            }
-            std::thread::sleep(std::time::Duration::from_millis(50));
+            event(DanmakuEventKind::Guard {
+                level: guard_level,
+                level_name: role_name,
+                months: Some(months),
+            })
+            .user(to_danmaku_user(user))
+        }
+        BiliMessage::Gift {
+            user,
+            gift_id,
+            gift_name,
+            num,
+            total_cny,
+            combo_id,
+        } => event(DanmakuEventKind::Gift {
+            gift_id: Some(gift_id.to_string()),
+            gift_name,
+            count: num,
+            value_cny: Some(total_cny),
+            combo_id,
+            combo_total: None,
+        })
+        .user(to_danmaku_user(user)),
+        BiliMessage::ComboGift {
+            user,
+            gift_id,
+            gift_name,
+            total_num,
+            total_cny,
+            combo_id,
+        } => event(DanmakuEventKind::Gift {
+            gift_id: Some(gift_id.to_string()),
+            gift_name,
+            count: 0,
+            value_cny: Some(total_cny),
+            combo_id,
+            combo_total: Some(total_num),
+        })
+        .user(to_danmaku_user(user)),
+        BiliMessage::Interact { user, kind } => match kind {
+            InteractKind::Enter => event(DanmakuEventKind::Enter),
+            InteractKind::Follow => event(DanmakuEventKind::Follow),
+            // 分享直播间没有对应的事件类型
+            InteractKind::Share => return None,
         }
-    });
+        .user(to_danmaku_user(user)),
+        BiliMessage::Like { user } => {
+            event(DanmakuEventKind::Like { count: 1 }).user(to_danmaku_user(user))
+        }
+        BiliMessage::OnlineRank { count } => event(DanmakuEventKind::RoomStats {
+            online: Some(count),
+            watched: None,
+            likes: None,
+            display: None,
+        }),
+        BiliMessage::Watched { num, text } => event(DanmakuEventKind::RoomStats {
+            online: None,
+            watched: Some(num),
+            likes: None,
+            display: Some(text).filter(|t| !t.is_empty()),
+        }),
+        BiliMessage::RoomChange { title, area_name } => event(DanmakuEventKind::RoomInfo {
+            title: Some(title).filter(|t| !t.is_empty()),
+            area: Some(area_name).filter(|a| !a.is_empty()),
+        }),
+        BiliMessage::Live => live_status(true, None),
+        BiliMessage::Preparing => live_status(false, None),
+        BiliMessage::CutOff { message } => live_status(false, Some(message)),
+        BiliMessage::Warning { message } => event(DanmakuEventKind::Notice { message }),
+        BiliMessage::Unsupported { .. } => return None,
+    };
+    Some(event)
+}
 
-    // Spawn a tokio task to listen for shutdown and set stop flag
-    let stop_flag_for_task = stop_flag.clone();
-    tokio::spawn(async move {
-        let _ = rx_shutdown.recv().await; // wait for shutdown signal
-        stop_flag_for_task.store(true, Ordering::Relaxed);
-    });
+/// B 站弹幕监听，由 `DanmakuRegistry` 管理生命周期
+pub struct BilibiliDanmakuListener;
 
-    Ok(())
+impl DanmakuListener for BilibiliDanmakuListener {
+    fn run(
+        &self,
+        ctx: ListenerContext,
+        stop_rx: mpsc::Receiver<()>,
+    ) -> BoxFuture<'static, Result<(), String>> {
+        Box::pin(run_listener(ctx, stop_rx))
+    }
 }
 
-#[tauri::command]
-pub async fn stop_bilibili_danmaku_listener(
-    room_id: String,
-    state: tauri::State<'_, crate::platforms::common::BilibiliDanmakuState>,
-) -> Result<(), String> {
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&room_id)
-    };
-    if let Some(tx) = previous_tx {
-        match tx.send(()).await {
-            Ok(()) => Ok(()),
-            Err(_) => Err("停止Bilibili弹幕监听失败：接收方已关闭".to_string()),
-        }
-    } else {
-        Ok(())
-    }
+async fn run_listener(ctx: ListenerContext, stop_rx: mpsc::Receiver<()>) -> Result<(), String> {
+    let room_id = ctx.room_id.clone();
+    let mut client = BiliLiveClient::new(&room_id, ctx.options.cookie.as_deref());
+    let mut guards = GuardDedup::default();
+    let mut fatal_error = None;
+    client
+        .run(stop_rx, |event| match event {
+            ClientEvent::Message(msg) => {
+                if let Some(event) = to_danmaku_event(&room_id, msg, &mut guards) {
+                    ctx.emit(event);
+                }
+            }
+            ClientEvent::Connected { host } => {
+                println!("[Bilibili Danmaku] room {} connected via {}", room_id, host);
+                ctx.connected();
+            }
+            ClientEvent::Reconnecting { attempt, delay } => ctx.reconnecting(attempt, delay),
+            ClientEvent::Error {
+                message,
+                will_retry,
+            } => {
+                if !will_retry {
+                    fatal_error = Some(message.clone());
+                }
+                emit_danmaku_error(
+                    &ctx.app_handle,
+                    DanmakuPlatform::Bilibili,
+                    &room_id,
+                    message,
+                    will_retry,
+                );
+            }
+        })
+        .await;
+    println!("[Bilibili Danmaku] room {} listener stopped", room_id);
+    fatal_error.map_or(Ok(()), Err)
 }
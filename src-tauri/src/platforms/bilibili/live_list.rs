@@ -1,3 +1,5 @@
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use md5::{Digest, Md5};
 
 // 引入 generate_bilibili_w_webid 以便在缺失时后端自动初始化
@@ -67,7 +69,6 @@ pub async fn fetch_bilibili_live_list(
         pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
     params.push(("w_rid".to_string(), w_rid));
 
-    let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36";
     let url = "https://api.live.bilibili.com/xlive/web-interface/v1/second/getList";
     let query_str = params
         .iter()
@@ -78,19 +79,15 @@ pub async fn fetch_bilibili_live_list(
 
     println!("[Bilibili] Fetch live list: w_webid={}, area_id={}, parent_area_id={}, page={}, wts={}, w_rid={}", w_webid, area_id, parent_area_id, page, wts, &params.iter().find(|(k,_)| k=="w_rid").map(|(_,v)| v.clone()).unwrap_or_default());
     println!("[Bilibili] GET {}", full_url);
-    println!(
-        "[Bilibili] Headers: User-Agent={}, Referer={}, Cookie={}",
-        ua, "https://www.bilibili.com/", "buvid3=i;"
-    );
 
     let client = reqwest::Client::builder()
-        .user_agent(ua)
-        .no_proxy()
+        .outbound_proxy(Some("BILIBILI"))
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
 
     let resp = client
         .get(url)
+        .upstream_headers(Some("BILIBILI"), url)
         .header("Referer", "https://www.bilibili.com/")
         .header("Cookie", "buvid3=i;")
         .query(&params)
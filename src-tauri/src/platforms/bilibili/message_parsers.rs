@@ -0,0 +1,225 @@
+use serde_json::Value;
+
+use super::models::{BiliMedal, BiliMessage, BiliUser, InteractKind};
+
+// 金瓜子与人民币的比例
+const GOLD_PER_CNY: f64 = 1000.0;
+
+// B 站字段有时是数字有时是字符串
+fn as_u64(v: &Value) -> u64 {
+    match v {
+        Value::Number(n) => n
+            .as_u64()
+            .or_else(|| n.as_f64().map(|f| f.max(0.0) as u64))
+            .unwrap_or(0),
+        Value::String(s) => s.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn as_u32(v: &Value) -> u32 {
+    as_u64(v).min(u64::from(u32::MAX)) as u32
+}
+
+fn as_string(v: &Value) -> String {
+    v.as_str().unwrap_or_default().to_string()
+}
+
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+// medal_info / fans_medal 对象；未佩戴时名称为空或等级为 0
+fn parse_medal_object(v: &Value) -> Option<BiliMedal> {
+    let name = as_string(&v["medal_name"]);
+    let level = as_u32(&v["medal_level"]);
+    if name.is_empty() || level == 0 {
+        return None;
+    }
+    Some(BiliMedal {
+        name,
+        level,
+        anchor_name: non_empty(as_string(&v["anchor_uname"])),
+        anchor_room_id: Some(as_u64(&v["anchor_roomid"])).filter(|id| *id > 0),
+    })
+}
+
+// DANMU_MSG 的 info[3]：[等级, 名称, 主播名, 房间号, ...]
+fn parse_medal_array(v: &Value) -> Option<BiliMedal> {
+    let name = as_string(&v[1]);
+    let level = as_u32(&v[0]);
+    if name.is_empty() || level == 0 {
+        return None;
+    }
+    Some(BiliMedal {
+        name,
+        level,
+        anchor_name: non_empty(as_string(&v[2])),
+        anchor_room_id: Some(as_u64(&v[3])).filter(|id| *id > 0),
+    })
+}
+
+// data 中 uid/uname 加上勋章对象的通用用户结构
+fn parse_data_user(data: &Value, name_key: &str, medal_key: &str) -> BiliUser {
+    BiliUser {
+        uid: as_u64(&data["uid"]),
+        uname: as_string(&data[name_key]),
+        level: 0,
+        guard_level: as_u32(&data["guard_level"]),
+        medal: parse_medal_object(&data[medal_key]),
+    }
+}
+
+fn parse_danmu(json: &Value) -> BiliMessage {
+    let info = &json["info"];
+    let uname = match info[2][1].as_str() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => "<unknown>".to_string(),
+    };
+    BiliMessage::Danmu {
+        user: BiliUser {
+            uid: as_u64(&info[2][0]),
+            uname,
+            level: as_u32(&info[4][0]),
+            guard_level: as_u32(&info[7]),
+            medal: parse_medal_array(&info[3]),
+        },
+        text: as_string(&info[1]),
+        color: as_u32(&info[0][3]),
+        timestamp_ms: as_u64(&info[0][4]) as i64,
+    }
+}
+
+fn parse_super_chat(data: &Value) -> BiliMessage {
+    let user_info = &data["user_info"];
+    BiliMessage::SuperChat {
+        user: BiliUser {
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&user_info["uname"]),
+            level: as_u32(&user_info["user_level"]),
+            guard_level: as_u32(&user_info["guard_level"]),
+            medal: parse_medal_object(&data["medal_info"]),
+        },
+        message: as_string(&data["message"]),
+        price_cny: as_u64(&data["price"]) as f64,
+        duration_secs: as_u32(&data["time"]),
+    }
+}
+
+fn guard_role_name(level: u32) -> &'static str {
+    match level {
+        1 => "总督",
+        2 => "提督",
+        _ => "舰长",
+    }
+}
+
+// GUARD_BUY 与 USER_TOAST_MSG 字段基本一致，后者带有 role_name
+fn parse_guard(data: &Value) -> BiliMessage {
+    let guard_level = as_u32(&data["guard_level"]);
+    let role_name = match as_string(&data["role_name"]) {
+        name if name.is_empty() => guard_role_name(guard_level).to_string(),
+        name => name,
+    };
+    BiliMessage::Guard {
+        user: BiliUser {
+            uid: as_u64(&data["uid"]),
+            uname: as_string(&data["username"]),
+            guard_level,
+            ..Default::default()
+        },
+        guard_level,
+        role_name,
+        months: as_u32(&data["num"]).max(1),
+        price_cny: as_u64(&data["price"]) as f64 / GOLD_PER_CNY,
+    }
+}
+
+fn parse_gift(data: &Value) -> BiliMessage {
+    let total_cny = if data["coin_type"].as_str() == Some("gold") {
+        as_u64(&data["total_coin"]) as f64 / GOLD_PER_CNY
+    } else {
+        0.0
+    };
+    BiliMessage::Gift {
+        user: parse_data_user(data, "uname", "medal_info"),
+        gift_id: as_u64(&data["giftId"]),
+        gift_name: as_string(&data["giftName"]),
+        num: as_u32(&data["num"]).max(1),
+        total_cny,
+        combo_id: non_empty(as_string(&data["batch_combo_id"])),
+    }
+}
+
+fn parse_combo_gift(data: &Value) -> BiliMessage {
+    BiliMessage::ComboGift {
+        user: parse_data_user(data, "uname", "medal_info"),
+        gift_id: as_u64(&data["gift_id"]),
+        gift_name: as_string(&data["gift_name"]),
+        total_num: as_u32(&data["total_num"]),
+        total_cny: as_u64(&data["combo_total_coin"]) as f64 / GOLD_PER_CNY,
+        combo_id: non_empty(as_string(&data["batch_combo_id"])),
+    }
+}
+
+// msg_type: 1 进场 2 关注 3 分享 4 特别关注 5 互粉
+fn parse_interact(json: &Value, data: &Value) -> BiliMessage {
+    let kind = match as_u64(&data["msg_type"]) {
+        1 => InteractKind::Enter,
+        2 | 4 | 5 => InteractKind::Follow,
+        3 => InteractKind::Share,
+        _ => return unsupported(json),
+    };
+    BiliMessage::Interact {
+        user: parse_data_user(data, "uname", "fans_medal"),
+        kind,
+    }
+}
+
+fn unsupported(json: &Value) -> BiliMessage {
+    BiliMessage::Unsupported {
+        cmd: as_string(&json["cmd"]),
+    }
+}
+
+/// 解析一条业务消息（op=5 的 JSON）
+pub fn parse_message(json: &Value) -> BiliMessage {
+    let cmd = json["cmd"].as_str().unwrap_or("");
+    let data = &json["data"];
+    // DANMU_MSG 在部分房间会带上 ":4:0:2:2:2:0" 之类的后缀
+    match cmd.split(':').next().unwrap_or("") {
+        "DANMU_MSG" => parse_danmu(json),
+        "SUPER_CHAT_MESSAGE" => parse_super_chat(data),
+        "GUARD_BUY" | "USER_TOAST_MSG" => parse_guard(data),
+        "SEND_GIFT" => parse_gift(data),
+        "COMBO_SEND" => parse_combo_gift(data),
+        "INTERACT_WORD" => parse_interact(json, data),
+        "LIKE_INFO_V3_CLICK" => BiliMessage::Like {
+            user: parse_data_user(data, "uname", "fans_medal"),
+        },
+        "ONLINE_RANK_COUNT" => BiliMessage::OnlineRank {
+            count: as_u32(if data["online_count"].is_null() {
+                &data["count"]
+            } else {
+                &data["online_count"]
+            }),
+        },
+        "WATCHED_CHANGE" => BiliMessage::Watched {
+            num: as_u32(&data["num"]),
+            text: as_string(&data["text_large"]),
+        },
+        "ROOM_CHANGE" => BiliMessage::RoomChange {
+            title: as_string(&data["title"]),
+            area_name: as_string(&data["area_name"]),
+        },
+        "LIVE" => BiliMessage::Live,
+        "PREPARING" => BiliMessage::Preparing,
+        "WARNING" => BiliMessage::Warning {
+            message: as_string(&json["msg"]),
+        },
+        "CUT_OFF" => BiliMessage::CutOff {
+            message: as_string(&json["msg"]),
+        },
+        _ => unsupported(json),
+    }
+}
@@ -6,6 +6,7 @@ pub mod stream_url;
 pub mod streamer_info;
 // 新增模块声明
 pub mod auth;
+pub mod message_parsers;
 pub mod models;
 pub mod search;
 pub mod websocket;
@@ -1,6 +1,5 @@
 // src/models.rs
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -42,21 +41,117 @@ pub struct AuthMessage {
 }
 
 impl AuthMessage {
-    pub fn from(map: &HashMap<String, String>) -> AuthMessage {
+    pub fn new(uid: u64, roomid: u64, token: &str) -> AuthMessage {
         AuthMessage {
-            uid: map.get("uid").unwrap().parse::<u64>().unwrap(),
-            roomid: map.get("room_id").unwrap().parse::<u64>().unwrap(),
+            uid,
+            roomid,
             protover: 3,
             platform: "web".to_string(),
             type_: 2,
-            key: map.get("token").unwrap().to_string(),
+            key: token.to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// 粉丝勋章
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiliMedal {
+    pub name: String,
+    pub level: u32,
+    pub anchor_name: Option<String>,
+    pub anchor_room_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BiliUser {
+    pub uid: u64,
+    pub uname: String,
+    /// 用户等级（UL），部分消息不带时为 0
+    pub level: u32,
+    /// 大航海等级：1 总督 2 提督 3 舰长，0 为无
+    pub guard_level: u32,
+    pub medal: Option<BiliMedal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractKind {
+    Enter,
+    Follow,
+    Share,
+}
+
+/// 金额统一折算为人民币：金瓜子 1000 = 1 元
+#[derive(Debug, Clone, PartialEq)]
 pub enum BiliMessage {
-    Danmu { user: String, text: String },
-    Gift { user: String, gift: String },
-    Unsupported { cmd: String },
+    Danmu {
+        user: BiliUser,
+        text: String,
+        color: u32,
+        timestamp_ms: i64,
+    },
+    SuperChat {
+        user: BiliUser,
+        message: String,
+        price_cny: f64,
+        duration_secs: u32,
+    },
+    /// 上舰，GUARD_BUY 与 USER_TOAST_MSG 会针对同一次购买各发一条
+    Guard {
+        user: BiliUser,
+        guard_level: u32,
+        role_name: String,
+        months: u32,
+        price_cny: f64,
+    },
+    Gift {
+        user: BiliUser,
+        gift_id: u64,
+        gift_name: String,
+        num: u32,
+        /// 银瓜子礼物为 0
+        total_cny: f64,
+        combo_id: Option<String>,
+    },
+    /// 连击结束后的汇总，数量已包含在此前的 Gift 中
+    ComboGift {
+        user: BiliUser,
+        gift_id: u64,
+        gift_name: String,
+        total_num: u32,
+        total_cny: f64,
+        combo_id: Option<String>,
+    },
+    Interact {
+        user: BiliUser,
+        kind: InteractKind,
+    },
+    Like {
+        user: BiliUser,
+    },
+    /// ONLINE_RANK_COUNT，高能榜人数
+    OnlineRank {
+        count: u32,
+    },
+    /// WATCHED_CHANGE，累计看过人数
+    Watched {
+        num: u32,
+        text: String,
+    },
+    RoomChange {
+        title: String,
+        area_name: String,
+    },
+    Live,
+    Preparing,
+    /// 超管警告
+    Warning {
+        message: String,
+    },
+    /// 直播被超管切断
+    CutOff {
+        message: String,
+    },
+    Unsupported {
+        cmd: String,
+    },
 }
@@ -1,11 +1,11 @@
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::header::{COOKIE, REFERER, USER_AGENT};
+use reqwest::header::COOKIE;
 use serde::Serialize;
 use serde_json::Value;
 
-const DEFAULT_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
-const LIVE_REFERER: &str = "https://live.bilibili.com/";
 const SEARCH_ENDPOINT: &str = "https://api.bilibili.com/x/web-interface/search/type";
 const FINGERPRINT_ENDPOINT: &str = "https://api.bilibili.com/x/frontend/finger/spi";
 
@@ -101,8 +101,7 @@ async fn ensure_buvid(client: &reqwest::Client, cookie_header: &mut String) -> R
 
     let mut request = client
         .get(FINGERPRINT_ENDPOINT)
-        .header(USER_AGENT, DEFAULT_UA)
-        .header(REFERER, LIVE_REFERER);
+        .upstream_headers(Some("BILIBILI"), FINGERPRINT_ENDPOINT);
 
     if !cookie_header.trim().is_empty() {
         request = request.header(COOKIE, cookie_header.as_str());
@@ -151,7 +150,7 @@ pub async fn search_bilibili_rooms(
     let mut cookie_header = cookie.unwrap_or_default();
 
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("BILIBILI"))
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
 
@@ -159,8 +158,7 @@ pub async fn search_bilibili_rooms(
 
     let mut req = client
         .get(SEARCH_ENDPOINT)
-        .header(USER_AGENT, DEFAULT_UA)
-        .header(REFERER, LIVE_REFERER)
+        .upstream_headers(Some("BILIBILI"), SEARCH_ENDPOINT)
         .query(&[
             ("context", ""),
             ("search_type", "live"),
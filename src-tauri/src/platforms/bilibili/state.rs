@@ -1,3 +1,5 @@
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use std::sync::{Arc, Mutex};
 
 #[derive(Default, Clone)]
@@ -9,22 +11,18 @@ pub struct BilibiliState {
 pub async fn generate_bilibili_w_webid(
     state: tauri::State<'_, BilibiliState>,
 ) -> Result<String, String> {
-    let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36";
     let url = "https://live.bilibili.com/lol";
     println!("[Bilibili] Generating w_webid: GET {}", url);
-    println!(
-        "[Bilibili] Headers: User-Agent={}, Referer={} ",
-        ua, "https://www.bilibili.com/"
-    );
 
     let client = reqwest::Client::builder()
-        .user_agent(ua)
-        .no_proxy()
+        .outbound_proxy(Some("BILIBILI"))
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
 
+    // w_webid 与 UA 绑定，UA 与直播列表请求一样取自请求头规则表
     let resp = client
         .get(url)
+        .upstream_headers(Some("BILIBILI"), url)
         .header("Referer", "https://www.bilibili.com/")
         .send()
         .await
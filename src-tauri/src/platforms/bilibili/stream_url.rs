@@ -1,4 +1,6 @@
-use reqwest::header::{HeaderMap, HeaderValue, COOKIE, REFERER, USER_AGENT};
+use crate::platforms::common::header_rules::upstream_headers;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
+use reqwest::header::{HeaderValue, COOKIE};
 use serde_json::Value;
 use tauri::command;
 
@@ -26,14 +28,10 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         });
     }
 
-    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
-
-    // Build headers
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_str(ua).unwrap());
-    headers.insert(
-        REFERER,
-        HeaderValue::from_static("https://live.bilibili.com/"),
+    // UA/Referer/Origin 来自请求头规则表；Origin 用于通过部分接口的 CSRF 检查
+    let mut headers = upstream_headers(
+        Some("BILIBILI"),
+        "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo",
     );
     if let Some(c) = cookie.as_ref() {
         let c_trimmed = c.trim();
@@ -52,14 +50,9 @@ pub async fn get_bilibili_live_stream_url_with_quality(
         }
     }
 
-    // 添加必要的 Origin，以符合部分接口对 CSRF 的检查
-    headers.insert(
-        reqwest::header::ORIGIN,
-        HeaderValue::from_static("https://live.bilibili.com"),
-    );
     let client = reqwest::Client::builder()
         .default_headers(headers)
-        .no_proxy()
+        .outbound_proxy(Some("BILIBILI"))
         .build()
         .map_err(|e| format!("Failed to build client: {}", e))?;
 
@@ -1,7 +1,8 @@
+use crate::platforms::common::header_rules::upstream_headers;
 use crate::platforms::common::FollowHttpClient;
 use md5;
 use md5::{Digest, Md5};
-use reqwest::header::{HeaderMap, HeaderValue, COOKIE, REFERER, USER_AGENT};
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -121,15 +122,8 @@ pub async fn fetch_bilibili_streamer_info(
         });
     }
 
-    let ua = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
-
-    // Build headers (include optional cookie)
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_str(ua).unwrap());
-    headers.insert(
-        REFERER,
-        HeaderValue::from_static("https://live.bilibili.com/"),
-    );
+    // Build headers from the rules table (include optional cookie)
+    let mut headers = upstream_headers(Some("BILIBILI"), "https://api.live.bilibili.com/");
     if let Some(c) = cookie.as_ref() {
         if !c.is_empty() {
             headers.insert(
@@ -139,7 +133,7 @@ pub async fn fetch_bilibili_streamer_info(
         }
     }
 
-    let client = &follow_http.0.inner;
+    let client = &follow_http.client(Some("BILIBILI"))?.inner;
 
     // Get WBI keys and build sign
     let (img_key, sub_key) = get_wbi_keys(client, &headers).await?;
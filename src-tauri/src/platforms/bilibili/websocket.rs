@@ -1,18 +1,27 @@
 // src/websocket.rs
-use native_tls::TlsStream;
+use futures_util::{SinkExt, StreamExt};
 use serde_json::Value;
-use std::collections::VecDeque;
-use std::net::TcpStream;
+use std::io::Read;
 use std::sync::OnceLock;
-use std::time::{Duration, Instant};
-use tungstenite::{client, Message, WebSocket};
-use url::Url;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{interval_at, sleep, Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
 
-use super::auth::{init_server_no_cookie, init_server_with_cookie};
+use super::auth::init_server;
+use super::message_parsers::parse_message;
 use super::models::{BiliMessage, DanmuServer, MsgHead};
+use crate::platforms::common::network_proxy;
 
 static DEBUG_FLAG: OnceLock<bool> = OnceLock::new();
 
+const HEADER_LEN: usize = 16;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// 服务端每次心跳都会回包，超过两个周期没有任何数据视为连接已死
+const READ_TIMEOUT: Duration = Duration::from_secs(70);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
 pub fn is_debug_enabled() -> bool {
     *DEBUG_FLAG.get_or_init(|| {
         std::env::var("DMF_DEBUG")
@@ -29,238 +38,225 @@ macro_rules! ws_debug {
     }
 }
 
+/// 客户端向调用方汇报的事件
+pub enum ClientEvent {
+    Message(BiliMessage),
+    /// 认证通过，`host` 为当前连接的弹幕服务器
+    Connected {
+        host: String,
+    },
+    /// 连接失败或断开；`will_retry` 为 false 时客户端已退出
+    Error {
+        message: String,
+        will_retry: bool,
+    },
+    /// 即将在 `delay` 后进行第 `attempt` 次重连
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+}
+
+enum ConnectionOutcome {
+    Stop,
+    Disconnected { authenticated: bool, reason: String },
+}
+
+/// 一个 websocket 帧解出的业务数据包
+enum Packet {
+    AuthReply(i64),
+    Popularity(u32),
+    Business(Value),
+}
+
 pub struct BiliLiveClient {
-    ws: WebSocket<TlsStream<TcpStream>>,
-    auth_msg: String,
+    room_id: String,
+    cookie: Option<String>,
     // Keep server host list for reconnection
-    host_list: Value,
-    // Heartbeat scheduling
-    last_heartbeat: Instant,
-    heartbeat_interval: Duration,
-    // Pending messages parsed from current/previous frames
-    pending: VecDeque<BiliMessage>,
+    servers: Vec<DanmuServer>,
+    auth_msg: Option<String>,
+    next_server: usize,
 }
 
 impl BiliLiveClient {
-    pub fn new_with_cookie(cookies: &str, room_id: &str) -> Self {
-        let (v, auth) = init_server_with_cookie(cookies, room_id);
-        ws_debug!("[websocket] server_info host_list: {:?}", v["host_list"]);
-        let ws = connect(v["host_list"].clone());
-        ws_debug!("[websocket] connected via cookie for room {}", room_id);
+    pub fn new(room_id: &str, cookie: Option<&str>) -> Self {
         BiliLiveClient {
-            ws,
-            auth_msg: serde_json::to_string(&auth).unwrap(),
-            host_list: v["host_list"].clone(),
-            last_heartbeat: Instant::now(),
-            heartbeat_interval: Duration::from_secs(30),
-            pending: VecDeque::new(),
+            room_id: room_id.to_string(),
+            cookie: cookie.map(str::to_string),
+            servers: Vec::new(),
+            auth_msg: None,
+            next_server: 0,
         }
     }
 
-    pub fn new_without_cookie(room_id: &str) -> Self {
-        let (v, auth) = init_server_no_cookie(room_id);
+    // 重新获取服务器列表与 token；token 有时效，整轮服务器都连不上时也会刷新
+    async fn refresh_server_info(&mut self) -> Result<(), String> {
+        let (v, auth) = init_server(self.cookie.as_deref(), &self.room_id).await?;
         ws_debug!("[websocket] server_info host_list: {:?}", v["host_list"]);
-        let ws = connect(v["host_list"].clone());
-        ws_debug!("[websocket] connected without cookie for room {}", room_id);
-        BiliLiveClient {
-            ws,
-            auth_msg: serde_json::to_string(&auth).unwrap(),
-            host_list: v["host_list"].clone(),
-            last_heartbeat: Instant::now(),
-            heartbeat_interval: Duration::from_secs(30),
-            pending: VecDeque::new(),
-        }
-    }
-
-    pub fn send_auth(&mut self) {
-        let pkt = make_packet(self.auth_msg.as_str(), Operation::AUTH);
-        ws_debug!("[websocket] sending auth packet, len={}", pkt.len());
-        let _ = self.ws.send(Message::Binary(pkt));
-    }
-
-    pub fn send_heart_beat(&mut self) {
-        let pkt = make_packet("{}", Operation::HEARTBEAT);
-        ws_debug!("[websocket] sending heartbeat, len={}", pkt.len());
-        let _ = self.ws.send(Message::Binary(pkt));
-        // update heartbeat timestamp
-        self.last_heartbeat = Instant::now();
+        self.servers = gen_damu_list(&v["host_list"]);
+        self.auth_msg = Some(
+            serde_json::to_string(&auth)
+                .map_err(|e| format!("Failed to serialize auth message: {}", e))?,
+        );
+        self.next_server = 0;
+        Ok(())
     }
 
-    // Periodically send heartbeat to keep the connection alive
-    fn maybe_send_heartbeat(&mut self) {
-        if self.last_heartbeat.elapsed() >= self.heartbeat_interval {
-            ws_debug!("[websocket] periodic heartbeat due");
-            self.send_heart_beat();
+    /// 连接并持续接收弹幕，直到 `stop_rx` 收到信号（或发送端全部被丢弃）。
+    /// 断线后按指数退避重连，并依次轮换 `host_list` 中的服务器
+    pub async fn run(
+        &mut self,
+        mut stop_rx: mpsc::Receiver<()>,
+        mut on_event: impl FnMut(ClientEvent),
+    ) {
+        if self.room_id.parse::<u64>().is_err() {
+            on_event(ClientEvent::Error {
+                message: format!("Invalid Bilibili room id: {}", self.room_id),
+                will_retry: false,
+            });
+            return;
         }
-    }
+        let mut backoff = INITIAL_BACKOFF;
+        let mut failures = 0usize;
+        let mut attempt = 0u32;
 
-    // Try to reconnect using the cached host list, and re-authenticate
-    fn reconnect(&mut self) {
-        for attempt in 1..=2 {
-            ws_debug!("[websocket] attempting reconnect (attempt {attempt}/2)...");
-            match std::panic::catch_unwind({
-                let host_list = self.host_list.clone();
-                move || connect(host_list)
-            }) {
-                Ok(new_ws) => {
-                    self.ws = new_ws;
-                    ws_debug!(
-                        "[websocket] reconnect successful on attempt {attempt}, resending auth"
-                    );
-                    self.send_auth();
-                    return;
-                }
-                Err(_) => {
-                    ws_debug!("[websocket] reconnect attempt {attempt} failed");
+        loop {
+            if self.auth_msg.is_none() || failures >= self.servers.len().max(1) {
+                failures = 0;
+                if let Err(e) = self.refresh_server_info().await {
+                    self.auth_msg = None;
+                    on_event(ClientEvent::Error {
+                        message: e,
+                        will_retry: true,
+                    });
                 }
             }
-        }
-        ws_debug!("[websocket] reconnect failed after 2 attempts; will retry on next read cycle");
-    }
 
-    // Parse one frame and collect all messages into pending queue
-    pub fn parse_ws_message(&mut self, resv: Vec<u8>) -> Option<BiliMessage> {
-        ws_debug!("[websocket] parse_ws_message: total_len={}", resv.len());
-        let mut offset = 0;
-        let header = &resv[0..16];
-        let mut head_1 = get_msg_header(header);
-        ws_debug!(
-            "[websocket] header op={} ver={} pack_len={} seq={} hdr_size={}",
-            head_1.operation,
-            head_1.ver,
-            head_1.pack_len,
-            head_1.seq_id,
-            head_1.raw_header_size
-        );
-        if head_1.operation == 5 || head_1.operation == 8 {
-            loop {
-                let body: &[u8] = &resv[offset + 16..offset + (head_1.pack_len as usize)];
-                ws_debug!(
-                    "[websocket] chunk offset={} pack_len={} ver={} op={}",
-                    offset,
-                    head_1.pack_len,
-                    head_1.ver,
-                    head_1.operation
-                );
-                if let Some(msg) = self.parse_business_message(head_1, body) {
-                    // push and continue to collect more messages
-                    self.pending.push_back(msg);
-                }
-                offset += head_1.pack_len as usize;
-                if offset >= resv.len() {
-                    break;
+            if let Some(auth_msg) = self.auth_msg.as_deref() {
+                let server = &self.servers[self.next_server % self.servers.len()];
+                match run_connection(server, auth_msg, &mut stop_rx, &mut on_event).await {
+                    ConnectionOutcome::Stop => break,
+                    ConnectionOutcome::Disconnected {
+                        authenticated,
+                        reason,
+                    } => {
+                        if authenticated {
+                            backoff = INITIAL_BACKOFF;
+                            failures = 0;
+                            attempt = 0;
+                        } else {
+                            failures += 1;
+                        }
+                        on_event(ClientEvent::Error {
+                            message: format!("{}: {}", server.host, reason),
+                            will_retry: true,
+                        });
+                    }
                 }
-                let temp_head = &resv[offset..(offset + 16)];
-                head_1 = get_msg_header(temp_head);
+                self.next_server += 1;
             }
-        } else if head_1.operation == 3 {
-            let mut body: [u8; 4] = [0, 0, 0, 0];
-            body[0] = resv[16];
-            body[1] = resv[17];
-            body[2] = resv[18];
-            body[3] = resv[19];
-            let _popularity = i32::from_be_bytes(body);
-            ws_debug!(
-                "[websocket] popularity message op=3; popularity={}",
-                _popularity
-            );
-        } else {
-            ws_debug!("[websocket] unknown op={}, ignoring", head_1.operation);
-        }
-        None
-    }
 
-    fn parse_business_message(&mut self, h: MsgHead, b: &[u8]) -> Option<BiliMessage> {
-        ws_debug!(
-            "[websocket] parse_business_message op={} ver={} body_len={} ",
-            h.operation,
-            h.ver,
-            b.len()
-        );
-        if h.operation == 5 {
-            if h.ver == 3 {
-                let res: Vec<u8> = match decompress(b) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        ws_debug!("[websocket] decompress error: {:?}", e);
-                        return None;
-                    }
-                };
-                ws_debug!("[websocket] decompressed len={}", res.len());
-                return self.parse_ws_message(res);
-            } else if h.ver == 0 {
-                let s = match String::from_utf8(b.to_vec()) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        ws_debug!("[websocket] utf8 error: {:?}", e);
-                        return None;
-                    }
-                };
-                ws_debug!("[websocket] ver0 business json str len={}", s.len());
-                let res_json: Value = match serde_json::from_str(s.as_str()) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        ws_debug!("[websocket] json parse error: {:?}", e);
-                        return None;
-                    }
-                };
-                ws_debug!(
-                    "[websocket] business cmd={}",
-                    res_json["cmd"].as_str().unwrap_or("<unknown>")
-                );
-                if let Some(m) = handle(res_json) {
-                    // push into queue, but do not return immediately
-                    self.pending.push_back(m);
-                }
-                None
-            } else {
-                ws_debug!("[websocket] unknown compression ver={}, skip", h.ver);
-                None
+            ws_debug!("[websocket] reconnecting in {:?}", backoff);
+            attempt += 1;
+            on_event(ClientEvent::Reconnecting {
+                attempt,
+                delay: backoff,
+            });
+            tokio::select! {
+                _ = sleep(backoff) => {}
+                _ = stop_rx.recv() => break,
             }
-        } else if h.operation == 8 {
-            ws_debug!("[websocket] op=8 (auth reply), sending heartbeat");
-            self.send_heart_beat();
-            None
-        } else {
-            ws_debug!("[websocket] unsupported business op={}, skip", h.operation);
-            None
+            backoff = (backoff * 2).min(MAX_BACKOFF);
         }
+        ws_debug!("[websocket] client for room {} stopped", self.room_id);
     }
+}
 
-    pub fn read_once(&mut self) -> Option<BiliMessage> {
-        // If we already have pending messages, deliver one immediately
-        if let Some(m) = self.pending.pop_front() {
-            return Some(m);
-        }
+async fn run_connection(
+    server: &DanmuServer,
+    auth_msg: &str,
+    stop_rx: &mut mpsc::Receiver<()>,
+    on_event: &mut impl FnMut(ClientEvent),
+) -> ConnectionOutcome {
+    let disconnected = |authenticated: bool, reason: String| ConnectionOutcome::Disconnected {
+        authenticated,
+        reason,
+    };
+    let ws_url = format!("wss://{}:{}/sub", server.host, server.wss_port);
+    ws_debug!("[websocket] connecting {}", ws_url);
+    let connect = network_proxy::connect_websocket(ws_url.as_str(), Some("BILIBILI"));
+    let ws = tokio::select! {
+        res = connect => match res {
+            Ok((ws, _)) => ws,
+            Err(e) => return disconnected(false, format!("connect failed: {}", e)),
+        },
+        _ = stop_rx.recv() => return ConnectionOutcome::Stop,
+    };
+    let (mut write, mut read) = ws.split();
+    if let Err(e) = write
+        .send(Message::Binary(make_packet(auth_msg, Operation::AUTH)))
+        .await
+    {
+        return disconnected(false, format!("send auth failed: {}", e));
+    }
 
-        // ensure heartbeat keeps alive
-        self.maybe_send_heartbeat();
+    let mut authenticated = false;
+    let mut last_recv = Instant::now();
+    let mut heartbeat = interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
-        let readable = self.ws.can_read();
-        ws_debug!("[websocket] can_read={} ", readable);
-        if self.ws.can_read() {
-            let msg = self.ws.read();
-            match msg {
-                Ok(m) => {
-                    let res = m.into_data();
-                    ws_debug!("[websocket] read frame bytes={} ", res.len());
-                    if res.len() >= 16 {
-                        // parse and fill pending queue
-                        let _ = self.parse_ws_message(res);
-                        // return one
-                        return self.pending.pop_front();
-                    } else {
-                        ws_debug!("[websocket] frame too short (<16), ignore");
+    loop {
+        tokio::select! {
+            _ = stop_rx.recv() => {
+                let _ = write.close().await;
+                return ConnectionOutcome::Stop;
+            }
+            _ = heartbeat.tick() => {
+                if last_recv.elapsed() >= READ_TIMEOUT {
+                    return disconnected(authenticated, "read timed out".to_string());
+                }
+                ws_debug!("[websocket] sending heartbeat");
+                if let Err(e) = write.send(Message::Binary(make_packet("{}", Operation::HEARTBEAT))).await {
+                    return disconnected(authenticated, format!("send heartbeat failed: {}", e));
+                }
+            }
+            msg = read.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    last_recv = Instant::now();
+                    let mut packets = Vec::new();
+                    decode_packets(&data, &mut packets);
+                    for packet in packets {
+                        match packet {
+                            Packet::AuthReply(0) => {
+                                ws_debug!("[websocket] auth ok on {}", server.host);
+                                authenticated = true;
+                                on_event(ClientEvent::Connected { host: server.host.clone() });
+                                if let Err(e) = write.send(Message::Binary(make_packet("{}", Operation::HEARTBEAT))).await {
+                                    return disconnected(authenticated, format!("send heartbeat failed: {}", e));
+                                }
+                            }
+                            Packet::AuthReply(code) => {
+                                return disconnected(false, format!("auth rejected, code={}", code));
+                            }
+                            Packet::Popularity(_popularity) => {
+                                ws_debug!("[websocket] popularity={}", _popularity);
+                            }
+                            Packet::Business(json) => {
+                                on_event(ClientEvent::Message(parse_message(&json)));
+                            }
+                        }
                     }
                 }
-                Err(e) => {
-                    ws_debug!("[websocket] read error: {:?}", e);
-                    // try to reconnect on read error
-                    self.reconnect();
+                Some(Ok(Message::Close(frame))) => {
+                    return disconnected(authenticated, format!("closed by server: {:?}", frame));
                 }
+                Some(Ok(_)) => {
+                    last_recv = Instant::now();
+                }
+                Some(Err(e)) => return disconnected(authenticated, format!("read error: {}", e)),
+                None => return disconnected(authenticated, "connection closed".to_string()),
             }
         }
-        None
     }
 }
 
@@ -268,158 +264,134 @@ pub fn gen_damu_list(list: &serde_json::Value) -> Vec<DanmuServer> {
     let mut res: Vec<DanmuServer> = Vec::new();
     if let Some(server_list) = list.as_array() {
         ws_debug!("[websocket] host_list size={}", server_list.len());
-        if server_list.is_empty() {
-            ws_debug!("[websocket] host_list empty, using default server");
-            res.push(DanmuServer::default());
-        } else {
-            for s in server_list {
-                let host = s["host"]
-                    .as_str()
-                    .unwrap_or("broadcastlv.chat.bilibili.com");
-                let port = s["port"].as_i64().unwrap_or(2243) as i32;
-                let wss_port = s["wss_port"].as_i64().unwrap_or(443) as i32;
-                let ws_port = s["ws_port"].as_i64().unwrap_or(2244) as i32;
-                ws_debug!(
-                    "[websocket] server {}:{} (wss_port={}, ws_port={})",
-                    host,
-                    port,
-                    wss_port,
-                    ws_port
-                );
-                res.push(DanmuServer {
-                    host: host.to_string(),
-                    port,
-                    wss_port,
-                    ws_port,
-                });
-            }
+        for s in server_list {
+            let host = s["host"]
+                .as_str()
+                .unwrap_or("broadcastlv.chat.bilibili.com");
+            let port = s["port"].as_i64().unwrap_or(2243) as i32;
+            let wss_port = s["wss_port"].as_i64().unwrap_or(443) as i32;
+            let ws_port = s["ws_port"].as_i64().unwrap_or(2244) as i32;
+            ws_debug!(
+                "[websocket] server {}:{} (wss_port={}, ws_port={})",
+                host,
+                port,
+                wss_port,
+                ws_port
+            );
+            res.push(DanmuServer {
+                host: host.to_string(),
+                port,
+                wss_port,
+                ws_port,
+            });
         }
-    } else {
-        ws_debug!("[websocket] host_list not an array, using default server");
+    }
+    if res.is_empty() {
+        ws_debug!("[websocket] host_list empty or invalid, using default server");
         res.push(DanmuServer::default());
     }
     res
 }
 
-fn find_server(vd: Vec<DanmuServer>) -> (String, String, String) {
-    let (host, wss_port) = (vd.get(0).unwrap().host.clone(), vd.get(0).unwrap().wss_port);
-    ws_debug!(
-        "[websocket] choose server host={} wss_port={}",
-        host,
-        wss_port
-    );
-    (
-        host.clone(),
-        format!("{}:{}", host.clone(), wss_port),
-        format!("wss://{}:{}/sub", host, wss_port),
-    )
-}
-
-pub fn connect(v: Value) -> WebSocket<TlsStream<TcpStream>> {
-    let danmu_server = gen_damu_list(&v);
-    let (host, url, ws_url) = find_server(danmu_server);
-    ws_debug!("[websocket] connecting tcp {} and ws {}", url, ws_url);
-    let connector: native_tls::TlsConnector = native_tls::TlsConnector::new().unwrap();
-    let stream: TcpStream = TcpStream::connect(url).unwrap();
-    let stream: native_tls::TlsStream<TcpStream> =
-        connector.connect(host.as_str(), stream).unwrap();
-    let (socket, _resp) =
-        client(Url::parse(ws_url.as_str()).unwrap(), stream).expect("Can't connect");
-    ws_debug!("[websocket] websocket handshake complete");
-    socket
-}
-
 pub enum Operation {
     AUTH,
     HEARTBEAT,
 }
 
 pub fn make_packet(body: &str, ops: Operation) -> Vec<u8> {
-    let json: Value = serde_json::from_str(body).unwrap();
-    let temp = json.to_string();
-    let body_content: &[u8] = temp.as_bytes();
-    let pack_len: [u8; 4] = ((16 + body.len()) as u32).to_be_bytes();
-    let raw_header_size: [u8; 2] = (16 as u16).to_be_bytes();
-    let ver: [u8; 2] = (1 as u16).to_be_bytes();
+    let body_content: &[u8] = body.as_bytes();
+    let pack_len: [u8; 4] = ((HEADER_LEN + body_content.len()) as u32).to_be_bytes();
+    let raw_header_size: [u8; 2] = (HEADER_LEN as u16).to_be_bytes();
+    let ver: [u8; 2] = 1u16.to_be_bytes();
     let operation: [u8; 4] = match ops {
-        Operation::AUTH => (7 as u32).to_be_bytes(),
-        Operation::HEARTBEAT => (2 as u32).to_be_bytes(),
+        Operation::AUTH => 7u32.to_be_bytes(),
+        Operation::HEARTBEAT => 2u32.to_be_bytes(),
     };
-    let seq_id: [u8; 4] = (1 as u32).to_be_bytes();
+    let seq_id: [u8; 4] = 1u32.to_be_bytes();
     let mut res = pack_len.to_vec();
-    res.append(&mut raw_header_size.to_vec());
-    res.append(&mut ver.to_vec());
-    res.append(&mut operation.to_vec());
-    res.append(&mut seq_id.to_vec());
-    res.append(&mut body_content.to_vec());
+    res.extend_from_slice(&raw_header_size);
+    res.extend_from_slice(&ver);
+    res.extend_from_slice(&operation);
+    res.extend_from_slice(&seq_id);
+    res.extend_from_slice(body_content);
     res
 }
 
 pub fn get_msg_header(v_s: &[u8]) -> MsgHead {
-    let mut pack_len: [u8; 4] = [0; 4];
-    let mut raw_header_size: [u8; 2] = [0; 2];
-    let mut ver: [u8; 2] = [0; 2];
-    let mut operation: [u8; 4] = [0; 4];
-    let mut seq_id: [u8; 4] = [0; 4];
-    for (i, v) in v_s.iter().enumerate() {
-        if i < 4 {
-            pack_len[i] = *v;
-            continue;
-        }
-        if i < 6 {
-            raw_header_size[i - 4] = *v;
-            continue;
-        }
-        if i < 8 {
-            ver[i - 6] = *v;
-            continue;
-        }
-        if i < 12 {
-            operation[i - 8] = *v;
-            continue;
+    MsgHead {
+        pack_len: u32::from_be_bytes([v_s[0], v_s[1], v_s[2], v_s[3]]),
+        raw_header_size: u16::from_be_bytes([v_s[4], v_s[5]]),
+        ver: u16::from_be_bytes([v_s[6], v_s[7]]),
+        operation: u32::from_be_bytes([v_s[8], v_s[9], v_s[10], v_s[11]]),
+        seq_id: u32::from_be_bytes([v_s[12], v_s[13], v_s[14], v_s[15]]),
+    }
+}
+
+// 一个帧里可能连续拼接多个包；压缩包（protover 2 zlib / 3 brotli）解压后递归拆分
+fn decode_packets(data: &[u8], out: &mut Vec<Packet>) {
+    let mut offset = 0;
+    while offset + HEADER_LEN <= data.len() {
+        let head = get_msg_header(&data[offset..offset + HEADER_LEN]);
+        let pack_len = head.pack_len as usize;
+        let header_len = (head.raw_header_size as usize).max(HEADER_LEN);
+        if pack_len < header_len || offset + pack_len > data.len() {
+            ws_debug!(
+                "[websocket] malformed packet at offset={} pack_len={} frame_len={}",
+                offset,
+                pack_len,
+                data.len()
+            );
+            return;
         }
-        if i < 16 {
-            seq_id[i - 12] = *v;
-            continue;
+        let body = &data[offset + header_len..offset + pack_len];
+        ws_debug!(
+            "[websocket] packet op={} ver={} pack_len={} seq={}",
+            head.operation,
+            head.ver,
+            head.pack_len,
+            head.seq_id
+        );
+        match (head.operation, head.ver) {
+            (5, 2) => match decompress_zlib(body) {
+                Ok(inner) => decode_packets(&inner, out),
+                Err(e) => ws_debug!("[websocket] zlib decompress error: {:?}", e),
+            },
+            (5, 3) => match decompress(body) {
+                Ok(inner) => decode_packets(&inner, out),
+                Err(e) => ws_debug!("[websocket] brotli decompress error: {:?}", e),
+            },
+            (5, _) => match serde_json::from_slice::<Value>(body) {
+                Ok(json) => out.push(Packet::Business(json)),
+                Err(e) => ws_debug!("[websocket] json parse error: {:?}", e),
+            },
+            (8, _) => {
+                let code = serde_json::from_slice::<Value>(body)
+                    .ok()
+                    .and_then(|v| v["code"].as_i64())
+                    .unwrap_or(0);
+                out.push(Packet::AuthReply(code));
+            }
+            (3, _) if body.len() >= 4 => {
+                out.push(Packet::Popularity(u32::from_be_bytes([
+                    body[0], body[1], body[2], body[3],
+                ])));
+            }
+            (op, _) => ws_debug!("[websocket] unknown op={}, ignoring", op),
         }
-    }
-    MsgHead {
-        pack_len: u32::from_be_bytes(pack_len),
-        raw_header_size: u16::from_be_bytes(raw_header_size),
-        ver: u16::from_be_bytes(ver),
-        operation: u32::from_be_bytes(operation),
-        seq_id: u32::from_be_bytes(seq_id),
+        offset += pack_len;
     }
 }
 
 pub fn decompress(body: &[u8]) -> std::io::Result<Vec<u8>> {
     use brotlic::DecompressorReader;
-    use std::io::Read;
     let mut decompressed_reader: DecompressorReader<&[u8]> = DecompressorReader::new(body);
     let mut decoded_input = Vec::new();
     let _ = decompressed_reader.read_to_end(&mut decoded_input)?;
     Ok(decoded_input)
 }
 
-pub fn handle(json: Value) -> Option<BiliMessage> {
-    let category = json["cmd"].as_str().unwrap_or("");
-    match category {
-        "DANMU_MSG" => Some(BiliMessage::Danmu {
-            user: json["info"][2][1]
-                .as_str()
-                .unwrap_or("<unknown>")
-                .to_string(),
-            text: json["info"][1].as_str().unwrap_or("").to_string(),
-        }),
-        "SEND_GIFT" => Some(BiliMessage::Gift {
-            user: json["info"][2][1]
-                .as_str()
-                .unwrap_or("<unknown>")
-                .to_string(),
-            gift: json["info"][1].as_str().unwrap_or("").to_string(),
-        }),
-        _ => Some(BiliMessage::Unsupported {
-            cmd: category.to_string(),
-        }),
-    }
+fn decompress_zlib(body: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded)?;
+    Ok(decoded)
 }
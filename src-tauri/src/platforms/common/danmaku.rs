@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{Emitter, Runtime};
+
+/// 四个平台的弹幕统一通过该事件发往前端
+pub const DANMAKU_EVENT: &str = "danmaku-event";
+/// 弹幕连接出错（断线、鉴权失败等）时发出，不再 panic 或静默失败
+pub const DANMAKU_ERROR_EVENT: &str = "danmaku-error";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, specta::Type)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DanmakuPlatform {
+    Douyu,
+    Douyin,
+    Huya,
+    Bilibili,
+}
+
+impl DanmakuPlatform {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DanmakuPlatform::Douyu => "DOUYU",
+            DanmakuPlatform::Douyin => "DOUYIN",
+            DanmakuPlatform::Huya => "HUYA",
+            DanmakuPlatform::Bilibili => "BILIBILI",
+        }
+    }
+}
+
+/// 粉丝牌 / 徽章
+#[derive(Serialize, Deserialize, Clone, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuMedal {
+    pub name: String,
+    pub level: u32,
+    /// 粉丝牌所属主播，B 站等平台会给出
+    pub anchor_name: Option<String>,
+    pub anchor_room_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuUser {
+    pub id: Option<String>,
+    pub nickname: String,
+    /// 平台的用户 / 财富等级，0 表示未知
+    pub level: u32,
+    pub medal: Option<DanmakuMedal>,
+    /// 贵族身份（虎牙贵族等），没有时为 null
+    pub noble: Option<DanmakuNoble>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuNoble {
+    pub level: u32,
+    pub name: String,
+}
+
+impl DanmakuUser {
+    pub fn named(nickname: impl Into<String>) -> Self {
+        Self {
+            nickname: nickname.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(mut self, id: impl ToString) -> Self {
+        let id = id.to_string();
+        if !id.is_empty() && id != "0" {
+            self.id = Some(id);
+        }
+        self
+    }
+
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn medal_info(mut self, medal: Option<DanmakuMedal>) -> Self {
+        self.medal = medal.filter(|m| !m.name.is_empty() && m.level > 0);
+        self
+    }
+
+    /// 等级为 0 时视为没有贵族身份
+    pub fn noble(mut self, level: u32, name: impl Into<String>) -> Self {
+        if level > 0 {
+            self.noble = Some(DanmakuNoble {
+                level,
+                name: name.into(),
+            });
+        }
+        self
+    }
+
+    /// 名称为空或等级为 0 时视为未佩戴
+    pub fn medal(mut self, name: impl Into<String>, level: u32) -> Self {
+        let name = name.into();
+        if !name.is_empty() && level > 0 {
+            self.medal = Some(DanmakuMedal {
+                name,
+                level,
+                ..Default::default()
+            });
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, specta::Type)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DanmakuEventKind {
+    Chat {
+        content: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Gift {
+        gift_id: Option<String>,
+        gift_name: String,
+        count: u32,
+        /// 折合人民币的总价值，平台未给出时为空
+        value_cny: Option<f64>,
+        /// 同一次连击的礼物共用一个 id
+        combo_id: Option<String>,
+        /// 连击累计数量；连击结束的汇总消息 count 为 0，只带累计值
+        combo_total: Option<u32>,
+    },
+    Enter,
+    Follow,
+    Like {
+        count: u32,
+    },
+    /// B 站醒目留言、斗鱼超级弹幕等付费消息
+    #[serde(rename_all = "camelCase")]
+    SuperChat {
+        content: String,
+        value_cny: f64,
+        /// 置顶展示时长
+        duration_seconds: Option<u32>,
+    },
+    /// 舰长 / 贵族 / 会员等开通与续费
+    #[serde(rename_all = "camelCase")]
+    Guard {
+        level: u32,
+        level_name: String,
+        /// 购买时长；平台消息里没有时长（例如斗鱼贵族）时为 None
+        months: Option<u32>,
+    },
+    RoomStats {
+        online: Option<u32>,
+        watched: Option<u32>,
+        likes: Option<u32>,
+        /// 平台给出的展示文案，例如 "1.2万在线"
+        display: Option<String>,
+    },
+    LiveStatus {
+        live: bool,
+        reason: Option<String>,
+    },
+    /// 直播间标题、分区变更
+    RoomInfo {
+        title: Option<String>,
+        area: Option<String>,
+    },
+    /// 平台的房间通知、超管警告等
+    Notice {
+        message: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuEvent {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    /// 毫秒时间戳
+    pub timestamp: i64,
+    /// 房间统计、开关播等消息没有用户
+    pub user: Option<DanmakuUser>,
+    /// `#RRGGBB`
+    pub color: Option<String>,
+    #[serde(flatten)]
+    pub kind: DanmakuEventKind,
+}
+
+impl DanmakuEvent {
+    pub fn new(
+        platform: DanmakuPlatform,
+        room_id: impl Into<String>,
+        kind: DanmakuEventKind,
+    ) -> Self {
+        Self {
+            platform,
+            room_id: room_id.into(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            user: None,
+            color: None,
+            kind,
+        }
+    }
+
+    pub fn chat(
+        platform: DanmakuPlatform,
+        room_id: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            platform,
+            room_id,
+            DanmakuEventKind::Chat {
+                content: content.into(),
+            },
+        )
+    }
+
+    pub fn user(mut self, user: DanmakuUser) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn color(mut self, color: Option<String>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// 平台给出的时间戳（毫秒）可用时替换接收时间
+    pub fn timestamp(mut self, timestamp_ms: i64) -> Self {
+        if timestamp_ms > 0 {
+            self.timestamp = timestamp_ms;
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuErrorPayload {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    pub message: String,
+    /// false 表示监听已退出，需要重新启动
+    pub will_retry: bool,
+}
+
+/// 把 0xRRGGBB 数值转成 `#rrggbb`；白色视为默认颜色返回空
+pub fn rgb_color(value: i64) -> Option<String> {
+    if value <= 0 || value >= 0xFFFFFF {
+        return None;
+    }
+    Some(format!("#{:06x}", value))
+}
+
+pub fn emit_danmaku<R: Runtime>(emitter: &impl Emitter<R>, event: &DanmakuEvent) {
+    if let Err(e) = emitter.emit(DANMAKU_EVENT, event) {
+        eprintln!(
+            "[Danmaku {} {}] Failed to emit event: {}",
+            event.platform.as_str(),
+            event.room_id,
+            e
+        );
+    }
+}
+
+pub fn emit_danmaku_error<R: Runtime>(
+    emitter: &impl Emitter<R>,
+    platform: DanmakuPlatform,
+    room_id: &str,
+    message: String,
+    will_retry: bool,
+) {
+    eprintln!(
+        "[Danmaku {} {}] {}{}",
+        platform.as_str(),
+        room_id,
+        message,
+        if will_retry { " (will retry)" } else { "" }
+    );
+    let payload = DanmakuErrorPayload {
+        platform,
+        room_id: room_id.to_string(),
+        message,
+        will_retry,
+    };
+    let _ = emitter.emit(DANMAKU_ERROR_EVENT, payload);
+}
+
+/// 导出前端使用的 TypeScript 类型定义
+pub fn export_typescript_bindings(path: &Path) -> Result<(), String> {
+    let mut types = specta::TypeCollection::default();
+    types
+        .register::<DanmakuEvent>()
+        .register::<DanmakuErrorPayload>()
+        .register::<super::danmaku_listener::DanmakuStatePayload>()
+        .register::<super::danmaku_listener::DanmakuListenerInfo>()
+        .register::<super::danmaku_filter::DanmakuFilterSettings>()
+        .register::<super::danmaku_archive::DanmakuArchiveInfo>()
+        .register::<super::danmaku_archive::DanmakuArchiveSettings>()
+        .register::<super::danmaku_export::DanmakuExportFormat>()
+        .register::<super::danmaku_export::DanmakuExportOptions>()
+        .register::<super::danmaku_export::DanmakuExportResult>()
+        .register::<super::danmaku_replay::DanmakuReplayInfo>()
+        .register::<super::danmaku_replay::DanmakuReplayPayload>()
+        .register::<super::danmaku_replay::DanmakuReplayState>();
+    specta_typescript::Typescript::default()
+        .bigint(specta_typescript::BigIntExportBehavior::Number)
+        .export_to(path, &types)
+        .map_err(|e| format!("Failed to export danmaku bindings: {}", e))
+}
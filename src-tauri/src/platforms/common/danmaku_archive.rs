@@ -0,0 +1,707 @@
+// 弹幕存档：每个房间的每次连接写一个 gzip 压缩的 JSONL 文件
+//
+// 目录结构为 {数据目录}/DTV/danmaku-archive/{平台}/{房间号}/{会话}.jsonl.gz，
+// 会话结束时在旁边写 {会话}.meta.json。gzip 定期 sync flush，进程异常退出时已写入的部分仍可读取。
+//
+// 存档可以关闭；开启时按保留天数和总大小上限清理最旧的会话，在会话结束和修改设置时执行。
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::danmaku::{DanmakuEvent, DanmakuPlatform};
+
+const ARCHIVE_DIR_NAME: &str = "danmaku-archive";
+const ARCHIVE_EXT: &str = ".jsonl.gz";
+const META_EXT: &str = ".meta.json";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const SESSION_ID_FORMAT: &str = "%Y%m%d-%H%M%S";
+const SETTINGS_FILE_NAME: &str = "danmaku-archive.json";
+const DEFAULT_MAX_AGE_DAYS: u32 = 30;
+const DEFAULT_MAX_TOTAL_MB: u64 = 2048;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+const PLATFORMS: [DanmakuPlatform; 4] = [
+    DanmakuPlatform::Douyu,
+    DanmakuPlatform::Douyin,
+    DanmakuPlatform::Huya,
+    DanmakuPlatform::Bilibili,
+];
+
+// 正在写入的存档，不允许删除
+static ACTIVE_SESSIONS: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+static SETTINGS: Lazy<RwLock<DanmakuArchiveSettings>> = Lazy::new(|| RwLock::new(load_settings()));
+// 多个会话同时结束时清理串行进行
+static PRUNE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 存档开关与保留策略；上限为空表示不限制
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DanmakuArchiveSettings {
+    /// 关闭后新开始的监听不再写存档，已有存档保留
+    pub enabled: bool,
+    /// 超过天数的会话被删除
+    pub max_age_days: Option<u32>,
+    /// 所有存档的总大小上限（MB），超出时从最旧的会话开始删除
+    pub max_total_mb: Option<u64>,
+}
+
+impl Default for DanmakuArchiveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: Some(DEFAULT_MAX_AGE_DAYS),
+            max_total_mb: Some(DEFAULT_MAX_TOTAL_MB),
+        }
+    }
+}
+
+impl DanmakuArchiveSettings {
+    // 0 视为不限制，避免误设后清空全部存档
+    fn normalized(mut self) -> Self {
+        self.max_age_days = self.max_age_days.filter(|days| *days > 0);
+        self.max_total_mb = self.max_total_mb.filter(|mb| *mb > 0);
+        self
+    }
+}
+
+/// 存档中的一行；offset_ms 为相对会话开始的接收时间，回放按它还原节奏
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRecord {
+    pub offset_ms: u64,
+    pub event: DanmakuEvent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveMeta {
+    room_id: String,
+    started_at: i64,
+    duration_ms: u64,
+    event_count: u64,
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuArchiveInfo {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    pub session_id: String,
+    /// 毫秒时间戳
+    pub started_at: i64,
+    /// 会话未正常结束（或仍在写入）时为空
+    pub duration_ms: Option<u64>,
+    pub event_count: Option<u64>,
+    pub size_bytes: u64,
+    /// 正在写入
+    pub active: bool,
+}
+
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(SETTINGS_FILE_NAME)
+}
+
+fn load_settings() -> DanmakuArchiveSettings {
+    let path = settings_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return DanmakuArchiveSettings::default();
+    };
+    match serde_json::from_str::<DanmakuArchiveSettings>(&raw) {
+        Ok(settings) => settings.normalized(),
+        Err(e) => {
+            eprintln!(
+                "[DanmakuArchive] Ignoring invalid settings in {}: {}",
+                path.display(),
+                e
+            );
+            DanmakuArchiveSettings::default()
+        }
+    }
+}
+
+fn save_settings(settings: &DanmakuArchiveSettings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize danmaku archive settings: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn archive_root() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(ARCHIVE_DIR_NAME)
+}
+
+// 房间号、会话号会拼进路径，只保留安全字符
+pub(crate) fn sanitize_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn room_dir(platform: DanmakuPlatform, room_id: &str) -> PathBuf {
+    archive_root()
+        .join(platform.as_str())
+        .join(sanitize_component(room_id))
+}
+
+fn session_paths(
+    platform: DanmakuPlatform,
+    room_id: &str,
+    session_id: &str,
+) -> Result<(PathBuf, PathBuf), String> {
+    if session_id.is_empty() || sanitize_component(session_id) != session_id {
+        return Err(format!("Invalid archive session id: {}", session_id));
+    }
+    let dir = room_dir(platform, room_id);
+    Ok((
+        dir.join(format!("{}{}", session_id, ARCHIVE_EXT)),
+        dir.join(format!("{}{}", session_id, META_EXT)),
+    ))
+}
+
+/// 单个监听会话的存档写入器；第一条消息到达时才创建文件
+pub struct DanmakuArchiveWriter {
+    platform: DanmakuPlatform,
+    room_id: String,
+    started: Instant,
+    started_at: chrono::DateTime<chrono::Local>,
+    session_id: Option<String>,
+    encoder: Option<GzEncoder<BufWriter<File>>>,
+    event_count: u64,
+    last_flush: Instant,
+    // 打开或写入失败后不再重试，避免每条消息都刷错误日志
+    failed: bool,
+}
+
+impl DanmakuArchiveWriter {
+    pub fn new(platform: DanmakuPlatform, room_id: &str) -> Self {
+        let now = Instant::now();
+        Self {
+            platform,
+            room_id: room_id.to_string(),
+            started: now,
+            started_at: chrono::Local::now(),
+            session_id: None,
+            encoder: None,
+            event_count: 0,
+            last_flush: now,
+            failed: false,
+        }
+    }
+
+    fn open(&mut self) -> Result<(), String> {
+        let dir = room_dir(self.platform, &self.room_id);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        // 同一秒内重连时加序号区分
+        let base = self.started_at.format(SESSION_ID_FORMAT).to_string();
+        let mut session_id = base.clone();
+        let mut seq = 1;
+        while dir.join(format!("{}{}", session_id, ARCHIVE_EXT)).exists() {
+            seq += 1;
+            session_id = format!("{}_{}", base, seq);
+        }
+        let path = dir.join(format!("{}{}", session_id, ARCHIVE_EXT));
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        ACTIVE_SESSIONS.lock().unwrap().insert(path);
+        self.encoder = Some(GzEncoder::new(BufWriter::new(file), Compression::default()));
+        self.session_id = Some(session_id);
+        Ok(())
+    }
+
+    /// `received` 为消息到达时间，写入可能晚于接收
+    pub fn write(&mut self, received: Instant, event: &DanmakuEvent) {
+        if self.failed {
+            return;
+        }
+        if self.encoder.is_none() {
+            if let Err(e) = self.open() {
+                eprintln!("[DanmakuArchive] {}", e);
+                self.failed = true;
+                return;
+            }
+        }
+        let record = ArchiveRecord {
+            offset_ms: received.saturating_duration_since(self.started).as_millis() as u64,
+            event: event.clone(),
+        };
+        let Some(encoder) = self.encoder.as_mut() else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *encoder, &record)
+            .map_err(|e| e.to_string())
+            .and_then(|_| encoder.write_all(b"\n").map_err(|e| e.to_string()))
+            .and_then(|_| {
+                if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+                    self.last_flush = Instant::now();
+                    encoder.flush().map_err(|e| e.to_string())
+                } else {
+                    Ok(())
+                }
+            });
+        match result {
+            Ok(()) => self.event_count += 1,
+            Err(e) => {
+                eprintln!(
+                    "[DanmakuArchive] Failed to write {} {}: {}",
+                    self.platform.as_str(),
+                    self.room_id,
+                    e
+                );
+                self.failed = true;
+            }
+        }
+    }
+
+    /// 写入 gzip 结尾和会话信息；可重复调用
+    pub fn finish(&mut self) {
+        let (Some(encoder), Some(session_id)) = (self.encoder.take(), self.session_id.as_deref())
+        else {
+            return;
+        };
+        let Ok((path, meta_path)) = session_paths(self.platform, &self.room_id, session_id) else {
+            return;
+        };
+        let finished = encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| e.to_string());
+        ACTIVE_SESSIONS.lock().unwrap().remove(&path);
+        if let Err(e) = finished {
+            eprintln!(
+                "[DanmakuArchive] Failed to finish {}: {}",
+                path.display(),
+                e
+            );
+            return;
+        }
+        let meta = ArchiveMeta {
+            room_id: self.room_id.clone(),
+            started_at: self.started_at.timestamp_millis(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            event_count: self.event_count,
+        };
+        let written = serde_json::to_string_pretty(&meta)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| std::fs::write(&meta_path, raw).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!(
+                "[DanmakuArchive] Failed to write {}: {}",
+                meta_path.display(),
+                e
+            );
+        }
+    }
+}
+
+enum ArchiveCommand {
+    Event(Instant, Box<DanmakuEvent>),
+    Finish,
+}
+
+/// 存档写入线程的句柄：监听任务只把消息送进通道，gzip 压缩和文件 I/O 都在专门的写入线程里完成
+#[derive(Clone)]
+pub struct DanmakuArchive {
+    tx: mpsc::UnboundedSender<ArchiveCommand>,
+    writer: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl DanmakuArchive {
+    /// 存档已关闭时返回 None；设置只在监听开始时读取
+    pub fn spawn(platform: DanmakuPlatform, room_id: &str) -> Option<Self> {
+        if !SETTINGS.read().unwrap().enabled {
+            return None;
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut writer = DanmakuArchiveWriter::new(platform, room_id);
+        let handle = tokio::task::spawn_blocking(move || {
+            while let Some(ArchiveCommand::Event(received, event)) = rx.blocking_recv() {
+                writer.write(received, &event);
+            }
+            writer.finish();
+            if writer.session_id.is_some() {
+                prune_archives();
+            }
+        });
+        Some(Self {
+            tx,
+            writer: Arc::new(Mutex::new(Some(handle))),
+        })
+    }
+
+    pub fn write(&self, event: &DanmakuEvent) {
+        let _ = self.tx.send(ArchiveCommand::Event(
+            Instant::now(),
+            Box::new(event.clone()),
+        ));
+    }
+
+    /// 写完已排队的消息后写入 gzip 结尾和会话信息，等待写入线程退出；可重复调用
+    pub async fn close(&self) {
+        let _ = self.tx.send(ArchiveCommand::Finish);
+        let handle = self.writer.lock().unwrap().take();
+        if let Some(handle) = handle {
+            if let Err(e) = handle.await {
+                eprintln!("[DanmakuArchive] Writer task failed: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for DanmakuArchiveWriter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// 读取一个会话；文件末尾不完整（异常退出）时返回已读到的部分
+pub fn read_session(
+    platform: DanmakuPlatform,
+    room_id: &str,
+    session_id: &str,
+) -> Result<Vec<ArchiveRecord>, String> {
+    let (path, _) = session_paths(platform, room_id, session_id)?;
+    let file =
+        File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let reader = BufReader::new(GzDecoder::new(BufReader::new(file)));
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(record) = serde_json::from_str::<ArchiveRecord>(&line) {
+            records.push(record);
+        }
+    }
+    records.sort_by_key(|r| r.offset_ms);
+    Ok(records)
+}
+
+fn started_at_from_id(session_id: &str) -> Option<i64> {
+    let stamp = session_id.get(..15)?;
+    let naive = chrono::NaiveDateTime::parse_from_str(stamp, SESSION_ID_FORMAT).ok()?;
+    naive
+        .and_local_timezone(chrono::Local)
+        .single()
+        .map(|t| t.timestamp_millis())
+}
+
+fn list_room(platform: DanmakuPlatform, dir: &Path, out: &mut Vec<DanmakuArchiveInfo>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let dir_name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let active = ACTIVE_SESSIONS.lock().unwrap().clone();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(session_id) = name.strip_suffix(ARCHIVE_EXT) else {
+            continue;
+        };
+        let meta = std::fs::read_to_string(dir.join(format!("{}{}", session_id, META_EXT)))
+            .ok()
+            .and_then(|raw| serde_json::from_str::<ArchiveMeta>(&raw).ok());
+        let Some(started_at) = meta
+            .as_ref()
+            .map(|m| m.started_at)
+            .or_else(|| started_at_from_id(session_id))
+        else {
+            continue;
+        };
+        out.push(DanmakuArchiveInfo {
+            platform,
+            room_id: meta
+                .as_ref()
+                .map(|m| m.room_id.clone())
+                .unwrap_or_else(|| dir_name.clone()),
+            session_id: session_id.to_string(),
+            started_at,
+            duration_ms: meta.as_ref().map(|m| m.duration_ms),
+            event_count: meta.as_ref().map(|m| m.event_count),
+            size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            active: active.contains(&path),
+        });
+    }
+}
+
+fn list_archives(
+    platform: Option<DanmakuPlatform>,
+    room_id: Option<&str>,
+) -> Vec<DanmakuArchiveInfo> {
+    let mut archives = Vec::new();
+    for p in PLATFORMS
+        .into_iter()
+        .filter(|p| platform.is_none_or(|want| want == *p))
+    {
+        match room_id {
+            Some(room_id) => list_room(p, &room_dir(p, room_id), &mut archives),
+            None => {
+                let Ok(rooms) = std::fs::read_dir(archive_root().join(p.as_str())) else {
+                    continue;
+                };
+                for room in rooms.flatten().filter(|e| e.path().is_dir()) {
+                    list_room(p, &room.path(), &mut archives);
+                }
+            }
+        }
+    }
+    archives.sort_by_key(|a| std::cmp::Reverse(a.started_at));
+    archives
+}
+
+/// 按保留策略挑出要删除的会话；`archives` 须按开始时间倒序。
+/// 从最新的会话开始累计大小，超出上限后更旧的会话全部删除；正在写入的会话计入大小但不删除
+fn expired_sessions(
+    archives: &[DanmakuArchiveInfo],
+    settings: &DanmakuArchiveSettings,
+    now_ms: i64,
+) -> Vec<usize> {
+    let max_bytes = settings
+        .max_total_mb
+        .map(|mb| mb.saturating_mul(1024 * 1024));
+    let min_started_at = settings
+        .max_age_days
+        .map(|days| now_ms - i64::from(days) * DAY_MS);
+    let mut total = 0u64;
+    let mut over_limit = false;
+    let mut expired = Vec::new();
+    for (index, archive) in archives.iter().enumerate() {
+        if !archive.active {
+            if min_started_at.is_some_and(|min| archive.started_at < min) {
+                expired.push(index);
+                continue;
+            }
+            over_limit = over_limit
+                || max_bytes.is_some_and(|max| total.saturating_add(archive.size_bytes) > max);
+            if over_limit {
+                expired.push(index);
+                continue;
+            }
+        }
+        total = total.saturating_add(archive.size_bytes);
+    }
+    expired
+}
+
+fn remove_session(
+    platform: DanmakuPlatform,
+    room_id: &str,
+    session_id: &str,
+) -> Result<(), String> {
+    let (path, meta_path) = session_paths(platform, room_id, session_id)?;
+    if ACTIVE_SESSIONS.lock().unwrap().contains(&path) {
+        return Err("该存档正在写入，停止弹幕监听后再删除".to_string());
+    }
+    std::fs::remove_file(&path)
+        .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+    let _ = std::fs::remove_file(&meta_path);
+    // 房间目录空了就一起删掉
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::remove_dir(dir);
+    }
+    Ok(())
+}
+
+/// 按当前设置删除过期或超出总大小的存档；阻塞调用
+fn prune_archives() {
+    let _guard = PRUNE_LOCK.lock().unwrap();
+    let settings = SETTINGS.read().unwrap().clone();
+    if settings.max_age_days.is_none() && settings.max_total_mb.is_none() {
+        return;
+    }
+    let archives = list_archives(None, None);
+    let expired = expired_sessions(
+        &archives,
+        &settings,
+        chrono::Local::now().timestamp_millis(),
+    );
+    if expired.is_empty() {
+        return;
+    }
+    let mut removed = 0;
+    for archive in expired.into_iter().map(|i| &archives[i]) {
+        // room_id 取自目录名时已经过 sanitize，再次处理结果不变
+        match remove_session(archive.platform, &archive.room_id, &archive.session_id) {
+            Ok(()) => removed += 1,
+            Err(e) => eprintln!("[DanmakuArchive] Failed to prune: {}", e),
+        }
+    }
+    println!("[DanmakuArchive] Pruned {} archived session(s)", removed);
+}
+
+// ---- Tauri 命令 ----
+
+/// 列出存档，按开始时间倒序；可按平台、房间筛选
+#[tauri::command]
+pub async fn list_danmaku_archives(
+    platform: Option<DanmakuPlatform>,
+    room_id: Option<String>,
+) -> Result<Vec<DanmakuArchiveInfo>, String> {
+    tokio::task::spawn_blocking(move || list_archives(platform, room_id.as_deref()))
+        .await
+        .map_err(|e| format!("Failed to list danmaku archives: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_danmaku_archive(
+    platform: DanmakuPlatform,
+    room_id: String,
+    session_id: String,
+) -> Result<(), String> {
+    remove_session(platform, &room_id, &session_id)?;
+    println!(
+        "[DanmakuArchive] Deleted {} {} {}",
+        platform.as_str(),
+        room_id,
+        session_id
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_danmaku_archive_settings() -> DanmakuArchiveSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// 保存存档设置并立即按新的保留策略清理；开关对之后开始的监听生效
+#[tauri::command]
+pub async fn set_danmaku_archive_settings(
+    settings: DanmakuArchiveSettings,
+) -> Result<DanmakuArchiveSettings, String> {
+    let settings = settings.normalized();
+    {
+        let mut current = SETTINGS.write().unwrap();
+        save_settings(&settings)?;
+        *current = settings.clone();
+    }
+    println!("[DanmakuArchive] Settings updated: {:?}", settings);
+    tokio::task::spawn_blocking(prune_archives)
+        .await
+        .map_err(|e| format!("Failed to prune danmaku archives: {}", e))?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+    const NOW: i64 = 100 * DAY_MS;
+
+    fn archive(session_id: &str, age_days: i64, size_mb: u64, active: bool) -> DanmakuArchiveInfo {
+        DanmakuArchiveInfo {
+            platform: DanmakuPlatform::Douyu,
+            room_id: "9999".to_string(),
+            session_id: session_id.to_string(),
+            started_at: NOW - age_days * DAY_MS,
+            duration_ms: None,
+            event_count: None,
+            size_bytes: size_mb * MB,
+            active,
+        }
+    }
+
+    fn settings(max_age_days: Option<u32>, max_total_mb: Option<u64>) -> DanmakuArchiveSettings {
+        DanmakuArchiveSettings {
+            enabled: true,
+            max_age_days,
+            max_total_mb,
+        }
+    }
+
+    #[test]
+    fn expires_sessions_past_max_age() {
+        let archives = [
+            archive("a", 1, 1, false),
+            archive("b", 10, 1, false),
+            archive("c", 40, 1, false),
+        ];
+        assert_eq!(
+            expired_sessions(&archives, &settings(Some(30), None), NOW),
+            [2]
+        );
+        assert!(expired_sessions(&archives, &settings(None, None), NOW).is_empty());
+    }
+
+    #[test]
+    fn keeps_newest_sessions_within_size_cap() {
+        let archives = [
+            archive("a", 1, 4, false),
+            archive("b", 2, 4, false),
+            archive("c", 3, 4, false),
+            // 比 c 更旧，即使放得下也一并删除
+            archive("d", 4, 1, false),
+        ];
+        assert_eq!(
+            expired_sessions(&archives, &settings(None, Some(10)), NOW),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn active_sessions_count_towards_cap_but_are_never_deleted() {
+        let archives = [
+            archive("a", 1, 8, true),
+            archive("b", 2, 4, false),
+            archive("c", 50, 1, true),
+        ];
+        assert_eq!(
+            expired_sessions(&archives, &settings(Some(30), Some(10)), NOW),
+            [1]
+        );
+    }
+
+    #[test]
+    fn old_sessions_do_not_use_up_the_size_cap() {
+        let archives = [
+            archive("a", 1, 4, false),
+            archive("b", 2, 4, false),
+            archive("c", 40, 8, false),
+        ];
+        assert_eq!(
+            expired_sessions(&archives, &settings(Some(30), Some(10)), NOW),
+            [2]
+        );
+    }
+
+    #[test]
+    fn zero_limits_mean_unlimited() {
+        let normalized = settings(Some(0), Some(0)).normalized();
+        assert_eq!(normalized, settings(None, None));
+        let parsed: DanmakuArchiveSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed, DanmakuArchiveSettings::default());
+    }
+}
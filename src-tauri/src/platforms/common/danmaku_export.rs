@@ -0,0 +1,539 @@
+// 把弹幕存档转换成 B 站 XML（<d p="...">）和 ASS 字幕
+//
+// 只导出聊天和醒目留言：聊天为滚动弹幕，醒目留言为顶部固定弹幕。
+// 时间取存档的相对接收时间，加上 time_offset_ms 后为负的弹幕会被丢弃。
+// ASS 的顶部固定弹幕占屏幕最上方的几条轨道，滚动弹幕排在其下；轨道全部占满时新弹幕被丢弃。
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use super::danmaku::{DanmakuEventKind, DanmakuPlatform};
+use super::danmaku_archive::{self, ArchiveRecord};
+
+const DEFAULT_COLOR: u32 = 0xFFFFFF;
+// B 站 XML 的弹幕模式
+const XML_MODE_SCROLL: u8 = 1;
+const XML_MODE_TOP: u8 = 5;
+const XML_FONT_SIZE: u32 = 25;
+// ASS 里为顶部固定弹幕保留的轨道数
+const TOP_LANES: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum DanmakuExportFormat {
+    Xml,
+    Ass,
+}
+
+impl DanmakuExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            DanmakuExportFormat::Xml => "xml",
+            DanmakuExportFormat::Ass => "ass",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DanmakuExportOptions {
+    /// 整体平移，正数让弹幕晚出现；用于对齐录像的开头
+    pub time_offset_ms: i64,
+    // 以下只对 ASS 生效
+    pub width: u32,
+    pub height: u32,
+    pub font_name: String,
+    pub font_size: u32,
+    /// 0~1
+    pub opacity: f64,
+    pub outline: f64,
+    /// 滚动弹幕从右到左穿过屏幕的时间
+    pub scroll_duration_ms: u32,
+    /// 顶部固定弹幕的停留时间
+    pub fixed_duration_ms: u32,
+    /// 滚动弹幕可以使用的屏幕高度比例，0~1；从顶部固定弹幕的轨道下方开始计算
+    pub display_area: f64,
+}
+
+impl Default for DanmakuExportOptions {
+    fn default() -> Self {
+        Self {
+            time_offset_ms: 0,
+            width: 1920,
+            height: 1080,
+            font_name: "Microsoft YaHei".to_string(),
+            font_size: 48,
+            opacity: 0.8,
+            outline: 2.0,
+            scroll_duration_ms: 10_000,
+            fixed_duration_ms: 5_000,
+            display_area: 0.8,
+        }
+    }
+}
+
+impl DanmakuExportOptions {
+    fn normalized(mut self) -> Self {
+        self.width = self.width.max(16);
+        self.height = self.height.max(16);
+        self.font_size = self.font_size.clamp(8, 256);
+        self.opacity = if self.opacity.is_finite() {
+            self.opacity.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        self.outline = if self.outline.is_finite() {
+            self.outline.clamp(0.0, 10.0)
+        } else {
+            0.0
+        };
+        self.scroll_duration_ms = self.scroll_duration_ms.max(1000);
+        self.fixed_duration_ms = self.fixed_duration_ms.max(500);
+        self.display_area = if self.display_area.is_finite() {
+            self.display_area.clamp(0.1, 1.0)
+        } else {
+            1.0
+        };
+        // 逗号会破坏 ASS 的 Style 行
+        self.font_name = self.font_name.replace(',', " ").trim().to_string();
+        if self.font_name.is_empty() {
+            self.font_name = Self::default().font_name;
+        }
+        self
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Scroll,
+    Top,
+}
+
+/// 可导出的一条弹幕，time_ms 已加上偏移
+struct ExportItem {
+    time_ms: u64,
+    placement: Placement,
+    text: String,
+    color: u32,
+    user_hash: String,
+    timestamp_secs: i64,
+}
+
+// FNV-1a，B 站 XML 的用户字段只需要一个稳定的匿名值
+fn user_hash(value: &str) -> String {
+    let hash = value.bytes().fold(0x811c9dc5u32, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x01000193)
+    });
+    format!("{:08x}", hash)
+}
+
+fn parse_color(color: Option<&str>) -> u32 {
+    color
+        .and_then(|c| u32::from_str_radix(c.trim_start_matches('#'), 16).ok())
+        .filter(|c| *c <= 0xFFFFFF)
+        .unwrap_or(DEFAULT_COLOR)
+}
+
+fn collect_items(records: &[ArchiveRecord], time_offset_ms: i64) -> Vec<ExportItem> {
+    let mut items: Vec<ExportItem> = records
+        .iter()
+        .filter_map(|record| {
+            let event = &record.event;
+            let (placement, text) = match &event.kind {
+                DanmakuEventKind::Chat { content } => (Placement::Scroll, content),
+                DanmakuEventKind::SuperChat { content, .. } => (Placement::Top, content),
+                _ => return None,
+            };
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                return None;
+            }
+            let time_ms = i64::try_from(record.offset_ms)
+                .ok()?
+                .checked_add(time_offset_ms)?;
+            let user = event
+                .user
+                .as_ref()
+                .map(|u| u.id.clone().unwrap_or_else(|| u.nickname.clone()))
+                .unwrap_or_default();
+            Some(ExportItem {
+                time_ms: u64::try_from(time_ms).ok()?,
+                placement,
+                text,
+                color: parse_color(event.color.as_deref()),
+                user_hash: user_hash(&user),
+                timestamp_secs: event.timestamp / 1000,
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| item.time_ms);
+    items
+}
+
+// XML 1.0 不允许的控制字符直接去掉
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c if (c as u32) < 0x20 && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 转成 B 站弹幕 XML
+fn to_bilibili_xml(items: &[ExportItem]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<i>\n");
+    xml.push_str("  <chatserver>chat.bilibili.com</chatserver>\n  <chatid>0</chatid>\n");
+    let _ = writeln!(xml, "  <maxlimit>{}</maxlimit>", items.len().max(1));
+    xml.push_str("  <state>0</state>\n  <real_name>0</real_name>\n  <source>DTV</source>\n");
+    for (index, item) in items.iter().enumerate() {
+        let mode = match item.placement {
+            Placement::Scroll => XML_MODE_SCROLL,
+            Placement::Top => XML_MODE_TOP,
+        };
+        // p = 时间,模式,字号,颜色,发送时间戳,弹幕池,用户,弹幕 id
+        let _ = writeln!(
+            xml,
+            "  <d p=\"{:.3},{},{},{},{},0,{},{}\">{}</d>",
+            item.time_ms as f64 / 1000.0,
+            mode,
+            XML_FONT_SIZE,
+            item.color,
+            item.timestamp_secs,
+            item.user_hash,
+            index + 1,
+            escape_xml(&item.text)
+        );
+    }
+    xml.push_str("</i>\n");
+    xml
+}
+
+// 粗略估算文字宽度：全角字符按一个字号，半角按半个
+fn text_width(text: &str, font_size: u32) -> f64 {
+    let font_size = f64::from(font_size);
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                font_size * 0.5
+            } else {
+                font_size
+            }
+        })
+        .sum()
+}
+
+fn ass_time(ms: u64) -> String {
+    let cs = ms / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6_000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+// ASS 没有转义，花括号和反斜杠换成全角
+fn escape_ass(text: &str) -> String {
+    text.replace('\\', "＼")
+        .replace('{', "｛")
+        .replace('}', "｝")
+}
+
+// ASS 颜色为 &HBBGGRR
+fn ass_color(rgb: u32) -> String {
+    format!(
+        "{:02X}{:02X}{:02X}",
+        rgb & 0xFF,
+        (rgb >> 8) & 0xFF,
+        (rgb >> 16) & 0xFF
+    )
+}
+
+/// 一条滚动轨道上最后一条弹幕的进场时间、宽度和速度
+#[derive(Clone, Copy)]
+struct ScrollSlot {
+    start_ms: f64,
+    width: f64,
+    speed: f64,
+}
+
+/// 轨道分配：同一轨道上后一条既不能在前一条完全进场前出现，也不能在离场前追上它。
+/// 轨道号从屏幕顶端数起，0..top_lanes 为顶部固定弹幕，之后是滚动弹幕，两者互不重叠
+struct LaneLayout {
+    screen_width: f64,
+    duration_ms: f64,
+    scroll: Vec<Option<ScrollSlot>>,
+    // 顶部轨道被占用到的时间
+    top: Vec<f64>,
+}
+
+impl LaneLayout {
+    fn new(options: &DanmakuExportOptions, lane_height: f64) -> Self {
+        // 屏幕太矮时两类弹幕至少各留一条
+        let total_lanes = ((f64::from(options.height) / lane_height).floor() as usize).max(2);
+        let top_lanes = TOP_LANES.min(total_lanes - 1);
+        let scroll_lanes =
+            ((f64::from(options.height) * options.display_area) / lane_height).floor() as usize;
+        Self {
+            screen_width: f64::from(options.width),
+            duration_ms: f64::from(options.scroll_duration_ms),
+            scroll: vec![None; scroll_lanes.clamp(1, total_lanes - top_lanes)],
+            top: vec![f64::NEG_INFINITY; top_lanes],
+        }
+    }
+
+    // 返回还需要等多久这条轨道才空出来，0 表示可以直接放
+    fn scroll_wait(&self, slot: Option<ScrollSlot>, start_ms: f64, speed: f64) -> f64 {
+        let Some(prev) = slot else {
+            return 0.0;
+        };
+        // 前一条尾部进入屏幕的时间
+        let entered = prev.start_ms + prev.width / prev.speed;
+        // 新弹幕头部到达左边缘时，前一条必须已经离场
+        let prev_left = prev.start_ms + self.duration_ms;
+        let head_arrives = start_ms + self.screen_width / speed;
+        (entered - start_ms).max(prev_left - head_arrives).max(0.0)
+    }
+
+    /// 选第一条空闲的滚动轨道，返回屏幕上的轨道号；都被占用时返回 None，这条弹幕不导出
+    fn place_scroll(&mut self, start_ms: f64, width: f64) -> Option<usize> {
+        let speed = (self.screen_width + width) / self.duration_ms;
+        let lane = self
+            .scroll
+            .iter()
+            .position(|slot| self.scroll_wait(*slot, start_ms, speed) <= 0.0)?;
+        self.scroll[lane] = Some(ScrollSlot {
+            start_ms,
+            width,
+            speed,
+        });
+        Some(self.top.len() + lane)
+    }
+
+    /// 选第一条空闲的顶部轨道；都被占用时返回 None
+    fn place_top(&mut self, start_ms: f64, end_ms: f64) -> Option<usize> {
+        let lane = self
+            .top
+            .iter()
+            .position(|busy_until| *busy_until <= start_ms)?;
+        self.top[lane] = end_ms;
+        Some(lane)
+    }
+}
+
+/// 转成 ASS 字幕，同时返回实际写入的条数（轨道占满时丢弃的不算）
+fn to_ass(items: &[ExportItem], options: &DanmakuExportOptions) -> (String, usize) {
+    let lane_height = (f64::from(options.font_size) * 1.15).ceil();
+    let mut layout = LaneLayout::new(options, lane_height);
+    // 透明度写进样式，行内只覆盖颜色
+    let alpha = format!("{:02X}", ((1.0 - options.opacity) * 255.0).round() as u8);
+
+    let mut ass = String::new();
+    let _ = write!(
+        ass,
+        "[Script Info]\n\
+         ; Generated by DTV\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {width}\n\
+         PlayResY: {height}\n\
+         WrapStyle: 2\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Danmaku,{font},{size},&H{alpha}FFFFFF,&H{alpha}FFFFFF,&H{alpha}000000,&H{alpha}000000,0,0,0,0,100,100,0,0,1,{outline},0,7,0,0,0,1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        width = options.width,
+        height = options.height,
+        font = options.font_name,
+        size = options.font_size,
+        alpha = alpha,
+        outline = options.outline,
+    );
+
+    let mut count = 0;
+    for item in items {
+        let start = item.time_ms as f64;
+        let width = text_width(&item.text, options.font_size);
+        let color = if item.color == DEFAULT_COLOR {
+            String::new()
+        } else {
+            format!("\\c&H{}&", ass_color(item.color))
+        };
+        let (end, effect) = match item.placement {
+            Placement::Scroll => {
+                let Some(lane) = layout.place_scroll(start, width) else {
+                    continue;
+                };
+                let y = lane as f64 * lane_height;
+                (
+                    start + f64::from(options.scroll_duration_ms),
+                    format!("\\move({},{},{},{})", options.width, y, -width.ceil(), y),
+                )
+            }
+            Placement::Top => {
+                let end = start + f64::from(options.fixed_duration_ms);
+                let Some(lane) = layout.place_top(start, end) else {
+                    continue;
+                };
+                let y = lane as f64 * lane_height;
+                (end, format!("\\an8\\pos({},{})", options.width / 2, y))
+            }
+        };
+        let _ = writeln!(
+            ass,
+            "Dialogue: 0,{},{},Danmaku,,0,0,0,,{{{}{}}}{}",
+            ass_time(item.time_ms),
+            ass_time(end as u64),
+            effect,
+            color,
+            escape_ass(&item.text)
+        );
+        count += 1;
+    }
+    (ass, count)
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuExportResult {
+    pub path: String,
+    /// 写入的弹幕条数
+    pub count: u32,
+}
+
+// ---- Tauri 命令 ----
+
+/// 导出一个存档会话；不指定路径时写到默认录制目录
+#[tauri::command]
+pub async fn export_danmaku_archive(
+    platform: DanmakuPlatform,
+    room_id: String,
+    session_id: String,
+    format: DanmakuExportFormat,
+    options: Option<DanmakuExportOptions>,
+    output_path: Option<String>,
+) -> Result<DanmakuExportResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let records = danmaku_archive::read_session(platform, &room_id, &session_id)?;
+        let options = options.unwrap_or_default().normalized();
+        let items = collect_items(&records, options.time_offset_ms);
+        let (content, count) = match format {
+            DanmakuExportFormat::Xml => (to_bilibili_xml(&items), items.len()),
+            DanmakuExportFormat::Ass => to_ass(&items, &options),
+        };
+
+        let path = match output_path.map(|p| p.trim().to_string()) {
+            Some(p) if !p.is_empty() => PathBuf::from(p),
+            _ => crate::recording::default_output_dir().join(format!(
+                "{}_{}_{}.{}",
+                platform.as_str(),
+                danmaku_archive::sanitize_component(&room_id),
+                session_id,
+                format.extension()
+            )),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        println!(
+            "[DanmakuExport] Wrote {} danmaku to {}",
+            count,
+            path.display()
+        );
+        Ok(DanmakuExportResult {
+            path: path.to_string_lossy().to_string(),
+            count: count as u32,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to export danmaku: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LANE_HEIGHT: f64 = 100.0;
+
+    // 1000x1000 的屏幕，每条轨道 100，滚动弹幕 10 秒穿过屏幕
+    fn layout(display_area: f64) -> LaneLayout {
+        let options = DanmakuExportOptions {
+            width: 1000,
+            height: 1000,
+            display_area,
+            ..DanmakuExportOptions::default()
+        };
+        LaneLayout::new(&options, LANE_HEIGHT)
+    }
+
+    #[test]
+    fn scroll_lanes_start_below_the_top_band() {
+        let mut layout = layout(0.5);
+        assert_eq!(layout.top.len(), TOP_LANES);
+        assert_eq!(layout.scroll.len(), 5);
+        assert_eq!(layout.place_top(0.0, 5000.0), Some(0));
+        assert_eq!(layout.place_top(0.0, 5000.0), Some(1));
+        assert_eq!(layout.place_scroll(0.0, 100.0), Some(TOP_LANES));
+    }
+
+    #[test]
+    fn scroll_region_stays_on_screen() {
+        let layout = layout(1.0);
+        assert_eq!(layout.top.len() + layout.scroll.len(), 10);
+    }
+
+    #[test]
+    fn reuses_scroll_lane_once_previous_item_has_entered() {
+        let mut layout = layout(0.1);
+        assert_eq!(layout.scroll.len(), 1);
+        // 宽 100，速度 1100px / 10s，约 0.91 秒完全进场
+        assert_eq!(layout.place_scroll(0.0, 100.0), Some(TOP_LANES));
+        assert_eq!(layout.place_scroll(500.0, 100.0), None);
+        assert_eq!(layout.place_scroll(1000.0, 100.0), Some(TOP_LANES));
+    }
+
+    #[test]
+    fn faster_item_must_not_catch_up_with_previous_one() {
+        let mut layout = layout(0.1);
+        assert_eq!(layout.place_scroll(0.0, 10.0), Some(TOP_LANES));
+        // 更长的弹幕速度更快，1 秒后进场会在左边缘追上前一条
+        assert_eq!(layout.place_scroll(1000.0, 2000.0), None);
+        assert_eq!(layout.place_scroll(7000.0, 2000.0), Some(TOP_LANES));
+    }
+
+    #[test]
+    fn drops_top_items_while_all_top_lanes_are_busy() {
+        let mut layout = layout(0.8);
+        assert_eq!(layout.place_top(0.0, 5000.0), Some(0));
+        assert_eq!(layout.place_top(1000.0, 6000.0), Some(1));
+        assert_eq!(layout.place_top(2000.0, 7000.0), None);
+        assert_eq!(layout.place_top(5000.0, 10000.0), Some(0));
+    }
+
+    #[test]
+    fn tiny_screen_keeps_one_lane_of_each_kind() {
+        let options = DanmakuExportOptions {
+            height: 16,
+            ..DanmakuExportOptions::default()
+        };
+        let mut layout = LaneLayout::new(&options, LANE_HEIGHT);
+        assert_eq!(layout.top.len(), 1);
+        assert_eq!(layout.scroll.len(), 1);
+        assert_eq!(layout.place_scroll(0.0, 100.0), Some(1));
+    }
+}
@@ -0,0 +1,670 @@
+// 弹幕过滤：解码之后、推送前端之前按规则丢弃或折叠消息
+//
+// 规则分全局和单个房间两级。房间规则的列表与全局合并，数值项（最低等级、折叠窗口）优先取房间的；
+// 房间规则 enabled=false 时该房间完全不过滤。
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use super::danmaku::{DanmakuEvent, DanmakuEventKind, DanmakuPlatform};
+use super::danmaku_listener;
+
+const SETTINGS_FILE_NAME: &str = "danmaku-filter.json";
+const MAX_DEDUPE_WINDOW_SECS: u32 = 600;
+// 每个房间最多跟踪的不同文本数，超出时丢掉最早的（不再补发折叠汇总）
+const MAX_TRACKED_MESSAGES: usize = 2048;
+
+static SETTINGS: Lazy<RwLock<Arc<VersionedSettings>>> = Lazy::new(|| {
+    RwLock::new(Arc::new(VersionedSettings {
+        version: 0,
+        settings: load_settings(),
+    }))
+});
+// 串行化所有修改：读取、修改、落盘与替换内存副本在同一把锁内完成，并发修改不会互相覆盖
+static SETTINGS_UPDATE: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 可以单独隐藏的消息类型；房间统计、开关播、房间信息不受过滤影响
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DanmakuMessageType {
+    Chat,
+    Gift,
+    Enter,
+    Follow,
+    Like,
+    SuperChat,
+    Guard,
+    Notice,
+}
+
+impl DanmakuMessageType {
+    fn of(kind: &DanmakuEventKind) -> Option<Self> {
+        let ty = match kind {
+            DanmakuEventKind::Chat { .. } => Self::Chat,
+            DanmakuEventKind::Gift { .. } => Self::Gift,
+            DanmakuEventKind::Enter => Self::Enter,
+            DanmakuEventKind::Follow => Self::Follow,
+            DanmakuEventKind::Like { .. } => Self::Like,
+            DanmakuEventKind::SuperChat { .. } => Self::SuperChat,
+            DanmakuEventKind::Guard { .. } => Self::Guard,
+            DanmakuEventKind::Notice { .. } => Self::Notice,
+            DanmakuEventKind::RoomStats { .. }
+            | DanmakuEventKind::LiveStatus { .. }
+            | DanmakuEventKind::RoomInfo { .. } => return None,
+        };
+        Some(ty)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DanmakuFilterRules {
+    pub enabled: bool,
+    /// 不区分大小写的子串匹配
+    pub keywords: Vec<String>,
+    pub regexes: Vec<String>,
+    /// 用户 id 或昵称，完全匹配
+    pub blocked_users: Vec<String>,
+    /// 白名单用户的消息跳过所有规则
+    pub allowed_users: Vec<String>,
+    /// 只作用于聊天弹幕；平台没给出等级（0）时不拦截
+    pub min_user_level: Option<u32>,
+    /// 窗口内相同的聊天只显示第一条，窗口结束后补一条 "×N"
+    pub dedupe_window_secs: Option<u32>,
+    pub hidden_types: Vec<DanmakuMessageType>,
+}
+
+impl Default for DanmakuFilterRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keywords: Vec::new(),
+            regexes: Vec::new(),
+            blocked_users: Vec::new(),
+            allowed_users: Vec::new(),
+            min_user_level: None,
+            dedupe_window_secs: None,
+            hidden_types: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuRoomFilter {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    pub rules: DanmakuFilterRules,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DanmakuFilterSettings {
+    pub global: DanmakuFilterRules,
+    pub rooms: Vec<DanmakuRoomFilter>,
+}
+
+fn clean_list(list: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    list.into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty() && seen.insert(s.clone()))
+        .collect()
+}
+
+impl DanmakuFilterRules {
+    fn normalized(mut self) -> Result<Self, String> {
+        self.keywords = clean_list(self.keywords);
+        self.regexes = clean_list(self.regexes);
+        for pattern in &self.regexes {
+            Regex::new(pattern).map_err(|e| format!("Invalid regex {:?}: {}", pattern, e))?;
+        }
+        self.blocked_users = clean_list(self.blocked_users);
+        self.allowed_users = clean_list(self.allowed_users);
+        self.dedupe_window_secs = self
+            .dedupe_window_secs
+            .filter(|secs| *secs > 0)
+            .map(|secs| secs.min(MAX_DEDUPE_WINDOW_SECS));
+        let mut seen = HashSet::new();
+        self.hidden_types.retain(|ty| seen.insert(*ty));
+        Ok(self)
+    }
+}
+
+impl DanmakuFilterSettings {
+    fn normalized(self) -> Result<Self, String> {
+        let global = self.global.normalized()?;
+        // 同一房间出现多次时保留最后一条
+        let mut rooms: Vec<DanmakuRoomFilter> = Vec::new();
+        for room in self.rooms {
+            let room_id = danmaku_listener::normalize_room_id(room.platform, &room.room_id);
+            if room_id.is_empty() {
+                continue;
+            }
+            let rules = room
+                .rules
+                .normalized()
+                .map_err(|e| format!("{} ({} {})", e, room.platform.as_str(), room_id))?;
+            rooms.retain(|r| !(r.platform == room.platform && r.room_id == room_id));
+            rooms.push(DanmakuRoomFilter {
+                platform: room.platform,
+                room_id,
+                rules,
+            });
+        }
+        Ok(Self { global, rooms })
+    }
+
+    fn room(&self, platform: DanmakuPlatform, room_id: &str) -> Option<&DanmakuFilterRules> {
+        self.rooms
+            .iter()
+            .find(|r| r.platform == platform && r.room_id == room_id)
+            .map(|r| &r.rules)
+    }
+}
+
+struct VersionedSettings {
+    version: u64,
+    settings: DanmakuFilterSettings,
+}
+
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(SETTINGS_FILE_NAME)
+}
+
+fn load_settings() -> DanmakuFilterSettings {
+    let path = settings_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return DanmakuFilterSettings::default();
+    };
+    let parsed = serde_json::from_str::<DanmakuFilterSettings>(&raw)
+        .map_err(|e| e.to_string())
+        .and_then(DanmakuFilterSettings::normalized);
+    match parsed {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!(
+                "[DanmakuFilter] Ignoring invalid settings in {}: {}",
+                path.display(),
+                e
+            );
+            DanmakuFilterSettings::default()
+        }
+    }
+}
+
+fn save_settings(settings: &DanmakuFilterSettings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize danmaku filter settings: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 在当前设置的基础上修改并保存；写盘失败时内存中的设置保持不变
+fn update_settings(
+    update: impl FnOnce(DanmakuFilterSettings) -> Result<DanmakuFilterSettings, String>,
+) -> Result<DanmakuFilterSettings, String> {
+    let _guard = SETTINGS_UPDATE.lock().unwrap();
+    let settings = update(SETTINGS.read().unwrap().settings.clone())?;
+    save_settings(&settings)?;
+    let mut current = SETTINGS.write().unwrap();
+    *current = Arc::new(VersionedSettings {
+        version: current.version + 1,
+        settings: settings.clone(),
+    });
+    Ok(settings)
+}
+
+/// 合并后对某个房间生效的规则
+struct CompiledRules {
+    keywords: Vec<String>,
+    regexes: Vec<Regex>,
+    blocked_users: HashSet<String>,
+    allowed_users: HashSet<String>,
+    min_user_level: Option<u32>,
+    dedupe_window: Option<Duration>,
+    hidden_types: HashSet<DanmakuMessageType>,
+}
+
+impl CompiledRules {
+    /// 两级都关闭时返回 None，即不过滤
+    fn resolve(
+        settings: &DanmakuFilterSettings,
+        platform: DanmakuPlatform,
+        room_id: &str,
+    ) -> Option<Self> {
+        let room = settings.room(platform, room_id);
+        if room.is_some_and(|r| !r.enabled) {
+            return None;
+        }
+        let scopes: Vec<&DanmakuFilterRules> = Some(&settings.global)
+            .filter(|g| g.enabled)
+            .into_iter()
+            .chain(room)
+            .collect();
+        if scopes.is_empty() {
+            return None;
+        }
+        // 数值项房间优先
+        let scalar =
+            |f: fn(&DanmakuFilterRules) -> Option<u32>| scopes.iter().rev().find_map(|r| f(r));
+
+        let mut regexes = Vec::new();
+        for pattern in scopes.iter().flat_map(|r| r.regexes.iter()) {
+            match Regex::new(pattern) {
+                Ok(re) => regexes.push(re),
+                Err(e) => eprintln!("[DanmakuFilter] Skipping regex {:?}: {}", pattern, e),
+            }
+        }
+        Some(Self {
+            keywords: scopes
+                .iter()
+                .flat_map(|r| r.keywords.iter())
+                .map(|k| k.to_lowercase())
+                .collect(),
+            regexes,
+            blocked_users: scopes
+                .iter()
+                .flat_map(|r| r.blocked_users.iter().cloned())
+                .collect(),
+            allowed_users: scopes
+                .iter()
+                .flat_map(|r| r.allowed_users.iter().cloned())
+                .collect(),
+            min_user_level: scalar(|r| r.min_user_level),
+            dedupe_window: scalar(|r| r.dedupe_window_secs)
+                .map(|secs| Duration::from_secs(u64::from(secs))),
+            hidden_types: scopes
+                .iter()
+                .flat_map(|r| r.hidden_types.iter().copied())
+                .collect(),
+        })
+    }
+
+    fn user_in(list: &HashSet<String>, event: &DanmakuEvent) -> bool {
+        let Some(user) = &event.user else {
+            return false;
+        };
+        user.id.as_ref().is_some_and(|id| list.contains(id)) || list.contains(&user.nickname)
+    }
+
+    fn allows(&self, event: &DanmakuEvent) -> bool {
+        if let Some(ty) = DanmakuMessageType::of(&event.kind) {
+            if self.hidden_types.contains(&ty) {
+                return false;
+            }
+        }
+        if Self::user_in(&self.blocked_users, event) {
+            return false;
+        }
+        if let Some(text) = event_text(&event.kind) {
+            let lower = text.to_lowercase();
+            if self.keywords.iter().any(|k| lower.contains(k.as_str())) {
+                return false;
+            }
+            if self.regexes.iter().any(|re| re.is_match(text)) {
+                return false;
+            }
+        }
+        if let (Some(min), DanmakuEventKind::Chat { .. }) = (self.min_user_level, &event.kind) {
+            let level = event.user.as_ref().map(|u| u.level).unwrap_or(0);
+            if level > 0 && level < min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn event_text(kind: &DanmakuEventKind) -> Option<&str> {
+    match kind {
+        DanmakuEventKind::Chat { content } | DanmakuEventKind::SuperChat { content, .. } => {
+            Some(content)
+        }
+        DanmakuEventKind::Notice { message } => Some(message),
+        _ => None,
+    }
+}
+
+// 折叠时忽略大小写和多余空白
+fn dedupe_key(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct FoldedMessage {
+    first_seen: Instant,
+    count: u32,
+    event: DanmakuEvent,
+}
+
+/// 单个监听的过滤状态；规则变更后在下一条消息时生效
+pub struct DanmakuFilter {
+    platform: DanmakuPlatform,
+    room_id: String,
+    version: Option<u64>,
+    rules: Option<Arc<CompiledRules>>,
+    folded: HashMap<String, FoldedMessage>,
+    // 按首次出现的顺序排列，用于过期和容量淘汰
+    fold_order: VecDeque<String>,
+}
+
+impl DanmakuFilter {
+    pub fn new(platform: DanmakuPlatform, room_id: &str) -> Self {
+        Self {
+            platform,
+            room_id: room_id.to_string(),
+            version: None,
+            rules: None,
+            folded: HashMap::new(),
+            fold_order: VecDeque::new(),
+        }
+    }
+
+    fn refresh_rules(&mut self) {
+        let current = SETTINGS.read().unwrap().clone();
+        if self.version == Some(current.version) {
+            return;
+        }
+        self.version = Some(current.version);
+        self.rules =
+            CompiledRules::resolve(&current.settings, self.platform, &self.room_id).map(Arc::new);
+    }
+
+    /// 过滤一条消息，返回需要推送的事件：可能为空，也可能带上已到期的折叠汇总
+    pub fn process(&mut self, event: DanmakuEvent) -> Vec<DanmakuEvent> {
+        self.refresh_rules();
+        self.apply(event, Instant::now())
+    }
+
+    /// 补发已到期的折叠汇总，由监听定时调用，房间安静时也能看到最后的计数
+    pub fn flush(&mut self) -> Vec<DanmakuEvent> {
+        self.refresh_rules();
+        self.flush_at(Instant::now())
+    }
+
+    fn flush_at(&mut self, now: Instant) -> Vec<DanmakuEvent> {
+        match self.rules.clone() {
+            Some(rules) => self.flush_expired(now, rules.dedupe_window),
+            None => {
+                self.folded.clear();
+                self.fold_order.clear();
+                Vec::new()
+            }
+        }
+    }
+
+    fn apply(&mut self, event: DanmakuEvent, now: Instant) -> Vec<DanmakuEvent> {
+        let Some(rules) = self.rules.clone() else {
+            self.folded.clear();
+            self.fold_order.clear();
+            return vec![event];
+        };
+
+        let mut out = self.flush_expired(now, rules.dedupe_window);
+        if CompiledRules::user_in(&rules.allowed_users, &event) {
+            out.push(event);
+            return out;
+        }
+        if !rules.allows(&event) {
+            return out;
+        }
+        let (Some(_), DanmakuEventKind::Chat { content }) = (rules.dedupe_window, &event.kind)
+        else {
+            out.push(event);
+            return out;
+        };
+
+        let key = dedupe_key(content);
+        if let Some(folded) = self.folded.get_mut(&key) {
+            folded.count += 1;
+            return out;
+        }
+        if self.fold_order.len() >= MAX_TRACKED_MESSAGES {
+            if let Some(oldest) = self.fold_order.pop_front() {
+                self.folded.remove(&oldest);
+            }
+        }
+        self.fold_order.push_back(key.clone());
+        self.folded.insert(
+            key,
+            FoldedMessage {
+                first_seen: now,
+                count: 1,
+                event: event.clone(),
+            },
+        );
+        out.push(event);
+        out
+    }
+
+    // 窗口结束的重复消息补发一条 "原文 ×N"
+    fn flush_expired(&mut self, now: Instant, window: Option<Duration>) -> Vec<DanmakuEvent> {
+        let mut out = Vec::new();
+        while let Some(key) = self.fold_order.front() {
+            let expired = match (window, self.folded.get(key)) {
+                (Some(window), Some(folded)) => now.duration_since(folded.first_seen) >= window,
+                _ => true,
+            };
+            if !expired {
+                break;
+            }
+            let Some(key) = self.fold_order.pop_front() else {
+                break;
+            };
+            let Some(folded) = self.folded.remove(&key) else {
+                continue;
+            };
+            if folded.count < 2 {
+                continue;
+            }
+            let mut summary = folded.event;
+            if let DanmakuEventKind::Chat { content } = &mut summary.kind {
+                *content = format!("{} ×{}", content, folded.count);
+            }
+            summary.timestamp = chrono::Utc::now().timestamp_millis();
+            out.push(summary);
+        }
+        out
+    }
+}
+
+// ---- Tauri 命令 ----
+
+#[tauri::command]
+pub fn get_danmaku_filter_settings() -> DanmakuFilterSettings {
+    SETTINGS.read().unwrap().settings.clone()
+}
+
+/// 保存全部过滤规则，正在运行的监听在下一条消息时生效
+#[tauri::command]
+pub fn set_danmaku_filter_settings(
+    settings: DanmakuFilterSettings,
+) -> Result<DanmakuFilterSettings, String> {
+    let settings = settings.normalized()?;
+    let settings = update_settings(|_| Ok(settings))?;
+    println!(
+        "[DanmakuFilter] Settings updated: {} room rule(s)",
+        settings.rooms.len()
+    );
+    Ok(settings)
+}
+
+/// 设置或删除（rules 为 null）单个房间的规则
+#[tauri::command]
+pub fn set_room_danmaku_filter(
+    platform: DanmakuPlatform,
+    room_id: String,
+    rules: Option<DanmakuFilterRules>,
+) -> Result<DanmakuFilterSettings, String> {
+    let room_id = danmaku_listener::normalize_room_id(platform, &room_id);
+    if room_id.is_empty() {
+        return Err("房间号不能为空".to_string());
+    }
+    let rules = rules.map(DanmakuFilterRules::normalized).transpose()?;
+    let settings = update_settings(|mut settings| {
+        settings
+            .rooms
+            .retain(|r| !(r.platform == platform && r.room_id == room_id));
+        if let Some(rules) = rules {
+            settings.rooms.push(DanmakuRoomFilter {
+                platform,
+                room_id: room_id.clone(),
+                rules,
+            });
+        }
+        Ok(settings)
+    })?;
+    println!(
+        "[DanmakuFilter] Room rules updated for {} {}",
+        platform.as_str(),
+        room_id
+    );
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::danmaku::DanmakuUser;
+    use super::*;
+
+    const ROOM: &str = "9999";
+
+    fn chat(nickname: &str, level: u32, content: &str) -> DanmakuEvent {
+        DanmakuEvent::chat(DanmakuPlatform::Douyu, ROOM, content.to_string())
+            .user(DanmakuUser::named(nickname).id(nickname.len()).level(level))
+    }
+
+    fn content(event: &DanmakuEvent) -> &str {
+        event_text(&event.kind).unwrap_or_default()
+    }
+
+    fn filter_with(settings: &DanmakuFilterSettings) -> DanmakuFilter {
+        let mut filter = DanmakuFilter::new(DanmakuPlatform::Douyu, ROOM);
+        filter.rules = CompiledRules::resolve(settings, DanmakuPlatform::Douyu, ROOM).map(Arc::new);
+        filter
+    }
+
+    fn room(rules: DanmakuFilterRules) -> DanmakuRoomFilter {
+        DanmakuRoomFilter {
+            platform: DanmakuPlatform::Douyu,
+            room_id: ROOM.to_string(),
+            rules,
+        }
+    }
+
+    #[test]
+    fn normalizes_and_rejects_invalid_regexes() {
+        let rules = DanmakuFilterRules {
+            keywords: vec![" 刷屏 ".into(), "".into(), "刷屏".into()],
+            dedupe_window_secs: Some(100_000),
+            hidden_types: vec![DanmakuMessageType::Enter, DanmakuMessageType::Enter],
+            ..Default::default()
+        }
+        .normalized()
+        .unwrap();
+        assert_eq!(rules.keywords, vec!["刷屏"]);
+        assert_eq!(rules.dedupe_window_secs, Some(MAX_DEDUPE_WINDOW_SECS));
+        assert_eq!(rules.hidden_types, vec![DanmakuMessageType::Enter]);
+
+        let invalid = DanmakuFilterRules {
+            regexes: vec!["(".into()],
+            ..Default::default()
+        };
+        assert!(invalid.normalized().is_err());
+    }
+
+    #[test]
+    fn merges_global_and_room_rules() {
+        let settings = DanmakuFilterSettings {
+            global: DanmakuFilterRules {
+                keywords: vec!["SPAM".into()],
+                blocked_users: vec!["troll".into()],
+                min_user_level: Some(10),
+                ..Default::default()
+            },
+            rooms: vec![room(DanmakuFilterRules {
+                regexes: vec![r"^\d+$".into()],
+                allowed_users: vec!["vip".into()],
+                min_user_level: Some(3),
+                hidden_types: vec![DanmakuMessageType::Enter],
+                ..Default::default()
+            })],
+        };
+        let mut filter = filter_with(&settings);
+        let now = Instant::now();
+        let mut passes = |event: DanmakuEvent| !filter.apply(event, now).is_empty();
+
+        assert!(!passes(chat("a", 20, "buy spam now")));
+        assert!(!passes(chat("a", 20, "666")));
+        assert!(!passes(chat("troll", 20, "hello")));
+        // 房间的最低等级覆盖全局；没有等级的用户不拦截
+        assert!(passes(chat("a", 5, "hello")));
+        assert!(!passes(chat("a", 2, "hello again")));
+        assert!(passes(chat("a", 0, "no level")));
+        assert!(passes(chat("vip", 1, "spam 666")));
+        assert!(!passes(DanmakuEvent::new(
+            DanmakuPlatform::Douyu,
+            ROOM,
+            DanmakuEventKind::Enter
+        )));
+    }
+
+    #[test]
+    fn disabled_room_rules_bypass_global_rules() {
+        let settings = DanmakuFilterSettings {
+            global: DanmakuFilterRules {
+                keywords: vec!["spam".into()],
+                ..Default::default()
+            },
+            rooms: vec![room(DanmakuFilterRules {
+                enabled: false,
+                ..Default::default()
+            })],
+        };
+        assert!(CompiledRules::resolve(&settings, DanmakuPlatform::Douyu, ROOM).is_none());
+        assert!(CompiledRules::resolve(&settings, DanmakuPlatform::Douyu, "1").is_some());
+    }
+
+    #[test]
+    fn folds_repeats_and_flushes_the_summary_without_new_messages() {
+        let settings = DanmakuFilterSettings {
+            global: DanmakuFilterRules {
+                dedupe_window_secs: Some(5),
+                ..Default::default()
+            },
+            rooms: Vec::new(),
+        };
+        let mut filter = filter_with(&settings);
+        let start = Instant::now();
+
+        assert_eq!(filter.apply(chat("a", 1, "Hello  World"), start).len(), 1);
+        assert!(filter.apply(chat("b", 1, "hello world"), start).is_empty());
+        assert!(filter.apply(chat("c", 1, "HELLO world"), start).is_empty());
+        assert!(filter.flush_at(start + Duration::from_secs(4)).is_empty());
+
+        let flushed = filter.flush_at(start + Duration::from_secs(5));
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(content(&flushed[0]), "Hello  World ×3");
+        assert!(filter.flush_at(start + Duration::from_secs(10)).is_empty());
+
+        // 窗口结束后同样的内容重新计数
+        let again = filter.apply(chat("a", 1, "hello world"), start + Duration::from_secs(11));
+        assert_eq!(again.len(), 1);
+        assert_eq!(content(&again[0]), "hello world");
+    }
+}
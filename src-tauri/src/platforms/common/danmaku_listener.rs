@@ -0,0 +1,419 @@
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{mpsc, watch};
+
+use super::danmaku::{emit_danmaku, DanmakuEvent, DanmakuPlatform};
+use super::danmaku_archive::DanmakuArchive;
+use super::danmaku_filter::DanmakuFilter;
+
+pub const DANMAKU_STATE_EVENT: &str = "danmaku-state";
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+// 折叠汇总的补发间隔
+const FOLD_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 弹幕连接状态，随 `danmaku-state` 事件推送
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, specta::Type)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum DanmakuConnectionState {
+    Connecting,
+    Connected,
+    /// 第 `attempt` 次重连，`delayMs` 后开始
+    #[serde(rename_all = "camelCase")]
+    Reconnecting {
+        attempt: u32,
+        delay_ms: u32,
+    },
+    /// 监听已退出；主动停止时 reason 为 null
+    Closed {
+        reason: Option<String>,
+    },
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuStatePayload {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    #[serde(flatten)]
+    pub state: DanmakuConnectionState,
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuListenerInfo {
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    /// 共享这条连接的订阅数
+    pub subscribers: u32,
+    /// 毫秒时间戳
+    pub started_at: i64,
+    #[serde(flatten)]
+    pub state: DanmakuConnectionState,
+}
+
+/// 平台相关的启动参数
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuStartOptions {
+    /// B 站登录 Cookie，未登录时弹幕用户名会被打码
+    pub cookie: Option<String>,
+}
+
+/// 一个平台的弹幕监听实现
+pub trait DanmakuListener: Send + Sync {
+    /// 注册表的键，同时也是事件里的 room_id；前端应使用同一个值过滤事件
+    fn normalize_room_id(&self, room_id: &str) -> String {
+        room_id.trim().to_string()
+    }
+
+    /// 连接并持续推送弹幕，直到 `stop_rx` 收到信号或监听自行结束（例如下播）。
+    /// 返回 Err 时作为关闭原因推送给前端
+    fn run(
+        &self,
+        ctx: ListenerContext,
+        stop_rx: mpsc::Receiver<()>,
+    ) -> BoxFuture<'static, Result<(), String>>;
+}
+
+fn listener_for(platform: DanmakuPlatform) -> Box<dyn DanmakuListener> {
+    match platform {
+        DanmakuPlatform::Douyu => {
+            Box::new(crate::platforms::douyu::danmu_start::DouyuDanmakuListener)
+        }
+        DanmakuPlatform::Douyin => {
+            Box::new(crate::platforms::douyin::douyin_danmu_listener::DouyinDanmakuListener)
+        }
+        DanmakuPlatform::Huya => Box::new(crate::platforms::huya::danmaku::HuyaDanmakuListener),
+        DanmakuPlatform::Bilibili => {
+            Box::new(crate::platforms::bilibili::danmaku::BilibiliDanmakuListener)
+        }
+    }
+}
+
+/// 按平台规则规范房间号，与注册表和事件里使用的一致
+pub fn normalize_room_id(platform: DanmakuPlatform, room_id: &str) -> String {
+    listener_for(platform).normalize_room_id(room_id)
+}
+
+/// 传给监听任务的上下文，用于推送事件和汇报连接状态
+#[derive(Clone)]
+pub struct ListenerContext {
+    pub app_handle: AppHandle,
+    pub platform: DanmakuPlatform,
+    pub room_id: String,
+    pub options: DanmakuStartOptions,
+    registry: DanmakuRegistry,
+    generation: u64,
+    filter: Arc<Mutex<DanmakuFilter>>,
+    // 存档关闭时为空
+    archive: Option<DanmakuArchive>,
+}
+
+impl ListenerContext {
+    /// 原始消息写入存档，经过滤规则后推送给前端
+    pub fn emit(&self, event: DanmakuEvent) {
+        if let Some(archive) = &self.archive {
+            archive.write(&event);
+        }
+        let events = self.filter.lock().unwrap().process(event);
+        for event in &events {
+            emit_danmaku(&self.app_handle, event);
+        }
+    }
+
+    fn flush_folded(&self) {
+        let events = self.filter.lock().unwrap().flush();
+        for event in &events {
+            emit_danmaku(&self.app_handle, event);
+        }
+    }
+
+    pub fn set_state(&self, state: DanmakuConnectionState) {
+        if self
+            .registry
+            .update_state(self.platform, &self.room_id, self.generation, &state)
+        {
+            emit_state(&self.app_handle, self.platform, &self.room_id, state);
+        }
+    }
+
+    pub fn connected(&self) {
+        self.set_state(DanmakuConnectionState::Connected);
+    }
+
+    pub fn connecting(&self) {
+        self.set_state(DanmakuConnectionState::Connecting);
+    }
+
+    pub fn reconnecting(&self, attempt: u32, delay: Duration) {
+        self.set_state(DanmakuConnectionState::Reconnecting {
+            attempt,
+            delay_ms: delay.as_millis().min(u128::from(u32::MAX)) as u32,
+        });
+    }
+}
+
+/// 运行监听任务，同时定时补发到期的折叠汇总
+async fn run_with_fold_flush(
+    ctx: &ListenerContext,
+    mut task: BoxFuture<'static, Result<(), String>>,
+) -> Result<(), String> {
+    let mut ticker = tokio::time::interval(FOLD_FLUSH_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            result = &mut task => return result,
+            _ = ticker.tick() => ctx.flush_folded(),
+        }
+    }
+}
+
+fn emit_state(
+    app_handle: &AppHandle,
+    platform: DanmakuPlatform,
+    room_id: &str,
+    state: DanmakuConnectionState,
+) {
+    let payload = DanmakuStatePayload {
+        platform,
+        room_id: room_id.to_string(),
+        state,
+    };
+    if let Err(e) = app_handle.emit(DANMAKU_STATE_EVENT, payload) {
+        eprintln!("[Danmaku] Failed to emit state event: {}", e);
+    }
+}
+
+struct ListenerEntry {
+    generation: u64,
+    subscribers: u32,
+    started_at: i64,
+    state: DanmakuConnectionState,
+    stop_tx: mpsc::Sender<()>,
+}
+
+type ListenerKey = (DanmakuPlatform, String);
+
+/// 所有平台弹幕监听的注册表：同一房间只保持一条连接，按订阅数决定何时断开
+#[derive(Default, Clone)]
+pub struct DanmakuRegistry {
+    listeners: Arc<Mutex<HashMap<ListenerKey, ListenerEntry>>>,
+    // 尚未退出的监听任务（含已停止、正在收尾的），按代号索引；发送端随任务结束而关闭
+    running: Arc<Mutex<HashMap<u64, watch::Receiver<()>>>>,
+}
+
+impl DanmakuRegistry {
+    fn info(key: &ListenerKey, entry: &ListenerEntry) -> DanmakuListenerInfo {
+        DanmakuListenerInfo {
+            platform: key.0,
+            room_id: key.1.clone(),
+            subscribers: entry.subscribers,
+            started_at: entry.started_at,
+            state: entry.state.clone(),
+        }
+    }
+
+    /// 订阅房间弹幕；已有连接时只增加订阅数
+    pub fn start(
+        &self,
+        app_handle: &AppHandle,
+        platform: DanmakuPlatform,
+        room_id: &str,
+        options: DanmakuStartOptions,
+    ) -> Result<DanmakuListenerInfo, String> {
+        let listener = listener_for(platform);
+        let room_id = listener.normalize_room_id(room_id);
+        if room_id.is_empty() {
+            return Err("房间号不能为空".to_string());
+        }
+        let key = (platform, room_id.clone());
+
+        let (ctx, stop_rx, info) = {
+            let mut listeners = self.listeners.lock().unwrap();
+            if let Some(entry) = listeners.get_mut(&key) {
+                entry.subscribers += 1;
+                return Ok(Self::info(&key, entry));
+            }
+
+            let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+            let (stop_tx, stop_rx) = mpsc::channel::<()>(1);
+            let entry = ListenerEntry {
+                generation,
+                subscribers: 1,
+                started_at: chrono::Local::now().timestamp_millis(),
+                state: DanmakuConnectionState::Connecting,
+                stop_tx,
+            };
+            let info = Self::info(&key, &entry);
+            listeners.insert(key, entry);
+            let ctx = ListenerContext {
+                app_handle: app_handle.clone(),
+                platform,
+                room_id: room_id.clone(),
+                options,
+                registry: self.clone(),
+                generation,
+                filter: Arc::new(Mutex::new(DanmakuFilter::new(platform, &room_id))),
+                archive: DanmakuArchive::spawn(platform, &room_id),
+            };
+            (ctx, stop_rx, info)
+        };
+
+        println!(
+            "[Danmaku] Starting {} listener for room {}",
+            platform.as_str(),
+            room_id
+        );
+        emit_state(
+            app_handle,
+            platform,
+            &room_id,
+            DanmakuConnectionState::Connecting,
+        );
+        let registry = self.clone();
+        let task = listener.run(ctx.clone(), stop_rx);
+        let (done_tx, done_rx) = watch::channel(());
+        self.running.lock().unwrap().insert(ctx.generation, done_rx);
+        tokio::spawn(async move {
+            let reason = run_with_fold_flush(&ctx, task).await.err();
+            registry.finish(&ctx, reason).await;
+            registry.running.lock().unwrap().remove(&ctx.generation);
+            drop(done_tx);
+        });
+        Ok(info)
+    }
+
+    /// 取消一个订阅；订阅数归零时断开连接。返回剩余订阅数
+    pub fn stop(&self, platform: DanmakuPlatform, room_id: &str) -> u32 {
+        let key = (platform, normalize_room_id(platform, room_id));
+        let mut listeners = self.listeners.lock().unwrap();
+        let Some(entry) = listeners.get_mut(&key) else {
+            return 0;
+        };
+        entry.subscribers = entry.subscribers.saturating_sub(1);
+        if entry.subscribers > 0 {
+            return entry.subscribers;
+        }
+        if let Some(entry) = listeners.remove(&key) {
+            let _ = entry.stop_tx.try_send(());
+        }
+        0
+    }
+
+    /// 断开所有连接并等待监听任务写完存档（退出时使用），最多等待 `timeout`。返回断开前的房间
+    pub async fn shutdown_all(&self, timeout: Duration) -> Vec<(DanmakuPlatform, String)> {
+        let drained: Vec<_> = self.listeners.lock().unwrap().drain().collect();
+        let rooms = drained
+            .into_iter()
+            .map(|(key, entry)| {
+                let _ = entry.stop_tx.try_send(());
+                key
+            })
+            .collect();
+        let running: Vec<_> = self.running.lock().unwrap().values().cloned().collect();
+        let wait_all = futures_util::future::join_all(
+            running
+                .into_iter()
+                .map(|mut done| async move { while done.changed().await.is_ok() {} }),
+        );
+        if tokio::time::timeout(timeout, wait_all).await.is_err() {
+            eprintln!("[Danmaku] Timed out waiting for listeners to close");
+        }
+        rooms
+    }
+
+    pub fn list(&self) -> Vec<DanmakuListenerInfo> {
+        let listeners = self.listeners.lock().unwrap();
+        let mut list: Vec<_> = listeners
+            .iter()
+            .map(|(key, entry)| Self::info(key, entry))
+            .collect();
+        list.sort_by_key(|info| info.started_at);
+        list
+    }
+
+    // 只更新同一代的条目，避免已停止的旧任务覆盖新连接的状态
+    fn update_state(
+        &self,
+        platform: DanmakuPlatform,
+        room_id: &str,
+        generation: u64,
+        state: &DanmakuConnectionState,
+    ) -> bool {
+        let mut listeners = self.listeners.lock().unwrap();
+        match listeners.get_mut(&(platform, room_id.to_string())) {
+            Some(entry) if entry.generation == generation => {
+                entry.state = state.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // 监听任务退出：移除自己的条目并推送 closed；同房间已有新连接时不推送
+    async fn finish(&self, ctx: &ListenerContext, reason: Option<String>) {
+        if let Some(archive) = &ctx.archive {
+            archive.close().await;
+        }
+        let superseded = {
+            let mut listeners = self.listeners.lock().unwrap();
+            let key = (ctx.platform, ctx.room_id.clone());
+            match listeners.get(&key) {
+                Some(entry) if entry.generation == ctx.generation => {
+                    listeners.remove(&key);
+                    false
+                }
+                Some(_) => true,
+                None => false,
+            }
+        };
+        println!(
+            "[Danmaku] {} listener for room {} closed{}",
+            ctx.platform.as_str(),
+            ctx.room_id,
+            reason
+                .as_deref()
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default()
+        );
+        if !superseded {
+            emit_state(
+                &ctx.app_handle,
+                ctx.platform,
+                &ctx.room_id,
+                DanmakuConnectionState::Closed { reason },
+            );
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn start_danmaku(
+    platform: DanmakuPlatform,
+    room_id: String,
+    options: Option<DanmakuStartOptions>,
+    app_handle: AppHandle,
+    registry: State<'_, DanmakuRegistry>,
+) -> Result<DanmakuListenerInfo, String> {
+    registry.start(&app_handle, platform, &room_id, options.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn stop_danmaku(
+    platform: DanmakuPlatform,
+    room_id: String,
+    registry: State<'_, DanmakuRegistry>,
+) -> Result<u32, String> {
+    Ok(registry.stop(platform, &room_id))
+}
+
+#[tauri::command]
+pub fn list_danmaku_listeners(registry: State<'_, DanmakuRegistry>) -> Vec<DanmakuListenerInfo> {
+    registry.list()
+}
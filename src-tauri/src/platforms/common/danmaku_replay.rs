@@ -0,0 +1,330 @@
+// 弹幕回放：按存档里的相对时间重新推送给前端，支持倍速、跳转和暂停
+//
+// 回放结束后任务不会退出，停在末尾等待跳转或 stop_danmaku_replay。
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration, Instant};
+
+use super::danmaku::{DanmakuEvent, DanmakuPlatform};
+use super::danmaku_archive::{self, ArchiveRecord};
+
+pub const DANMAKU_REPLAY_EVENT: &str = "danmaku-replay";
+pub const DANMAKU_REPLAY_STATE_EVENT: &str = "danmaku-replay-state";
+
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 16.0;
+
+static NEXT_REPLAY_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuReplayPayload {
+    pub replay_id: u32,
+    /// 该条消息在会话中的位置
+    pub position_ms: u64,
+    pub event: DanmakuEvent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DanmakuReplayStatus {
+    Playing,
+    Paused,
+    /// 已推送到最后一条，可以跳转回去继续
+    Finished,
+    Stopped,
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuReplayState {
+    pub replay_id: u32,
+    pub status: DanmakuReplayStatus,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub speed: f64,
+    pub event_count: u32,
+}
+
+enum ReplayCommand {
+    Pause,
+    Resume,
+    Seek(u64),
+    Speed(f64),
+    Stop,
+}
+
+#[derive(Default, Clone)]
+pub struct DanmakuReplayManager(Arc<Mutex<HashMap<u32, mpsc::UnboundedSender<ReplayCommand>>>>);
+
+impl DanmakuReplayManager {
+    fn send(&self, replay_id: u32, command: ReplayCommand) -> Result<(), String> {
+        let replays = self.0.lock().unwrap();
+        let tx = replays
+            .get(&replay_id)
+            .ok_or_else(|| format!("Replay {} not found", replay_id))?;
+        tx.send(command)
+            .map_err(|_| format!("Replay {} has already exited", replay_id))
+    }
+}
+
+fn clamp_speed(speed: f64) -> f64 {
+    if speed.is_finite() {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    } else {
+        1.0
+    }
+}
+
+/// 回放时钟：位置 = 基准位置 + 基准时刻以来的实际时间 × 倍速
+struct ReplayClock {
+    base_position: u64,
+    base_instant: Instant,
+    speed: f64,
+    paused: bool,
+}
+
+impl ReplayClock {
+    fn position(&self) -> u64 {
+        if self.paused {
+            return self.base_position;
+        }
+        let elapsed = self.base_instant.elapsed().as_secs_f64() * 1000.0 * self.speed;
+        self.base_position + elapsed as u64
+    }
+
+    fn rebase(&mut self, position: u64) {
+        self.base_position = position;
+        self.base_instant = Instant::now();
+    }
+
+    fn pause(&mut self) {
+        let position = self.position();
+        self.rebase(position);
+        self.paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.base_instant = Instant::now();
+        self.paused = false;
+    }
+
+    fn set_speed(&mut self, speed: f64) {
+        let position = self.position();
+        self.rebase(position);
+        self.speed = speed;
+    }
+
+    /// 距离播放到 target 还需要等待的实际时间
+    fn until(&self, target: u64) -> Duration {
+        let remaining = target.saturating_sub(self.position()) as f64;
+        Duration::from_secs_f64(remaining / 1000.0 / self.speed)
+    }
+}
+
+struct Replay {
+    app_handle: AppHandle,
+    replay_id: u32,
+    records: Vec<ArchiveRecord>,
+    next: usize,
+    clock: ReplayClock,
+}
+
+impl Replay {
+    fn duration_ms(&self) -> u64 {
+        self.records.last().map(|r| r.offset_ms).unwrap_or(0)
+    }
+
+    fn finished(&self) -> bool {
+        self.next >= self.records.len()
+    }
+
+    fn seek(&mut self, position: u64) {
+        let position = position.min(self.duration_ms());
+        self.clock.rebase(position);
+        self.next = self.records.partition_point(|r| r.offset_ms < position);
+    }
+
+    fn emit_state(&self, status: DanmakuReplayStatus) {
+        let state = DanmakuReplayState {
+            replay_id: self.replay_id,
+            status,
+            position_ms: self.clock.position().min(self.duration_ms()),
+            duration_ms: self.duration_ms(),
+            speed: self.clock.speed,
+            event_count: self.records.len() as u32,
+        };
+        let _ = self.app_handle.emit(DANMAKU_REPLAY_STATE_EVENT, state);
+    }
+
+    fn current_status(&self) -> DanmakuReplayStatus {
+        if self.finished() {
+            DanmakuReplayStatus::Finished
+        } else if self.clock.paused {
+            DanmakuReplayStatus::Paused
+        } else {
+            DanmakuReplayStatus::Playing
+        }
+    }
+
+    // 推送所有已到时间的消息
+    fn emit_due(&mut self) {
+        let position = self.clock.position();
+        while let Some(record) = self.records.get(self.next) {
+            if record.offset_ms > position {
+                break;
+            }
+            let payload = DanmakuReplayPayload {
+                replay_id: self.replay_id,
+                position_ms: record.offset_ms,
+                event: record.event.clone(),
+            };
+            if let Err(e) = self.app_handle.emit(DANMAKU_REPLAY_EVENT, payload) {
+                eprintln!("[DanmakuReplay {}] Failed to emit: {}", self.replay_id, e);
+            }
+            self.next += 1;
+        }
+        if self.finished() {
+            let end = self.duration_ms();
+            self.clock.rebase(end);
+            self.clock.paused = true;
+            self.emit_state(DanmakuReplayStatus::Finished);
+        }
+    }
+
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<ReplayCommand>) {
+        self.emit_state(self.current_status());
+        loop {
+            let wait = match self.records.get(self.next) {
+                Some(record) if !self.clock.paused => Some(self.clock.until(record.offset_ms)),
+                _ => None,
+            };
+            tokio::select! {
+                command = rx.recv() => {
+                    match command {
+                        None | Some(ReplayCommand::Stop) => break,
+                        Some(ReplayCommand::Pause) => self.clock.pause(),
+                        Some(ReplayCommand::Resume) => {
+                            if !self.finished() {
+                                self.clock.resume();
+                            }
+                        }
+                        Some(ReplayCommand::Seek(position)) => {
+                            let paused = self.clock.paused && !self.finished();
+                            self.seek(position);
+                            // 从末尾跳回来时继续播放
+                            self.clock.paused = paused;
+                        }
+                        Some(ReplayCommand::Speed(speed)) => self.clock.set_speed(speed),
+                    }
+                    self.emit_state(self.current_status());
+                }
+                _ = sleep(wait.unwrap_or_default()), if wait.is_some() => self.emit_due(),
+            }
+        }
+        self.emit_state(DanmakuReplayStatus::Stopped);
+    }
+}
+
+#[derive(Serialize, Clone, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuReplayInfo {
+    pub replay_id: u32,
+    pub duration_ms: u64,
+    pub event_count: u32,
+}
+
+// ---- Tauri 命令 ----
+
+/// 开始回放一个存档会话；事件通过 `danmaku-replay` 推送，按 replayId 区分
+#[tauri::command]
+pub async fn start_danmaku_replay(
+    platform: DanmakuPlatform,
+    room_id: String,
+    session_id: String,
+    speed: Option<f64>,
+    position_ms: Option<u64>,
+    app_handle: AppHandle,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<DanmakuReplayInfo, String> {
+    let records = tokio::task::spawn_blocking(move || {
+        danmaku_archive::read_session(platform, &room_id, &session_id)
+    })
+    .await
+    .map_err(|e| format!("Failed to read danmaku archive: {}", e))??;
+
+    let replay_id = NEXT_REPLAY_ID.fetch_add(1, Ordering::Relaxed);
+    let mut replay = Replay {
+        app_handle,
+        replay_id,
+        records,
+        next: 0,
+        clock: ReplayClock {
+            base_position: 0,
+            base_instant: Instant::now(),
+            speed: clamp_speed(speed.unwrap_or(1.0)),
+            paused: false,
+        },
+    };
+    replay.seek(position_ms.unwrap_or(0));
+    let info = DanmakuReplayInfo {
+        replay_id,
+        duration_ms: replay.duration_ms(),
+        event_count: replay.records.len() as u32,
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    manager.0.lock().unwrap().insert(replay_id, tx);
+    let manager = manager.inner().clone();
+    tokio::spawn(async move {
+        replay.run(rx).await;
+        manager.0.lock().unwrap().remove(&replay_id);
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn pause_danmaku_replay(
+    replay_id: u32,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<(), String> {
+    manager.send(replay_id, ReplayCommand::Pause)
+}
+
+#[tauri::command]
+pub fn resume_danmaku_replay(
+    replay_id: u32,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<(), String> {
+    manager.send(replay_id, ReplayCommand::Resume)
+}
+
+#[tauri::command]
+pub fn seek_danmaku_replay(
+    replay_id: u32,
+    position_ms: u64,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<(), String> {
+    manager.send(replay_id, ReplayCommand::Seek(position_ms))
+}
+
+#[tauri::command]
+pub fn set_danmaku_replay_speed(
+    replay_id: u32,
+    speed: f64,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<(), String> {
+    manager.send(replay_id, ReplayCommand::Speed(clamp_speed(speed)))
+}
+
+#[tauri::command]
+pub fn stop_danmaku_replay(
+    replay_id: u32,
+    manager: State<'_, DanmakuReplayManager>,
+) -> Result<(), String> {
+    manager.send(replay_id, ReplayCommand::Stop)
+}
@@ -0,0 +1,342 @@
+use crate::platforms::douyin::web_api::DEFAULT_USER_AGENT as DOUYIN_USER_AGENT;
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+const RULES_FILE_NAME: &str = "header-rules.json";
+const DEFAULT_UPSTREAM_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36";
+const MOBILE_UPSTREAM_USER_AGENT: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1";
+
+static RULES: Lazy<RwLock<Arc<HeaderRuleSet>>> =
+    Lazy::new(|| RwLock::new(Arc::new(HeaderRuleSet::load())));
+
+/// 一条上游请求头规则。`platform` 与 `hosts` 任一命中即生效（两者都为空时匹配所有请求），
+/// 按顺序叠加，后面的规则覆盖前面的同名请求头；值为空字符串表示删除该请求头
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRule {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub platform: Option<String>,
+    // `*.example.com` 同时匹配 `example.com` 本身
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// 配置文件格式；默认追加在内置规则之后，`replaceDefaults` 为 true 时完全替换内置规则
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HeaderRulesFile {
+    #[serde(default)]
+    replace_defaults: bool,
+    #[serde(default)]
+    rules: Vec<HeaderRule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRulesInfo {
+    pub config_path: String,
+    pub rules: Vec<HeaderRule>,
+}
+
+struct CompiledRule {
+    platform: Option<String>,
+    hosts: Vec<String>,
+    // None 表示删除
+    headers: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+struct HeaderRuleSet {
+    rules: Vec<HeaderRule>,
+    compiled: Vec<CompiledRule>,
+}
+
+fn rule(
+    name: &str,
+    platform: Option<&str>,
+    hosts: &[&str],
+    headers: &[(&str, &str)],
+) -> HeaderRule {
+    HeaderRule {
+        name: Some(name.to_string()),
+        platform: platform.map(str::to_string),
+        hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        headers: headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    }
+}
+
+/// 内置规则。平台模块的取流/接口请求、图片代理、FLV 代理与录制都从这里取 Referer/Origin/UA，
+/// 只有与具体页面相关的请求头（如带房间号的 Referer）才在调用处覆盖。
+///
+/// 与迁移前各处硬编码的差异：
+/// - B 站所有请求（含 FLV 拉流与接口）都会带 `Origin: https://live.bilibili.com`；
+/// - 抖音图片的 Referer 由 `https://www.douyin.com/` 改为 `https://live.douyin.com/`，
+///   抖音请求统一使用 a_bogus 签名所用的 UA；
+/// - 移动端站点（m.huya.com、mp.huya.com、m.douyu.com）统一使用同一个 iOS UA
+fn default_rules() -> Vec<HeaderRule> {
+    vec![
+        rule(
+            "default",
+            None,
+            &[],
+            &[("User-Agent", DEFAULT_UPSTREAM_USER_AGENT)],
+        ),
+        rule(
+            "bilibili",
+            Some("BILIBILI"),
+            &[
+                "*.bilibili.com",
+                "*.hdslb.com",
+                "*.biliimg.com",
+                "*.bilivideo.com",
+                "*.bilivideo.cn",
+            ],
+            &[
+                ("Referer", "https://live.bilibili.com/"),
+                ("Origin", "https://live.bilibili.com"),
+            ],
+        ),
+        rule(
+            "huya",
+            Some("HUYA"),
+            &[
+                "*.huya.com",
+                "*.hy-cdn.com",
+                "*.huyaimg.com",
+                "*.msstatic.com",
+            ],
+            &[
+                ("Referer", "https://www.huya.com/"),
+                ("Origin", "https://www.huya.com"),
+            ],
+        ),
+        rule(
+            "douyin",
+            Some("DOUYIN"),
+            &[
+                "*.douyin.com",
+                "*.douyinpic.com",
+                "*.douyincdn.com",
+                "*.douyinliving.com",
+                "*.douyinvod.com",
+            ],
+            &[
+                ("Referer", "https://live.douyin.com/"),
+                ("Origin", "https://live.douyin.com"),
+                // a_bogus 与弹幕签名以 UA 为输入，签名方通过 `signed_request_headers` 读取同一个值
+                ("User-Agent", DOUYIN_USER_AGENT),
+            ],
+        ),
+        rule(
+            "douyu",
+            Some("DOUYU"),
+            &["*.douyu.com", "*.douyucdn.cn", "*.douyucdn2.cn"],
+            &[("Referer", "https://www.douyu.com/")],
+        ),
+        rule(
+            "huya-mobile",
+            None,
+            &["m.huya.com", "mp.huya.com"],
+            &[
+                ("User-Agent", MOBILE_UPSTREAM_USER_AGENT),
+                ("Referer", "https://m.huya.com/"),
+                ("Origin", "https://m.huya.com"),
+            ],
+        ),
+        rule(
+            "douyu-mobile",
+            None,
+            &["m.douyu.com"],
+            &[
+                ("User-Agent", MOBILE_UPSTREAM_USER_AGENT),
+                ("Referer", "https://m.douyu.com/"),
+            ],
+        ),
+    ]
+}
+
+/// 主机名通配匹配：`*.example.com` 匹配 `example.com` 及其所有子域名
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => {
+            host == suffix
+                || (host.len() > suffix.len()
+                    && host.ends_with(suffix)
+                    && host.as_bytes()[host.len() - suffix.len() - 1] == b'.')
+        }
+        None => host == pattern,
+    }
+}
+
+impl CompiledRule {
+    fn compile(rule: &HeaderRule) -> Result<Self, String> {
+        let label = rule.name.as_deref().unwrap_or("<unnamed>");
+        let mut headers = Vec::with_capacity(rule.headers.len());
+        for (name, value) in &rule.headers {
+            let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|e| format!("rule {}: invalid header name {}: {}", label, name, e))?;
+            let header_value =
+                if value.is_empty() {
+                    None
+                } else {
+                    Some(HeaderValue::from_str(value).map_err(|e| {
+                        format!("rule {}: invalid value for {}: {}", label, name, e)
+                    })?)
+                };
+            headers.push((header_name, header_value));
+        }
+        Ok(Self {
+            platform: rule
+                .platform
+                .as_deref()
+                .map(|p| p.trim().to_ascii_uppercase())
+                .filter(|p| !p.is_empty()),
+            hosts: rule
+                .hosts
+                .iter()
+                .map(|h| h.trim().trim_end_matches('.').to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            headers,
+        })
+    }
+
+    fn matches(&self, platform: Option<&str>, host: Option<&str>) -> bool {
+        if self.platform.is_none() && self.hosts.is_empty() {
+            return true;
+        }
+        let platform_hit = matches!(
+            (self.platform.as_deref(), platform),
+            (Some(expected), Some(actual)) if expected.eq_ignore_ascii_case(actual)
+        );
+        let host_hit =
+            host.is_some_and(|host| self.hosts.iter().any(|pattern| host_matches(pattern, host)));
+        platform_hit || host_hit
+    }
+}
+
+impl HeaderRuleSet {
+    fn config_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("DTV")
+            .join(RULES_FILE_NAME)
+    }
+
+    fn load() -> Self {
+        let path = Self::config_path();
+        let file = match std::fs::read_to_string(&path) {
+            Ok(raw) => match serde_json::from_str::<HeaderRulesFile>(&raw) {
+                Ok(file) => {
+                    println!(
+                        "[HeaderRules] Loaded {} rule(s) from {}",
+                        file.rules.len(),
+                        path.display()
+                    );
+                    file
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[HeaderRules] Ignoring invalid config {}: {}",
+                        path.display(),
+                        e
+                    );
+                    HeaderRulesFile::default()
+                }
+            },
+            Err(_) => HeaderRulesFile::default(),
+        };
+
+        let mut rules = if file.replace_defaults {
+            Vec::new()
+        } else {
+            default_rules()
+        };
+        rules.extend(file.rules);
+
+        let mut kept = Vec::with_capacity(rules.len());
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            match CompiledRule::compile(&rule) {
+                Ok(c) => {
+                    compiled.push(c);
+                    kept.push(rule);
+                }
+                Err(e) => eprintln!("[HeaderRules] Skipping {}", e),
+            }
+        }
+        Self {
+            rules: kept,
+            compiled,
+        }
+    }
+
+    fn headers_for(&self, platform: Option<&str>, url: &str) -> HeaderMap {
+        let host = reqwest::Url::parse(url).ok().and_then(|u| {
+            u.host_str()
+                .map(|h| h.trim_end_matches('.').to_ascii_lowercase())
+        });
+        let mut headers = HeaderMap::new();
+        for rule in &self.compiled {
+            if !rule.matches(platform, host.as_deref()) {
+                continue;
+            }
+            for (name, value) in &rule.headers {
+                match value {
+                    Some(value) => {
+                        headers.insert(name.clone(), value.clone());
+                    }
+                    None => {
+                        headers.remove(name);
+                    }
+                }
+            }
+        }
+        headers
+    }
+}
+
+/// 按规则表计算访问 `url` 时应带的 Referer/Origin/User-Agent 等请求头
+pub fn upstream_headers(platform: Option<&str>, url: &str) -> HeaderMap {
+    let rules = RULES.read().unwrap().clone();
+    rules.headers_for(platform, url)
+}
+
+/// 给请求挂上规则表中的请求头；之后再设置的同名请求头会覆盖规则
+pub trait UpstreamHeadersExt: Sized {
+    fn upstream_headers(self, platform: Option<&str>, url: &str) -> Self;
+}
+
+impl UpstreamHeadersExt for reqwest::RequestBuilder {
+    fn upstream_headers(self, platform: Option<&str>, url: &str) -> Self {
+        self.headers(upstream_headers(platform, url))
+    }
+}
+
+fn rules_info() -> HeaderRulesInfo {
+    HeaderRulesInfo {
+        config_path: HeaderRuleSet::config_path().display().to_string(),
+        rules: RULES.read().unwrap().rules.clone(),
+    }
+}
+
+#[tauri::command]
+pub fn get_upstream_header_rules() -> HeaderRulesInfo {
+    rules_info()
+}
+
+/// 重新读取配置文件，平台更换防盗链规则时无需重启
+#[tauri::command]
+pub fn reload_upstream_header_rules() -> HeaderRulesInfo {
+    *RULES.write().unwrap() = Arc::new(HeaderRuleSet::load());
+    rules_info()
+}
@@ -1,6 +1,8 @@
+use super::network_proxy::{self, OutboundProxyExt};
 use reqwest::header::{HeaderMap as ReqwestHeaderMap, HeaderName, HeaderValue, USER_AGENT};
 use reqwest::{cookie::Jar, Client, RequestBuilder, Response};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/109.0.0.0 Safari/537.36";
@@ -40,9 +42,9 @@ impl HttpClient {
         })
     }
 
-    /// 创建一个绕过所有代理的直连HTTP客户端
-    /// 这个客户端将忽略系统代理设置，直接连接到目标服务器
-    pub fn new_direct_connection() -> Result<Self, String> {
+    /// 创建一个忽略系统代理的HTTP客户端
+    /// 只使用应用内为该平台配置的出站代理，未配置时直接连接到目标服务器
+    pub fn new_direct_connection(platform: Option<&str>) -> Result<Self, String> {
         let mut default_headers = ReqwestHeaderMap::new();
         default_headers.insert(
             USER_AGENT,
@@ -55,7 +57,7 @@ impl HttpClient {
         let client_builder = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECONDS))
             .cookie_provider(cookie_jar)
-            .no_proxy(); // 关键：禁用所有代理设置
+            .outbound_proxy(platform); // 关键：不读取系统代理设置
 
         let inner_client = client_builder
             .build()
@@ -67,8 +69,11 @@ impl HttpClient {
         })
     }
 
-    /// 直连 + 限制连接池规模，用于关注刷新等低并发任务
-    pub fn new_direct_limited(max_idle_per_host: usize) -> Result<Self, String> {
+    /// 直连（或该平台的出站代理）+ 限制连接池规模，用于关注刷新等低并发任务
+    pub fn new_direct_limited(
+        platform: Option<&str>,
+        max_idle_per_host: usize,
+    ) -> Result<Self, String> {
         let mut default_headers = ReqwestHeaderMap::new();
         default_headers.insert(
             USER_AGENT,
@@ -81,7 +86,7 @@ impl HttpClient {
         let client_builder = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECONDS))
             .cookie_provider(cookie_jar)
-            .no_proxy()
+            .outbound_proxy(platform)
             .pool_max_idle_per_host(max_idle_per_host)
             .pool_idle_timeout(Duration::from_secs(FOLLOW_POOL_IDLE_TIMEOUT_SECONDS));
 
@@ -277,14 +282,37 @@ impl HttpClient {
     }
 }
 
-/// 专用于关注刷新等低并发任务的 HTTP 客户端包装
-#[derive(Debug, Clone)]
-pub struct FollowHttpClient(pub HttpClient);
+/// 关注刷新、取流地址解析与录制共用的 HTTP 客户端：每个平台各一个，
+/// 出站代理设置保存后，下次取用时按新设置重建
+#[derive(Debug, Clone, Default)]
+pub struct FollowHttpClient {
+    // 平台名（大写，全局为空串） -> (构建时的设置版本, 客户端)
+    clients: Arc<Mutex<HashMap<String, (u64, HttpClient)>>>,
+}
 
 impl FollowHttpClient {
     pub fn new() -> Result<Self, String> {
-        Ok(Self(HttpClient::new_direct_limited(
-            FOLLOW_POOL_MAX_IDLE_PER_HOST,
-        )?))
+        let follow = Self::default();
+        // 启动时先建好全局客户端，构建失败可以尽早暴露
+        follow.client(None)?;
+        Ok(follow)
+    }
+
+    /// 取得对应平台当前代理设置下的客户端；`platform` 为空时使用全局设置
+    pub fn client(&self, platform: Option<&str>) -> Result<HttpClient, String> {
+        let key = platform
+            .map(|p| p.trim().to_ascii_uppercase())
+            .unwrap_or_default();
+        let generation = network_proxy::settings_generation();
+        let mut clients = self.clients.lock().unwrap();
+        if let Some((built_for, client)) = clients.get(&key) {
+            if *built_for == generation {
+                return Ok(client.clone());
+            }
+        }
+        let platform = (!key.is_empty()).then_some(key.as_str());
+        let client = HttpClient::new_direct_limited(platform, FOLLOW_POOL_MAX_IDLE_PER_HOST)?;
+        clients.insert(key, (generation, client.clone()));
+        Ok(client)
     }
 }
@@ -1,14 +1,20 @@
 #![allow(unused_imports)]
+pub mod danmaku;
+pub mod danmaku_archive;
+pub mod danmaku_export;
+pub mod danmaku_filter;
+pub mod danmaku_listener;
+pub mod danmaku_replay;
+pub mod header_rules;
 pub mod http_client;
+pub mod network_proxy;
 pub mod types;
 pub mod types_rust;
 
 // Re-export necessary types to make them available directly under platforms::common::TypeName
+pub use danmaku::{
+    emit_danmaku, emit_danmaku_error, DanmakuEvent, DanmakuEventKind, DanmakuPlatform, DanmakuUser,
+};
 pub use http_client::FollowHttpClient;
-pub use types::BilibiliDanmakuState;
-pub use types::DanmakuFrontendPayload;
-pub use types::DouyinDanmakuState;
-pub use types::DouyuDanmakuState;
 pub use types::GetStreamUrlPayload;
-pub use types::HuyaDanmakuState;
 pub use types::LiveStreamInfo;
@@ -0,0 +1,778 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Response;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const SETTINGS_FILE_NAME: &str = "network-proxy.json";
+// 代理密码不写进上面的设置文件，单独保存在同目录的这个文件里（Unix 下权限 0600）。
+// 键为 "global" 或平台名，值为明文密码；删除该文件即清除所有已保存的代理密码
+const CREDENTIALS_FILE_NAME: &str = "network-proxy-credentials.json";
+const GLOBAL_CREDENTIALS_KEY: &str = "global";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+// CONNECT 响应头的上限，防止异常代理无限输出
+const MAX_CONNECT_RESPONSE_BYTES: usize = 8 * 1024;
+
+// 读取设置时密码以占位符返回；保存时收到占位符表示沿用已保存的密码
+const PASSWORD_MASK: &str = "********";
+
+static SETTINGS: Lazy<RwLock<NetworkProxySettings>> = Lazy::new(|| RwLock::new(load_settings()));
+// 每次保存设置后递增，长期持有的客户端据此判断是否需要重建
+static SETTINGS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboundProxyScheme {
+    Http,
+    // 与代理之间走 TLS
+    Https,
+    // 目标域名交给代理解析（socks5h）
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboundProxy {
+    pub scheme: OutboundProxyScheme,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// 单个平台的覆盖设置；平台不在表中时沿用全局设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum PlatformProxyOverride {
+    // 即使配置了全局代理也直连
+    Direct,
+    Custom(OutboundProxy),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkProxySettings {
+    #[serde(default)]
+    pub global: Option<OutboundProxy>,
+    // 键为平台名（DOUYU / DOUYIN / HUYA / BILIBILI），不区分大小写
+    #[serde(default)]
+    pub platforms: HashMap<String, PlatformProxyOverride>,
+}
+
+impl OutboundProxy {
+    fn validate(&self) -> Result<(), String> {
+        if self.host.trim().is_empty() {
+            return Err("Proxy host is empty".to_string());
+        }
+        if self.port == 0 {
+            return Err(format!("Invalid proxy port for {}", self.host));
+        }
+        if let Some(username) = self.username.as_deref() {
+            // SOCKS5 用户名/密码认证的长度字段只有一个字节
+            if username.len() > 255 || self.password.as_deref().unwrap_or("").len() > 255 {
+                return Err("Proxy username/password is too long".to_string());
+            }
+        }
+        self.reqwest_url().map(|_| ())
+    }
+
+    fn credentials(&self) -> Option<(&str, &str)> {
+        self.username
+            .as_deref()
+            .filter(|u| !u.is_empty())
+            .map(|u| (u, self.password.as_deref().unwrap_or("")))
+    }
+
+    fn reqwest_url(&self) -> Result<reqwest::Url, String> {
+        let scheme = match self.scheme {
+            OutboundProxyScheme::Http => "http",
+            OutboundProxyScheme::Https => "https",
+            OutboundProxyScheme::Socks5 => "socks5h",
+        };
+        let host = self.host.trim();
+        let host = if host.contains(':') && !host.starts_with('[') {
+            format!("[{}]", host)
+        } else {
+            host.to_string()
+        };
+        let mut url = reqwest::Url::parse(&format!("{}://{}:{}", scheme, host, self.port))
+            .map_err(|e| format!("Invalid proxy address {}: {}", self.host, e))?;
+        if let Some((username, password)) = self.credentials() {
+            url.set_username(username)
+                .and_then(|_| url.set_password(Some(password)))
+                .map_err(|_| format!("Invalid proxy credentials for {}", self.host))?;
+        }
+        Ok(url)
+    }
+
+    fn masked(mut self) -> Self {
+        if self.password.as_deref().is_some_and(|p| !p.is_empty()) {
+            self.password = Some(PASSWORD_MASK.to_string());
+        }
+        self
+    }
+
+    fn restore_password(&mut self, previous: Option<&OutboundProxy>) {
+        if self.password.as_deref() == Some(PASSWORD_MASK) {
+            self.password = previous.and_then(|p| p.password.clone());
+        }
+    }
+
+    fn basic_auth_header(&self) -> Option<String> {
+        use base64::Engine;
+
+        self.credentials().map(|(username, password)| {
+            let token = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password));
+            format!("Basic {}", token)
+        })
+    }
+}
+
+impl NetworkProxySettings {
+    fn normalized(mut self) -> Result<Self, String> {
+        if let Some(global) = self.global.as_mut() {
+            global.host = global.host.trim().to_string();
+            global.validate()?;
+        }
+        let mut platforms = HashMap::new();
+        for (platform, mut entry) in self.platforms.drain() {
+            if let PlatformProxyOverride::Custom(proxy) = &mut entry {
+                proxy.host = proxy.host.trim().to_string();
+                proxy
+                    .validate()
+                    .map_err(|e| format!("{} ({})", e, platform))?;
+            }
+            platforms.insert(platform.trim().to_ascii_uppercase(), entry);
+        }
+        self.platforms = platforms;
+        Ok(self)
+    }
+
+    /// 返回给前端的副本，不包含明文密码
+    fn masked(&self) -> Self {
+        Self {
+            global: self.global.clone().map(OutboundProxy::masked),
+            platforms: self
+                .platforms
+                .iter()
+                .map(|(platform, entry)| {
+                    let entry = match entry {
+                        PlatformProxyOverride::Custom(proxy) => {
+                            PlatformProxyOverride::Custom(proxy.clone().masked())
+                        }
+                        PlatformProxyOverride::Direct => PlatformProxyOverride::Direct,
+                    };
+                    (platform.clone(), entry)
+                })
+                .collect(),
+        }
+    }
+
+    // 前端原样回传的占位符换回同一位置上已保存的密码
+    fn restore_masked_passwords(&mut self, previous: &Self) {
+        if let Some(global) = self.global.as_mut() {
+            global.restore_password(previous.global.as_ref());
+        }
+        for (platform, entry) in self.platforms.iter_mut() {
+            if let PlatformProxyOverride::Custom(proxy) = entry {
+                let previous = match previous.platforms.get(platform) {
+                    Some(PlatformProxyOverride::Custom(previous)) => Some(previous),
+                    _ => None,
+                };
+                proxy.restore_password(previous);
+            }
+        }
+    }
+
+    // 各个代理配置及其在密码文件中的键
+    fn proxies_mut(&mut self) -> impl Iterator<Item = (&str, &mut OutboundProxy)> {
+        let global = self
+            .global
+            .as_mut()
+            .map(|proxy| (GLOBAL_CREDENTIALS_KEY, proxy));
+        let platforms = self
+            .platforms
+            .iter_mut()
+            .filter_map(|(platform, entry)| match entry {
+                PlatformProxyOverride::Custom(proxy) => Some((platform.as_str(), proxy)),
+                PlatformProxyOverride::Direct => None,
+            });
+        global.into_iter().chain(platforms)
+    }
+
+    /// 拆出密码：返回不含密码的设置与各位置的密码
+    fn split_passwords(&self) -> (Self, HashMap<String, String>) {
+        let mut stripped = self.clone();
+        let mut passwords = HashMap::new();
+        for (key, proxy) in stripped.proxies_mut() {
+            if let Some(password) = proxy.password.take().filter(|p| !p.is_empty()) {
+                passwords.insert(key.to_string(), password);
+            }
+        }
+        (stripped, passwords)
+    }
+
+    fn apply_passwords(&mut self, passwords: &HashMap<String, String>) {
+        for (key, proxy) in self.proxies_mut() {
+            if proxy.password.is_none() {
+                proxy.password = passwords.get(key).cloned();
+            }
+        }
+    }
+
+    fn resolve(&self, platform: Option<&str>) -> Option<OutboundProxy> {
+        let entry = platform.and_then(|p| self.platforms.get(&p.trim().to_ascii_uppercase()));
+        match entry {
+            Some(PlatformProxyOverride::Direct) => None,
+            Some(PlatformProxyOverride::Custom(proxy)) => Some(proxy.clone()),
+            None => self.global.clone(),
+        }
+    }
+}
+
+fn settings_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+}
+
+fn settings_path() -> PathBuf {
+    settings_dir().join(SETTINGS_FILE_NAME)
+}
+
+fn credentials_path() -> PathBuf {
+    settings_dir().join(CREDENTIALS_FILE_NAME)
+}
+
+fn load_passwords() -> HashMap<String, String> {
+    let path = credentials_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return HashMap::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|e| {
+        eprintln!(
+            "[NetworkProxy] Ignoring invalid credentials in {}: {}",
+            path.display(),
+            e
+        );
+        HashMap::new()
+    })
+}
+
+fn save_passwords(passwords: &HashMap<String, String>) -> Result<(), String> {
+    let path = credentials_path();
+    if passwords.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        };
+    }
+    let raw = serde_json::to_string_pretty(passwords)
+        .map_err(|e| format!("Failed to serialize proxy credentials: {}", e))?;
+    write_private_file(&path, raw.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// 只允许当前用户读写
+fn write_private_file(path: &std::path::Path, content: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(content)
+}
+
+fn load_settings() -> NetworkProxySettings {
+    let path = settings_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return NetworkProxySettings::default();
+    };
+    let parsed = serde_json::from_str::<NetworkProxySettings>(&raw)
+        .map_err(|e| e.to_string())
+        .and_then(NetworkProxySettings::normalized);
+    match parsed {
+        Ok(mut settings) => {
+            // 旧版本把密码写在设置文件里，读到后迁移到密码文件
+            if !settings.split_passwords().1.is_empty() {
+                if let Err(e) = save_settings(&settings) {
+                    eprintln!("[NetworkProxy] Failed to migrate proxy passwords: {}", e);
+                }
+            }
+            settings.apply_passwords(&load_passwords());
+            settings
+        }
+        Err(e) => {
+            eprintln!(
+                "[NetworkProxy] Ignoring invalid settings in {}: {}",
+                path.display(),
+                e
+            );
+            NetworkProxySettings::default()
+        }
+    }
+}
+
+fn save_settings(settings: &NetworkProxySettings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let (settings, passwords) = settings.split_passwords();
+    save_passwords(&passwords)?;
+    let raw = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize network proxy settings: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 设置版本号，每次保存后递增
+pub fn settings_generation() -> u64 {
+    SETTINGS_GENERATION.load(Ordering::Acquire)
+}
+
+/// 当前对某个平台生效的出站代理；`None` 表示直连
+pub fn proxy_for(platform: Option<&str>) -> Option<OutboundProxy> {
+    SETTINGS.read().unwrap().resolve(platform)
+}
+
+fn reqwest_proxy(platform: Option<&str>) -> Option<reqwest::Proxy> {
+    let proxy = proxy_for(platform)?;
+    match proxy.reqwest_url().and_then(|url| {
+        reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy {}: {}", proxy.host, e))
+    }) {
+        Ok(proxy) => Some(proxy),
+        Err(e) => {
+            // 设置在保存时已校验，这里只会在配置文件被手动改坏时出现
+            eprintln!("[NetworkProxy] {}", e);
+            None
+        }
+    }
+}
+
+/// 给 reqwest 客户端挂上应用内配置的出站代理；未配置时直连，且始终忽略系统代理
+pub trait OutboundProxyExt: Sized {
+    fn outbound_proxy(self, platform: Option<&str>) -> Self;
+}
+
+impl OutboundProxyExt for reqwest::ClientBuilder {
+    fn outbound_proxy(self, platform: Option<&str>) -> Self {
+        match reqwest_proxy(platform) {
+            Some(proxy) => self.no_proxy().proxy(proxy),
+            None => self.no_proxy(),
+        }
+    }
+}
+
+impl OutboundProxyExt for reqwest::blocking::ClientBuilder {
+    fn outbound_proxy(self, platform: Option<&str>) -> Self {
+        match reqwest_proxy(platform) {
+            Some(proxy) => self.no_proxy().proxy(proxy),
+            None => self.no_proxy(),
+        }
+    }
+}
+
+// ---- 隧道握手（报文构造与解析） ----
+
+fn http_connect_request(proxy: &OutboundProxy, host: &str, port: u16) -> String {
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = format!(
+        "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\nProxy-Connection: keep-alive\r\n",
+        authority
+    );
+    if let Some(auth) = proxy.basic_auth_header() {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", auth));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+fn check_connect_response(head: &[u8]) -> io::Result<()> {
+    let text = String::from_utf8_lossy(head);
+    let status_line = text.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if status == "200" {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "Proxy refused CONNECT: {}",
+            status_line.trim()
+        )))
+    }
+}
+
+fn socks5_greeting(proxy: &OutboundProxy) -> Vec<u8> {
+    if proxy.credentials().is_some() {
+        // 无认证 + 用户名/密码
+        vec![0x05, 0x02, 0x00, 0x02]
+    } else {
+        vec![0x05, 0x01, 0x00]
+    }
+}
+
+fn socks5_auth_request(proxy: &OutboundProxy) -> Vec<u8> {
+    let (username, password) = proxy.credentials().unwrap_or(("", ""));
+    let mut request = vec![0x01, username.len() as u8];
+    request.extend_from_slice(username.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+    request
+}
+
+fn socks5_connect_request(host: &str, port: u16) -> io::Result<Vec<u8>> {
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            if host.len() > 255 {
+                return Err(io::Error::other(format!("Host name too long: {}", host)));
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    Ok(request)
+}
+
+fn socks5_check_method(reply: [u8; 2], proxy: &OutboundProxy) -> io::Result<bool> {
+    match reply {
+        [0x05, 0x00] => Ok(false),
+        [0x05, 0x02] if proxy.credentials().is_some() => Ok(true),
+        _ => Err(io::Error::other(
+            "SOCKS5 proxy rejected the offered authentication methods",
+        )),
+    }
+}
+
+fn socks5_check_auth(reply: [u8; 2]) -> io::Result<()> {
+    if reply[1] == 0x00 {
+        Ok(())
+    } else {
+        Err(io::Error::other("SOCKS5 proxy authentication failed"))
+    }
+}
+
+/// 校验 CONNECT 应答的前 4 字节，返回其后剩余的地址 + 端口长度
+fn socks5_reply_remaining(head: [u8; 4]) -> io::Result<Option<usize>> {
+    if head[0] != 0x05 {
+        return Err(io::Error::other("Invalid SOCKS5 reply"));
+    }
+    if head[1] != 0x00 {
+        return Err(io::Error::other(format!(
+            "SOCKS5 CONNECT failed with code {}",
+            head[1]
+        )));
+    }
+    match head[3] {
+        0x01 => Ok(Some(4 + 2)),
+        0x04 => Ok(Some(16 + 2)),
+        // 域名：还需先读一个长度字节
+        0x03 => Ok(None),
+        other => Err(io::Error::other(format!(
+            "Unknown SOCKS5 address type {}",
+            other
+        ))),
+    }
+}
+
+// ---- 异步连接（tokio / tokio-tungstenite） ----
+
+/// 经出站代理建立的异步 TCP 连接；HTTPS 代理时外层还有一层到代理的 TLS
+#[derive(Debug)]
+pub enum OutboundStream {
+    Tcp(TcpStream),
+    Tls(Box<tokio_native_tls::TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for OutboundStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            OutboundStream::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            OutboundStream::Tls(s) => Pin::new(s.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for OutboundStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            OutboundStream::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            OutboundStream::Tls(s) => Pin::new(s.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            OutboundStream::Tcp(s) => Pin::new(s).poll_flush(cx),
+            OutboundStream::Tls(s) => Pin::new(s.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            OutboundStream::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            OutboundStream::Tls(s) => Pin::new(s.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+pub type OutboundWebSocket = WebSocketStream<MaybeTlsStream<OutboundStream>>;
+
+async fn http_connect_async<S>(
+    stream: &mut S,
+    proxy: &OutboundProxy,
+    host: &str,
+    port: u16,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream
+        .write_all(http_connect_request(proxy, host, port).as_bytes())
+        .await?;
+    // 逐字节读到空行，避免把隧道后续数据读进缓冲
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_CONNECT_RESPONSE_BYTES {
+            return Err(io::Error::other("Proxy CONNECT response too large"));
+        }
+        stream.read_exact(&mut byte).await?;
+        head.push(byte[0]);
+    }
+    check_connect_response(&head)
+}
+
+async fn socks5_connect_async(
+    stream: &mut TcpStream,
+    proxy: &OutboundProxy,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    stream.write_all(&socks5_greeting(proxy)).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if socks5_check_method(reply, proxy)? {
+        stream.write_all(&socks5_auth_request(proxy)).await?;
+        stream.read_exact(&mut reply).await?;
+        socks5_check_auth(reply)?;
+    }
+    stream
+        .write_all(&socks5_connect_request(host, port)?)
+        .await?;
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    let remaining = match socks5_reply_remaining(head)? {
+        Some(len) => len,
+        None => stream.read_u8().await? as usize + 2,
+    };
+    let mut rest = vec![0u8; remaining];
+    stream.read_exact(&mut rest).await?;
+    Ok(())
+}
+
+async fn open_tunnel(proxy: &OutboundProxy, host: &str, port: u16) -> io::Result<OutboundStream> {
+    let mut tcp = TcpStream::connect((proxy.host.as_str(), proxy.port)).await?;
+    match proxy.scheme {
+        OutboundProxyScheme::Http => {
+            http_connect_async(&mut tcp, proxy, host, port).await?;
+            Ok(OutboundStream::Tcp(tcp))
+        }
+        OutboundProxyScheme::Https => {
+            let connector = native_tls::TlsConnector::new().map_err(io::Error::other)?;
+            let mut tls = tokio_native_tls::TlsConnector::from(connector)
+                .connect(&proxy.host, tcp)
+                .await
+                .map_err(io::Error::other)?;
+            http_connect_async(&mut tls, proxy, host, port).await?;
+            Ok(OutboundStream::Tls(Box::new(tls)))
+        }
+        OutboundProxyScheme::Socks5 => {
+            socks5_connect_async(&mut tcp, proxy, host, port).await?;
+            Ok(OutboundStream::Tcp(tcp))
+        }
+    }
+}
+
+/// 按平台设置连接 `host:port`，必要时经由代理隧道
+pub async fn connect_tcp(
+    platform: Option<&str>,
+    host: &str,
+    port: u16,
+) -> io::Result<OutboundStream> {
+    let connect = async {
+        match proxy_for(platform) {
+            Some(proxy) => open_tunnel(&proxy, host, port).await,
+            None => Ok(OutboundStream::Tcp(TcpStream::connect((host, port)).await?)),
+        }
+    };
+    tokio::time::timeout(CONNECT_TIMEOUT, connect)
+        .await
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("Connect to {}:{} timed out", host, port),
+            )
+        })?
+}
+
+/// `tokio_tungstenite::connect_async` 的替代：遵循出站代理设置
+pub async fn connect_websocket<R>(
+    request: R,
+    platform: Option<&str>,
+) -> Result<(OutboundWebSocket, Response), tokio_tungstenite::tungstenite::Error>
+where
+    R: IntoClientRequest + Unpin,
+{
+    let request = request.into_client_request()?;
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .map(|h| h.trim_start_matches('[').trim_end_matches(']').to_string())
+        .ok_or(tokio_tungstenite::tungstenite::Error::Url(
+            tokio_tungstenite::tungstenite::error::UrlError::NoHostName,
+        ))?;
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        });
+    let stream = connect_tcp(platform, &host, port).await?;
+    tokio_tungstenite::client_async_tls_with_config(request, stream, None, None).await
+}
+
+// ---- Tauri 命令 ----
+
+/// 密码以占位符返回，不会把明文发给前端
+#[tauri::command]
+pub fn get_network_proxy_settings() -> NetworkProxySettings {
+    SETTINGS.read().unwrap().masked()
+}
+
+/// 保存出站代理设置；按次新建的客户端与弹幕连接立即生效，
+/// 共享的关注/取流客户端在下次使用时按新设置重建，无需重启
+#[tauri::command]
+pub fn set_network_proxy_settings(
+    settings: NetworkProxySettings,
+) -> Result<NetworkProxySettings, String> {
+    let mut settings = settings.normalized()?;
+    {
+        // 持有写锁完成整个更新，避免并发保存互相覆盖
+        let mut current = SETTINGS.write().unwrap();
+        settings.restore_masked_passwords(&current);
+        save_settings(&settings)?;
+        *current = settings.clone();
+    }
+    SETTINGS_GENERATION.fetch_add(1, Ordering::AcqRel);
+    println!(
+        "[NetworkProxy] Settings updated: global={}, overrides={}",
+        settings
+            .global
+            .as_ref()
+            .map(|p| format!("{:?}://{}:{}", p.scheme, p.host, p.port))
+            .unwrap_or_else(|| "direct".to_string()),
+        settings.platforms.len()
+    );
+    Ok(settings.masked())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(password: Option<&str>) -> OutboundProxy {
+        OutboundProxy {
+            scheme: OutboundProxyScheme::Socks5,
+            host: "127.0.0.1".to_string(),
+            port: 1080,
+            username: Some("user".to_string()),
+            password: password.map(str::to_string),
+        }
+    }
+
+    fn settings() -> NetworkProxySettings {
+        let mut platforms = HashMap::new();
+        platforms.insert(
+            "HUYA".to_string(),
+            PlatformProxyOverride::Custom(proxy(Some("huya-secret"))),
+        );
+        platforms.insert("DOUYU".to_string(), PlatformProxyOverride::Direct);
+        NetworkProxySettings {
+            global: Some(proxy(Some("global-secret"))),
+            platforms,
+        }
+    }
+
+    #[test]
+    fn passwords_are_kept_out_of_the_settings_file() {
+        let (stripped, passwords) = settings().split_passwords();
+        let raw = serde_json::to_string(&stripped).unwrap();
+        assert!(!raw.contains("secret"));
+        assert_eq!(passwords.len(), 2);
+        assert_eq!(passwords[GLOBAL_CREDENTIALS_KEY], "global-secret");
+        assert_eq!(passwords["HUYA"], "huya-secret");
+
+        let mut restored = stripped;
+        restored.apply_passwords(&passwords);
+        assert_eq!(restored, settings());
+    }
+
+    #[test]
+    fn masked_passwords_round_trip() {
+        let saved = settings();
+        let masked = saved.masked();
+        assert_eq!(
+            masked.global.as_ref().unwrap().password.as_deref(),
+            Some(PASSWORD_MASK)
+        );
+        let mut submitted = masked;
+        submitted.restore_masked_passwords(&saved);
+        assert_eq!(submitted, saved);
+    }
+}
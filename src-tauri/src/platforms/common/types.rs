@@ -57,41 +57,3 @@ pub struct LiveStreamInfo {
     // 新增：直播间的 web_rid（用于关注列表以 web_id 为主键）
     pub web_rid: Option<String>,
 }
-
-// Moved from main.rs
-// State for the Douyin Danmaku listener
-#[derive(Default)]
-#[allow(dead_code)]
-pub struct DouyinDanmakuState(
-    pub std::sync::Mutex<std::collections::HashMap<String, tokio::sync::mpsc::Sender<()>>>,
-);
-
-// State for the Bilibili Danmaku listener
-#[derive(Default)]
-#[allow(dead_code)]
-pub struct BilibiliDanmakuState(
-    pub std::sync::Mutex<std::collections::HashMap<String, tokio::sync::mpsc::Sender<()>>>,
-);
-
-// State for the Douyu Danmaku listener
-#[derive(Default)]
-#[allow(dead_code)]
-pub struct DouyuDanmakuState(
-    pub std::sync::Mutex<std::collections::HashMap<String, tokio::sync::mpsc::Sender<()>>>,
-);
-
-// State for the Huya Danmaku listener
-#[derive(Default)]
-#[allow(dead_code)]
-pub struct HuyaDanmakuState(
-    pub std::sync::Mutex<std::collections::HashMap<String, tokio::sync::mpsc::Sender<()>>>,
-);
-
-#[derive(Serialize, Clone, Debug, specta::Type)]
-pub struct DanmakuFrontendPayload {
-    pub room_id: String,
-    pub user: String,
-    pub content: String,
-    pub user_level: i64,
-    pub fans_club_level: i32,
-}
@@ -2,25 +2,58 @@ use flate2::read::GzDecoder;
 use futures_util::{stream::SplitStream, StreamExt};
 use prost::Message as ProstMessage; // For decode/encode
 use std::io::Read;
-use tauri::Emitter;
 use tokio::sync::mpsc::Sender;
 use tokio_tungstenite::tungstenite::protocol::Message as WsMessage; // Import the Emitter trait for app_handle.emit()
 
+use crate::platforms::common::danmaku_listener::ListenerContext;
+use crate::platforms::common::{DanmakuEvent, DanmakuEventKind};
 use crate::platforms::douyin::danmu::gen::{PushFrame, Response}; // Removed ::douyin
-use crate::platforms::douyin::danmu::message_parsers;
+use crate::platforms::douyin::danmu::message_parsers::{self, GiftDeduper};
 use crate::platforms::douyin::danmu::websocket_connection::WsStream; // Corrected path // Corrected path
 
+/// 消息循环结束的原因
+pub enum HandlerExit {
+    /// 连接断开，可以重连
+    Disconnected,
+    /// 收到下播通知，不需要再重连
+    LiveEnded,
+}
+
+// 按 method 分发到对应的解析函数；未处理的 method 返回 Ok(None)
+fn parse_message(
+    method: &str,
+    payload: &[u8],
+    room_id: &str,
+    gifts: &mut GiftDeduper,
+) -> Result<Option<DanmakuEvent>, Box<dyn std::error::Error + Send + Sync>> {
+    match method {
+        "WebcastChatMessage" => message_parsers::parse_chat_message(payload, room_id),
+        "WebcastEmojiChatMessage" => message_parsers::parse_emoji_chat_message(payload, room_id),
+        "WebcastGiftMessage" => message_parsers::parse_gift_message(payload, room_id, gifts),
+        "WebcastMemberMessage" => message_parsers::parse_member_message(payload, room_id),
+        "WebcastLikeMessage" => message_parsers::parse_like_message(payload, room_id),
+        "WebcastSocialMessage" => message_parsers::parse_social_message(payload, room_id),
+        "WebcastRoomUserSeqMessage" => {
+            message_parsers::parse_room_user_seq_message(payload, room_id)
+        }
+        "WebcastRoomStatsMessage" => message_parsers::parse_room_stats_message(payload, room_id),
+        "WebcastControlMessage" => message_parsers::parse_control_message(payload, room_id),
+        _ => Ok(None),
+    }
+}
+
 // This function will handle the message receiving loop and parsing
 pub async fn handle_received_messages(
     mut read_stream: SplitStream<WsStream>,
     ack_tx: Sender<WsMessage>,
-    app_handle: tauri::AppHandle, // Added AppHandle
-    room_id: String,              // Added room_id parameter
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctx: ListenerContext,
+) -> Result<HandlerExit, Box<dyn std::error::Error + Send + Sync>> {
+    let room_id = ctx.room_id.clone();
     println!(
         "[Douyin Danmaku] Message handler started for room_id: {}",
         room_id
     );
+    let mut gifts = GiftDeduper::default();
     while let Some(message_result) = read_stream.next().await {
         match message_result {
             Ok(ws_msg) => {
@@ -60,32 +93,36 @@ pub async fn handle_received_messages(
                                         }
                                         for msg in response.messages_list {
                                             // println!("  -> Method: {}, Payload Length: {}", msg.method, msg.payload.len());
-                                            let mut danmaku_to_send = None;
-                                            if msg.method == "WebcastChatMessage" {
-                                                match message_parsers::parse_chat_message(
-                                                    &msg.payload,
-                                                    &room_id,
-                                                ) {
-                                                    Ok(Some(chat_payload)) => {
-                                                        danmaku_to_send = Some(chat_payload);
-                                                    }
-                                                    Ok(None) => { /* Not a message to display or ignored */
+                                            let event = match parse_message(
+                                                &msg.method,
+                                                &msg.payload,
+                                                &room_id,
+                                                &mut gifts,
+                                            ) {
+                                                Ok(Some(event)) => event,
+                                                Ok(None) => continue,
+                                                Err(e) => {
+                                                    // 聊天消息解析失败很常见，不打印
+                                                    if msg.method != "WebcastChatMessage" {
+                                                        eprintln!(
+                                                            "[Douyin Danmaku] Failed to parse {}: {}",
+                                                            msg.method, e
+                                                        );
                                                     }
-                                                    Err(_e) => { /* Error already logged in parser */
-                                                    }
-                                                }
-                                            }
-                                            // Add other message types here if needed, similar to ChatMessage
-                                            // else if msg.method == "WebcastMemberMessage" { ... }
-
-                                            if let Some(payload) = danmaku_to_send {
-                                                // Use app_handle.emit for Tauri v2 style global event emitting
-                                                if let Err(e) = app_handle
-                                                    .emit("danmaku-message", payload.clone())
-                                                {
-                                                    // payload needs to be Clone for emit
-                                                    eprintln!("[Douyin Danmaku] Failed to emit danmaku event: {}", e);
+                                                    continue;
                                                 }
+                                            };
+                                            let live_ended = matches!(
+                                                event.kind,
+                                                DanmakuEventKind::LiveStatus { live: false, .. }
+                                            );
+                                            ctx.emit(event);
+                                            if live_ended {
+                                                println!(
+                                                    "[Douyin Danmaku] Live ended for room_id: {}",
+                                                    room_id
+                                                );
+                                                return Ok(HandlerExit::LiveEnded);
                                             }
                                         }
                                     }
@@ -126,5 +163,5 @@ pub async fn handle_received_messages(
         }
     }
     println!("[Douyin Danmaku] Message handler finished.");
-    Ok(())
+    Ok(HandlerExit::Disconnected)
 }
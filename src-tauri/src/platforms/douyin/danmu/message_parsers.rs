@@ -1,49 +1,62 @@
-use super::gen::{ChatMessage, LikeMessage, MemberMessage, RoomStatsMessage}; // Updated to directly use types from gen
-use crate::platforms::common::DanmakuFrontendPayload;
-use prost::Message as ProstMessage; // For .decode() // Use shared payload type
+use super::gen::{
+    ChatMessage, ControlMessage, EmojiChatMessage, GiftMessage, LikeMessage, MemberMessage,
+    RoomStatsMessage, RoomUserSeqMessage, SocialMessage, User,
+}; // Updated to directly use types from gen
+use crate::platforms::common::{DanmakuEvent, DanmakuEventKind, DanmakuPlatform, DanmakuUser};
+use prost::Message as ProstMessage; // For .decode()
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+type ParseResult = Result<Option<DanmakuEvent>, Box<dyn std::error::Error + Send + Sync>>;
+
+// 1 抖币 = 0.1 元
+const DIAMOND_PER_CNY: f64 = 10.0;
+// 记住最近多少条礼物 msg_id，服务端偶尔会重复推送同一条
+const RECENT_GIFT_IDS: usize = 512;
+// 收不到 repeat_end 的连击在最后一次推送后保留多久
+const COMBO_TTL: Duration = Duration::from_secs(120);
+// 同时跟踪的连击上限，超出时淘汰最久没有更新的
+const MAX_COMBOS: usize = 1024;
+// ControlMessage.status = 3 表示下播
+const CONTROL_STATUS_LIVE_ENDED: i32 = 3;
+// SocialMessage.action = 1 表示关注
+const SOCIAL_ACTION_FOLLOW: u64 = 1;
+
+// 抖音用户 -> 统一的弹幕用户：财富等级取 pay_grade，粉丝牌取 fans_club.data
+fn to_danmaku_user(user: &User) -> DanmakuUser {
+    // 获取用户等级 (来自 demo)
+    let user_level = user.pay_grade.as_ref().map(|pg| pg.level).unwrap_or(0);
+    // 获取粉丝牌等级 (来自 demo)
+    // 注意：demo中的 fans_club.data.level 路径，确保你的 proto 定义一致
+    let fans_club = user.fans_club.as_ref().and_then(|fc| fc.data.as_ref());
+    DanmakuUser::named(user.nick_name.clone())
+        .id(user.id)
+        .level(user_level.max(0) as u32)
+        .medal(
+            fans_club.map(|d| d.club_name.clone()).unwrap_or_default(),
+            fans_club.map(|d| d.level.max(0) as u32).unwrap_or(0),
+        )
+}
 
 // Parser for ChatMessage
-pub fn parse_chat_message(
-    payload: &[u8],
-    current_room_id: &str,
-) -> Result<Option<DanmakuFrontendPayload>, Box<dyn std::error::Error + Send + Sync>> {
+pub fn parse_chat_message(payload: &[u8], current_room_id: &str) -> ParseResult {
     match ChatMessage::decode(payload) {
         Ok(chat_msg) => {
-            if let Some(user) = chat_msg.user {
-                // 获取用户等级 (来自 demo)
-                let user_level = user.pay_grade.as_ref().map(|pg| pg.level).unwrap_or(0);
-                // 获取粉丝牌等级 (来自 demo)
-                // 注意：demo中的 fans_club.data.level 路径，确保你的 proto 定义一致
-                // 如果你的 User 结构体中 fans_club 直接是 FansClubData 类型，则不需要 .data
-                // 假设 FansClub 结构体包含一个 Option<FansClubData> 类型的字段 data
-                let fans_club_level = user
-                    .fans_club
-                    .as_ref()
-                    .and_then(|fc| fc.data.as_ref()) // 如果 FansClub 直接是 FansClubData，则为 .map(|fc| fc.level)
-                    .map(|fcd| fcd.level)
-                    .unwrap_or(0);
-
-                Ok(Some(DanmakuFrontendPayload {
-                    room_id: current_room_id.to_string(), // Populate room_id
-                    user: user.nick_name.clone(),
-                    content: chat_msg.content.clone(),
-                    user_level,
-                    fans_club_level,
-                    // r#type: "chat".to_string(),
-                }))
+            let event = DanmakuEvent::chat(
+                DanmakuPlatform::Douyin,
+                current_room_id,
+                chat_msg.content.clone(),
+            )
+            .color(Some(chat_msg.full_screen_text_color.clone()).filter(|c| c.starts_with('#')));
+            if let Some(user) = chat_msg.user.as_ref() {
+                Ok(Some(event.user(to_danmaku_user(user))))
             } else {
                 // 对于没有用户信息的聊天消息 (例如系统消息)，也可能需要发送，但等级为0
                 println!(
                     "    【聊天msg】Content: {} (no user info)",
                     chat_msg.content
                 );
-                Ok(Some(DanmakuFrontendPayload {
-                    room_id: current_room_id.to_string(), // Populate room_id
-                    user: "系统".to_string(),             // Or some other placeholder
-                    content: chat_msg.content.clone(),
-                    user_level: 0,
-                    fans_club_level: 0,
-                }))
+                Ok(Some(event.user(DanmakuUser::named("系统"))))
             }
         }
         Err(e) => {
@@ -53,80 +66,328 @@ pub fn parse_chat_message(
     }
 }
 
-// Demo 中此函数返回 Result<(), ...> 并且只打印，这里保持原有返回 Option<DanmakuFrontendPayload> 结构
-// 如果不需要将进场消息发送到前端，可以保持返回 Ok(None)
-#[allow(dead_code)] // ADDED to suppress warning
-pub fn parse_member_message(
-    payload: &[u8],
-    _current_room_id: &str,
-) -> Result<Option<DanmakuFrontendPayload>, Box<dyn std::error::Error + Send + Sync>> {
+// 进场消息
+pub fn parse_member_message(payload: &[u8], current_room_id: &str) -> ParseResult {
     match MemberMessage::decode(payload) {
         Ok(member_msg) => {
-            if let Some(user) = member_msg.user {
-                let user_level = user.pay_grade.as_ref().map(|pg| pg.level).unwrap_or(0);
-                let fans_club_level = user
-                    .fans_club
-                    .as_ref()
-                    .and_then(|fc| fc.data.as_ref())
-                    .map(|fcd| fcd.level)
-                    .unwrap_or(0);
-
-                println!(
-                    "    【进场msg】[用户等级: {}][粉丝牌等级: {}]{} 进入了直播间",
-                    user_level, fans_club_level, user.nick_name
-                );
-                Ok(None) // 当前不发送到前端
+            if let Some(user) = member_msg.user.as_ref() {
+                Ok(Some(
+                    DanmakuEvent::new(
+                        DanmakuPlatform::Douyin,
+                        current_room_id,
+                        DanmakuEventKind::Enter,
+                    )
+                    .user(to_danmaku_user(user)),
+                ))
             } else {
                 println!("    【进场msg】MemberMessage without user details.");
                 Ok(None)
             }
         }
-        Err(e) => {
-            eprintln!("    【X】Failed to parse MemberMessage in parser: {}", e);
-            Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-        }
+        Err(e) => Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
     }
 }
 
 // Parser for LikeMessage (点赞消息)
-// Demo 中此函数返回 Result<(), ...> 并且只打印
-#[allow(dead_code)] // ADDED to suppress warning
-pub fn parse_like_message(
-    payload: &[u8],
-    _current_room_id: &str,
-) -> Result<Option<DanmakuFrontendPayload>, Box<dyn std::error::Error + Send + Sync>> {
+pub fn parse_like_message(payload: &[u8], current_room_id: &str) -> ParseResult {
     match LikeMessage::decode(payload) {
         Ok(like_msg) => {
-            if let Some(user) = like_msg.user {
-                println!(
-                    "    【点赞msg】{} 点了{}个赞",
-                    user.nick_name, like_msg.count
-                );
-            } else {
-                println!("    【点赞msg】点赞 {} 个 (无用户信息)", like_msg.count);
+            let event = DanmakuEvent::new(
+                DanmakuPlatform::Douyin,
+                current_room_id,
+                DanmakuEventKind::Like {
+                    count: like_msg.count.min(u64::from(u32::MAX)) as u32,
+                },
+            );
+            Ok(Some(match like_msg.user.as_ref() {
+                Some(user) => event.user(to_danmaku_user(user)),
+                None => event,
+            }))
+        }
+        Err(e) => Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+    }
+}
+
+pub fn parse_room_stats_message(payload: &[u8], current_room_id: &str) -> ParseResult {
+    match RoomStatsMessage::decode(payload) {
+        Ok(stats_msg) => Ok(Some(DanmakuEvent::new(
+            DanmakuPlatform::Douyin,
+            current_room_id,
+            DanmakuEventKind::RoomStats {
+                online: None,
+                watched: u32::try_from(stats_msg.total).ok(),
+                likes: None,
+                display: Some(stats_msg.display_long).filter(|d| !d.is_empty()),
+            },
+        ))),
+        Err(e) => Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+    }
+}
+
+/// 连击礼物去重。
+/// 抖音连击时每次点击都会推送一条 GiftMessage，repeat_count 为累计次数，
+/// 这里只发出相对上一条的增量；同一 msg_id 的重复推送直接丢弃
+#[derive(Default)]
+pub struct GiftDeduper {
+    seen_ids: HashSet<u64>,
+    seen_order: VecDeque<u64>,
+    // (用户, 礼物, group_id) -> (已发出的累计数量, 最后一次推送时间)
+    combos: HashMap<(u64, u64, u64), (u64, Instant)>,
+    last_sweep: Option<Instant>,
+}
+
+impl GiftDeduper {
+    fn is_repeated_msg(&mut self, msg_id: u64) -> bool {
+        if msg_id == 0 {
+            return false;
+        }
+        if !self.seen_ids.insert(msg_id) {
+            return true;
+        }
+        self.seen_order.push_back(msg_id);
+        if self.seen_order.len() > RECENT_GIFT_IDS {
+            if let Some(old) = self.seen_order.pop_front() {
+                self.seen_ids.remove(&old);
             }
-            Ok(None) // 点赞消息通常不直接作为弹幕显示在列表
         }
-        Err(e) => {
-            eprintln!("    【X】Failed to parse LikeMessage in parser: {}", e);
-            Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
+        false
+    }
+
+    /// 连击结束时服务端会发 repeat_end，但断线或丢包时可能收不到，过期的连击在这里清掉
+    fn sweep_combos(&mut self, now: Instant) {
+        let due = self
+            .last_sweep
+            .is_none_or(|last| now.duration_since(last) >= COMBO_TTL);
+        if !due && self.combos.len() < MAX_COMBOS {
+            return;
+        }
+        self.last_sweep = Some(now);
+        self.combos
+            .retain(|_, (_, seen)| now.duration_since(*seen) < COMBO_TTL);
+        while self.combos.len() >= MAX_COMBOS {
+            let Some(oldest) = self
+                .combos
+                .iter()
+                .min_by_key(|(_, (_, seen))| *seen)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            self.combos.remove(&oldest);
         }
     }
+
+    /// 返回本条消息新增的数量和连击累计数量；没有新增时返回 None
+    fn take_increment(&mut self, gift_msg: &GiftMessage) -> Option<(u64, Option<u64>)> {
+        self.take_increment_at(gift_msg, Instant::now())
+    }
+
+    fn take_increment_at(
+        &mut self,
+        gift_msg: &GiftMessage,
+        now: Instant,
+    ) -> Option<(u64, Option<u64>)> {
+        let group_count = gift_msg.group_count.max(1);
+        let is_combo = gift_msg.gift.as_ref().map(|g| g.combo).unwrap_or(false);
+        if !is_combo || gift_msg.group_id == 0 {
+            let count = gift_msg.repeat_count.max(1) * group_count;
+            return Some((count, None));
+        }
+
+        let user_id = gift_msg.user.as_ref().map(|u| u.id).unwrap_or(0);
+        let key = (user_id, gift_msg.gift_id, gift_msg.group_id);
+        let total = gift_msg.repeat_count * group_count;
+        let sent = self.combos.get(&key).map(|(sent, _)| *sent).unwrap_or(0);
+        if gift_msg.repeat_end == 1 {
+            self.combos.remove(&key);
+        } else {
+            self.sweep_combos(now);
+            self.combos.insert(key, (total.max(sent), now));
+        }
+        (total > sent).then(|| (total - sent, Some(total)))
+    }
 }
 
-#[allow(dead_code)] // ADDED to suppress warning
-pub fn parse_room_stats_message(
+pub fn parse_gift_message(
     payload: &[u8],
-    _current_room_id: &str,
-) -> Result<Option<DanmakuFrontendPayload>, Box<dyn std::error::Error + Send + Sync>> {
-    match RoomStatsMessage::decode(payload) {
-        Ok(stats_msg) => {
-            println!("    【直播间统计msg】{}", stats_msg.display_long);
-            Ok(None) // 统计信息通常不作为普通弹幕显示
+    current_room_id: &str,
+    deduper: &mut GiftDeduper,
+) -> ParseResult {
+    let gift_msg = GiftMessage::decode(payload)?;
+    let msg_id = gift_msg.common.as_ref().map(|c| c.msg_id).unwrap_or(0);
+    if deduper.is_repeated_msg(msg_id) {
+        return Ok(None);
+    }
+    let Some((count, combo_total)) = deduper.take_increment(&gift_msg) else {
+        return Ok(None);
+    };
+
+    let gift = gift_msg.gift.as_ref();
+    let diamonds = gift.map(|g| g.diamond_count).unwrap_or(0);
+    let event = DanmakuEvent::new(
+        DanmakuPlatform::Douyin,
+        current_room_id,
+        DanmakuEventKind::Gift {
+            gift_id: Some(gift_msg.gift_id.to_string()),
+            gift_name: gift.map(|g| g.name.clone()).unwrap_or_default(),
+            count: count.min(u64::from(u32::MAX)) as u32,
+            value_cny: (diamonds > 0).then(|| f64::from(diamonds) * count as f64 / DIAMOND_PER_CNY),
+            combo_id: combo_total.map(|_| gift_msg.group_id.to_string()),
+            combo_total: combo_total.map(|t| t.min(u64::from(u32::MAX)) as u32),
+        },
+    );
+    Ok(Some(match gift_msg.user.as_ref() {
+        Some(user) => event.user(to_danmaku_user(user)),
+        None => event,
+    }))
+}
+
+// 关注/分享；分享没有对应的事件类型
+pub fn parse_social_message(payload: &[u8], current_room_id: &str) -> ParseResult {
+    let social_msg = SocialMessage::decode(payload)?;
+    if social_msg.action != SOCIAL_ACTION_FOLLOW {
+        return Ok(None);
+    }
+    let event = DanmakuEvent::new(
+        DanmakuPlatform::Douyin,
+        current_room_id,
+        DanmakuEventKind::Follow,
+    );
+    Ok(Some(match social_msg.user.as_ref() {
+        Some(user) => event.user(to_danmaku_user(user)),
+        None => event,
+    }))
+}
+
+// 在线人数：total 为当前在线，total_user 为累计观看
+pub fn parse_room_user_seq_message(payload: &[u8], current_room_id: &str) -> ParseResult {
+    let seq_msg = RoomUserSeqMessage::decode(payload)?;
+    Ok(Some(DanmakuEvent::new(
+        DanmakuPlatform::Douyin,
+        current_room_id,
+        DanmakuEventKind::RoomStats {
+            online: u32::try_from(seq_msg.total).ok(),
+            watched: u32::try_from(seq_msg.total_user).ok().filter(|t| *t > 0),
+            likes: None,
+            display: Some(seq_msg.total_user_str).filter(|d| !d.is_empty()),
+        },
+    )))
+}
+
+// 表情弹幕：default_content 是 "[捂脸]" 这类文本形式
+pub fn parse_emoji_chat_message(payload: &[u8], current_room_id: &str) -> ParseResult {
+    let emoji_msg = EmojiChatMessage::decode(payload)?;
+    let content = if emoji_msg.default_content.is_empty() {
+        emoji_msg
+            .emoji_content
+            .as_ref()
+            .map(|text| {
+                text.pieces_list
+                    .iter()
+                    .map(|piece| piece.string_value.as_str())
+                    .collect::<String>()
+            })
+            .unwrap_or_default()
+    } else {
+        emoji_msg.default_content.clone()
+    };
+    if content.is_empty() {
+        return Ok(None);
+    }
+    let event = DanmakuEvent::chat(DanmakuPlatform::Douyin, current_room_id, content);
+    Ok(Some(match emoji_msg.user.as_ref() {
+        Some(user) => event.user(to_danmaku_user(user)),
+        None => event,
+    }))
+}
+
+// 目前只关心下播，其它状态（暂停、恢复）忽略
+pub fn parse_control_message(payload: &[u8], current_room_id: &str) -> ParseResult {
+    let control_msg = ControlMessage::decode(payload)?;
+    if control_msg.status != CONTROL_STATUS_LIVE_ENDED {
+        return Ok(None);
+    }
+    Ok(Some(DanmakuEvent::new(
+        DanmakuPlatform::Douyin,
+        current_room_id,
+        DanmakuEventKind::LiveStatus {
+            live: false,
+            reason: Some("直播已结束".to_string()),
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::gen::GiftStruct;
+    use super::*;
+
+    fn combo(user_id: u64, group_id: u64, repeat_count: u64, repeat_end: u32) -> GiftMessage {
+        GiftMessage {
+            gift_id: 463,
+            group_count: 1,
+            repeat_count,
+            repeat_end,
+            group_id,
+            user: Some(User {
+                id: user_id,
+                ..Default::default()
+            }),
+            gift: Some(GiftStruct {
+                combo: true,
+                ..Default::default()
+            }),
+            ..Default::default()
         }
-        Err(e) => {
-            eprintln!("    【X】Failed to parse RoomStatsMessage in parser: {}", e);
-            Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
+    }
+
+    #[test]
+    fn emits_combo_increments_only() {
+        let mut deduper = GiftDeduper::default();
+        assert_eq!(
+            deduper.take_increment(&combo(1, 7, 1, 0)),
+            Some((1, Some(1)))
+        );
+        assert_eq!(
+            deduper.take_increment(&combo(1, 7, 3, 0)),
+            Some((2, Some(3)))
+        );
+        // 乱序到达的旧累计数不会重复计数
+        assert_eq!(deduper.take_increment(&combo(1, 7, 2, 0)), None);
+        assert_eq!(
+            deduper.take_increment(&combo(1, 7, 5, 1)),
+            Some((2, Some(5)))
+        );
+        assert!(deduper.combos.is_empty());
+    }
+
+    #[test]
+    fn sweeps_combos_that_never_end() {
+        let mut deduper = GiftDeduper::default();
+        let start = Instant::now();
+        deduper.take_increment_at(&combo(1, 7, 1, 0), start);
+        deduper.take_increment_at(&combo(2, 8, 1, 0), start + COMBO_TTL / 2);
+
+        deduper.take_increment_at(
+            &combo(3, 9, 1, 0),
+            start + COMBO_TTL + Duration::from_secs(1),
+        );
+        let mut users: Vec<u64> = deduper.combos.keys().map(|k| k.0).collect();
+        users.sort();
+        assert_eq!(users, vec![2, 3]);
+    }
+
+    #[test]
+    fn caps_tracked_combos() {
+        let mut deduper = GiftDeduper::default();
+        let start = Instant::now();
+        for i in 0..(MAX_COMBOS as u64 + 10) {
+            deduper.take_increment_at(&combo(i, i, 1, 0), start + Duration::from_millis(i));
         }
+        assert!(deduper.combos.len() <= MAX_COMBOS);
+        // 淘汰的是最久没有更新的连击
+        assert!(!deduper.combos.contains_key(&(0, 463, 0)));
+        let newest = MAX_COMBOS as u64 + 9;
+        assert!(deduper.combos.contains_key(&(newest, 463, newest)));
     }
 }
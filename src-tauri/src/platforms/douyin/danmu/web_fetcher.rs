@@ -1,15 +1,15 @@
+use crate::platforms::common::header_rules::{upstream_headers, UpstreamHeadersExt};
 use crate::platforms::common::http_client::HttpClient;
+use crate::platforms::common::network_proxy::OutboundWebSocket;
 use crate::platforms::douyin::web_api::{
-    fetch_room_data, normalize_douyin_live_id, DouyinRoomData, DEFAULT_USER_AGENT,
+    fetch_room_data, normalize_douyin_live_id, signed_request_headers, DouyinRoomData,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use std::sync::Arc;
-use tokio::net::TcpStream;
 use tokio::sync::Mutex;
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, REFERER, USER_AGENT};
+use reqwest::header::{HeaderValue, ACCEPT, ACCEPT_LANGUAGE, REFERER, USER_AGENT};
 
 // New struct for frontend
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -38,7 +38,7 @@ pub struct DouyinLiveWebFetcher {
     resolved_info: Option<ResolvedRoomInfo>,
     pub user_agent: String,
     pub http_client: HttpClient,
-    pub(crate) _ws_stream: Option<Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>>,
+    pub(crate) _ws_stream: Option<Arc<Mutex<OutboundWebSocket>>>,
     // 新增字段：用于 WebSocket 和签名所需
     pub dy_cookie: Option<String>,
     pub user_unique_id: Option<String>,
@@ -47,7 +47,7 @@ pub struct DouyinLiveWebFetcher {
 impl DouyinLiveWebFetcher {
     pub fn new(live_id: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // 使用直连HTTP客户端，绕过所有代理设置
-        let http_client = HttpClient::new_direct_connection()
+        let http_client = HttpClient::new_direct_connection(Some("DOUYIN"))
             .map_err(|e| format!("Failed to create direct connection HttpClient: {}", e))?;
         let normalized_live_id = normalize_douyin_live_id(live_id);
 
@@ -55,7 +55,8 @@ impl DouyinLiveWebFetcher {
             live_id: normalized_live_id,
             room_id: None,
             resolved_info: None,
-            user_agent: DEFAULT_USER_AGENT.to_string(),
+            // 整个会话固定使用规则表给出的 UA，与 Cookie 和签名保持一致
+            user_agent: signed_request_headers("https://live.douyin.com/").1,
             http_client,
             _ws_stream: None,
             dy_cookie: None,
@@ -142,8 +143,8 @@ impl DouyinLiveWebFetcher {
             .http_client
             .inner
             .head(homepage_url)
+            .upstream_headers(Some("DOUYIN"), homepage_url)
             .header("User-Agent", &self.user_agent)
-            .header("Referer", "https://live.douyin.com")
             .header("Authority", "live.douyin.com")
             .send()
             .await?;
@@ -169,8 +170,8 @@ impl DouyinLiveWebFetcher {
             .http_client
             .inner
             .get(homepage_url)
+            .upstream_headers(Some("DOUYIN"), homepage_url)
             .header("User-Agent", &self.user_agent)
-            .header("Referer", "https://live.douyin.com")
             .send()
             .await?;
 
@@ -317,7 +318,8 @@ impl DouyinLiveWebFetcher {
             .into());
         }
 
-        let mut headers = HeaderMap::new();
+        let mut headers = upstream_headers(Some("DOUYIN"), &url);
+        headers.insert(USER_AGENT, HeaderValue::from_str(&self.user_agent)?);
         headers.insert(
             ACCEPT,
             HeaderValue::from_static("application/json, text/plain, */*"),
@@ -402,7 +404,7 @@ pub async fn fetch_douyin_room_info(live_id: String) -> Result<DouyinFollowListR
     );
     let normalized_id = normalize_douyin_live_id(&live_id);
 
-    let http_client = HttpClient::new_direct_connection()
+    let http_client = HttpClient::new_direct_connection(Some("DOUYIN"))
         .map_err(|e| format!("Failed to create direct connection HttpClient: {}", e))?;
 
     let DouyinRoomData { room } = fetch_room_data(&http_client, &normalized_id, None)
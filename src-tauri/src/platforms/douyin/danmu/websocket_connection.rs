@@ -1,12 +1,10 @@
 use chrono::Utc;
 use futures_util::{stream::SplitStream, SinkExt, StreamExt};
 use std::time::Duration;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc::{self, Sender};
 use tokio::sync::watch;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::protocol::Message as WsMessage;
-use tokio_tungstenite::{connect_async, MaybeTlsStream};
 use urlencoding;
 // use url::Url; // REMOVED AGAIN
 // use rand::Rng; // REMOVED AGAIN
@@ -15,10 +13,11 @@ use urlencoding;
 use super::gen::PushFrame; // Removed ::douyin
 use super::signature; // For generate_signature
 use super::web_fetcher::DouyinLiveWebFetcher;
+use crate::platforms::common::network_proxy::{self, OutboundWebSocket};
 use prost::Message as ProstMessage; // For encoding heartbeat
 
 // Define a type alias for the WebSocket stream for brevity
-pub type WsStream = tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub type WsStream = OutboundWebSocket;
 
 // This function will establish the connection and spawn send/heartbeat tasks.
 // It returns the read half of the stream and the sender for the outgoing message channel.
@@ -82,7 +81,8 @@ pub async fn connect_and_manage_websocket(
     headers.insert("user-agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36".parse()?);
     headers.insert("Cookie", ws_cookie_header.parse()?);
 
-    let (ws_stream, _response) = connect_async(client_request).await?;
+    let (ws_stream, _response) =
+        network_proxy::connect_websocket(client_request, Some("DOUYIN")).await?;
 
     let (mut write, read) = ws_stream.split(); // read will be returned
 
@@ -1,76 +1,68 @@
+use crate::platforms::common::danmaku_listener::{DanmakuListener, ListenerContext};
+use crate::platforms::douyin::danmu::message_handler::HandlerExit;
 use crate::platforms::douyin::web_api::normalize_douyin_live_id;
+use futures_util::future::BoxFuture;
 use tokio::sync::mpsc as tokio_mpsc;
 use tokio::time::{sleep, Duration};
 
 enum ConnectionOutcome {
     Stop,
     Disconnected,
+    LiveEnded,
 }
 
-#[tauri::command]
-pub async fn start_douyin_danmu_listener(
-    payload: crate::platforms::common::GetStreamUrlPayload,
-    app_handle: tauri::AppHandle,
-    state: tauri::State<'_, crate::platforms::common::DouyinDanmakuState>,
-) -> Result<(), String> {
-    let room_id_or_url = payload.args.room_id_str;
-    println!(
-        "[Douyin Danmaku] Received request for room_id_or_url: {}",
-        room_id_or_url
-    );
-
-    let normalized_room_id = normalize_douyin_live_id(&room_id_or_url);
-
-    // Stop previous listener for this specific room if exists
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&normalized_room_id)
-    };
+/// 抖音弹幕监听，由 `DanmakuRegistry` 管理生命周期
+pub struct DouyinDanmakuListener;
 
-    if let Some(tx) = previous_tx {
-        println!(
-            "[Douyin Danmaku] Sending shutdown to previous Douyin listener task for room {}.",
-            normalized_room_id
-        );
-        if tx.send(()).await.is_err() {
-            eprintln!("[Douyin Danmaku] Failed to send shutdown. Task might have already completed or panicked.");
-        }
+impl DanmakuListener for DouyinDanmakuListener {
+    // 前端可能传入直播间链接或 web_rid，统一成 web_rid
+    fn normalize_room_id(&self, room_id: &str) -> String {
+        normalize_douyin_live_id(room_id)
     }
 
-    let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
-    {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.insert(normalized_room_id.clone(), tx_shutdown);
+    fn run(
+        &self,
+        ctx: ListenerContext,
+        rx_shutdown: tokio_mpsc::Receiver<()>,
+    ) -> BoxFuture<'static, Result<(), String>> {
+        Box::pin(run_listener(ctx, rx_shutdown))
     }
+}
 
-    let app_handle_clone = app_handle.clone();
-    let room_id_str_clone = normalized_room_id.clone();
-
-    tokio::spawn(async move {
-        println!(
-            "[Douyin Danmaku] Spawning listener for room: {}",
-            room_id_str_clone
-        );
-
-        let mut backoff_secs = 1u64;
-
-        loop {
-            let result = async {
-                let mut fetcher = crate::platforms::douyin::danmu::web_fetcher::DouyinLiveWebFetcher::new(&room_id_str_clone)?;
-                fetcher
-                    .fetch_room_details()
-                    .await
-                    .map_err(|e| format!("Failed to fetch room details: {}", e))?;
-
-                let actual_room_id = fetcher.get_room_id().await?;
-                let cookie_header = fetcher.get_dy_cookie().await?;
-                let user_unique_id = fetcher.get_user_unique_id().await?;
-                println!(
-                    "[Douyin Danmaku] Using: room_id={}, user_unique_id={}",
-                    actual_room_id, user_unique_id
-                );
+async fn run_listener(
+    ctx: ListenerContext,
+    mut rx_shutdown: tokio_mpsc::Receiver<()>,
+) -> Result<(), String> {
+    let room_id_str_clone = ctx.room_id.clone();
+    println!(
+        "[Douyin Danmaku] Spawning listener for room: {}",
+        room_id_str_clone
+    );
 
-                let (read_stream, ack_tx, shutdown_tx) = crate::platforms::douyin::danmu::websocket_connection::connect_and_manage_websocket(
+    let mut backoff_secs = 1u64;
+    let mut attempt = 0u32;
+
+    loop {
+        let result = async {
+            let mut fetcher =
+                crate::platforms::douyin::danmu::web_fetcher::DouyinLiveWebFetcher::new(
+                    &room_id_str_clone,
+                )?;
+            fetcher
+                .fetch_room_details()
+                .await
+                .map_err(|e| format!("Failed to fetch room details: {}", e))?;
+
+            let actual_room_id = fetcher.get_room_id().await?;
+            let cookie_header = fetcher.get_dy_cookie().await?;
+            let user_unique_id = fetcher.get_user_unique_id().await?;
+            println!(
+                "[Douyin Danmaku] Using: room_id={}, user_unique_id={}",
+                actual_room_id, user_unique_id
+            );
+
+            let (read_stream, ack_tx, shutdown_tx) =
+                crate::platforms::douyin::danmu::websocket_connection::connect_and_manage_websocket(
                     &fetcher,
                     &actual_room_id,
                     &cookie_header,
@@ -78,86 +70,74 @@ pub async fn start_douyin_danmu_listener(
                 )
                 .await?;
 
-                println!(
-                    "[Douyin Danmaku] WebSocket connected for room: {}",
-                    actual_room_id
-                );
+            println!(
+                "[Douyin Danmaku] WebSocket connected for room: {}",
+                actual_room_id
+            );
+            attempt = 0;
+            backoff_secs = 1;
+            ctx.connected();
 
-                let shutdown_tx_for_msg = shutdown_tx.clone();
-                tokio::select! {
-                    res = crate::platforms::douyin::danmu::message_handler::handle_received_messages(
-                        read_stream,
-                        ack_tx,
-                        app_handle_clone.clone(),
-                        actual_room_id.clone()
-                    ) => {
-                        let _ = shutdown_tx_for_msg.send(true);
-                        if let Err(e) = res {
-                            return Err(e);
-                        }
-                        Ok(ConnectionOutcome::Disconnected)
-                    }
-                    _ = rx_shutdown.recv() => {
-                        println!(
-                            "[Douyin Danmaku] Received shutdown signal for room {}.",
-                            actual_room_id
-                        );
-                        let _ = shutdown_tx.send(true);
-                        Ok(ConnectionOutcome::Stop)
+            // 事件里使用前端传入的 web_rid，而不是内部的 room_id
+            let shutdown_tx_for_msg = shutdown_tx.clone();
+            tokio::select! {
+                res = crate::platforms::douyin::danmu::message_handler::handle_received_messages(
+                    read_stream,
+                    ack_tx,
+                    ctx.clone()
+                ) => {
+                    let _ = shutdown_tx_for_msg.send(true);
+                    match res {
+                        Err(e) => Err(e),
+                        Ok(HandlerExit::Disconnected) => Ok(ConnectionOutcome::Disconnected),
+                        Ok(HandlerExit::LiveEnded) => Ok(ConnectionOutcome::LiveEnded),
                     }
                 }
-            }
-            .await;
-
-            match result {
-                Ok(ConnectionOutcome::Stop) => break,
-                Ok(ConnectionOutcome::Disconnected) => {
-                    eprintln!(
-                        "[Douyin Danmaku] Disconnected, retrying in {}s.",
-                        backoff_secs
-                    );
-                }
-                Err(e) => {
-                    eprintln!(
-                        "[Douyin Danmaku] Connection error: {}. Retrying in {}s.",
-                        e, backoff_secs
+                _ = rx_shutdown.recv() => {
+                    println!(
+                        "[Douyin Danmaku] Received shutdown signal for room {}.",
+                        actual_room_id
                     );
+                    let _ = shutdown_tx.send(true);
+                    Ok(ConnectionOutcome::Stop)
                 }
             }
+        }
+        .await;
 
-            let sleep_fut = sleep(Duration::from_secs(backoff_secs));
-            tokio::select! {
-                _ = sleep_fut => {}
-                _ = rx_shutdown.recv() => break,
+        match result {
+            Ok(ConnectionOutcome::Stop) => break,
+            Ok(ConnectionOutcome::LiveEnded) => {
+                // 下播后不再重连；下播已通过 LiveStatus 事件告知前端，这里正常关闭
+                println!(
+                    "[Douyin Danmaku] Live ended, listener for room {} closed.",
+                    room_id_str_clone
+                );
+                break;
+            }
+            Ok(ConnectionOutcome::Disconnected) => {
+                eprintln!(
+                    "[Douyin Danmaku] Disconnected, retrying in {}s.",
+                    backoff_secs
+                );
+            }
+            Err(e) => {
+                eprintln!(
+                    "[Douyin Danmaku] Connection error: {}. Retrying in {}s.",
+                    e, backoff_secs
+                );
             }
-            backoff_secs = (backoff_secs * 2).min(30);
         }
-    });
-    Ok(())
-}
-
-#[tauri::command]
-pub async fn stop_douyin_danmu_listener(
-    room_id: String,
-    state: tauri::State<'_, crate::platforms::common::DouyinDanmakuState>,
-) -> Result<(), String> {
-    println!(
-        "[Douyin Danmaku] stop_douyin_danmu_listener called for room_id={}",
-        room_id
-    );
 
-    let tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&room_id)
-    };
-
-    if let Some(tx) = tx {
-        if tx.send(()).await.is_err() {
-            eprintln!("[Douyin Danmaku] 停止信号发送失败");
+        attempt += 1;
+        ctx.reconnecting(attempt, Duration::from_secs(backoff_secs));
+        let sleep_fut = sleep(Duration::from_secs(backoff_secs));
+        tokio::select! {
+            _ = sleep_fut => {}
+            _ = rx_shutdown.recv() => break,
         }
-    } else {
-        println!("[Douyin Danmaku] 没有找到活跃的监听器需要停止");
+        backoff_secs = (backoff_secs * 2).min(30);
+        ctx.connecting();
     }
-
     Ok(())
 }
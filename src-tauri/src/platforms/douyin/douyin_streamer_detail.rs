@@ -1,14 +1,14 @@
+use crate::platforms::common::header_rules::upstream_headers;
 use crate::platforms::common::http_client::HttpClient;
 use crate::platforms::common::types::StreamVariant;
 use crate::platforms::common::GetStreamUrlPayload;
 use crate::platforms::common::LiveStreamInfo as CommonLiveStreamInfo;
 use crate::platforms::douyin::web_api::{
     choose_flv_stream, fetch_room_data, normalize_douyin_live_id, DouyinRoomData,
-    DEFAULT_USER_AGENT,
 };
 use crate::proxy::ProxyServerHandle;
 use regex::Regex;
-use reqwest::header::{HeaderMap, HeaderValue, REFERER, USER_AGENT};
+use reqwest::header::{HeaderValue, REFERER};
 use serde_json::Value;
 use tauri::{command, AppHandle, State};
 
@@ -65,7 +65,7 @@ pub async fn fetch_douyin_live_stream_info_by_quality(
         requested_id, quality
     );
 
-    let http_client = HttpClient::new_direct_connection()
+    let http_client = HttpClient::new_direct_connection(Some("DOUYIN"))
         .map_err(|e| format!("Failed to create direct connection HttpClient: {}", e))?;
 
     let normalized_id = normalize_douyin_live_id(&requested_id);
@@ -305,8 +305,7 @@ async fn fetch_origin_flv_from_live_page(
     web_id: &str,
 ) -> Result<Option<String>, String> {
     let url = format!("https://live.douyin.com/{}", web_id);
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+    let mut headers = upstream_headers(Some("DOUYIN"), &url);
     headers.insert(
         REFERER,
         HeaderValue::from_str(&url).map_err(|e| format!("Invalid Referer header: {}", e))?,
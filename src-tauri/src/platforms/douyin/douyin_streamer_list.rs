@@ -1,9 +1,9 @@
 use crate::platforms::common::http_client::HttpClient;
 use crate::platforms::douyin::a_bogus::generate_a_bogus;
-use crate::platforms::douyin::web_api::DEFAULT_USER_AGENT;
-use reqwest::header::{HeaderMap, HeaderValue, COOKIE, USER_AGENT};
+use crate::platforms::douyin::web_api::signed_request_headers;
+use reqwest::header::{HeaderValue, COOKIE};
 use serde::{Deserialize, Serialize};
-use tauri::State; // Removed SET_COOKIE
+
 use urlencoding::encode;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -79,7 +79,6 @@ pub struct DouyinLiveListResponse {
 
 #[tauri::command]
 pub async fn fetch_douyin_partition_rooms(
-    _http_client: State<'_, reqwest::Client>,
     partition: String,
     partition_type: String,
     offset: i32, // This is the offset for the current request (0, 15, 30...)
@@ -88,7 +87,7 @@ pub async fn fetch_douyin_partition_rooms(
     let count: i32 = 15; // Number of items requested per page, explicitly typed as i32
 
     // 使用直连HTTP客户端，绕过所有代理设置
-    let local_client = HttpClient::new_direct_connection()
+    let local_client = HttpClient::new_direct_connection(Some("DOUYIN"))
         .map_err(|e| format!("Failed to create direct connection HttpClient: {}", e))?;
 
     // Use hardcoded ttwid and odin_tt from the user's working test for now
@@ -97,13 +96,13 @@ pub async fn fetch_douyin_partition_rooms(
 
     let cookie_string = format!("odin_tt={}; ttwid={}", hardcoded_odin_tt, hardcoded_ttwid);
 
-    let mut headers = HeaderMap::new();
+    let (mut headers, user_agent) =
+        signed_request_headers("https://live.douyin.com/webcast/web/partition/detail/room/v2/");
     headers.insert(
         COOKIE,
         HeaderValue::from_str(&cookie_string)
             .map_err(|e| format!("Failed to create cookie header value: {}", e))?,
     );
-    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
 
     let params: Vec<(String, String)> = vec![
         ("aid".to_string(), "6383".to_string()),
@@ -129,7 +128,7 @@ pub async fn fetch_douyin_partition_rooms(
 
     let query = serde_urlencoded::to_string(&params)
         .map_err(|e| format!("Failed to encode Douyin partition params: {}", e))?;
-    let sign = generate_a_bogus(&query, DEFAULT_USER_AGENT);
+    let sign = generate_a_bogus(&query, &user_agent);
     let url = format!(
         "https://live.douyin.com/webcast/web/partition/detail/room/v2/?{}&a_bogus={}",
         query,
@@ -1,3 +1,4 @@
+use crate::platforms::common::header_rules::upstream_headers;
 use crate::platforms::common::http_client::HttpClient;
 use crate::platforms::douyin::a_bogus::generate_a_bogus;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, COOKIE, REFERER, USER_AGENT};
@@ -7,9 +8,25 @@ use serde_json::Value;
 const DEFAULT_COOKIE: &str =
     "ttwid=1%7C2iDIYVmjzMcpZ20fcaFde0VghXAA3NaNXE_SLR68IyE%7C1761045455%7Cab35197d5cfb21df6cbb2fa7ef1c9262206b062c315b9d04da746d0b37dfbc7d";
 // Align UA with the working Douyin Rust sample to keep a_bogus inputs consistent.
+// This is the default for the Douyin header rule; signers read the effective UA via `signed_request_headers`.
 pub const DEFAULT_USER_AGENT: &str =
     "Mozilla/5.0 (Windows NT 10.0; WOW64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.5845.97 Safari/537.36 Core/1.116.567.400 QQBrowser/19.7.6764.400";
 
+/// Headers from the rules table for a signed Douyin request, plus the UA that must be fed to the signer.
+/// Falls back to `DEFAULT_USER_AGENT` when a user rule removes the UA so the signature still matches.
+pub(crate) fn signed_request_headers(url: &str) -> (HeaderMap, String) {
+    let mut headers = upstream_headers(Some("DOUYIN"), url);
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(DEFAULT_USER_AGENT)
+        .to_string();
+    if let Ok(value) = HeaderValue::from_str(&user_agent) {
+        headers.insert(USER_AGENT, value);
+    }
+    (headers, user_agent)
+}
+
 #[derive(Debug, Clone)]
 pub struct DouyinRoomData {
     pub room: Value,
@@ -115,8 +132,8 @@ async fn fetch_room_from_api(
     web_id: &str,
     cookies: Option<&str>,
 ) -> Result<DouyinRoomData, String> {
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+    let (mut headers, user_agent) =
+        signed_request_headers("https://live.douyin.com/webcast/room/web/enter/");
     headers.insert(
         REFERER,
         HeaderValue::from_str(&format!("https://live.douyin.com/{web_id}"))
@@ -144,7 +161,7 @@ async fn fetch_room_from_api(
     ];
     let query = serde_urlencoded::to_string(&params)
         .map_err(|e| format!("Failed to encode Douyin enter params: {}", e))?;
-    let sign = generate_a_bogus(&query, DEFAULT_USER_AGENT);
+    let sign = generate_a_bogus(&query, &user_agent);
     let api = format!(
         "https://live.douyin.com/webcast/room/web/enter/?{}&a_bogus={}",
         query, sign
@@ -0,0 +1,229 @@
+// 斗鱼弹幕服务器推送的消息，由 STT 字典按 type 字段解析
+use std::collections::HashMap;
+
+use super::stt;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DouyuUser {
+    pub uid: String,
+    pub nickname: String,
+    pub level: u32,
+    /// 粉丝牌名称 / 等级 / 所属房间
+    pub badge_name: String,
+    pub badge_level: u32,
+    pub badge_room_id: String,
+    /// 贵族等级，0 表示没有
+    pub noble_level: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DouyuMessage {
+    /// chatmsg
+    Chat {
+        user: DouyuUser,
+        text: String,
+        color: Option<u32>,
+    },
+    /// uenter
+    Enter {
+        user: DouyuUser,
+    },
+    /// dgb 礼物；hits 为连击数
+    Gift {
+        user: DouyuUser,
+        gift_id: String,
+        gift_name: Option<String>,
+        count: u32,
+        hits: u32,
+    },
+    /// anbc 开通贵族 / rnewbc 续费贵族；广播到全站，room_id 为开通所在房间
+    Noble {
+        room_id: String,
+        uid: String,
+        nickname: String,
+        level: u32,
+        renew: bool,
+    },
+    /// spbc 全站礼物广播
+    GiftBroadcast {
+        room_id: String,
+        sender: String,
+        anchor: String,
+        gift_name: String,
+        count: u32,
+    },
+    /// ssd 超级弹幕
+    SuperDanmaku {
+        room_id: String,
+        content: String,
+    },
+    /// upgrade 用户升级
+    Upgrade {
+        user: DouyuUser,
+    },
+    /// rss 开关播
+    LiveStatus {
+        room_id: String,
+        live: bool,
+    },
+    Unsupported {
+        kind: String,
+    },
+}
+
+pub fn noble_name(level: u32) -> &'static str {
+    match level {
+        1 => "骑士",
+        2 => "子爵",
+        3 => "伯爵",
+        4 => "公爵",
+        5 => "国王",
+        6 => "皇帝",
+        7 => "游侠",
+        _ => "贵族",
+    }
+}
+
+fn field<'a>(msg: &'a HashMap<String, String>, key: &str) -> &'a str {
+    msg.get(key).map(String::as_str).unwrap_or("")
+}
+
+fn number(msg: &HashMap<String, String>, key: &str) -> u32 {
+    field(msg, key).parse().unwrap_or(0)
+}
+
+fn parse_user(msg: &HashMap<String, String>) -> DouyuUser {
+    let nickname = match field(msg, "nn") {
+        "" => "unknown",
+        nn => nn,
+    };
+    DouyuUser {
+        uid: field(msg, "uid").to_string(),
+        nickname: nickname.to_string(),
+        level: number(msg, "level"),
+        badge_name: field(msg, "bnn").to_string(),
+        badge_level: number(msg, "bl"),
+        badge_room_id: field(msg, "brid").to_string(),
+        noble_level: number(msg, "nl"),
+    }
+}
+
+/// 解析一条 STT 正文
+pub fn parse_message(body: &str) -> DouyuMessage {
+    let msg = stt::decode_map(body);
+    let kind = field(&msg, "type");
+    match kind {
+        "chatmsg" => DouyuMessage::Chat {
+            user: parse_user(&msg),
+            text: field(&msg, "txt").to_string(),
+            color: field(&msg, "col").parse().ok(),
+        },
+        "uenter" => DouyuMessage::Enter {
+            user: parse_user(&msg),
+        },
+        "dgb" => DouyuMessage::Gift {
+            user: parse_user(&msg),
+            gift_id: field(&msg, "gfid").to_string(),
+            gift_name: Some(field(&msg, "gfn").to_string()).filter(|n| !n.is_empty()),
+            count: number(&msg, "gfcnt").max(1),
+            hits: number(&msg, "hits"),
+        },
+        "anbc" | "rnewbc" => DouyuMessage::Noble {
+            room_id: field(&msg, "drid").to_string(),
+            uid: field(&msg, "uid").to_string(),
+            nickname: field(&msg, "unk").to_string(),
+            level: number(&msg, "nl"),
+            renew: kind == "rnewbc",
+        },
+        "spbc" => DouyuMessage::GiftBroadcast {
+            room_id: field(&msg, "drid").to_string(),
+            sender: field(&msg, "sn").to_string(),
+            anchor: field(&msg, "dn").to_string(),
+            gift_name: field(&msg, "gn").to_string(),
+            count: number(&msg, "gc").max(1),
+        },
+        "ssd" => DouyuMessage::SuperDanmaku {
+            room_id: field(&msg, "trid").to_string(),
+            content: field(&msg, "content").to_string(),
+        },
+        "upgrade" => DouyuMessage::Upgrade {
+            user: parse_user(&msg),
+        },
+        "rss" => DouyuMessage::LiveStatus {
+            room_id: field(&msg, "rid").to_string(),
+            live: field(&msg, "ss") == "1",
+        },
+        _ => DouyuMessage::Unsupported {
+            kind: kind.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chat_with_badge_and_color() {
+        let body = "type@=chatmsg/rid@=9999/uid@=123/nn@=观众@S甲/txt@=你好@A/col@=2/level@=15/bnn@=鱼丸/bl@=7/brid@=9999/nl@=3/";
+        let DouyuMessage::Chat { user, text, color } = parse_message(body) else {
+            panic!("expected a chat message");
+        };
+        assert_eq!(text, "你好@");
+        assert_eq!(color, Some(2));
+        assert_eq!(
+            user,
+            DouyuUser {
+                uid: "123".to_string(),
+                nickname: "观众/甲".to_string(),
+                level: 15,
+                badge_name: "鱼丸".to_string(),
+                badge_level: 7,
+                badge_room_id: "9999".to_string(),
+                noble_level: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_gifts_and_nobles() {
+        assert_eq!(
+            parse_message("type@=dgb/gfid@=824/gfcnt@=0/hits@=5/uid@=1/nn@=a/"),
+            DouyuMessage::Gift {
+                user: DouyuUser {
+                    uid: "1".to_string(),
+                    nickname: "a".to_string(),
+                    ..Default::default()
+                },
+                gift_id: "824".to_string(),
+                gift_name: None,
+                count: 1,
+                hits: 5,
+            }
+        );
+        assert_eq!(
+            parse_message("type@=rnewbc/drid@=9999/uid@=1/unk@=a/nl@=4/"),
+            DouyuMessage::Noble {
+                room_id: "9999".to_string(),
+                uid: "1".to_string(),
+                nickname: "a".to_string(),
+                level: 4,
+                renew: true,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_types() {
+        assert_eq!(
+            parse_message("type@=mrkl/"),
+            DouyuMessage::Unsupported {
+                kind: "mrkl".to_string()
+            }
+        );
+        assert!(matches!(
+            parse_message("type@=rss/rid@=9999/ss@=0/"),
+            DouyuMessage::LiveStatus { live: false, .. }
+        ));
+    }
+}
@@ -1,17 +1,132 @@
+use super::danmu_messages::{self, noble_name, DouyuMessage, DouyuUser};
+use super::stt::{self, PacketDecoder, SttValue};
+use crate::platforms::common::danmaku::DanmakuMedal;
+use crate::platforms::common::danmaku_listener::{DanmakuListener, ListenerContext};
+use crate::platforms::common::network_proxy;
+use crate::platforms::common::{DanmakuEvent, DanmakuEventKind, DanmakuPlatform, DanmakuUser};
+use futures_util::future::BoxFuture;
 use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
-use tauri::{Emitter, Window};
 use tokio::sync::mpsc;
-use tokio::sync::oneshot;
 use tokio::time::{sleep, Duration};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
-use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::Message};
+use tokio_tungstenite::tungstenite::Message;
 use url::Url;
 
+// 斗鱼彩色弹幕的 col 字段是颜色序号而不是 RGB 值
+fn douyu_color(col: Option<u32>) -> Option<String> {
+    let color = match col? {
+        1 => "#ff0000",
+        2 => "#1e87f0",
+        3 => "#7ac84b",
+        4 => "#ff7f00",
+        5 => "#9b39f4",
+        6 => "#ff69b4",
+        _ => return None,
+    };
+    Some(color.to_string())
+}
+
+fn to_danmaku_user(user: DouyuUser) -> DanmakuUser {
+    let noble_level = user.noble_level;
+    DanmakuUser::named(user.nickname)
+        .id(user.uid)
+        .level(user.level)
+        .noble(noble_level, noble_name(noble_level))
+        .medal_info(Some(DanmakuMedal {
+            name: user.badge_name,
+            level: user.badge_level,
+            anchor_name: None,
+            anchor_room_id: Some(user.badge_room_id).filter(|id| !id.is_empty()),
+        }))
+}
+
+fn to_danmaku_event(room_id: &str, msg: DouyuMessage) -> Option<DanmakuEvent> {
+    let event = |kind: DanmakuEventKind| DanmakuEvent::new(DanmakuPlatform::Douyu, room_id, kind);
+    let event = match msg {
+        DouyuMessage::Chat { user, text, color } => {
+            DanmakuEvent::chat(DanmakuPlatform::Douyu, room_id, text)
+                .user(to_danmaku_user(user))
+                .color(douyu_color(color))
+        }
+        DouyuMessage::Enter { user } => event(DanmakuEventKind::Enter).user(to_danmaku_user(user)),
+        DouyuMessage::Gift {
+            user,
+            gift_id,
+            gift_name,
+            count,
+            hits,
+        } => event(DanmakuEventKind::Gift {
+            gift_name: gift_name.unwrap_or_else(|| format!("礼物{}", gift_id)),
+            gift_id: Some(gift_id),
+            count,
+            value_cny: None,
+            combo_id: None,
+            combo_total: (hits > 1).then_some(hits),
+        })
+        .user(to_danmaku_user(user)),
+        // 贵族、礼物广播会推送到所有房间，只保留本房间的
+        DouyuMessage::Noble {
+            room_id: target,
+            uid,
+            nickname,
+            level,
+            renew: _,
+        } if target == room_id => event(DanmakuEventKind::Guard {
+            level,
+            level_name: noble_name(level).to_string(),
+            // 斗鱼的开通/续费广播不带时长
+            months: None,
+        })
+        .user(DanmakuUser::named(nickname).id(uid)),
+        DouyuMessage::GiftBroadcast {
+            room_id: target,
+            sender,
+            anchor,
+            gift_name,
+            count,
+        } if target == room_id => event(DanmakuEventKind::Notice {
+            message: format!("{} 给 {} 送出 {} ×{}", sender, anchor, gift_name, count),
+        }),
+        DouyuMessage::SuperDanmaku {
+            room_id: target,
+            content,
+        } if target.is_empty() || target == room_id => {
+            event(DanmakuEventKind::Notice { message: content })
+        }
+        DouyuMessage::Upgrade { user } => {
+            let message = format!("{} 升级到 {} 级", user.nickname, user.level);
+            event(DanmakuEventKind::Notice { message }).user(to_danmaku_user(user))
+        }
+        DouyuMessage::LiveStatus {
+            room_id: target,
+            live,
+        } if target.is_empty() || target == room_id => {
+            event(DanmakuEventKind::LiveStatus { live, reason: None })
+        }
+        _ => return None,
+    };
+    Some(event)
+}
+
+pub struct DouyuDanmakuListener;
+
+impl DanmakuListener for DouyuDanmakuListener {
+    fn run(
+        &self,
+        ctx: ListenerContext,
+        stop_rx: mpsc::Receiver<()>,
+    ) -> BoxFuture<'static, Result<(), String>> {
+        Box::pin(async move {
+            let mut client = DanmakuClient::new(ctx, stop_rx);
+            client.start().await;
+            Ok(())
+        })
+    }
+}
+
 pub struct DanmakuClient {
-    room_id: String,
-    window: Window,
-    stop_signal_rx: oneshot::Receiver<()>,
+    ctx: ListenerContext,
+    stop_signal_rx: mpsc::Receiver<()>,
 }
 
 enum ConnectionOutcome {
@@ -20,60 +135,44 @@ enum ConnectionOutcome {
 }
 
 impl DanmakuClient {
-    pub fn new(room_id: &str, window: Window, stop_signal_rx: oneshot::Receiver<()>) -> Self {
+    pub fn new(ctx: ListenerContext, stop_signal_rx: mpsc::Receiver<()>) -> Self {
         Self {
-            room_id: room_id.to_string(),
-            window,
+            ctx,
             stop_signal_rx,
         }
     }
 
-    fn encode_msg(&self, msg: &str) -> Vec<u8> {
-        let msg_bytes = msg.as_bytes();
-        let packet_len = msg_bytes.len() + 9;
-
-        let mut result = Vec::new();
-        result.extend_from_slice(&(packet_len as u32).to_le_bytes());
-        result.extend_from_slice(&(packet_len as u32).to_le_bytes());
-        result.extend_from_slice(&689u16.to_le_bytes());
-        result.push(0);
-        result.push(0);
-        result.extend_from_slice(msg_bytes);
-        result.push(0);
-
-        result
-    }
-
-    async fn run_connection(
-        &self,
-        stop_rx: &mut oneshot::Receiver<()>,
-    ) -> Result<ConnectionOutcome, Box<dyn std::error::Error>> {
+    async fn run_connection(&mut self) -> Result<ConnectionOutcome, Box<dyn std::error::Error>> {
+        let room_id = self.ctx.room_id.clone();
         let url = Url::parse("wss://danmuproxy.douyu.com:8506/")?;
         let mut request = url.into_client_request()?;
         request
             .headers_mut()
             .insert("Sec-WebSocket-Protocol", "binary".parse()?);
 
-        let (ws_stream, _) = connect_async_tls_with_config(request, None, false, None).await?;
+        let (ws_stream, _) = network_proxy::connect_websocket(request, Some("DOUYU")).await?;
 
         let (mut write, mut read) = ws_stream.split();
 
         // 发送登录请求
-        let login_msg = format!("type@=loginreq/roomid@={}/", self.room_id);
-        let login_data = self.encode_msg(&login_msg);
-        write.send(Message::Binary(login_data)).await?;
+        let login_msg = SttValue::map(vec![("type", "loginreq"), ("roomid", &room_id)]);
+        write
+            .send(Message::Binary(stt::encode_packet(&login_msg.encode())))
+            .await?;
 
         // 发送加入房间请求
-        let join_msg = format!("type@=joingroup/rid@={}/gid@=1/", self.room_id);
-        let join_data = self.encode_msg(&join_msg);
-        write.send(Message::Binary(join_data)).await?;
+        let join_msg = SttValue::map(vec![("type", "joingroup"), ("rid", &room_id), ("gid", "1")]);
+        write
+            .send(Message::Binary(stt::encode_packet(&join_msg.encode())))
+            .await?;
+        self.ctx.connected();
 
         // 创建消息通道
         let (tx, mut rx) = mpsc::channel(32);
 
         // 启动心跳任务
-        let heartbeat_msg = "type@=mrkl/";
-        let heartbeat_data = self.encode_msg(heartbeat_msg);
+        let heartbeat_msg = SttValue::map(vec![("type", "mrkl")]);
+        let heartbeat_data = stt::encode_packet(&heartbeat_msg.encode());
         let tx_clone = tx.clone();
 
         tokio::spawn(async move {
@@ -94,96 +193,28 @@ impl DanmakuClient {
             }
         });
 
-        let window = self.window.clone();
-        let room_id_clone = self.room_id.clone();
+        let mut packets = PacketDecoder::default();
 
         // Processing incoming messages
         loop {
             tokio::select! {
-                _ = &mut *stop_rx => {
-                    eprintln!("[Douyu Danmaku {}] Stop signal received, terminating listener.", room_id_clone);
+                _ = self.stop_signal_rx.recv() => {
+                    eprintln!("[Douyu Danmaku {}] Stop signal received, terminating listener.", room_id);
                     send_task.abort();
                     return Ok(ConnectionOutcome::Stop);
                 }
                 msg_option = read.next() => {
                     match msg_option {
                         Some(Ok(Message::Binary(data))) => {
-                            if data.len() < 13 {
-                                continue;
-                            }
-
-                            let content = String::from_utf8_lossy(&data[12..data.len()-1]);
-                            let mut result = HashMap::new();
-                            for item in content.split('/') {
-                                if item.is_empty() {
-                                    continue;
-                                }
-                                if let Some((key, value)) = item.split_once("@=") {
-                                    result.insert(
-                                        key.to_string(),
-                                        value.replace("@S", "/").replace("@A", "@")
-                                    );
+                            for body in packets.push(&data) {
+                                let msg = danmu_messages::parse_message(&body);
+                                if let Some(event) = to_danmaku_event(&room_id, msg) {
+                                    self.ctx.emit(event);
                                 }
                             }
-
-                            let event_name = format!("danmaku-{}", room_id_clone);
-
-                            if result.get("type").map_or(false, |t| t == "chatmsg") {
-                                let unknown = "unknown".to_string();
-                                let empty = "".to_string();
-                                let zero = "0".to_string();
-
-                                let danmaku = serde_json::json!({
-                                    "type": "chatmsg",
-                                    "nickname": result.get("nn").unwrap_or(&unknown),
-                                    "content": result.get("txt").unwrap_or(&empty),
-                                    "level": result.get("level").unwrap_or(&zero),
-                                    "badgeName": result.get("bnn").unwrap_or(&empty),
-                                    "badgeLevel": result.get("bl").unwrap_or(&zero),
-                                    "color": result.get("col").map_or(None, |c| Some(c.to_string())),
-                                    "room_id": room_id_clone.clone()
-                                });
-
-                                let _ = window.emit(&event_name, danmaku);
-
-                                // 统一向前端发送通用弹幕事件，便于跨平台 DanmuList 使用
-                                let _ = window.emit(
-                                    "danmaku-message",
-                                    crate::platforms::common::DanmakuFrontendPayload {
-                                        room_id: room_id_clone.clone(),
-                                        user: result.get("nn").unwrap_or(&unknown).to_string(),
-                                        content: result.get("txt").unwrap_or(&empty).to_string(),
-                                        user_level: result
-                                            .get("level")
-                                            .unwrap_or(&zero)
-                                            .parse::<i64>()
-                                            .unwrap_or(0),
-                                        fans_club_level: result
-                                            .get("bl")
-                                            .unwrap_or(&zero)
-                                            .parse::<i32>()
-                                            .unwrap_or(0),
-                                    },
-                                );
-                            } else if result.get("type").map_or(false, |t| t == "uenter") {
-                                let unknown = "unknown".to_string();
-                                let empty = "".to_string();
-                                let zero = "0".to_string();
-
-                                let uenter_msg = serde_json::json!({
-                                    "type": "uenter",
-                                    "uid": result.get("uid").unwrap_or(&empty),
-                                    "nickname": result.get("nn").unwrap_or(&unknown),
-                                    "level": result.get("level").unwrap_or(&zero),
-                                    "badgeName": result.get("bnn").unwrap_or(&empty),
-                                    "badgeLevel": result.get("bl").unwrap_or(&zero),
-                                    "room_id": room_id_clone.clone()
-                                });
-                                let _ = window.emit(&event_name, uenter_msg);
-                            }
                         }
                         Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
-                            eprintln!("[Douyu Danmaku {}] Websocket closed or error, terminating listener.", room_id_clone);
+                            eprintln!("[Douyu Danmaku {}] Websocket closed or error, terminating listener.", room_id);
                             send_task.abort();
                             return Ok(ConnectionOutcome::Disconnected);
                         }
@@ -194,35 +225,43 @@ impl DanmakuClient {
         }
     }
 
-    pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut stop_rx = std::mem::replace(&mut self.stop_signal_rx, oneshot::channel().1);
+    pub async fn start(&mut self) {
+        let room_id = self.ctx.room_id.clone();
         let mut backoff_secs = 1u64;
+        let mut attempt = 0u32;
 
         loop {
-            let outcome = self.run_connection(&mut stop_rx).await?;
-            match outcome {
-                ConnectionOutcome::Stop => {
-                    eprintln!("[Douyu Danmaku {}] Listener stopped.", self.room_id);
+            match self.run_connection().await {
+                Ok(ConnectionOutcome::Stop) => {
+                    eprintln!("[Douyu Danmaku {}] Listener stopped.", room_id);
                     break;
                 }
-                ConnectionOutcome::Disconnected => {
-                    eprintln!(
-                        "[Douyu Danmaku {}] Disconnected, retrying in {}s.",
-                        self.room_id, backoff_secs
-                    );
-                    let sleep_fut = sleep(Duration::from_secs(backoff_secs));
-                    tokio::select! {
-                        _ = sleep_fut => {}
-                        _ = &mut stop_rx => {
-                            eprintln!("[Douyu Danmaku {}] Stop signal received during backoff.", self.room_id);
-                            break;
-                        }
-                    }
-                    backoff_secs = (backoff_secs * 2).min(30);
-                    continue;
+                Ok(ConnectionOutcome::Disconnected) => {
+                    // 连上过一次就从头开始退避
+                    attempt = 0;
+                    backoff_secs = 1;
+                }
+                Err(e) => {
+                    eprintln!("[Douyu Danmaku {}] Connection failed: {}", room_id, e);
+                }
+            }
+
+            attempt += 1;
+            eprintln!(
+                "[Douyu Danmaku {}] Disconnected, retrying in {}s.",
+                room_id, backoff_secs
+            );
+            let delay = Duration::from_secs(backoff_secs);
+            self.ctx.reconnecting(attempt, delay);
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = self.stop_signal_rx.recv() => {
+                    eprintln!("[Douyu Danmaku {}] Stop signal received during backoff.", room_id);
+                    break;
                 }
             }
+            backoff_secs = (backoff_secs * 2).min(30);
+            self.ctx.connecting();
         }
-        Ok(())
     }
 }
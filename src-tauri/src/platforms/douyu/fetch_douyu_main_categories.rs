@@ -1,4 +1,4 @@
-use reqwest::header::USER_AGENT;
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
 use serde::{Deserialize, Serialize};
 use tauri::command;
 // Structs expected by the frontend
@@ -7,6 +7,7 @@ pub struct FrontendCate3Item {
     id: String,   // cate3Id
     name: String, // cate3Name
 }
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FrontendCate2Item {
@@ -153,14 +154,14 @@ pub async fn fetch_categories() -> Result<CategoriesApiResponse, String> {
 // Internal function to fetch and parse to the old frontend-specific structure
 async fn fetch_categories_douyu_raw() -> Result<Vec<RawFrontendCate1Item>, String> {
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("DOUYU"))
         .build()
         .map_err(|e| e.to_string())?;
     let url = "https://m.douyu.com/api/cate/list";
 
     let response = client
         .get(url)
-        .header(USER_AGENT, "Mozilla/5.0 (iPhone; CPU iPhone OS 13_2_3 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/13.0.3 Mobile/15E148 Safari/604.1")
+        .upstream_headers(Some("DOUYU"), url)
         .send()
         .await;
 
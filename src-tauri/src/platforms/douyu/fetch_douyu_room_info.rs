@@ -1,11 +1,12 @@
 // Douyu specific API logic will go here
 // NOTE: This module already uses reqwest and is consistent with the unified Douyu HTTP client approach.
 
-use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::header::HeaderValue;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tauri::State;
 
+use crate::platforms::common::header_rules::upstream_headers;
 use crate::platforms::common::FollowHttpClient;
 
 // Define the structure to be returned to TypeScript
@@ -24,7 +25,8 @@ pub async fn fetch_douyu_room_info(
     room_id: String,
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<DouyuFollowInfo, String> {
-    let mut headers = HeaderMap::new();
+    let url = format!("https://www.douyu.com/betard/{}", room_id);
+    let mut headers = upstream_headers(Some("DOUYU"), &url);
     headers.insert(
         "Accept",
         HeaderValue::from_static("application/json, text/plain, */*"),
@@ -39,12 +41,11 @@ pub async fn fetch_douyu_room_info(
         "Referer",
         HeaderValue::from_str(&format!("https://www.douyu.com/{}", room_id)).unwrap(),
     );
-    headers.insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
 
     let response_result = follow_http
-        .0
+        .client(Some("DOUYU"))?
         .inner
-        .get(format!("https://www.douyu.com/betard/{}", room_id))
+        .get(&url)
         .headers(headers)
         .send()
         .await;
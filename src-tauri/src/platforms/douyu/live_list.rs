@@ -1,3 +1,5 @@
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use serde::{Deserialize, Serialize};
 use tauri::command;
 
@@ -104,13 +106,13 @@ pub async fn fetch_live_list(offset: u32, cate2: String, limit: u32) -> Frontend
     );
 
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("DOUYU"))
         .build()
         .map_err(|e| e.to_string())
         .unwrap();
     let response_result = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (iPhone; CPU iPhone OS 13_2_3 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/13.0.3 Mobile/15E148 Safari/604.1")
+        .upstream_headers(Some("DOUYU"), &url)
         .send()
         .await;
 
@@ -239,7 +241,10 @@ pub async fn fetch_live_list_for_cate3(
     );
     println!("[Backend fetch_live_list_for_cate3] Fetching URL: {}", url);
 
-    let client = match reqwest::Client::builder().no_proxy().build() {
+    let client = match reqwest::Client::builder()
+        .outbound_proxy(Some("DOUYU"))
+        .build()
+    {
         Ok(c) => c,
         Err(e) => {
             return FrontendLiveListResponse {
@@ -251,7 +256,7 @@ pub async fn fetch_live_list_for_cate3(
     };
     let response_result = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
+        .upstream_headers(Some("DOUYU"), &url)
         .send()
         .await;
 
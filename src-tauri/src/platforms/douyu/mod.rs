@@ -1,9 +1,11 @@
+pub mod danmu_messages;
 pub mod danmu_start;
 pub mod fetch_douyu_main_categories;
 pub mod fetch_douyu_room_info;
 pub mod live_list;
 pub mod search_anchor;
 pub mod stream_url;
+pub mod stt;
 pub mod three_cate;
 pub mod types;
 
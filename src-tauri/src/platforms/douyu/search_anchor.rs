@@ -1,24 +1,15 @@
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use md5::Digest; // For hasher
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
-use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    redirect::Policy,
-    Client,
-};
+use reqwest::{redirect::Policy, Client};
 use std::time::{SystemTime, UNIX_EPOCH}; // For timestamp for did // For URL encoding keyword
 
 // Renamed from search_anchor to avoid ambiguity with Tauri command
 pub async fn perform_anchor_search(keyword: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let mut default_headers = HeaderMap::new();
-    default_headers.insert(
-        "User-Agent",
-        HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36"),
-    );
-
     let client = Client::builder()
         .redirect(Policy::limited(10))
-        .no_proxy()
-        .default_headers(default_headers)
+        .outbound_proxy(Some("DOUYU"))
         .build()?;
 
     let mut hasher = md5::Md5::new();
@@ -36,7 +27,8 @@ pub async fn perform_anchor_search(keyword: &str) -> Result<String, Box<dyn std:
     );
 
     let text = client
-        .get(url)
+        .get(&url)
+        .upstream_headers(Some("DOUYU"), &url)
         .header("Referer", "https://www.douyu.com/search/")
         .header("Cookie", format!("dy_did={}; acf_did={}", did, did))
         .send()
@@ -1,10 +1,8 @@
+use crate::platforms::common::header_rules::upstream_headers;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use deno_core::{JsRuntime, RuntimeOptions};
 use html_escape::decode_html_entities;
-use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    redirect::Policy,
-    Client,
-};
+use reqwest::{header::HeaderValue, redirect::Policy, Client};
 use serde::Deserialize;
 use serde_json::Value;
 #[cfg(target_os = "linux")]
@@ -58,7 +56,6 @@ struct DouYu {
 }
 
 const DEFAULT_DOUYU_CDN: &str = "ws-h5";
-const DEFAULT_DOUYU_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36";
 const DEFAULT_DOUYU_DID: &str = "10000000000000000000000000001501";
 const CRYPTO_JS: &str = include_str!("cryptojs.min.js");
 
@@ -88,16 +85,15 @@ fn normalize_douyu_cdn(input: Option<&str>) -> &'static str {
 
 impl DouYu {
     async fn new(rid: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        // 迁移到 reqwest：禁用系统代理、限制重定向、设置默认 UA/语言等头部
-        let mut default_headers = HeaderMap::new();
-        default_headers.insert("User-Agent", HeaderValue::from_static(DEFAULT_DOUYU_UA));
+        // 迁移到 reqwest：禁用系统代理、限制重定向；UA/Referer 取自请求头规则表，各接口再覆盖带房间号的 Referer
+        let mut default_headers = upstream_headers(Some("DOUYU"), "https://www.douyu.com/");
         default_headers.insert(
             "Accept-Language",
             HeaderValue::from_static("zh-CN,zh;q=0.9"),
         );
         let client = Client::builder()
             .redirect(Policy::limited(10))
-            .no_proxy()
+            .outbound_proxy(Some("DOUYU"))
             .default_headers(default_headers)
             .build()?;
 
@@ -0,0 +1,204 @@
+// 斗鱼 STT (Serialization Type Text) 编解码与封包
+//
+// 报文格式：4 字节长度 + 4 字节长度(重复) + 2 字节消息类型 + 1 字节加密 + 1 字节保留 + 正文 + '\0'，
+// 全部小端。长度不包含自身的 4 字节。一个 websocket 帧里可能有多个报文，也可能只有半个。
+//
+// 正文是 `key@=value/` 序列，'/' 转义为 "@S"，'@' 转义为 "@A"；
+// 嵌套的字典先编码成字符串再整体转义，所以解码时只拆一层，需要时再对字段值继续 decode_map。
+use std::collections::HashMap;
+
+// 客户端发往服务端的消息类型
+const MSG_TYPE_CLIENT: u16 = 689;
+// 长度字段之后的头部：长度(4) + 类型(2) + 加密(1) + 保留(1)
+const HEADER_AFTER_LEN: usize = 8;
+// 单个报文的上限，超过说明数据已经错位
+const MAX_PACKET_LEN: usize = 1 << 20;
+
+pub fn escape(s: &str) -> String {
+    s.replace('@', "@A").replace('/', "@S")
+}
+
+pub fn unescape(s: &str) -> String {
+    s.replace("@S", "/").replace("@A", "@")
+}
+
+/// 用于编码的 STT 值
+#[derive(Debug, Clone, PartialEq)]
+pub enum SttValue {
+    Str(String),
+    Map(Vec<(String, SttValue)>),
+}
+
+impl SttValue {
+    /// 按插入顺序构造字典，登录请求等要求 type 在最前
+    pub fn map<K: Into<String>, V: Into<SttValue>>(entries: Vec<(K, V)>) -> Self {
+        SttValue::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+
+    pub fn encode(&self) -> String {
+        match self {
+            SttValue::Str(s) => s.clone(),
+            SttValue::Map(entries) => entries
+                .iter()
+                .map(|(k, v)| format!("{}@={}/", escape(k), escape(&v.encode())))
+                .collect(),
+        }
+    }
+}
+
+impl From<&str> for SttValue {
+    fn from(s: &str) -> Self {
+        SttValue::Str(s.to_string())
+    }
+}
+
+impl From<String> for SttValue {
+    fn from(s: String) -> Self {
+        SttValue::Str(s)
+    }
+}
+
+/// 解码一层字典；值已经反转义，嵌套的字典可以再调用 decode_map
+pub fn decode_map(s: &str) -> HashMap<String, String> {
+    s.split('/')
+        .filter_map(|item| item.split_once("@="))
+        .map(|(k, v)| (unescape(k), unescape(v)))
+        .collect()
+}
+
+/// 将一条 STT 正文封包
+pub fn encode_packet(body: &str) -> Vec<u8> {
+    let body = body.as_bytes();
+    let packet_len = (HEADER_AFTER_LEN + body.len() + 1) as u32;
+
+    let mut packet = Vec::with_capacity(packet_len as usize + 4);
+    packet.extend_from_slice(&packet_len.to_le_bytes());
+    packet.extend_from_slice(&packet_len.to_le_bytes());
+    packet.extend_from_slice(&MSG_TYPE_CLIENT.to_le_bytes());
+    packet.push(0);
+    packet.push(0);
+    packet.extend_from_slice(body);
+    packet.push(0);
+    packet
+}
+
+/// 按长度头拆包，跨帧的半个报文会留到下一次
+#[derive(Default)]
+pub struct PacketDecoder {
+    buf: Vec<u8>,
+}
+
+impl PacketDecoder {
+    pub fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(data);
+
+        let mut bodies = Vec::new();
+        let mut offset = 0;
+        while self.buf.len() - offset >= 4 {
+            let len_bytes: [u8; 4] = self.buf[offset..offset + 4].try_into().unwrap_or([0; 4]);
+            let packet_len = u32::from_le_bytes(len_bytes) as usize;
+            if !(HEADER_AFTER_LEN..=MAX_PACKET_LEN).contains(&packet_len) {
+                eprintln!(
+                    "[Douyu Danmaku] Invalid packet length {}, dropping {} buffered bytes",
+                    packet_len,
+                    self.buf.len() - offset
+                );
+                offset = self.buf.len();
+                break;
+            }
+            let end = offset + 4 + packet_len;
+            if self.buf.len() < end {
+                break;
+            }
+            let body = &self.buf[offset + 4 + HEADER_AFTER_LEN..end];
+            let body = body.strip_suffix(&[0]).unwrap_or(body);
+            bodies.push(String::from_utf8_lossy(body).into_owned());
+            offset = end;
+        }
+        self.buf.drain(..offset);
+        bodies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_round_trip() {
+        for s in ["", "a/b@c", "@S", "@A", "@/", "//@@", "弹幕@=内容/"] {
+            assert_eq!(unescape(&escape(s)), s, "{:?}", s);
+        }
+        assert_eq!(escape("a/b@c"), "a@Sb@Ac");
+    }
+
+    #[test]
+    fn encodes_in_insertion_order_and_decodes_nested_maps() {
+        let value = SttValue::map(vec![
+            ("type", SttValue::from("loginreq")),
+            ("roomid", SttValue::from("9999")),
+            ("list", SttValue::map(vec![("a", "1/2"), ("b", "x@y")])),
+        ]);
+        let encoded = value.encode();
+        assert!(encoded.starts_with("type@=loginreq/roomid@=9999/"));
+
+        let outer = decode_map(&encoded);
+        assert_eq!(outer["type"], "loginreq");
+        let inner = decode_map(&outer["list"]);
+        assert_eq!(inner["a"], "1/2");
+        assert_eq!(inner["b"], "x@y");
+    }
+
+    #[test]
+    fn frames_packets_with_little_endian_header() {
+        let packet = encode_packet("type@=mrkl/");
+        let len = (HEADER_AFTER_LEN + "type@=mrkl/".len() + 1) as u32;
+        assert_eq!(&packet[0..4], &len.to_le_bytes());
+        assert_eq!(&packet[4..8], &len.to_le_bytes());
+        assert_eq!(&packet[8..10], &MSG_TYPE_CLIENT.to_le_bytes());
+        assert_eq!(&packet[10..12], &[0, 0]);
+        assert_eq!(packet.last(), Some(&0));
+        assert_eq!(packet.len(), len as usize + 4);
+    }
+
+    #[test]
+    fn splits_and_joins_packets_across_frames() {
+        let stream = [
+            encode_packet("type@=chatmsg/txt@=一/"),
+            encode_packet("type@=chatmsg/txt@=二/"),
+            encode_packet("type@=mrkl/"),
+        ]
+        .concat();
+        for chunk_size in [1, 3, 13, stream.len()] {
+            let mut decoder = PacketDecoder::default();
+            let bodies: Vec<String> = stream
+                .chunks(chunk_size)
+                .flat_map(|chunk| decoder.push(chunk))
+                .collect();
+            assert_eq!(
+                bodies,
+                [
+                    "type@=chatmsg/txt@=一/",
+                    "type@=chatmsg/txt@=二/",
+                    "type@=mrkl/"
+                ],
+                "chunk size {}",
+                chunk_size
+            );
+            assert!(decoder.buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn drops_misaligned_data_and_recovers() {
+        let mut decoder = PacketDecoder::default();
+        assert!(decoder.push(&[0xff, 0xff, 0xff, 0x7f, 1, 2, 3]).is_empty());
+        assert!(decoder.buf.is_empty());
+        assert_eq!(decoder.push(&encode_packet("type@=mrkl/")), ["type@=mrkl/"]);
+    }
+}
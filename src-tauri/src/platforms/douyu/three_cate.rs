@@ -1,3 +1,4 @@
+use crate::platforms::common::network_proxy::OutboundProxyExt;
 use crate::platforms::common::types_rust::{CommonPlatformCategoryRust, SupportedPlatformRust};
 use log::{error, info};
 use serde::Deserialize;
@@ -48,7 +49,12 @@ pub async fn fetch_three_cate(tag_id: i32) -> Result<Vec<CommonPlatformCategoryR
         tag_id_str
     );
 
-    match reqwest::get(&url).await {
+    let client = reqwest::Client::builder()
+        .outbound_proxy(Some("DOUYU"))
+        .build()
+        .map_err(|e| format!("Failed to build client: {}", e))?;
+
+    match client.get(&url).send().await {
         Ok(response) => {
             if response.status().is_success() {
                 let body_text = response
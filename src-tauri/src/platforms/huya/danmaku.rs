@@ -1,19 +1,19 @@
+use super::messages::{noble_name, HuyaPush, WebSocketCommand, WsPushMessage, CMD_MSG_PUSH};
+use crate::platforms::common::danmaku::rgb_color;
+use crate::platforms::common::danmaku_listener::{DanmakuListener, ListenerContext};
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::{self, OutboundProxyExt};
+use crate::platforms::common::{DanmakuEvent, DanmakuEventKind, DanmakuPlatform, DanmakuUser};
+use futures_util::future::BoxFuture;
 use futures_util::{SinkExt, StreamExt};
-use log::info;
-use tars_stream::prelude::*;
-use tauri::Emitter;
+use log::{debug, info, warn};
 use tokio::sync::mpsc as tokio_mpsc;
 use tokio::time::{sleep, Duration};
-use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
 
 const WS_URL: &str = "wss://cdnws.api.huya.com";
-// 恢复 HEARTBEAT 常量（被误删），供心跳发送使用
-const HEARTBEAT: &'static [u8] = b"\x00\x03\x1d\x00\x00\x69\x00\x00\x00\x69\x10\x03\x2c\x3c\x4c\x56\x08\x6f\x6e\x6c\x69\x6e\x65\x75\x69\x66\x0f\x4f\x6e\x55\x73\x65\x72\x48\x65\x61\x72\x74\x42\x65\x61\x74\x7d\x00\x00\x3c\x08\x00\x01\x06\x04\x74\x52\x65\x71\x1d\x00\x00\x2f\x0a\x0a\x0c\x16\x00\x26\x00\x36\x07\x61\x64\x72\x5f\x77\x61\x70\x46\x00\x0b\x12\x03\xae\xf0\x0f\x22\x03\xae\xf0\x0f\x3c\x42\x6d\x52\x02\x60\x5c\x60\x01\x7c\x82\x00\x0b\xb0\x1f\x9c\xac\x0b\x8c\x98\x0c\xa8\x0c";
-// const HEARTBEAT_BASE64: &str = "ABQdAAwsNgBM"; // same as Python
-#[allow(dead_code)]
-const HEARTBEAT_BASE64: &str = "ABQdAAwsNgBM"; // same as Python
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
 
-// Minimal JCE/TARS codec for required Huya structures
 enum ConnectionOutcome {
     Stop,
     Disconnected,
@@ -25,16 +25,16 @@ async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64), String> {
         room_id
     );
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("HUYA"))
         .build()
         .map_err(|e| e.to_string())?;
     let resp = client
-        .get(url)
-        .header("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36")
+        .get(&url)
+        .upstream_headers(Some("HUYA"), &url)
         .header("Accept", "*/*")
-        .header("Origin", "https://www.huya.com")
-        .header("Referer", "https://www.huya.com/")
-        .send().await.map_err(|e| e.to_string())?;
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
     let text = resp.text().await.map_err(|e| e.to_string())?;
     let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
@@ -70,7 +70,7 @@ async fn fetch_huya_ids(room_id: &str) -> Result<(i64, i64), String> {
         return Err("未找到频道ID，房间可能未开播".to_string());
     }
 
-    println!(
+    info!(
         "[Huya Danmaku] fetch_huya_ids: room_id={} yyid={} topSid={}",
         room_id, ayyuid, top_sid
     );
@@ -94,271 +94,134 @@ pub async fn fetch_huya_join_params(room_id: String) -> Result<HuyaJoinParams, S
     }
 }
 
-#[tauri::command]
-pub async fn start_huya_danmaku_listener(
-    payload: crate::platforms::common::GetStreamUrlPayload,
-    app_handle: tauri::AppHandle,
-    state: tauri::State<'_, crate::platforms::common::HuyaDanmakuState>,
+/// 虎牙弹幕监听，由 `DanmakuRegistry` 管理生命周期
+pub struct HuyaDanmakuListener;
+
+impl DanmakuListener for HuyaDanmakuListener {
+    fn run(
+        &self,
+        ctx: ListenerContext,
+        rx_shutdown: tokio_mpsc::Receiver<()>,
+    ) -> BoxFuture<'static, Result<(), String>> {
+        Box::pin(run_listener(ctx, rx_shutdown))
+    }
+}
+
+async fn run_listener(
+    ctx: ListenerContext,
+    mut rx_shutdown: tokio_mpsc::Receiver<()>,
 ) -> Result<(), String> {
-    let room_id_or_url = payload.args.room_id_str.clone();
-    println!(
-        "[Huya Danmaku] start listener room_id_or_url={}",
-        room_id_or_url
-    );
+    let room_id_clone = ctx.room_id.clone();
     info!(
-        "[Huya Danmaku] start listener room_id_or_url={}",
-        room_id_or_url
+        "[Huya Danmaku] spawned worker for room_id={}",
+        room_id_clone
     );
 
-    // 停止已有监听
-    let previous_tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&room_id_or_url)
-    };
-    if let Some(tx) = previous_tx {
-        if tx.send(()).await.is_err() {
-            eprintln!("[Huya Danmaku] 旧任务关闭失败，可能已退出。");
-        }
-    }
+    let mut backoff_secs = 1u64;
+    let mut attempt = 0u32;
 
-    // 创建新的关闭通道并保存到 State
-    let (tx_shutdown, mut rx_shutdown) = tokio_mpsc::channel::<()>(1);
-    {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.insert(room_id_or_url.clone(), tx_shutdown);
-    }
+    loop {
+        let result: anyhow::Result<ConnectionOutcome> = async {
+            let (ws_url, reg_data) = get_ws_info_tars(&room_id_clone)
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
 
-    let app_handle_clone = app_handle.clone();
-    let room_id_clone = room_id_or_url.clone();
+            info!(
+                "[Huya Danmaku] ws_url={} reg_len={}",
+                ws_url,
+                reg_data.len()
+            );
 
-    tokio::spawn(async move {
-        println!(
-            "[Huya Danmaku] spawned worker for room_id={}",
-            room_id_clone
-        );
-        info!(
-            "[Huya Danmaku] spawned worker for room_id={}",
-            room_id_clone
-        );
+            info!("[Huya Danmaku] connecting to {}", ws_url);
+            let (ws_stream, _) =
+                network_proxy::connect_websocket(ws_url.as_str(), Some("HUYA")).await?;
 
-        let mut backoff_secs = 1u64;
+            let (mut ws_write, mut ws_read) = ws_stream.split();
+            ws_write.send(WsMessage::Binary(reg_data)).await?;
+            attempt = 0;
+            ctx.connected();
 
-        loop {
-            let result: anyhow::Result<ConnectionOutcome> = async {
-                let (ws_url, reg_data) = get_ws_info_tars(&room_id_clone)
+            let heartbeat = WebSocketCommand::heartbeat().encode()?;
+            let hb_task = async {
+                let mut hb_seq = 0usize;
+                while ws_write
+                    .send(WsMessage::Binary(heartbeat.clone()))
                     .await
-                    .map_err(|e| anyhow::anyhow!(e))?;
-
-                println!(
-                    "[Huya Danmaku] ws_url={} reg_len={}",
-                    ws_url,
-                    reg_data.len()
-                );
-                info!(
-                    "[Huya Danmaku] ws_url={} reg_len={}",
-                    ws_url,
-                    reg_data.len()
-                );
-
-                println!("[Huya Danmaku] connecting to {}", ws_url);
-                info!("[Huya Danmaku] connecting to {}", ws_url);
-                let (ws_stream, _) = connect_async(&ws_url).await?;
-
-                let (mut ws_write, mut ws_read) = ws_stream.split();
-                ws_write.send(WsMessage::Binary(reg_data)).await?;
+                    .is_ok()
+                {
+                    hb_seq += 1;
+                    debug!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
+                    sleep(HEARTBEAT_INTERVAL).await;
+                }
+                Err::<(), anyhow::Error>(anyhow::anyhow!("Huya heartbeat send failed"))
+            };
 
-                let hb_task = async {
-                    let mut hb_seq = 0usize;
-                    while let Ok(_) = ws_write.send(WsMessage::Binary(HEARTBEAT.into())).await {
-                        hb_seq += 1;
-                        println!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
-                        info!("[Huya Danmaku] heartbeat sent #{}", hb_seq);
-                        sleep(Duration::from_secs(20)).await;
-                    }
-                    Err::<(), anyhow::Error>(anyhow::anyhow!("Huya heartbeat send failed"))
-                };
-
-                let recv_task = async {
-                    while let Some(m) = ws_read.next().await {
-                        let m = match m {
-                            Ok(x) => x,
-                            Err(e) => return Err(anyhow::anyhow!(e)),
-                        };
-                        match m {
-                            WsMessage::Binary(bin) => {
-                                let (top_cmd, nested_cmd) = peek_cmds(&bin);
-                                println!(
-                                    "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
-                                    bin.len(),
-                                    top_cmd,
-                                    nested_cmd
-                                );
-                                info!(
-                                    "[Huya Danmaku] WS msg: len={} top_cmd={:?} nested_cmd={:?}",
-                                    bin.len(),
-                                    top_cmd,
-                                    nested_cmd
-                                );
-                                match decode_msg_tars(&bin)? {
-                                    Some((nick, text)) => {
-                                        println!(
-                                            "[Huya Danmaku] decoded chat: {} -> {}",
-                                            nick, text
-                                        );
-                                        info!("[Huya Danmaku] decoded chat: {} -> {}", nick, text);
-                                        let _ = app_handle_clone.emit(
-                                            "danmaku-message",
-                                            crate::platforms::common::DanmakuFrontendPayload {
-                                                room_id: room_id_clone.clone(),
-                                                user: nick,
-                                                content: text,
-                                                user_level: 0,
-                                                fans_club_level: 0,
-                                            },
-                                        );
-                                    }
-                                    None => {
-                                        if top_cmd == Some(7) {
-                                            println!(
-                                                "[Huya Danmaku] non-chat or empty msg, nested={:?}",
-                                                nested_cmd
-                                            );
-                                            info!(
-                                                "[Huya Danmaku] non-chat or empty msg, nested={:?}",
-                                                nested_cmd
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            other => {
-                                println!("[Huya Danmaku] non-binary ws message: {:?}", other);
-                                info!("[Huya Danmaku] non-binary ws message: {:?}", other);
+            let recv_task = async {
+                while let Some(m) = ws_read.next().await {
+                    let m = match m {
+                        Ok(x) => x,
+                        Err(e) => return Err(anyhow::anyhow!(e)),
+                    };
+                    match m {
+                        WsMessage::Binary(bin) => match decode_msg_tars(&bin, &room_id_clone) {
+                            Ok(Some(event)) => ctx.emit(event),
+                            Ok(None) => {}
+                            // 单条消息解不出来不影响连接
+                            Err(e) => {
+                                debug!("[Huya Danmaku] decode failed: len={} err={}", bin.len(), e)
                             }
+                        },
+                        other => {
+                            debug!("[Huya Danmaku] non-binary ws message: {:?}", other);
                         }
                     }
-                    anyhow::Ok(())
-                };
-
-                tokio::select! {
-                    _ = rx_shutdown.recv() => Ok(ConnectionOutcome::Stop),
-                    it = hb_task => {
-                        if let Err(e) = it { eprintln!("[Huya Danmaku] {}", e); }
-                        Ok(ConnectionOutcome::Disconnected)
-                    }
-                    it = recv_task => {
-                        if let Err(e) = it { eprintln!("[Huya Danmaku] recv error: {}", e); }
-                        Ok(ConnectionOutcome::Disconnected)
-                    }
                 }
-            }
-            .await;
-
-            match result {
-                Ok(ConnectionOutcome::Stop) => break,
-                Ok(ConnectionOutcome::Disconnected) => {
-                    eprintln!(
-                        "[Huya Danmaku] Disconnected, retrying in {}s.",
-                        backoff_secs
-                    );
+                anyhow::Ok(())
+            };
+
+            tokio::select! {
+                _ = rx_shutdown.recv() => Ok(ConnectionOutcome::Stop),
+                it = hb_task => {
+                    if let Err(e) = it { warn!("[Huya Danmaku] {}", e); }
+                    Ok(ConnectionOutcome::Disconnected)
                 }
-                Err(e) => {
-                    eprintln!(
-                        "[Huya Danmaku] Connection error: {}. Retrying in {}s.",
-                        e, backoff_secs
-                    );
+                it = recv_task => {
+                    if let Err(e) = it { warn!("[Huya Danmaku] recv error: {}", e); }
+                    Ok(ConnectionOutcome::Disconnected)
                 }
             }
-
-            let sleep_fut = sleep(Duration::from_secs(backoff_secs));
-            tokio::select! {
-                _ = sleep_fut => {}
-                _ = rx_shutdown.recv() => break,
+        }
+        .await;
+
+        match result {
+            Ok(ConnectionOutcome::Stop) => break,
+            Ok(ConnectionOutcome::Disconnected) => {
+                warn!(
+                    "[Huya Danmaku] Disconnected, retrying in {}s.",
+                    backoff_secs
+                );
+            }
+            Err(e) => {
+                warn!(
+                    "[Huya Danmaku] Connection error: {}. Retrying in {}s.",
+                    e, backoff_secs
+                );
             }
-            backoff_secs = (backoff_secs * 2).min(30);
         }
-    });
-
-    Ok(())
-}
-
-#[tauri::command]
-pub async fn stop_huya_danmaku_listener(
-    room_id: String,
-    state: tauri::State<'_, crate::platforms::common::HuyaDanmakuState>,
-) -> Result<(), String> {
-    println!(
-        "[Huya Danmaku] stop_huya_danmaku_listener called for room_id={}",
-        room_id
-    );
 
-    // 取出当前监听的停止信号发送器
-    let tx = {
-        let mut lock = state.inner().0.lock().unwrap();
-        lock.remove(&room_id)
-    };
-
-    if let Some(tx) = tx {
-        if let Err(_) = tx.send(()).await {
-            println!("[Huya Danmaku] 停止信号发送失败，监听器可能已经退出");
-        } else {
-            println!("[Huya Danmaku] 停止信号已发送给 room_id={}", room_id);
+        attempt += 1;
+        ctx.reconnecting(attempt, Duration::from_secs(backoff_secs));
+        let sleep_fut = sleep(Duration::from_secs(backoff_secs));
+        tokio::select! {
+            _ = sleep_fut => {}
+            _ = rx_shutdown.recv() => break,
         }
-    } else {
-        println!("[Huya Danmaku] 没有找到活跃的监听器需要停止");
+        backoff_secs = (backoff_secs * 2).min(30);
+        ctx.connecting();
     }
-
     Ok(())
 }
 
-// 采用 tars_stream 的实现（参考 all_in_one.rs），保留 Tauri 命令，对旧 jce 逻辑停用
-
-struct HuyaUser {
-    _uid: i64,
-    _imid: i64,
-    name: String,
-    _gender: i32,
-}
-
-struct HuyaDanmakuFmt {
-    color: i32,
-}
-
-impl StructFromTars for HuyaUser {
-    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
-        let uid = decoder.read_int64(0, false, -1)?;
-        let imid = decoder.read_int64(1, false, -1)?;
-        let name = decoder.read_string(2, false, "".to_string())?;
-        let gender = decoder.read_int32(3, false, -1)?;
-        Ok(HuyaUser {
-            _uid: uid,
-            _imid: imid,
-            name,
-            _gender: gender,
-        })
-    }
-}
-
-impl StructFromTars for HuyaDanmakuFmt {
-    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
-        let color = decoder.read_int32(0, false, 16777215)?;
-        Ok(HuyaDanmakuFmt { color })
-    }
-}
-
-fn peek_cmds(data: &[u8]) -> (Option<i32>, Option<i64>) {
-    let mut ios = TarsDecoder::from(data);
-    let top_cmd = ios.read_int32(0, false, -1).ok();
-    let nested_cmd = ios
-        .read_bytes(1, false, Default::default())
-        .ok()
-        .and_then(|b1| {
-            let mut inner = TarsDecoder::from(b1.as_ref());
-            inner.read_int32(1, false, -1).ok().map(|v| v as i64)
-        });
-    (top_cmd, nested_cmd)
-}
-
 fn find_uid_in_json(v: &serde_json::Value) -> Option<String> {
     match v {
         serde_json::Value::Object(map) => {
@@ -393,10 +256,6 @@ fn find_uid_in_json(v: &serde_json::Value) -> Option<String> {
     }
 }
 
-fn gen_ua() -> String {
-    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
-}
-
 async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), String> {
     let url = if room_id_or_url.starts_with("http") {
         reqwest::Url::parse(room_id_or_url).map_err(|e| e.to_string())?
@@ -408,24 +267,22 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
         .path_segments()
         .and_then(|s| s.last())
         .ok_or_else(|| "房间ID解析失败".to_string())?;
-    println!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
     info!("[Huya Danmaku] get_ws_info_tars rid={}", rid);
 
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("HUYA"))
         .build()
         .map_err(|e| e.to_string())?;
+    let room_url = format!("https://www.huya.com/{}", rid);
     let resp_text = client
-        .get(format!("https://www.huya.com/{}", rid))
-        .header("User-Agent", gen_ua())
-        .header("Referer", "https://www.huya.com/")
+        .get(&room_url)
+        .upstream_headers(Some("HUYA"), &room_url)
         .send()
         .await
         .map_err(|e| e.to_string())?
         .text()
         .await
         .map_err(|e| e.to_string())?;
-    println!("[Huya Danmaku] fetched room page len={}", resp_text.len());
     info!("[Huya Danmaku] fetched room page len={}", resp_text.len());
 
     // 先尝试 TT_PROFILE_INFO 提取 lp
@@ -472,7 +329,7 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
         );
         let text = client
             .get(&url_api)
-            .header("User-Agent", gen_ua())
+            .upstream_headers(Some("HUYA"), &url_api)
             .send()
             .await
             .map_err(|e| e.to_string())?
@@ -488,96 +345,105 @@ async fn get_ws_info_tars(room_id_or_url: &str) -> Result<(String, Vec<u8>), Str
     if ayyuid.is_empty() {
         ayyuid = rid.to_string();
     }
-    println!("[Huya Danmaku] final ayyuid={}", ayyuid);
     info!("[Huya Danmaku] final ayyuid={}", ayyuid);
 
     let mut topics = Vec::new();
     topics.push(format!("live:{}", ayyuid));
     topics.push(format!("chat:{}", ayyuid));
-    println!("[Huya Danmaku] topics={:?}", topics);
     info!("[Huya Danmaku] topics={:?}", topics);
 
-    let mut oos = TarsEncoder::new();
-    oos.write_list(0, &topics).map_err(|e| e.to_string())?;
-    oos.write_string(1, &"".to_owned())
-        .map_err(|e| e.to_string())?;
-
-    let mut wscmd = TarsEncoder::new();
-    wscmd.write_int32(0, 16).map_err(|e| e.to_string())?;
-    wscmd
-        .write_bytes(1, &oos.to_bytes())
+    let reg = WebSocketCommand::register_groups(topics)
+        .and_then(|cmd| cmd.encode())
         .map_err(|e| e.to_string())?;
-    let b = wscmd.to_bytes();
-    println!("[Huya Danmaku] reg payload built, len={}", b.len());
-    info!("[Huya Danmaku] reg payload built, len={}", b.len());
+    debug!("[Huya Danmaku] reg payload built, len={}", reg.len());
 
-    Ok((WS_URL.to_owned(), b.as_ref().to_vec()))
+    Ok((WS_URL.to_owned(), reg))
 }
-
-fn decode_msg_tars(data: &[u8]) -> anyhow::Result<Option<(String, String)>> {
-    let mut ret: Option<(String, String)> = None;
-    let mut ios = TarsDecoder::from(data);
-    let top = ios.read_int32(0, false, -1)?;
-    if top != 7 {
-        println!("[Huya Danmaku] ignore msg: top_cmd={}", top);
-        info!("[Huya Danmaku] ignore msg: top_cmd={}", top);
-        return Ok(ret);
+fn decode_msg_tars(data: &[u8], room_id: &str) -> anyhow::Result<Option<DanmakuEvent>> {
+    let cmd = WebSocketCommand::decode(data)?;
+    if cmd.cmd_type != CMD_MSG_PUSH {
+        debug!("[Huya Danmaku] ignore msg: cmd_type={}", cmd.cmd_type);
+        return Ok(None);
     }
-    let b1 = ios.read_bytes(1, false, Default::default())?;
-    let mut inner = TarsDecoder::from(b1.as_ref());
-    let nested = inner.read_int32(1, false, -1).unwrap_or(-1);
-    let b2 = inner.read_bytes(2, false, Default::default())?;
-    println!("[Huya Danmaku] nested={} payload_len={}", nested, b2.len());
-    info!("[Huya Danmaku] nested={} payload_len={}", nested, b2.len());
-    let mut payload = TarsDecoder::from(b2.as_ref());
-
-    if nested == 1400 {
-        let user = payload
-            .read_struct(
-                0,
-                false,
-                HuyaUser {
-                    _uid: -1,
-                    _imid: -1,
-                    name: "".to_owned(),
-                    _gender: 1,
-                },
-            )
-            .unwrap_or(HuyaUser {
-                _uid: -1,
-                _imid: -1,
-                name: "".to_owned(),
-                _gender: 1,
-            });
-        let text = payload
-            .read_string(3, false, "".to_owned())
-            .unwrap_or_default();
-        let fmt = payload
-            .read_struct(6, false, HuyaDanmakuFmt { color: 16777215 })
-            .unwrap_or(HuyaDanmakuFmt { color: 16777215 });
-        if !text.is_empty() {
-            let nick = if !user.name.is_empty() {
-                user.name
-            } else {
+    let push = WsPushMessage::decode(&cmd.data)?;
+    let Some(message) = HuyaPush::decode(&push)? else {
+        debug!("[Huya Danmaku] unhandled uri={}", push.uri);
+        return Ok(None);
+    };
+
+    let event = |kind: DanmakuEventKind| DanmakuEvent::new(DanmakuPlatform::Huya, room_id, kind);
+    let event = match message {
+        HuyaPush::Chat(notice) => {
+            if notice.content.is_empty() {
+                return Ok(None);
+            }
+            let badge = notice.badge();
+            let sender = notice.user;
+            let nick = if sender.nick_name.is_empty() {
                 "匿名".to_string()
+            } else {
+                sender.nick_name
             };
-            let _color_hex = format!("{:06x}", if fmt.color <= 0 { 16777215 } else { fmt.color });
-            println!(
-                "[Huya Danmaku] decoded nested=1400 nick={} text={}",
-                nick, text
-            );
-            info!(
-                "[Huya Danmaku] decoded nested=1400 nick={} text={}",
-                nick, text
+            let mut user = DanmakuUser::named(nick).id(sender.uid.max(0)).noble(
+                sender.noble_level.max(0) as u32,
+                noble_name(sender.noble_level),
             );
-            ret = Some((nick, text));
-        } else {
-            println!("[Huya Danmaku] empty text in nested=1400");
-            info!("[Huya Danmaku] empty text in nested=1400");
+            if let Some(badge) = badge {
+                user = user.medal(badge.badge_name, badge.badge_level.max(0) as u32);
+            }
+            DanmakuEvent::chat(DanmakuPlatform::Huya, room_id, notice.content)
+                .user(user)
+                .color(rgb_color(i64::from(notice.bullet_format.font_color)))
         }
-    } else {
-        println!("[Huya Danmaku] non-chat nested={}, skip", nested);
-        info!("[Huya Danmaku] non-chat nested={}, skip", nested);
-    }
-    Ok(ret)
+        HuyaPush::Gift(gift) => {
+            // 广播里没有礼物名时用道具 ID 兜底
+            let gift_name = if gift.props_name.is_empty() {
+                format!("礼物{}", gift.item_type)
+            } else {
+                gift.props_name
+            };
+            event(DanmakuEventKind::Gift {
+                gift_id: Some(gift.item_type.to_string()),
+                gift_name,
+                count: gift.item_count.max(1) as u32,
+                value_cny: None,
+                combo_id: (gift.combo_seq_id > 0).then(|| gift.combo_seq_id.to_string()),
+                combo_total: None,
+            })
+            .user(
+                DanmakuUser::named(gift.sender_nick)
+                    .id(gift.sender_uid.max(0))
+                    .noble(gift.noble_level.max(0) as u32, noble_name(gift.noble_level)),
+            )
+        }
+        HuyaPush::NobleEnter(noble) => {
+            let name = if noble.name.is_empty() {
+                noble_name(noble.level).to_string()
+            } else {
+                noble.name
+            };
+            event(DanmakuEventKind::Enter).user(
+                DanmakuUser::named(noble.nick_name)
+                    .id(noble.uid.max(0))
+                    .noble(noble.level.max(0) as u32, name),
+            )
+        }
+        HuyaPush::VipEnter(banner) => event(DanmakuEventKind::Enter)
+            .user(DanmakuUser::named(banner.nick_name).id(banner.uid.max(0))),
+        HuyaPush::AttendeeCount(count) => event(DanmakuEventKind::RoomStats {
+            online: u32::try_from(count).ok(),
+            watched: None,
+            likes: None,
+            display: None,
+        }),
+        HuyaPush::BeginLive => event(DanmakuEventKind::LiveStatus {
+            live: true,
+            reason: None,
+        }),
+        HuyaPush::EndLive => event(DanmakuEventKind::LiveStatus {
+            live: false,
+            reason: None,
+        }),
+    };
+    Ok(Some(event))
 }
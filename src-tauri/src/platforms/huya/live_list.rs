@@ -87,7 +87,7 @@ pub async fn fetch_huya_live_list(
         i_page_size
     );
 
-    let client = match HttpClient::new_direct_connection() {
+    let client = match HttpClient::new_direct_connection(Some("HUYA")) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("[Huya Backend] Failed to init HTTP client: {}", e);
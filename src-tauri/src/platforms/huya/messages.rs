@@ -0,0 +1,523 @@
+// 虎牙弹幕 WebSocket 使用的 TARS 结构，字段编号对应网页端 HUYA.* 的 readFrom/writeTo
+use tars_stream::prelude::*;
+
+// WebSocketCommand.iCmdType
+pub const CMD_MSG_PUSH: i32 = 7;
+pub const CMD_REGISTER_GROUP: i32 = 16;
+pub const CMD_HEARTBEAT: i32 = 20;
+
+// WSPushMessage.iUri
+pub const URI_MESSAGE_NOTICE: i32 = 1400;
+pub const URI_NOBLE_ENTER: i32 = 6110;
+pub const URI_VIP_ENTER_BANNER: i32 = 6210;
+pub const URI_SEND_ITEM: i32 = 6501;
+pub const URI_BEGIN_LIVE: i32 = 8000;
+pub const URI_END_LIVE: i32 = 8001;
+pub const URI_ATTENDEE_COUNT: i32 = 8006;
+
+// DecorationInfo.iAppId：粉丝徽章
+const DECORATION_APP_BADGE: i32 = 10400;
+
+// 弹幕默认白色
+const DEFAULT_FONT_COLOR: i32 = 0xFFFFFF;
+
+/// 贵族等级对应的名称
+pub fn noble_name(level: i32) -> &'static str {
+    match level {
+        1 => "剑士",
+        2 => "骑士",
+        3 => "领主",
+        4 => "公爵",
+        5 => "君王",
+        6 => "帝皇",
+        7 => "超神帝皇",
+        _ => "",
+    }
+}
+
+/// 外层命令：注册、心跳和服务端推送都包在这里
+pub struct WebSocketCommand {
+    pub cmd_type: i32,
+    pub data: Vec<u8>,
+    pub request_id: i64,
+    pub trace_id: String,
+    pub encrypt_type: i32,
+}
+
+impl WebSocketCommand {
+    pub fn new(cmd_type: i32, data: Vec<u8>) -> Self {
+        WebSocketCommand {
+            cmd_type,
+            data,
+            request_id: 0,
+            trace_id: String::new(),
+            encrypt_type: 0,
+        }
+    }
+
+    /// 客户端心跳，编码结果即网页端的 "ABQdAAwsNgBM"
+    pub fn heartbeat() -> Self {
+        WebSocketCommand::new(CMD_HEARTBEAT, Vec::new())
+    }
+
+    pub fn register_groups(groups: Vec<String>) -> Result<Self, EncodeErr> {
+        let req = WsRegisterGroupReq {
+            group_ids: groups,
+            token: String::new(),
+        };
+        Ok(WebSocketCommand::new(CMD_REGISTER_GROUP, req.encode()?))
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeErr> {
+        let mut encoder = TarsEncoder::new();
+        self._encode_to(&mut encoder)?;
+        Ok(encoder.to_bytes().to_vec())
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeErr> {
+        Self::_decode_from(&mut TarsDecoder::from(data))
+    }
+}
+
+impl StructToTars for WebSocketCommand {
+    fn _encode_to(&self, encoder: &mut TarsEncoder) -> Result<(), EncodeErr> {
+        encoder.write_int32(0, self.cmd_type)?;
+        encoder.write_bytes(1, &self.data.clone().into())?;
+        encoder.write_int64(2, self.request_id)?;
+        encoder.write_string(3, &self.trace_id)?;
+        encoder.write_int32(4, self.encrypt_type)?;
+        Ok(())
+    }
+}
+
+impl StructFromTars for WebSocketCommand {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(WebSocketCommand {
+            cmd_type: decoder.read_int32(0, false, -1)?,
+            data: decoder.read_bytes(1, false, Default::default())?.to_vec(),
+            request_id: decoder.read_int64(2, false, 0)?,
+            trace_id: decoder.read_string(3, false, String::new())?,
+            encrypt_type: decoder.read_int32(4, false, 0)?,
+        })
+    }
+}
+
+/// 订阅 live:{ayyuid} / chat:{ayyuid} 分组
+pub struct WsRegisterGroupReq {
+    pub group_ids: Vec<String>,
+    pub token: String,
+}
+
+impl WsRegisterGroupReq {
+    fn encode(&self) -> Result<Vec<u8>, EncodeErr> {
+        let mut encoder = TarsEncoder::new();
+        encoder.write_list(0, &self.group_ids)?;
+        encoder.write_string(1, &self.token)?;
+        Ok(encoder.to_bytes().to_vec())
+    }
+}
+
+/// CMD_MSG_PUSH 的内容，msg 再按 uri 解成具体结构
+pub struct WsPushMessage {
+    pub uri: i32,
+    pub msg: Vec<u8>,
+}
+
+impl WsPushMessage {
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeErr> {
+        Self::_decode_from(&mut TarsDecoder::from(data))
+    }
+}
+
+impl StructFromTars for WsPushMessage {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(WsPushMessage {
+            uri: decoder.read_int32(1, false, -1)?,
+            msg: decoder.read_bytes(2, false, Default::default())?.to_vec(),
+        })
+    }
+}
+
+/// 按 uri 解出的推送消息
+pub enum HuyaPush {
+    Chat(MessageNotice),
+    Gift(SendItemSubBroadcastPacket),
+    NobleEnter(NobleBase),
+    VipEnter(VipEnterBanner),
+    AttendeeCount(i32),
+    BeginLive,
+    EndLive,
+}
+
+impl HuyaPush {
+    /// 不关心的 uri 返回 Ok(None)
+    pub fn decode(push: &WsPushMessage) -> Result<Option<Self>, DecodeErr> {
+        let mut decoder = TarsDecoder::from(push.msg.as_slice());
+        let message = match push.uri {
+            URI_MESSAGE_NOTICE => HuyaPush::Chat(MessageNotice::_decode_from(&mut decoder)?),
+            URI_SEND_ITEM => {
+                HuyaPush::Gift(SendItemSubBroadcastPacket::_decode_from(&mut decoder)?)
+            }
+            URI_NOBLE_ENTER => {
+                HuyaPush::NobleEnter(decoder.read_struct(0, false, NobleBase::default())?)
+            }
+            URI_VIP_ENTER_BANNER => HuyaPush::VipEnter(VipEnterBanner::_decode_from(&mut decoder)?),
+            URI_ATTENDEE_COUNT => HuyaPush::AttendeeCount(decoder.read_int32(0, false, 0)?),
+            URI_BEGIN_LIVE => HuyaPush::BeginLive,
+            URI_END_LIVE => HuyaPush::EndLive,
+            _ => return Ok(None),
+        };
+        Ok(Some(message))
+    }
+}
+
+#[derive(Default)]
+pub struct SenderInfo {
+    pub uid: i64,
+    pub nick_name: String,
+    pub noble_level: i32,
+}
+
+impl StructFromTars for SenderInfo {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(SenderInfo {
+            uid: decoder.read_int64(0, false, 0)?,
+            nick_name: decoder.read_string(2, false, String::new())?,
+            noble_level: decoder.read_int32(5, false, 0)?,
+        })
+    }
+}
+
+pub struct BulletFormat {
+    pub font_color: i32,
+}
+
+impl Default for BulletFormat {
+    fn default() -> Self {
+        BulletFormat {
+            font_color: DEFAULT_FONT_COLOR,
+        }
+    }
+}
+
+impl StructFromTars for BulletFormat {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(BulletFormat {
+            font_color: decoder.read_int32(0, false, DEFAULT_FONT_COLOR)?,
+        })
+    }
+}
+
+/// 弹幕前后缀装饰，iAppId=10400 时 data 是 BadgeInfo
+#[derive(Default)]
+pub struct DecorationInfo {
+    pub app_id: i32,
+    pub data: Vec<u8>,
+}
+
+impl StructFromTars for DecorationInfo {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(DecorationInfo {
+            app_id: decoder.read_int32(0, false, 0)?,
+            data: decoder.read_bytes(2, false, Default::default())?.to_vec(),
+        })
+    }
+}
+
+// 作为 list 元素解码时每个元素是 tag 0 的结构
+impl DecodeTars for DecorationInfo {
+    fn _decode(decoder: &mut TarsDecoder, tag: u8) -> Result<Self, DecodeErr> {
+        decoder.read_struct(tag, true, DecorationInfo::default())
+    }
+}
+
+#[derive(Default)]
+pub struct BadgeInfo {
+    pub badge_name: String,
+    pub badge_level: i32,
+}
+
+impl StructFromTars for BadgeInfo {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(BadgeInfo {
+            badge_name: decoder.read_string(3, false, String::new())?,
+            badge_level: decoder.read_int32(4, false, 0)?,
+        })
+    }
+}
+
+/// 1400 聊天弹幕
+pub struct MessageNotice {
+    pub user: SenderInfo,
+    pub content: String,
+    pub bullet_format: BulletFormat,
+    pub decoration_prefix: Vec<DecorationInfo>,
+    pub decoration_suffix: Vec<DecorationInfo>,
+}
+
+impl MessageNotice {
+    /// 粉丝徽章放在装饰前缀或后缀里，取第一个能解出来的
+    pub fn badge(&self) -> Option<BadgeInfo> {
+        self.decoration_prefix
+            .iter()
+            .chain(self.decoration_suffix.iter())
+            .filter(|d| d.app_id == DECORATION_APP_BADGE)
+            .find_map(|d| {
+                let mut decoder = TarsDecoder::from(d.data.as_slice());
+                decoder.read_struct(0, false, BadgeInfo::default()).ok()
+            })
+            .filter(|b| !b.badge_name.is_empty())
+    }
+}
+
+impl StructFromTars for MessageNotice {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(MessageNotice {
+            user: decoder.read_struct(0, false, SenderInfo::default())?,
+            content: decoder.read_string(3, false, String::new())?,
+            bullet_format: decoder.read_struct(6, false, BulletFormat::default())?,
+            // 装饰解析失败不影响弹幕本身
+            decoration_prefix: decoder.read_list(8, false, Vec::new()).unwrap_or_default(),
+            decoration_suffix: decoder.read_list(9, false, Vec::new()).unwrap_or_default(),
+        })
+    }
+}
+
+/// 6501 礼物广播
+pub struct SendItemSubBroadcastPacket {
+    pub item_type: i32,
+    pub item_count: i32,
+    pub sender_uid: i64,
+    pub sender_nick: String,
+    pub props_name: String,
+    pub noble_level: i32,
+    pub combo_seq_id: i64,
+}
+
+impl StructFromTars for SendItemSubBroadcastPacket {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(SendItemSubBroadcastPacket {
+            item_type: decoder.read_int32(0, false, 0)?,
+            item_count: decoder.read_int32(2, false, 0)?,
+            sender_uid: decoder.read_int64(4, false, 0)?,
+            sender_nick: decoder.read_string(6, false, String::new())?,
+            props_name: decoder.read_string(20, false, String::new())?,
+            noble_level: decoder.read_int32(28, false, 0)?,
+            combo_seq_id: decoder.read_int64(39, false, 0)?,
+        })
+    }
+}
+
+/// 6110 贵族进场
+#[derive(Default)]
+pub struct NobleBase {
+    pub uid: i64,
+    pub nick_name: String,
+    pub level: i32,
+    pub name: String,
+}
+
+impl StructFromTars for NobleBase {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(NobleBase {
+            uid: decoder.read_int64(0, false, 0)?,
+            nick_name: decoder.read_string(1, false, String::new())?,
+            level: decoder.read_int32(2, false, 0)?,
+            name: decoder.read_string(3, false, String::new())?,
+        })
+    }
+}
+
+/// 6210 用户进场横幅
+pub struct VipEnterBanner {
+    pub uid: i64,
+    pub nick_name: String,
+}
+
+impl StructFromTars for VipEnterBanner {
+    fn _decode_from(decoder: &mut TarsDecoder) -> Result<Self, DecodeErr> {
+        Ok(VipEnterBanner {
+            uid: decoder.read_int64(0, false, 0)?,
+            nick_name: decoder.read_string(1, false, String::new())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按字段编号手工编码的 TARS 结构，用来构造服务端推送
+    enum Field {
+        Int(i64),
+        Str(&'static str),
+        Bytes(Vec<u8>),
+        Struct(Fields),
+        StructList(Vec<Fields>),
+    }
+
+    struct Fields(Vec<(u8, Field)>);
+
+    impl StructToTars for Fields {
+        fn _encode_to(&self, encoder: &mut TarsEncoder) -> Result<(), EncodeErr> {
+            for (tag, field) in &self.0 {
+                match field {
+                    Field::Int(v) => encoder.write_int64(*tag, *v)?,
+                    Field::Str(v) => encoder.write_string(*tag, &v.to_string())?,
+                    Field::Bytes(v) => encoder.write_bytes(*tag, &v.clone().into())?,
+                    Field::Struct(v) => encoder.write_struct(*tag, v)?,
+                    Field::StructList(v) => encoder.write_list(*tag, v)?,
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl EncodeTars for Fields {
+        fn _encode(&self, encoder: &mut TarsEncoder, tag: u8) -> Result<(), EncodeErr> {
+            encoder.write_struct(tag, self)
+        }
+    }
+
+    impl Fields {
+        fn to_bytes(&self) -> Vec<u8> {
+            let mut encoder = TarsEncoder::new();
+            self._encode_to(&mut encoder).unwrap();
+            encoder.to_bytes().to_vec()
+        }
+
+        /// 作为 tag 0 的结构再包一层，对应 read_struct(0, ..) 的读法
+        fn wrapped(self) -> Vec<u8> {
+            Fields(vec![(0, Field::Struct(self))]).to_bytes()
+        }
+    }
+
+    /// 服务端推送的完整帧：WebSocketCommand(7) -> WSPushMessage(uri) -> msg
+    fn push_frame(uri: i32, msg: Vec<u8>) -> Vec<u8> {
+        let push = Fields(vec![
+            (1, Field::Int(i64::from(uri))),
+            (2, Field::Bytes(msg)),
+        ]);
+        WebSocketCommand::new(CMD_MSG_PUSH, push.to_bytes())
+            .encode()
+            .unwrap()
+    }
+
+    fn decode_frame(frame: &[u8]) -> Option<HuyaPush> {
+        let command = WebSocketCommand::decode(frame).unwrap();
+        assert_eq!(command.cmd_type, CMD_MSG_PUSH);
+        HuyaPush::decode(&WsPushMessage::decode(&command.data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encodes_heartbeat_like_the_web_client() {
+        // 网页端心跳 "ABQdAAwsNgBM" 的 base64 解码
+        assert_eq!(
+            WebSocketCommand::heartbeat().encode().unwrap(),
+            [0x00, 0x14, 0x1d, 0x00, 0x0c, 0x2c, 0x36, 0x00, 0x4c]
+        );
+    }
+
+    #[test]
+    fn register_command_round_trips() {
+        let groups = vec!["live:123".to_string(), "chat:123".to_string()];
+        let encoded = WebSocketCommand::register_groups(groups.clone())
+            .unwrap()
+            .encode()
+            .unwrap();
+        let command = WebSocketCommand::decode(&encoded).unwrap();
+        assert_eq!(command.cmd_type, CMD_REGISTER_GROUP);
+        assert_eq!(command.request_id, 0);
+        let mut decoder = TarsDecoder::from(command.data.as_slice());
+        let decoded: Vec<String> = decoder.read_list(0, true, Vec::new()).unwrap();
+        assert_eq!(decoded, groups);
+    }
+
+    #[test]
+    fn decodes_chat_with_badge() {
+        let badge = Fields(vec![(3, Field::Str("小虎牙")), (4, Field::Int(12))]);
+        let notice = Fields(vec![
+            (
+                0,
+                Field::Struct(Fields(vec![
+                    (0, Field::Int(10086)),
+                    (2, Field::Str("观众")),
+                    (5, Field::Int(3)),
+                ])),
+            ),
+            (3, Field::Str("你好")),
+            (6, Field::Struct(Fields(vec![(0, Field::Int(0xFF0000))]))),
+            (
+                8,
+                Field::StructList(vec![Fields(vec![
+                    (0, Field::Int(i64::from(DECORATION_APP_BADGE))),
+                    (2, Field::Bytes(badge.wrapped())),
+                ])]),
+            ),
+        ]);
+
+        let Some(HuyaPush::Chat(chat)) =
+            decode_frame(&push_frame(URI_MESSAGE_NOTICE, notice.to_bytes()))
+        else {
+            panic!("expected a chat message");
+        };
+        assert_eq!(chat.content, "你好");
+        assert_eq!(chat.user.uid, 10086);
+        assert_eq!(chat.user.nick_name, "观众");
+        assert_eq!(chat.user.noble_level, 3);
+        assert_eq!(chat.bullet_format.font_color, 0xFF0000);
+        let badge = chat.badge().unwrap();
+        assert_eq!(
+            (badge.badge_name.as_str(), badge.badge_level),
+            ("小虎牙", 12)
+        );
+    }
+
+    #[test]
+    fn missing_optional_fields_use_defaults() {
+        let notice = Fields(vec![(3, Field::Str("hi"))]);
+        let Some(HuyaPush::Chat(chat)) =
+            decode_frame(&push_frame(URI_MESSAGE_NOTICE, notice.to_bytes()))
+        else {
+            panic!("expected a chat message");
+        };
+        assert_eq!(chat.bullet_format.font_color, DEFAULT_FONT_COLOR);
+        assert!(chat.badge().is_none());
+    }
+
+    #[test]
+    fn decodes_gift_and_noble_pushes() {
+        let gift = Fields(vec![
+            (0, Field::Int(4)),
+            (2, Field::Int(66)),
+            (4, Field::Int(10086)),
+            (6, Field::Str("观众")),
+            (20, Field::Str("虎粮")),
+            (39, Field::Int(987_654_321_012)),
+        ]);
+        let Some(HuyaPush::Gift(gift)) = decode_frame(&push_frame(URI_SEND_ITEM, gift.to_bytes()))
+        else {
+            panic!("expected a gift");
+        };
+        assert_eq!((gift.item_type, gift.item_count), (4, 66));
+        assert_eq!(gift.props_name, "虎粮");
+        assert_eq!(gift.combo_seq_id, 987_654_321_012);
+
+        let noble = Fields(vec![(1, Field::Str("贵族")), (2, Field::Int(5))]).wrapped();
+        let Some(HuyaPush::NobleEnter(noble)) = decode_frame(&push_frame(URI_NOBLE_ENTER, noble))
+        else {
+            panic!("expected a noble enter");
+        };
+        assert_eq!(noble.nick_name, "贵族");
+        assert_eq!(noble_name(noble.level), "君王");
+    }
+
+    #[test]
+    fn ignores_unknown_uris() {
+        assert!(decode_frame(&push_frame(9999, Vec::new())).is_none());
+        assert!(matches!(
+            decode_frame(&push_frame(URI_END_LIVE, Vec::new())),
+            Some(HuyaPush::EndLive)
+        ));
+    }
+}
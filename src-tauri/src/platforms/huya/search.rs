@@ -1,6 +1,6 @@
-use reqwest::header::{
-    HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, ORIGIN, REFERER, USER_AGENT,
-};
+use crate::platforms::common::header_rules::upstream_headers;
+use crate::platforms::common::network_proxy::OutboundProxyExt;
+use reqwest::header::{HeaderValue, ACCEPT, ACCEPT_LANGUAGE, REFERER};
 use serde::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -18,17 +18,15 @@ pub async fn search_huya_anchors(
     page: Option<usize>,
 ) -> Result<Vec<HuyaAnchorItem>, String> {
     let client = reqwest::Client::builder()
-        .no_proxy()
+        .outbound_proxy(Some("HUYA"))
         .build()
         .map_err(|e| e.to_string())?;
     let url = "https://search.cdn.huya.com/";
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36"));
+    let mut headers = upstream_headers(Some("HUYA"), url);
     headers.insert(
         REFERER,
         HeaderValue::from_static("https://www.huya.com/search/"),
     );
-    headers.insert(ORIGIN, HeaderValue::from_static("https://www.huya.com"));
     headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
     headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("zh-CN,zh;q=0.9"));
 
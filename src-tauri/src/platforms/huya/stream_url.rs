@@ -6,18 +6,16 @@ use base64::{engine::general_purpose, Engine as _};
 use md5::{Digest, Md5};
 use rand::Rng;
 use regex::Regex;
-use reqwest::header::{
-    HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, COOKIE, ORIGIN, REFERER, USER_AGENT,
-};
+use reqwest::header::{HeaderValue, ACCEPT, ACCEPT_LANGUAGE, COOKIE};
 use serde::Serialize;
 use serde_json::Value;
 use tauri::State;
 
+use crate::platforms::common::header_rules::upstream_headers;
 use crate::platforms::common::FollowHttpClient;
 
-const IOS_MOBILE_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1";
-const DESKTOP_UA: &str =
-    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:123.0) Gecko/20100101 Firefox/123.0";
+// 移动端回退请求按 m.huya.com 匹配规则表，取移动端 UA/Referer
+const MOBILE_SITE_URL: &str = "https://m.huya.com/";
 
 #[derive(Clone, Debug, Serialize)]
 #[allow(non_snake_case)]
@@ -158,8 +156,7 @@ async fn check_live_status(
     room_id: &str,
 ) -> Result<bool, Box<dyn Error + Send + Sync>> {
     let url = format!("https://m.huya.com/{}", room_id);
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; WOW64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/102.0.0.0 Safari/537.36"));
+    let headers = upstream_headers(Some("HUYA"), &url);
 
     let resp = client.get(&url).headers(headers).send().await?;
     let text = resp.text().await?;
@@ -207,11 +204,8 @@ async fn fetch_room_detail(
         "https://mp.huya.com/cache.php?m=Live&do=profileRoom&roomid={}&showSecret=1",
         room_id
     );
-    let mut headers = HeaderMap::new();
+    let mut headers = upstream_headers(Some("HUYA"), &url);
     headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
-    headers.insert(ORIGIN, HeaderValue::from_static("https://m.huya.com"));
-    headers.insert(REFERER, HeaderValue::from_static("https://m.huya.com/"));
-    headers.insert(USER_AGENT, HeaderValue::from_static(IOS_MOBILE_UA));
 
     let resp = client.get(&url).headers(headers).send().await?;
     let text = resp.text().await?;
@@ -288,25 +282,25 @@ async fn fetch_web_stream_data_with_headers(
     use_mobile_headers: bool,
 ) -> Result<HuyaWebStreamData, Box<dyn Error + Send + Sync>> {
     let url = format!("https://www.huya.com/{}", room_id);
-    let mut headers = HeaderMap::new();
+    let mut headers = if use_mobile_headers {
+        upstream_headers(Some("HUYA"), MOBILE_SITE_URL)
+    } else {
+        upstream_headers(Some("HUYA"), &url)
+    };
     if use_mobile_headers {
-        headers.insert(USER_AGENT, HeaderValue::from_static(IOS_MOBILE_UA));
         headers.insert(
             ACCEPT,
             HeaderValue::from_static(
                 "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
             ),
         );
-        headers.insert(REFERER, HeaderValue::from_static("https://m.huya.com/"));
     } else {
-        headers.insert(USER_AGENT, HeaderValue::from_static(DESKTOP_UA));
         headers.insert(
             ACCEPT,
             HeaderValue::from_static(
                 "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
             ),
         );
-        headers.insert(REFERER, HeaderValue::from_static("https://www.huya.com/"));
     }
     headers.insert(
         ACCEPT_LANGUAGE,
@@ -569,7 +563,7 @@ pub async fn get_huya_unified_cmd(
     follow_http: State<'_, FollowHttpClient>,
 ) -> Result<HuyaUnifiedResponse, String> {
     get_huya_unified_with_client(
-        &follow_http.0.inner,
+        &follow_http.client(Some("HUYA"))?.inner,
         &room_id,
         quality.as_deref(),
         line.as_deref(),
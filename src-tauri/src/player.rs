@@ -0,0 +1,523 @@
+use crate::platforms::bilibili::stream_url::get_bilibili_live_stream_url_with_quality;
+use crate::platforms::common::header_rules::upstream_headers;
+use crate::platforms::common::types::{GetStreamUrlArgs, GetStreamUrlPayload, StreamVariant};
+use crate::platforms::common::{FollowHttpClient, LiveStreamInfo};
+use crate::platforms::douyin::douyin_streamer_detail::fetch_douyin_live_stream_info_by_quality;
+use crate::platforms::douyu::get_stream_url_with_quality;
+use crate::platforms::huya::stream_url::{get_huya_unified_with_client, HuyaUnifiedResponse};
+use crate::recording::{normalize_platform, normalize_quality};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalPlayer {
+    Mpv,
+    Vlc,
+    Potplayer,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalPlayerPayload {
+    pub platform: String,
+    pub room_id: String,
+    pub quality: Option<String>,
+    pub cookie: Option<String>,
+    pub player: ExternalPlayer,
+    // 未指定时按平台常见安装位置/PATH 查找
+    pub player_path: Option<String>,
+    // 即使播放器能设置请求头也经本地代理播放
+    #[serde(default)]
+    pub force_proxy: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchExternalPlayerResponse {
+    pub program: String,
+    pub stream_url: String,
+    pub via_proxy: bool,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStreamPlaylistPayload {
+    pub platform: String,
+    pub room_id: String,
+    pub quality: Option<String>,
+    pub cookie: Option<String>,
+    // 扩展名决定格式：.m3u / .m3u8 / .strm
+    pub output_path: String,
+}
+
+/// 选中的播放地址及访问它所需的请求头
+struct ResolvedStream {
+    url: String,
+    is_flv: bool,
+    title: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+fn is_flv_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    !lower.contains(".m3u8") && (lower.contains(".flv") || lower.contains("flv?"))
+}
+
+fn huya_stream_info(response: HuyaUnifiedResponse) -> LiveStreamInfo {
+    let available_streams = response
+        .flv_tx_urls
+        .iter()
+        .map(|entry| StreamVariant {
+            url: entry.url.clone(),
+            format: Some("flv".to_string()),
+            desc: Some(entry.quality.clone()),
+            qn: None,
+            protocol: Some("http".to_string()),
+        })
+        .collect();
+    LiveStreamInfo {
+        title: response.title,
+        anchor_name: response.nick,
+        avatar: response.avatar,
+        stream_url: response.selected_url.clone(),
+        status: Some(if response.is_live { 1 } else { 0 }),
+        error_message: None,
+        upstream_url: response.selected_url,
+        available_streams: Some(available_streams),
+        normalized_room_id: None,
+        web_rid: None,
+    }
+}
+
+fn single_stream_info(url: String) -> LiveStreamInfo {
+    LiveStreamInfo {
+        title: None,
+        anchor_name: None,
+        avatar: None,
+        stream_url: Some(url.clone()),
+        status: Some(1),
+        error_message: None,
+        upstream_url: Some(url.clone()),
+        available_streams: Some(vec![StreamVariant {
+            format: Some(if is_flv_url(&url) { "flv" } else { "hls" }.to_string()),
+            url,
+            desc: None,
+            qn: None,
+            protocol: Some("http".to_string()),
+        }]),
+        normalized_room_id: None,
+        web_rid: None,
+    }
+}
+
+/// 各平台统一为 `LiveStreamInfo`，离线时返回错误
+async fn resolve_live_stream_info(
+    platform: &str,
+    room_id: &str,
+    quality: &str,
+    cookie: Option<&str>,
+    follow_client: &reqwest::Client,
+) -> Result<LiveStreamInfo, String> {
+    match platform {
+        "DOUYU" => {
+            let url = get_stream_url_with_quality(room_id, quality, None)
+                .await
+                .map_err(|e| format!("Douyu stream url failed: {}", e))?;
+            Ok(single_stream_info(url))
+        }
+        "DOUYIN" => {
+            let info = fetch_douyin_live_stream_info_by_quality(room_id, quality).await?;
+            if info.status != Some(2) {
+                return Err("Douyin streamer is offline".to_string());
+            }
+            Ok(info)
+        }
+        "HUYA" => {
+            let response =
+                get_huya_unified_with_client(follow_client, room_id, Some(quality), None).await?;
+            if !response.is_live {
+                return Err("Huya streamer is offline".to_string());
+            }
+            Ok(huya_stream_info(response))
+        }
+        "BILIBILI" => {
+            let info = get_bilibili_live_stream_url_with_quality(
+                GetStreamUrlPayload {
+                    args: GetStreamUrlArgs {
+                        room_id_str: room_id.to_string(),
+                    },
+                },
+                quality.to_string(),
+                cookie.map(|v| v.to_string()),
+            )
+            .await?;
+            if info.status != Some(1) {
+                return Err(info
+                    .error_message
+                    .unwrap_or_else(|| "Bilibili streamer is offline".to_string()));
+            }
+            Ok(info)
+        }
+        _ => Err(format!("Unsupported platform: {}", platform)),
+    }
+}
+
+/// 优先使用平台已按清晰度选好的上游地址，否则从 `available_streams` 中挑选（同清晰度的 FLV 优先）
+fn pick_stream_url(info: &LiveStreamInfo, quality: &str) -> Option<String> {
+    if let Some(url) = info
+        .upstream_url
+        .clone()
+        .or_else(|| info.stream_url.clone())
+    {
+        return Some(url);
+    }
+    let variants = info.available_streams.as_deref().unwrap_or_default();
+    let is_flv = |v: &&StreamVariant| {
+        v.format
+            .as_deref()
+            .map(|f| f.eq_ignore_ascii_case("flv"))
+            .unwrap_or_else(|| is_flv_url(&v.url))
+    };
+    let same_quality = |v: &&StreamVariant| v.desc.as_deref() == Some(quality);
+    variants
+        .iter()
+        .find(|v| same_quality(v) && is_flv(v))
+        .or_else(|| variants.iter().find(is_flv))
+        .or_else(|| variants.first())
+        .map(|v| v.url.clone())
+}
+
+async fn resolve_stream(
+    platform: &str,
+    room_id: &str,
+    quality: Option<&str>,
+    cookie: Option<&str>,
+    follow_client: &reqwest::Client,
+) -> Result<ResolvedStream, String> {
+    let platform = normalize_platform(platform)
+        .ok_or_else(|| format!("Unsupported platform: {}", platform))?;
+    let room_id = room_id.trim();
+    if room_id.is_empty() {
+        return Err("room_id is required".to_string());
+    }
+    let quality = normalize_quality(quality);
+    let cookie = cookie.map(str::trim).filter(|v| !v.is_empty());
+
+    let info = resolve_live_stream_info(platform, room_id, &quality, cookie, follow_client).await?;
+    let url = pick_stream_url(&info, &quality)
+        .ok_or_else(|| format!("No playable stream for {} room {}", platform, room_id))?;
+    let headers = upstream_headers(Some(platform), &url)
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|v| (canonical_header_name(name.as_str()), v.to_string()))
+        })
+        .collect();
+    let title = match (&info.anchor_name, &info.title) {
+        (Some(anchor), Some(title)) => Some(format!("{} - {}", anchor, title)),
+        (Some(anchor), None) => Some(anchor.clone()),
+        (None, title) => title.clone(),
+    };
+    Ok(ResolvedStream {
+        is_flv: is_flv_url(&url),
+        url,
+        title,
+        headers,
+    })
+}
+
+// HeaderMap 中的名字是小写的，播放器参数与播放列表里用常见写法
+fn canonical_header_name(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+impl ExternalPlayer {
+    fn default_program(&self) -> String {
+        let candidates: &[&str] = match self {
+            #[cfg(target_os = "windows")]
+            ExternalPlayer::Mpv => &["mpv.exe"],
+            #[cfg(target_os = "macos")]
+            ExternalPlayer::Mpv => &[
+                "/opt/homebrew/bin/mpv",
+                "/usr/local/bin/mpv",
+                "/Applications/mpv.app/Contents/MacOS/mpv",
+            ],
+            #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+            ExternalPlayer::Mpv => &["mpv"],
+            #[cfg(target_os = "windows")]
+            ExternalPlayer::Vlc => &[
+                r"C:\Program Files\VideoLAN\VLC\vlc.exe",
+                r"C:\Program Files (x86)\VideoLAN\VLC\vlc.exe",
+                "vlc.exe",
+            ],
+            #[cfg(target_os = "macos")]
+            ExternalPlayer::Vlc => &["/Applications/VLC.app/Contents/MacOS/VLC"],
+            #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+            ExternalPlayer::Vlc => &["vlc"],
+            ExternalPlayer::Potplayer => &[
+                r"C:\Program Files\DAUM\PotPlayer\PotPlayerMini64.exe",
+                r"C:\Program Files (x86)\DAUM\PotPlayer\PotPlayerMini.exe",
+                "PotPlayerMini64.exe",
+            ],
+        };
+        // 绝对路径按存在与否挑选，最后一个候选交给 PATH 查找
+        candidates
+            .iter()
+            .find(|c| Path::new(c).is_absolute() && Path::new(c).exists())
+            .or_else(|| candidates.last())
+            .map(|c| c.to_string())
+            .unwrap_or_default()
+    }
+
+    /// 把请求头转换为播放器参数；播放器无法携带全部请求头时返回 None
+    fn header_args(&self, headers: &[(String, String)]) -> Option<Vec<String>> {
+        match self {
+            ExternalPlayer::Mpv => Some(
+                headers
+                    .iter()
+                    .map(|(name, value)| match name.to_ascii_lowercase().as_str() {
+                        "referer" => format!("--referrer={}", value),
+                        "user-agent" => format!("--user-agent={}", value),
+                        _ => format!("--http-header-fields-append={}: {}", name, value),
+                    })
+                    .collect(),
+            ),
+            // VLC 只能设置 Referer 与 User-Agent
+            ExternalPlayer::Vlc => headers
+                .iter()
+                .map(|(name, value)| match name.to_ascii_lowercase().as_str() {
+                    "referer" => Some(format!("--http-referrer={}", value)),
+                    "user-agent" => Some(format!("--http-user-agent={}", value)),
+                    _ => None,
+                })
+                .collect(),
+            // PotPlayer 的命令行无法设置请求头
+            ExternalPlayer::Potplayer => {
+                if headers.is_empty() {
+                    Some(Vec::new())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn title_args(&self, title: &str) -> Vec<String> {
+        match self {
+            ExternalPlayer::Mpv => vec![format!("--force-media-title={}", title)],
+            ExternalPlayer::Vlc => vec![format!("--meta-title={}", title)],
+            ExternalPlayer::Potplayer => Vec::new(),
+        }
+    }
+}
+
+/// 解析直播间地址并用外部播放器打开；播放器无法携带所需请求头时经本地代理转发（仅 FLV）
+#[tauri::command]
+pub async fn launch_external_player(
+    app_handle: AppHandle,
+    follow_http: State<'_, FollowHttpClient>,
+    payload: ExternalPlayerPayload,
+) -> Result<LaunchExternalPlayerResponse, String> {
+    let stream = resolve_stream(
+        &payload.platform,
+        &payload.room_id,
+        payload.quality.as_deref(),
+        payload.cookie.as_deref(),
+        &follow_http.client(Some(&payload.platform))?.inner,
+    )
+    .await?;
+
+    let direct_args = if payload.force_proxy {
+        None
+    } else {
+        payload.player.header_args(&stream.headers)
+    };
+    let (mut args, stream_url, via_proxy) = match direct_args {
+        Some(args) => (args, stream.url.clone(), false),
+        None => {
+            if !stream.is_flv {
+                return Err(format!(
+                    "{:?} cannot send the headers this HLS stream requires; use mpv instead",
+                    payload.player
+                ));
+            }
+            // 本地代理按规则表补齐请求头，播放器只需访问 127.0.0.1
+            let base_url = crate::proxy::ensure_static_proxy_server(&app_handle)?;
+            let proxied = format!(
+                "{}/live.flv?url={}",
+                base_url,
+                urlencoding::encode(&stream.url)
+            );
+            (Vec::new(), proxied, true)
+        }
+    };
+    if let Some(title) = stream.title.as_deref() {
+        args.extend(payload.player.title_args(title));
+    }
+    args.push(stream_url.clone());
+
+    let program = payload
+        .player_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| payload.player.default_program());
+
+    println!(
+        "[Rust/player.rs] Launching {} for {} room {} (via_proxy={})",
+        program, payload.platform, payload.room_id, via_proxy
+    );
+    Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to launch {}: {}", program, e))?;
+
+    Ok(LaunchExternalPlayerResponse {
+        program,
+        stream_url,
+        via_proxy,
+        title: stream.title,
+    })
+}
+
+// M3U 按行解析，写入的内容不能带换行，否则会被当成新的指令或条目
+fn m3u_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn render_m3u(stream: &ResolvedStream) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    out.push_str(&format!(
+        "#EXTINF:-1,{}\n",
+        m3u_line(stream.title.as_deref().unwrap_or("Live"))
+    ));
+    // VLC 识别 EXTVLCOPT，其余 IPTV 播放器多识别 EXTHTTP
+    if let Some(referer) = header_value(&stream.headers, "Referer") {
+        out.push_str(&format!("#EXTVLCOPT:http-referrer={}\n", m3u_line(referer)));
+    }
+    if let Some(ua) = header_value(&stream.headers, "User-Agent") {
+        out.push_str(&format!("#EXTVLCOPT:http-user-agent={}\n", m3u_line(ua)));
+    }
+    if !stream.headers.is_empty() {
+        let map: serde_json::Map<String, serde_json::Value> = stream
+            .headers
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect();
+        out.push_str(&format!("#EXTHTTP:{}\n", serde_json::Value::Object(map)));
+    }
+    out.push_str(&m3u_line(&stream.url));
+    out.push('\n');
+    out
+}
+
+// Kodi 的 .strm：`url|Header=value&Header=value`
+fn render_strm(stream: &ResolvedStream) -> String {
+    if stream.headers.is_empty() {
+        return format!("{}\n", stream.url);
+    }
+    let headers = stream
+        .headers
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}|{}\n", stream.url, headers)
+}
+
+/// 导出带请求头的 .m3u/.strm；CDN 地址通常带时效签名，过期后需重新导出
+#[tauri::command]
+pub async fn export_stream_playlist(
+    follow_http: State<'_, FollowHttpClient>,
+    payload: ExportStreamPlaylistPayload,
+) -> Result<String, String> {
+    let path = PathBuf::from(payload.output_path.trim());
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !matches!(extension.as_str(), "m3u" | "m3u8" | "strm") {
+        return Err(format!(
+            "Unsupported playlist type for {}; use .m3u or .strm",
+            path.display()
+        ));
+    }
+
+    let stream = resolve_stream(
+        &payload.platform,
+        &payload.room_id,
+        payload.quality.as_deref(),
+        payload.cookie.as_deref(),
+        &follow_http.client(Some(&payload.platform))?.inner,
+    )
+    .await?;
+    let content = if extension == "strm" {
+        render_strm(&stream)
+    } else {
+        render_m3u(&stream)
+    };
+
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_title_cannot_inject_lines() {
+        let stream = ResolvedStream {
+            url: "https://example.com/live.flv".to_string(),
+            is_flv: true,
+            title: Some("标题\r\n#EXTVLCOPT:http-referrer=evil\nhttps://evil/".to_string()),
+            headers: vec![(
+                "Referer".to_string(),
+                "https://live.example.com/".to_string(),
+            )],
+        };
+        let m3u = render_m3u(&stream);
+        let lines: Vec<_> = m3u.lines().collect();
+        assert_eq!(lines.len(), 5, "{}", m3u);
+        assert!(lines[1].starts_with("#EXTINF:-1,标题"));
+        assert_eq!(
+            lines[2],
+            "#EXTVLCOPT:http-referrer=https://live.example.com/"
+        );
+        assert_eq!(lines[4], "https://example.com/live.flv");
+    }
+}
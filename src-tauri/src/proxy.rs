@@ -1,25 +1,119 @@
-use actix_web::{dev::ServerHandle, web, App, HttpResponse, HttpServer, Responder};
-use futures_util::TryStreamExt;
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use bytes::Bytes;
+use futures_util::{StreamExt, TryStreamExt};
 use reqwest::Client;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::io::ErrorKind;
-use std::net::TcpStream;
-use std::sync::Mutex as StdMutex;
-use std::time::Duration;
-use tauri::{AppHandle, State};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
+use crate::platforms::common::network_proxy::{self, OutboundProxyExt};
+use crate::platforms::common::FollowHttpClient;
+use crate::recording::{known_output_dirs, normalize_quality, RecordingManager};
+use crate::shutdown::RunningRoom;
+use crate::streams::{StreamBudget, StreamKind, StreamLease, StreamManager, StreamRequest};
+use tokio::sync::watch;
+
+mod adaptive;
+mod audio;
+pub(crate) mod flv;
+mod image_cache;
+mod image_transform;
+mod metrics;
+mod recordings;
+mod security;
+mod timeshift;
+
+use adaptive::{AdaptivePump, AdaptiveSource};
+use audio::AudioOutput;
+use image_cache::{CacheLookup, CachedImage, ImageCache, ImageCacheStats};
+use image_transform::{is_avif, ImageTransform};
+use metrics::{
+    ClientMeter, MetricsRegistry, SessionMetrics, SessionMetricsSnapshot, UpstreamProbe,
+};
+use recordings::RecordingLibrary;
+use security::{
+    LiveFlvToken, ProxyAccess, ProxyAccessInfo, ProxyToken, PublicOnlyResolver, SessionCastToken,
+};
+use timeshift::{TimeShiftBuffer, TimeShiftConfig, TimeShiftStatus, TimeShiftStorage};
 
 // Define a struct to hold the server handle in a Tauri managed state
 #[derive(Default)]
 pub struct ProxyServerHandle(pub StdMutex<Option<ServerHandle>>);
 
+// 图片代理的磁盘缓存，由 Tauri 管理并共享给各个代理 server 的 worker
+pub struct ImageProxyCache(pub Arc<ImageCache>);
+
+impl Default for ImageProxyCache {
+    fn default() -> Self {
+        Self(Arc::new(ImageCache::new(
+            ImageCache::default_dir(),
+            image_cache::DEFAULT_MAX_BYTES,
+        )))
+    }
+}
+
+// 代理访问控制（访问令牌、上游白名单、局域网共享），每次启动重新生成令牌
+pub struct ProxyAccessPolicy(pub Arc<ProxyAccess>);
+
+impl Default for ProxyAccessPolicy {
+    fn default() -> Self {
+        Self(Arc::new(ProxyAccess::default()))
+    }
+}
+
+// 各 FLV 会话的运行指标登记表，供 `/metrics` 路由与会话列表命令读取
+pub struct ProxyMetricsRegistry(pub Arc<MetricsRegistry>);
+
+impl Default for ProxyMetricsRegistry {
+    fn default() -> Self {
+        Self(Arc::new(MetricsRegistry::default()))
+    }
+}
+
+// 可在应用内回放的录制目录
+pub struct RecordingFileLibrary(pub Arc<RecordingLibrary>);
+
+impl Default for RecordingFileLibrary {
+    fn default() -> Self {
+        Self(Arc::new(RecordingLibrary::default()))
+    }
+}
+
 struct FlvProxySession {
     handle: ServerHandle,
     port: u16,
+    // 会话启动时已开启局域网共享才会监听网卡地址
+    lan_ip: Option<IpAddr>,
+    // 局域网地址使用的会话级令牌，停止会话时吊销
+    cast_token: Arc<SessionCastToken>,
     upstream_url: String,
     platform: String,
     room_id: Option<String>,
+    // 会话缓冲区：回看或自适应清晰度任一开启时存在
+    time_shift: Option<Arc<TimeShiftBuffer>>,
+    time_shift_enabled: bool,
+    time_shift_stop_tx: Option<watch::Sender<bool>>,
+    metrics: Arc<SessionMetrics>,
+    active_quality: Option<Arc<StdMutex<String>>>,
+}
+
+impl FlvProxySession {
+    async fn shutdown(self) {
+        self.cast_token.revoke();
+        if let Some(stop_tx) = &self.time_shift_stop_tx {
+            let _ = stop_tx.send(true);
+        }
+        if let Some(buffer) = &self.time_shift {
+            buffer.close();
+        }
+        self.handle.stop(false).await;
+    }
 }
 
 #[derive(Default)]
@@ -31,6 +125,18 @@ pub struct StartFlvProxySessionPayload {
     upstream_url: String,
     platform: String,
     room_id: Option<String>,
+    // 回看窗口（秒）；缺省或为 0 时不开启回看
+    time_shift_seconds: Option<u32>,
+    // "memory" 或 "disk"
+    time_shift_storage: Option<String>,
+    time_shift_max_mb: Option<u64>,
+    // 自适应清晰度：带宽不足时自动降档，恢复后回升，需要 room_id
+    adaptive_quality: Option<bool>,
+    // 上游地址对应的清晰度（原画/高清/标清）
+    quality: Option<String>,
+    cookie: Option<String>,
+    // 后台收听：优先使用平台的纯音频流，`/live.flv` 只输出音频
+    audio_only: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -38,6 +144,24 @@ pub struct StartFlvProxySessionPayload {
 pub struct StartFlvProxySessionResponse {
     session_id: String,
     proxy_url: String,
+    ws_proxy_url: String,
+    // 开启局域网共享时可供其他设备访问的地址
+    lan_proxy_url: Option<String>,
+    audio_proxy_url: String,
+    aac_proxy_url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlvProxySessionInfo {
+    session_id: String,
+    platform: String,
+    room_id: Option<String>,
+    port: u16,
+    upstream_url: String,
+    time_shift: bool,
+    quality: Option<String>,
+    metrics: SessionMetricsSnapshot,
 }
 
 async fn find_free_port() -> u16 {
@@ -45,9 +169,17 @@ async fn find_free_port() -> u16 {
     34719
 }
 
-fn build_proxy_http_client() -> Client {
-    Client::builder()
-        .no_proxy()
+// 拉流客户端同样遵循出站代理设置；`platform` 为空时使用全局设置
+fn build_proxy_http_client(access: &Arc<ProxyAccess>, platform: Option<&str>) -> Client {
+    let mut builder = Client::builder();
+    // 经 HTTP CONNECT / socks5h 代理时目标域名由代理解析，本地解析只会用于代理自身的地址；
+    // 此时请求地址和每一跳重定向仍经 `check_url_static` 拒绝内网、回环等 IP 字面量
+    if network_proxy::proxy_for(platform).is_none() {
+        builder = builder.dns_resolver(Arc::new(PublicOnlyResolver::new(access.clone())));
+    }
+    builder
+        .outbound_proxy(platform)
+        .redirect(security::redirect_policy(access.clone()))
         .http1_only()
         .gzip(false)
         .brotli(false)
@@ -71,91 +203,248 @@ fn generate_session_id() -> String {
 #[derive(Deserialize)]
 struct ImageQuery {
     url: String,
+    // 可选的缩放/转码参数，见 image_transform
+    w: Option<u32>,
+    h: Option<u32>,
+    format: Option<String>,
+    q: Option<u8>,
+}
+
+const IMAGE_ACCEPT: &str = "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5";
+const IMAGE_ACCEPT_NO_AVIF: &str = "image/webp,image/png,image/jpeg,image/*;q=0.8,*/*;q=0.5";
+
+fn cached_image_response(image: CachedImage, cache_status: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(image.content_type)
+        .insert_header(("Content-Length", image.bytes.len().to_string()))
+        .insert_header((
+            "Cache-Control",
+            format!("private, max-age={}", image.fresh_for.max(0)),
+        ))
+        .insert_header(("X-DTV-Cache", cache_status.to_string()))
+        .body(image.bytes)
+}
+
+// 原图缓存按 Accept 区分：同一 URL 在不同 Accept 下上游可能返回 AVIF 或其他格式
+fn original_cache_key(url: &str, accept: &str) -> String {
+    if accept == IMAGE_ACCEPT {
+        url.to_string()
+    } else {
+        format!("{}#accept=no-avif", url)
+    }
+}
+
+// 取得原图：优先使用缓存，过期时做条件请求，上游失败时回退到过期副本
+async fn fetch_image(
+    client: &Client,
+    cache: &ImageCache,
+    url: &str,
+    accept: &str,
+) -> Result<(CachedImage, &'static str), HttpResponse> {
+    let cache_key = original_cache_key(url, accept);
+    let mut validators = match cache.lookup(&cache_key).await {
+        CacheLookup::Fresh(image) => return Ok((image, "HIT")),
+        CacheLookup::Stale(validators) => Some(validators),
+        CacheLookup::Miss => None,
+    };
+
+    let upstream_response = loop {
+        // 防盗链所需的 Referer/Origin 由规则表按主机名决定
+        let mut req = client
+            .get(url)
+            .upstream_headers(None, url)
+            .header("Accept", accept);
+
+        // 已有过期副本时带上校验信息做条件请求
+        if let Some(validators) = &validators {
+            if let Some(etag) = &validators.etag {
+                req = req.header("If-None-Match", etag.as_str());
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                req = req.header("If-Modified-Since", last_modified.as_str());
+            }
+        }
+
+        match req.send().await {
+            Ok(upstream_response) => {
+                if upstream_response.status() == reqwest::StatusCode::NOT_MODIFIED
+                    && validators.is_some()
+                {
+                    if let Some(image) = cache
+                        .revalidated(&cache_key, upstream_response.headers())
+                        .await
+                    {
+                        return Ok((image, "REVALIDATED"));
+                    }
+                    // 本地副本在请求期间被淘汰或已损坏，去掉校验信息重新拉取完整内容
+                    validators = None;
+                    continue;
+                }
+                break upstream_response;
+            }
+            Err(e) => {
+                eprintln!(
+                    "[Rust/proxy.rs image] Failed to send request to upstream {}: {}",
+                    url, e
+                );
+                if let Some(image) = cache.stale_fallback(&cache_key).await {
+                    return Ok((image, "STALE"));
+                }
+                return Err(HttpResponse::InternalServerError()
+                    .body(format!("Error connecting to upstream IMAGE {}: {}", url, e)));
+            }
+        }
+    };
+
+    let content_type = upstream_response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    // 为避免 Windows 下 chunked 传输的 Early-EOF，改为一次性读取 bytes 并返回
+    if upstream_response.status().is_success() {
+        let upstream_headers = upstream_response.headers().clone();
+        match upstream_response.bytes().await {
+            Ok(bytes) => {
+                let fresh_for = cache
+                    .store(&cache_key, &content_type, &upstream_headers, &bytes)
+                    .await;
+                Ok((
+                    CachedImage {
+                        bytes,
+                        content_type,
+                        fresh_for,
+                    },
+                    "MISS",
+                ))
+            }
+            Err(e) => {
+                eprintln!("[Rust/proxy.rs image] Failed to read bytes: {}", e);
+                if let Some(image) = cache.stale_fallback(&cache_key).await {
+                    return Ok((image, "STALE"));
+                }
+                Err(HttpResponse::InternalServerError()
+                    .body(format!("Failed to read image bytes: {}", e)))
+            }
+        }
+    } else {
+        let status_from_reqwest = upstream_response.status();
+        // 上游 5xx 时优先返回过期副本
+        if status_from_reqwest.is_server_error() {
+            if let Some(image) = cache.stale_fallback(&cache_key).await {
+                return Ok((image, "STALE"));
+            }
+        }
+        let error_text = upstream_response
+            .text()
+            .await
+            .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
+        eprintln!(
+            "[Rust/proxy.rs image] Upstream request to {} failed with status: {}. Body: {}",
+            url, status_from_reqwest, error_text
+        );
+        let actix_status_code = actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        Err(HttpResponse::build(actix_status_code).body(format!(
+            "Error fetching IMAGE from upstream (reqwest): {}. Status: {}. Details: {}",
+            url, status_from_reqwest, error_text
+        )))
+    }
 }
 
 async fn image_proxy_handler(
+    _token: ProxyToken,
     query: web::Query<ImageQuery>,
     client: web::Data<Client>,
+    cache: web::Data<ImageCache>,
+    access: web::Data<ProxyAccess>,
 ) -> impl Responder {
     let url = query.url.clone();
     if url.is_empty() {
         return HttpResponse::BadRequest().body("Missing url query parameter");
     }
+    let parsed = match reqwest::Url::parse(&url) {
+        Ok(parsed) => parsed,
+        Err(e) => return HttpResponse::BadRequest().body(format!("Invalid url: {}", e)),
+    };
+    if let Err(e) = access.check_url_static(&parsed) {
+        return HttpResponse::Forbidden().body(e);
+    }
 
-    let mut req = client
-        .get(&url)
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-        )
-        .header(
-            "Accept",
-            "image/avif,image/webp,image/apng,image/*;q=0.8,*/*;q=0.5",
-        );
+    let transform =
+        match ImageTransform::from_query(query.w, query.h, query.format.as_deref(), query.q) {
+            Ok(transform) => transform,
+            Err(e) => return HttpResponse::BadRequest().body(e),
+        };
+
+    let Some(transform) = transform else {
+        return match fetch_image(client.get_ref(), cache.get_ref(), &url, IMAGE_ACCEPT).await {
+            Ok((image, cache_status)) => cached_image_response(image, cache_status),
+            Err(response) => response,
+        };
+    };
 
-    // Set a Referer to bypass hotlink protections
-    if url.contains("hdslb.com") || url.contains("bilibili.com") {
-        req = req
-            .header("Referer", "https://live.bilibili.com/")
-            .header("Origin", "https://live.bilibili.com");
-    } else if url.contains("huya.com") {
-        req = req
-            .header("Referer", "https://www.huya.com/")
-            .header("Origin", "https://www.huya.com");
-    } else if url.contains("douyin") || url.contains("douyinpic.com") {
-        req = req.header("Referer", "https://www.douyin.com/");
+    let variant_key = transform.cache_key(&url);
+    if let CacheLookup::Fresh(image) = cache.lookup(&variant_key).await {
+        return cached_image_response(image, "HIT");
     }
 
-    match req.send().await {
-        Ok(upstream_response) => {
-            let content_type = upstream_response
-                .headers()
-                .get(reqwest::header::CONTENT_TYPE)
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("application/octet-stream")
-                .to_string();
-
-            // 为避免 Windows 下 chunked 传输的 Early-EOF，改为一次性读取 bytes 并返回
-            if upstream_response.status().is_success() {
-                match upstream_response.bytes().await {
-                    Ok(bytes) => HttpResponse::Ok()
-                        .content_type(content_type)
-                        .insert_header(("Content-Length", bytes.len().to_string()))
-                        .insert_header(("Cache-Control", "no-store"))
-                        .body(bytes),
-                    Err(e) => {
-                        eprintln!("[Rust/proxy.rs image] Failed to read bytes: {}", e);
-                        HttpResponse::InternalServerError()
-                            .body(format!("Failed to read image bytes: {}", e))
-                    }
-                }
-            } else {
-                let status_from_reqwest = upstream_response.status();
-                let error_text = upstream_response
-                    .text()
-                    .await
-                    .unwrap_or_else(|e| format!("Failed to read error body from upstream: {}", e));
-                eprintln!(
-                    "[Rust/proxy.rs image] Upstream request to {} failed with status: {}. Body: {}",
-                    url, status_from_reqwest, error_text
-                );
-                let actix_status_code =
-                    actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
-                        .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
+    // 需要转码时不向上游请求 AVIF，部分旧版 WebView2 以及本地解码器都不支持
+    let (original, cache_status) = match fetch_image(
+        client.get_ref(),
+        cache.get_ref(),
+        &url,
+        IMAGE_ACCEPT_NO_AVIF,
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
 
-                HttpResponse::build(actix_status_code).body(format!(
-                    "Error fetching IMAGE from upstream (reqwest): {}. Status: {}. Details: {}",
-                    url, status_from_reqwest, error_text
-                ))
-            }
+    // 上游忽略了 Accept 仍返回 AVIF：本地无法解码，指定了格式时不能原样返回
+    if transform.has_format() && is_avif(&original.content_type, &original.bytes) {
+        eprintln!(
+            "[Rust/proxy.rs image] Upstream returned AVIF for {} despite Accept, cannot transcode",
+            url
+        );
+        return HttpResponse::BadGateway().body(format!(
+            "Upstream only serves AVIF for {}, which cannot be transcoded",
+            url
+        ));
+    }
+
+    let source = original.bytes.clone();
+    let transformed = web::block(move || transform.apply(&source)).await;
+    match transformed {
+        Ok(Ok((data, content_type))) => {
+            let bytes = Bytes::from(data);
+            let fresh_for = cache
+                .store_derived(&variant_key, content_type, original.fresh_for, &bytes)
+                .await;
+            cached_image_response(
+                CachedImage {
+                    bytes,
+                    content_type: content_type.to_string(),
+                    fresh_for,
+                },
+                cache_status,
+            )
+        }
+        // 仅缩放但无法解码时原样返回
+        Ok(Err(e)) => {
+            eprintln!("[Rust/proxy.rs image] Transform skipped for {}: {}", url, e);
+            cached_image_response(original, cache_status)
         }
         Err(e) => {
             eprintln!(
-                "[Rust/proxy.rs image] Failed to send request to upstream {}: {}",
+                "[Rust/proxy.rs image] Transform task failed for {}: {}",
                 url, e
             );
-            HttpResponse::InternalServerError()
-                .body(format!("Error connecting to upstream IMAGE {}: {}", url, e))
+            cached_image_response(original, cache_status)
         }
     }
 }
@@ -165,60 +454,105 @@ struct FlvQuery {
     url: String,
 }
 
-async fn proxy_flv_stream(client: &Client, url: String) -> HttpResponse {
-    if url.is_empty() {
-        return HttpResponse::BadRequest().body("Missing upstream url");
-    }
-
-    println!(
-        "[Rust/proxy.rs handler] Incoming FLV proxy request -> {}",
-        url
-    );
-
-    let mut req = client
-        .get(&url.clone())
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-        )
+// 平台未知（旧的 `/live.flv?url=` 路由）时仅按主机名匹配请求头规则
+fn build_flv_upstream_request(
+    client: &Client,
+    platform: Option<&str>,
+    url: &str,
+) -> reqwest::RequestBuilder {
+    client
+        .get(url)
+        .upstream_headers(platform, url)
         .header("Accept", "video/x-flv,application/octet-stream,*/*")
         .header("Range", "bytes=0-")
-        .header("Connection", "keep-alive");
+        .header("Connection", "keep-alive")
+}
 
-    // 如果是虎牙域名，添加必要的 Referer/Origin 头
-    if url.contains("huya.com") || url.contains("hy-cdn.com") || url.contains("huyaimg.com") {
-        req = req
-            .header("Referer", "https://www.huya.com/")
-            .header("Origin", "https://www.huya.com");
-    }
-    // 如果是B站域名，添加必要的 Referer 头
-    if url.contains("bilivideo") || url.contains("bilibili.com") || url.contains("hdslb.com") {
-        req = req.header("Referer", "https://live.bilibili.com/");
-    }
+// 为下游字节流挂上客户端计量，流被丢弃时客户端计数随之减少
+fn metered_stream<S, E>(
+    stream: S,
+    mut meter: ClientMeter,
+) -> impl futures_util::Stream<Item = Result<Bytes, E>>
+where
+    S: futures_util::Stream<Item = Result<Bytes, E>>,
+{
+    stream.inspect(move |item| {
+        if let Ok(chunk) = item {
+            meter.on_chunk(chunk.len());
+        }
+    })
+}
+
+// 预览连接在调度器中排队，超过排队上限返回 503
+async fn acquire_preview_lease(
+    budget: &Arc<StreamBudget>,
+    metrics: Option<&Arc<SessionMetrics>>,
+) -> Result<StreamLease, String> {
+    let request = match metrics {
+        Some(m) => StreamRequest::new(StreamKind::Preview, &m.platform, m.room_id.as_deref())
+            .owner(&m.session_id),
+        None => StreamRequest::new(StreamKind::Preview, "UNKNOWN", None),
+    };
+    let wait = budget.preview_wait();
+    budget.acquire_within(request, wait).await.ok_or_else(|| {
+        format!(
+            "Stream limit reached; no upstream slot became free within {}s",
+            wait.as_secs()
+        )
+    })
+}
 
-    match req.send().await {
+// 打开上游 FLV 流；失败时直接返回要回给客户端的错误响应
+async fn open_upstream_flv(
+    client: &Client,
+    budget: &Arc<StreamBudget>,
+    url: &str,
+    metrics: Option<Arc<SessionMetrics>>,
+) -> Result<impl futures_util::Stream<Item = Result<Bytes, std::io::Error>> + 'static, HttpResponse>
+{
+    let lease = match acquire_preview_lease(budget, metrics.as_ref()).await {
+        Ok(lease) => lease,
+        Err(e) => {
+            eprintln!("[Rust/proxy.rs handler] {} ({})", e, url);
+            return Err(HttpResponse::ServiceUnavailable().body(e));
+        }
+    };
+    let started = Instant::now();
+    let platform = metrics.as_ref().map(|m| m.platform.as_str());
+    match build_flv_upstream_request(client, platform, url)
+        .send()
+        .await
+    {
         Ok(upstream_response) => {
             if upstream_response.status().is_success() {
-                let mut response_builder = HttpResponse::Ok();
-                response_builder
-                    .content_type("video/x-flv")
-                    .insert_header(("Connection", "keep-alive"))
-                    .insert_header(("Cache-Control", "no-store"))
-                    .insert_header(("Accept-Ranges", "bytes"));
-
-                let byte_stream = upstream_response.bytes_stream().map_err(|e| {
-                    eprintln!(
-                        "[Rust/proxy.rs handler] Error reading bytes from upstream: {}",
-                        e
-                    );
-                    actix_web::error::ErrorInternalServerError(format!(
-                        "Upstream stream error: {}",
-                        e
-                    ))
+                let mut meter = metrics.map(|m| {
+                    m.record_upstream_connect(false);
+                    m.client_meter(Some(UpstreamProbe::new(started)))
                 });
-
-                response_builder.streaming(byte_stream)
+                // 租约随字节流一起释放；被录制抢占时结束响应，播放器重连后重新排队
+                let preempted = lease.preempted();
+                Ok(upstream_response
+                    .bytes_stream()
+                    .take_until(preempted)
+                    .inspect(move |item| {
+                        if let Ok(chunk) = item {
+                            lease.record_bytes(chunk.len());
+                            if let Some(meter) = meter.as_mut() {
+                                meter.on_chunk(chunk.len());
+                            }
+                        }
+                    })
+                    .map_err(|e| {
+                        eprintln!(
+                            "[Rust/proxy.rs handler] Error reading bytes from upstream: {}",
+                            e
+                        );
+                        std::io::Error::other(format!("Upstream stream error: {}", e))
+                    }))
             } else {
+                if let Some(m) = &metrics {
+                    m.record_upstream_error();
+                }
                 let status_from_reqwest = upstream_response.status(); // Renamed for clarity
                 let error_text = upstream_response
                     .text()
@@ -233,65 +567,590 @@ async fn proxy_flv_stream(client: &Client, url: String) -> HttpResponse {
                     actix_web::http::StatusCode::from_u16(status_from_reqwest.as_u16())
                         .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
 
-                HttpResponse::build(actix_status_code).body(format!(
+                Err(HttpResponse::build(actix_status_code).body(format!(
                     "Error fetching FLV stream from upstream (reqwest): {}. Status: {}. Details: {}",
                     url, status_from_reqwest, error_text
-                ))
+                )))
             }
         }
         Err(e) => {
+            if let Some(m) = &metrics {
+                m.record_upstream_error();
+            }
             eprintln!(
                 "[Rust/proxy.rs handler] Failed to send request to upstream {} with reqwest: {}",
                 url, e
             );
-            return HttpResponse::InternalServerError().body(format!(
+            Err(HttpResponse::InternalServerError().body(format!(
                 "Error connecting to upstream FLV stream {} with reqwest: {}",
                 url, e
+            )))
+        }
+    }
+}
+
+async fn proxy_flv_stream(
+    client: &Client,
+    budget: &Arc<StreamBudget>,
+    url: String,
+    metrics: Option<Arc<SessionMetrics>>,
+) -> HttpResponse {
+    if url.is_empty() {
+        return HttpResponse::BadRequest().body("Missing upstream url");
+    }
+
+    println!(
+        "[Rust/proxy.rs handler] Incoming FLV proxy request -> {}",
+        url
+    );
+
+    match open_upstream_flv(client, budget, &url, metrics).await {
+        Ok(byte_stream) => HttpResponse::Ok()
+            .content_type("video/x-flv")
+            .insert_header(("Connection", "keep-alive"))
+            .insert_header(("Cache-Control", "no-store"))
+            .insert_header(("Accept-Ranges", "bytes"))
+            .streaming(byte_stream),
+        Err(response) => response,
+    }
+}
+
+// Pushes an FLV byte stream to a WebSocket client as binary frames.
+// actix-ws hands outgoing frames to a bounded channel, so awaiting `binary()`
+// throttles the upstream read the same way the HTTP streaming body does.
+async fn pump_flv_to_websocket<S, E>(
+    byte_stream: S,
+    mut session: actix_ws::Session,
+    mut msg_stream: actix_ws::MessageStream,
+) where
+    S: futures_util::Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let mut byte_stream = byte_stream;
+    loop {
+        tokio::select! {
+            chunk = byte_stream.next() => {
+                match chunk {
+                    Some(Ok(bytes)) => {
+                        if session.binary(bytes).await.is_err() {
+                            // Client went away; dropping the upstream stream closes it.
+                            return;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        eprintln!(
+                            "[Rust/proxy.rs ws] Error reading bytes from upstream: {}",
+                            e
+                        );
+                        let _ = session
+                            .close(Some(actix_ws::CloseReason {
+                                code: actix_ws::CloseCode::Error,
+                                description: Some("upstream_stream_error".to_string()),
+                            }))
+                            .await;
+                        return;
+                    }
+                    None => {
+                        let _ = session.close(Some(actix_ws::CloseCode::Normal.into())).await;
+                        return;
+                    }
+                }
+            }
+            msg = msg_stream.next() => {
+                match msg {
+                    Some(Ok(actix_ws::Message::Ping(payload))) => {
+                        if session.pong(&payload).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => return,
+                }
+            }
+        }
+    }
+}
+
+async fn pump_upstream_flv_to_websocket(
+    client: Client,
+    budget: Arc<StreamBudget>,
+    url: String,
+    session: actix_ws::Session,
+    msg_stream: actix_ws::MessageStream,
+    metrics: Arc<SessionMetrics>,
+) {
+    let lease = match acquire_preview_lease(&budget, Some(&metrics)).await {
+        Ok(lease) => lease,
+        Err(e) => {
+            eprintln!("[Rust/proxy.rs ws] {} ({})", e, url);
+            let _ = session
+                .close(Some(actix_ws::CloseReason {
+                    code: actix_ws::CloseCode::Again,
+                    description: Some("stream_limit_reached".to_string()),
+                }))
+                .await;
+            return;
+        }
+    };
+    let started = Instant::now();
+    let upstream_response = match build_flv_upstream_request(&client, Some(&metrics.platform), &url)
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => resp,
+        Ok(resp) => {
+            metrics.record_upstream_error();
+            eprintln!(
+                "[Rust/proxy.rs ws] Upstream request to {} failed with status: {}",
+                url,
+                resp.status()
+            );
+            let _ = session
+                .close(Some(actix_ws::CloseReason {
+                    code: actix_ws::CloseCode::Error,
+                    description: Some(format!("upstream_status={}", resp.status())),
+                }))
+                .await;
+            return;
+        }
+        Err(e) => {
+            metrics.record_upstream_error();
+            eprintln!(
+                "[Rust/proxy.rs ws] Failed to send request to upstream {}: {}",
+                url, e
+            );
+            let _ = session
+                .close(Some(actix_ws::CloseReason {
+                    code: actix_ws::CloseCode::Error,
+                    description: Some("upstream_connect_failed".to_string()),
+                }))
+                .await;
+            return;
+        }
+    };
+
+    metrics.record_upstream_connect(false);
+    let meter = metrics.client_meter(Some(UpstreamProbe::new(started)));
+    let preempted = lease.preempted();
+    let upstream = upstream_response
+        .bytes_stream()
+        .take_until(preempted)
+        .inspect(move |item| {
+            if let Ok(chunk) = item {
+                lease.record_bytes(chunk.len());
+            }
+        });
+    let byte_stream = Box::pin(metered_stream(upstream, meter));
+    pump_flv_to_websocket(byte_stream, session, msg_stream).await;
+}
+
+#[derive(Deserialize)]
+struct FlvSessionQuery {
+    // 回看偏移（秒），负数表示落后直播的秒数，例如 offset=-120
+    offset: Option<i64>,
+}
+
+impl FlvSessionQuery {
+    // 只接受 0 或负数，正数（超前直播）直接拒绝
+    fn time_shift_seconds(&self) -> Result<u64, String> {
+        match self.offset {
+            Some(v) if v > 0 => Err(format!(
+                "Invalid offset {}: use a negative number of seconds behind live",
+                v
+            )),
+            Some(v) => Ok(v.unsigned_abs()),
+            None => Ok(0),
+        }
+    }
+}
+
+// 每个 FLV 会话 server 的共享上下文
+struct FlvSessionContext {
+    upstream_url: String,
+    // 仅自适应清晰度时也有会话缓冲区（0 秒窗口），是否允许回看以这个开关为准
+    time_shift: Option<Arc<TimeShiftBuffer>>,
+    time_shift_enabled: bool,
+    metrics: Arc<SessionMetrics>,
+    // `/live.flv` 也只输出音频
+    audio_only: bool,
+    budget: Arc<StreamBudget>,
+}
+
+async fn flv_ws_session_handler(
+    _token: ProxyToken,
+    req: HttpRequest,
+    body: web::Payload,
+    query: web::Query<FlvSessionQuery>,
+    context: web::Data<FlvSessionContext>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let url = context.upstream_url.clone();
+    if url.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Missing upstream url"));
+    }
+    let offset_seconds = match query.time_shift_seconds() {
+        Ok(v) => v,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+    if offset_seconds > 0 && !context.time_shift_enabled {
+        return Ok(HttpResponse::BadRequest().body("Time-shift is not enabled for this session"));
+    }
+
+    println!(
+        "[Rust/proxy.rs ws] Incoming WebSocket-FLV proxy request -> {} (offset={}s)",
+        url, offset_seconds
+    );
+
+    let (response, session, msg_stream) = actix_ws::handle(&req, body)?;
+    match &context.time_shift {
+        Some(buffer) => {
+            let meter = context.metrics.client_meter(None);
+            let byte_stream = Box::pin(metered_stream(buffer.subscribe(offset_seconds), meter));
+            actix_web::rt::spawn(pump_flv_to_websocket(byte_stream, session, msg_stream));
+        }
+        None => {
+            actix_web::rt::spawn(pump_upstream_flv_to_websocket(
+                client.get_ref().clone(),
+                context.budget.clone(),
+                url,
+                session,
+                msg_stream,
+                context.metrics.clone(),
             ));
         }
     }
+    Ok(response)
 }
 
 // Legacy query-based FLV proxy
 async fn flv_proxy_handler(
+    _token: ProxyToken,
     query: web::Query<FlvQuery>,
     client: web::Data<Client>,
+    access: web::Data<ProxyAccess>,
+    budget: web::Data<StreamBudget>,
 ) -> impl Responder {
-    proxy_flv_stream(client.get_ref(), query.url.clone()).await
+    if let Err(e) = access.check_upstream_url(&query.url) {
+        eprintln!(
+            "[Rust/proxy.rs handler] Rejected upstream {}: {}",
+            query.url, e
+        );
+        return HttpResponse::Forbidden().body(e);
+    }
+    proxy_flv_stream(
+        client.get_ref(),
+        &budget.into_inner(),
+        query.url.clone(),
+        None,
+    )
+    .await
 }
 
 // Session-based FLV proxy with fixed upstream URL
 async fn flv_proxy_session_handler(
-    upstream_url: web::Data<String>,
+    _token: LiveFlvToken,
+    query: web::Query<FlvSessionQuery>,
+    context: web::Data<FlvSessionContext>,
     client: web::Data<Client>,
 ) -> impl Responder {
-    proxy_flv_stream(client.get_ref(), upstream_url.get_ref().clone()).await
+    let offset_seconds = match query.time_shift_seconds() {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let audio = context.audio_only.then_some(AudioOutput::Flv);
+    serve_session_stream(offset_seconds, &context, client.get_ref(), audio).await
+}
+
+// 纯音频输出：`/audio.flv` 为只含音频 tag 的 FLV，`/audio.aac` 为 ADTS AAC
+async fn flv_audio_session_handler(
+    _token: ProxyToken,
+    req: HttpRequest,
+    query: web::Query<FlvSessionQuery>,
+    context: web::Data<FlvSessionContext>,
+    client: web::Data<Client>,
+) -> impl Responder {
+    let offset_seconds = match query.time_shift_seconds() {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let output = if req.path().ends_with(".aac") {
+        AudioOutput::Adts
+    } else {
+        AudioOutput::Flv
+    };
+    serve_session_stream(offset_seconds, &context, client.get_ref(), Some(output)).await
+}
+
+async fn serve_session_stream(
+    offset_seconds: u64,
+    context: &FlvSessionContext,
+    client: &Client,
+    audio: Option<AudioOutput>,
+) -> HttpResponse {
+    if offset_seconds > 0 && !context.time_shift_enabled {
+        return HttpResponse::BadRequest().body("Time-shift is not enabled for this session");
+    }
+    let byte_stream = match &context.time_shift {
+        // 开启回看后所有客户端都从会话缓冲区读取，offset=0 即从最新关键帧开始
+        Some(buffer) => metered_stream(
+            buffer.subscribe(offset_seconds),
+            context.metrics.client_meter(None),
+        )
+        .boxed_local(),
+        None => {
+            println!(
+                "[Rust/proxy.rs handler] Incoming FLV proxy request -> {}",
+                context.upstream_url
+            );
+            match open_upstream_flv(
+                client,
+                &context.budget,
+                &context.upstream_url,
+                Some(context.metrics.clone()),
+            )
+            .await
+            {
+                Ok(stream) => stream.boxed_local(),
+                Err(response) => return response,
+            }
+        }
+    };
+
+    let (content_type, body) = match audio {
+        Some(output) => (
+            output.content_type(),
+            audio::audio_only_stream(byte_stream, output).boxed_local(),
+        ),
+        None => ("video/x-flv", byte_stream),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(("Connection", "keep-alive"))
+        .insert_header(("Cache-Control", "no-store"))
+        .streaming(body)
+}
+
+// 固定端口代理的健康检查：用令牌对随机数作答，证明端口上是本进程启动的 server，令牌本身不出现在请求里
+async fn health_handler(
+    nonce: web::Path<String>,
+    access: web::Data<ProxyAccess>,
+) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .body(access.health_proof(&nonce))
+}
+
+// 向固定端口发起健康检查；端口被其他程序占用或作答不匹配时返回 false
+fn static_proxy_alive(port: u16, access: &ProxyAccess) -> bool {
+    use std::io::{Read, Write};
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, Duration::from_millis(500)) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(2)));
+    let nonce = generate_session_id();
+    let request = format!(
+        "GET /health/{} HTTP/1.0\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\n\r\n",
+        nonce, port
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut response = Vec::new();
+    let _ = stream.take(4096).read_to_end(&mut response);
+    let response = String::from_utf8_lossy(&response);
+    response.starts_with("HTTP/1.1 200") && response.ends_with(&access.health_proof(&nonce))
+}
+
+// Prometheus 抓取入口：`/{token}/metrics`
+async fn metrics_handler(
+    _token: ProxyToken,
+    registry: web::Data<MetricsRegistry>,
+) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .insert_header(("Cache-Control", "no-store"))
+        .body(registry.render_prometheus())
+}
+
+// 读到 FLV 文件头就返回其中的视频标志，不继续消费直播数据
+async fn probe_flv_has_video(response: reqwest::Response) -> Option<bool> {
+    let mut demuxer = flv::FlvDemuxer::new();
+    let mut stream = response.bytes_stream();
+    while let Some(Ok(chunk)) = stream.next().await {
+        for event in demuxer.push(&chunk).ok()? {
+            if let flv::FlvEvent::Header { has_video, .. } = event {
+                return Some(has_video);
+            }
+        }
+    }
+    None
+}
+
+// 平台提供纯音频变体时先试探一次：CDN 会忽略不认识的参数，
+// 因此只有文件头里的视频标志为 0 才改为拉取该地址，否则沿用原地址。
+// 两种情况下客户端输出都仍经过 AudioFilter；试探读完文件头即断开，尽量少占用上游
+async fn select_audio_only_upstream(
+    access: &Arc<ProxyAccess>,
+    platform: &str,
+    upstream_url: String,
+) -> String {
+    let Some(variant) = audio::audio_only_variant(platform, &upstream_url) else {
+        return upstream_url;
+    };
+    if access.check_upstream_url(&variant).is_err() {
+        return upstream_url;
+    }
+    let client = build_proxy_http_client(access, Some(platform));
+    let probe = tokio::time::timeout(Duration::from_secs(5), async {
+        let resp = build_flv_upstream_request(&client, Some(platform), &variant)
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        probe_flv_has_video(resp).await
+    })
+    .await;
+    match probe {
+        Ok(Some(false)) => {
+            println!(
+                "[Rust/proxy.rs] Using audio-only upstream variant for {}",
+                platform
+            );
+            variant
+        }
+        Ok(Some(true)) => {
+            println!(
+                "[Rust/proxy.rs] Audio-only variant for {} still carries video, keeping original upstream",
+                platform
+            );
+            upstream_url
+        }
+        _ => upstream_url,
+    }
+}
+
+// 会话缓冲区的常驻拉流同样受并发/带宽调度；排队期间会话被停止时直接关闭缓冲区
+async fn run_with_buffer_lease<F, Fut>(
+    budget: Arc<StreamBudget>,
+    request: StreamRequest,
+    buffer: Arc<TimeShiftBuffer>,
+    mut stop_rx: watch::Receiver<bool>,
+    pump: F,
+) where
+    F: FnOnce(watch::Receiver<bool>) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let Some(_lease) = budget.acquire_until_stopped(request, &mut stop_rx).await else {
+        buffer.close();
+        return;
+    };
+    pump(stop_rx).await;
 }
 
 #[tauri::command]
 pub async fn start_flv_proxy_session(
-    _app_handle: AppHandle,
+    app_handle: AppHandle,
     session_manager: State<'_, FlvProxySessionManager>,
+    access_policy: State<'_, ProxyAccessPolicy>,
+    metrics_registry: State<'_, ProxyMetricsRegistry>,
+    follow_http: State<'_, FollowHttpClient>,
+    stream_manager: State<'_, StreamManager>,
     payload: StartFlvProxySessionPayload,
 ) -> Result<StartFlvProxySessionResponse, String> {
     let upstream_url = payload.upstream_url.trim().to_string();
     if upstream_url.is_empty() {
         return Err("upstream_url is required".to_string());
     }
+    let access = access_policy.0.clone();
+    access.check_upstream_url(&upstream_url)?;
+
+    let audio_only = payload.audio_only.unwrap_or(false);
+    let upstream_url = if audio_only {
+        select_audio_only_upstream(&access, &payload.platform, upstream_url).await
+    } else {
+        upstream_url
+    };
 
     let session_id = generate_session_id();
     let runtime_session_id = session_id.clone();
     let runtime_platform = payload.platform.clone();
     let runtime_room_id = payload.room_id.clone();
-    let app_data_upstream_url = web::Data::new(upstream_url.clone());
 
-    let server_builder = HttpServer::new(move || {
-        let app_data_reqwest_client = web::Data::new(build_proxy_http_client());
+    let adaptive_source = if payload.adaptive_quality.unwrap_or(false) {
+        let room_id = payload
+            .room_id
+            .clone()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| "room_id is required for adaptive quality".to_string())?;
+        Some(AdaptiveSource {
+            platform: payload.platform.clone(),
+            room_id,
+            cookie: payload.cookie.clone(),
+            quality: normalize_quality(payload.quality.as_deref()),
+        })
+    } else {
+        None
+    };
+
+    let time_shift_enabled = payload.time_shift_seconds.is_some_and(|v| v > 0);
+    let time_shift = match payload.time_shift_seconds.filter(|v| *v > 0) {
+        Some(window_seconds) => {
+            let storage = TimeShiftStorage::parse(payload.time_shift_storage.as_deref())?;
+            let config = TimeShiftConfig::new(window_seconds, storage, payload.time_shift_max_mb);
+            Some(TimeShiftBuffer::new(&session_id, config)?)
+        }
+        // 自适应清晰度需要会话级拉流来做切换，借用最小窗口的内存缓冲区分发给客户端
+        None if adaptive_source.is_some() => Some(TimeShiftBuffer::new(
+            &session_id,
+            TimeShiftConfig::new(0, TimeShiftStorage::Memory, None),
+        )?),
+        None => None,
+    };
+    let active_quality = adaptive_source
+        .as_ref()
+        .map(|source| Arc::new(StdMutex::new(source.quality.clone())));
+    let metrics = SessionMetrics::new(&session_id, &payload.platform, payload.room_id.as_deref());
+    metrics_registry.0.register(&metrics);
+    let cast_token = Arc::new(SessionCastToken::issue());
+    let app_data_context = web::Data::new(FlvSessionContext {
+        upstream_url: upstream_url.clone(),
+        time_shift: time_shift.clone(),
+        time_shift_enabled,
+        metrics: metrics.clone(),
+        audio_only,
+        budget: stream_manager.0.clone(),
+    });
+
+    let server_access = access.clone();
+    let server_platform = payload.platform.clone();
+    let server_cast_token = cast_token.clone();
+    let mut server_builder = HttpServer::new(move || {
+        let app_data_reqwest_client = web::Data::new(build_proxy_http_client(
+            &server_access,
+            Some(&server_platform),
+        ));
         App::new()
             .app_data(app_data_reqwest_client)
-            .app_data(app_data_upstream_url.clone())
+            .app_data(app_data_context.clone())
+            .app_data(web::Data::from(server_access.clone()))
+            .app_data(web::Data::from(server_cast_token.clone()))
             .wrap(actix_cors::Cors::permissive())
-            .route("/live.flv", web::get().to(flv_proxy_session_handler))
+            .service(
+                web::scope("/{token}")
+                    .route("/live.flv", web::get().to(flv_proxy_session_handler))
+                    .route("/ws/live.flv", web::get().to(flv_ws_session_handler))
+                    .route("/audio.flv", web::get().to(flv_audio_session_handler))
+                    .route("/audio.aac", web::get().to(flv_audio_session_handler)),
+            )
     })
     .keep_alive(Duration::from_secs(120))
     .bind(("127.0.0.1", 0))
@@ -306,9 +1165,78 @@ pub async fn start_flv_proxy_session(
         })?;
     let runtime_port = port;
 
+    // 局域网共享：在同一端口上额外监听网卡地址，访问仍需令牌
+    let lan_bind_ip = access.lan_bind_ip();
+    if let Some(lan_ip) = lan_bind_ip {
+        server_builder = server_builder.bind((lan_ip, port)).map_err(|e| {
+            format!(
+                "[Rust/proxy.rs] Failed to bind FLV session proxy on {}:{}: {}",
+                lan_ip, port, e
+            )
+        })?;
+    }
+
     let server = server_builder.run();
     let handle = server.handle();
 
+    // 回看/自适应模式下由会话常驻拉流，客户端只从缓冲区读取
+    let time_shift_stop_tx = time_shift.as_ref().map(|buffer| {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let budget = stream_manager.0.clone();
+        let lease_request = StreamRequest::new(
+            StreamKind::SessionBuffer,
+            &payload.platform,
+            payload.room_id.as_deref(),
+        )
+        .owner(&session_id)
+        .counter(metrics.clone());
+        match (adaptive_source, active_quality.clone()) {
+            (Some(source), Some(active_quality)) => {
+                let pump = AdaptivePump {
+                    app_handle: app_handle.clone(),
+                    client: build_proxy_http_client(&access, Some(&payload.platform)),
+                    follow_http: follow_http.inner().clone(),
+                    access: access.clone(),
+                    session_id: session_id.clone(),
+                    source,
+                    initial_url: upstream_url.clone(),
+                    buffer: buffer.clone(),
+                    metrics: metrics.clone(),
+                    active_quality,
+                };
+                tauri::async_runtime::spawn(run_with_buffer_lease(
+                    budget,
+                    lease_request,
+                    buffer.clone(),
+                    stop_rx,
+                    move |stop_rx| adaptive::run_adaptive_pump(pump, stop_rx),
+                ));
+            }
+            _ => {
+                let client = build_proxy_http_client(&access, Some(&payload.platform));
+                let pump_url = upstream_url.clone();
+                let pump_buffer = buffer.clone();
+                let pump_metrics = metrics.clone();
+                tauri::async_runtime::spawn(run_with_buffer_lease(
+                    budget,
+                    lease_request,
+                    buffer.clone(),
+                    stop_rx,
+                    move |stop_rx| {
+                        timeshift::run_time_shift_pump(
+                            client,
+                            pump_url,
+                            pump_buffer,
+                            pump_metrics,
+                            stop_rx,
+                        )
+                    },
+                ));
+            }
+        }
+        stop_tx
+    });
+
     {
         let mut guard = session_manager.0.lock().unwrap();
         guard.insert(
@@ -316,9 +1244,16 @@ pub async fn start_flv_proxy_session(
             FlvProxySession {
                 handle,
                 port,
+                lan_ip: lan_bind_ip,
+                cast_token: cast_token.clone(),
                 upstream_url: upstream_url.clone(),
                 platform: payload.platform.clone(),
                 room_id: payload.room_id.clone(),
+                time_shift,
+                time_shift_enabled,
+                time_shift_stop_tx,
+                metrics,
+                active_quality,
             },
         );
     }
@@ -337,14 +1272,47 @@ pub async fn start_flv_proxy_session(
         }
     });
 
+    let token = access.token();
     Ok(StartFlvProxySessionResponse {
         session_id,
-        proxy_url: format!("http://127.0.0.1:{}/live.flv", port),
+        proxy_url: format!("http://127.0.0.1:{}/{}/live.flv", port, token),
+        ws_proxy_url: format!("ws://127.0.0.1:{}/{}/ws/live.flv", port, token),
+        audio_proxy_url: format!("http://127.0.0.1:{}/{}/audio.flv", port, token),
+        aac_proxy_url: format!("http://127.0.0.1:{}/{}/audio.aac", port, token),
+        lan_proxy_url: lan_bind_ip
+            .zip(cast_token.token())
+            .map(|(ip, cast)| format!("http://{}/{}/live.flv", SocketAddr::new(ip, port), cast)),
     })
 }
 
+/// 会话在局域网网卡上的 FLV 地址，供投屏等其他设备拉流；
+/// 地址里是会话级投屏令牌，只能访问该会话的 `/live.flv`
+pub(crate) fn session_lan_url(app_handle: &AppHandle, session_id: &str) -> Result<String, String> {
+    let manager = app_handle.state::<FlvProxySessionManager>();
+    let guard = manager.0.lock().unwrap();
+    let session = guard
+        .get(session_id)
+        .ok_or_else(|| format!("FLV proxy session {} not found", session_id))?;
+    let lan_ip = session.lan_ip.ok_or_else(|| {
+        format!(
+            "FLV proxy session {} is not reachable from the LAN; enable LAN sharing and restart the session",
+            session_id
+        )
+    })?;
+    let token = session
+        .cast_token
+        .token()
+        .ok_or_else(|| format!("FLV proxy session {} is stopping", session_id))?;
+    Ok(format!(
+        "http://{}/{}/live.flv",
+        SocketAddr::new(lan_ip, session.port),
+        token
+    ))
+}
+
 #[tauri::command]
 pub async fn stop_flv_proxy_session(
+    app_handle: AppHandle,
     session_manager: State<'_, FlvProxySessionManager>,
     session_id: String,
 ) -> Result<(), String> {
@@ -352,13 +1320,16 @@ pub async fn stop_flv_proxy_session(
         let mut guard = session_manager.0.lock().unwrap();
         guard.remove(&session_id)
     };
+    app_handle
+        .state::<crate::dlna::DlnaManager>()
+        .stop_session_monitors(&session_id);
 
     if let Some(session) = session {
         println!(
             "[Rust/proxy.rs] stop_flv_proxy_session: session={} platform={} room={:?} port={} upstream={}",
             session_id, session.platform, session.room_id, session.port, session.upstream_url
         );
-        session.handle.stop(false).await;
+        session.shutdown().await;
     } else {
         println!(
             "[Rust/proxy.rs] stop_flv_proxy_session: session {} not found (already stopped).",
@@ -370,30 +1341,106 @@ pub async fn stop_flv_proxy_session(
 
 #[tauri::command]
 pub async fn stop_all_flv_proxy_sessions(
+    app_handle: AppHandle,
     session_manager: State<'_, FlvProxySessionManager>,
 ) -> Result<(), String> {
     let sessions = {
         let mut guard = session_manager.0.lock().unwrap();
-        guard
-            .drain()
-            .map(|(_, session)| session)
-            .collect::<Vec<_>>()
+        guard.drain().collect::<Vec<_>>()
     };
 
-    for session in sessions {
+    let dlna = app_handle.state::<crate::dlna::DlnaManager>();
+    for (session_id, session) in sessions {
+        dlna.stop_session_monitors(&session_id);
         println!(
             "[Rust/proxy.rs] stop_all_flv_proxy_sessions: platform={} room={:?} port={} upstream={}",
             session.platform, session.room_id, session.port, session.upstream_url
         );
-        session.handle.stop(false).await;
+        session.shutdown().await;
     }
     Ok(())
 }
 
+/// 退出前关闭所有 FLV 会话（停止常驻拉流、关闭回看缓冲区）与主代理 server，返回关闭前的会话房间
+pub(crate) async fn shutdown_all(app_handle: &AppHandle) -> Vec<RunningRoom> {
+    let sessions = {
+        let manager = app_handle.state::<FlvProxySessionManager>();
+        let mut guard = manager.0.lock().unwrap();
+        guard
+            .drain()
+            .map(|(_, session)| session)
+            .collect::<Vec<_>>()
+    };
+    let rooms = sessions
+        .iter()
+        .map(|session| RunningRoom {
+            platform: session.platform.clone(),
+            room_id: session.room_id.clone(),
+        })
+        .collect();
+    futures_util::future::join_all(sessions.into_iter().map(FlvProxySession::shutdown)).await;
+
+    let server_handle = {
+        let state = app_handle.state::<ProxyServerHandle>();
+        let handle = state.0.lock().unwrap().take();
+        handle
+    };
+    if let Some(handle) = server_handle {
+        handle.stop(true).await;
+    }
+    rooms
+}
+
+#[tauri::command]
+pub async fn get_flv_proxy_time_shift_status(
+    session_manager: State<'_, FlvProxySessionManager>,
+    session_id: String,
+) -> Result<Option<TimeShiftStatus>, String> {
+    let guard = session_manager.0.lock().unwrap();
+    let session = guard
+        .get(&session_id)
+        .ok_or_else(|| format!("FLV proxy session {} not found", session_id))?;
+    Ok(session
+        .time_shift
+        .as_ref()
+        .filter(|_| session.time_shift_enabled)
+        .map(|buffer| buffer.status()))
+}
+
+#[tauri::command]
+pub async fn list_flv_proxy_sessions(
+    session_manager: State<'_, FlvProxySessionManager>,
+) -> Result<Vec<FlvProxySessionInfo>, String> {
+    let guard = session_manager.0.lock().unwrap();
+    let mut sessions: Vec<FlvProxySessionInfo> = guard
+        .iter()
+        .map(|(session_id, session)| FlvProxySessionInfo {
+            session_id: session_id.clone(),
+            platform: session.platform.clone(),
+            room_id: session.room_id.clone(),
+            port: session.port,
+            upstream_url: session.upstream_url.clone(),
+            time_shift: session.time_shift_enabled,
+            quality: session
+                .active_quality
+                .as_ref()
+                .map(|quality| quality.lock().unwrap().clone()),
+            metrics: session.metrics.snapshot(),
+        })
+        .collect();
+    sessions.sort_by_key(|info| info.port);
+    Ok(sessions)
+}
+
 #[tauri::command]
 pub async fn start_proxy(
     _app_handle: AppHandle,
     server_handle_state: State<'_, ProxyServerHandle>,
+    image_cache: State<'_, ImageProxyCache>,
+    access_policy: State<'_, ProxyAccessPolicy>,
+    metrics_registry: State<'_, ProxyMetricsRegistry>,
+    recording_library: State<'_, RecordingFileLibrary>,
+    stream_manager: State<'_, StreamManager>,
 ) -> Result<String, String> {
     let port = find_free_port().await;
 
@@ -403,14 +1450,37 @@ pub async fn start_proxy(
         existing_handle.stop(false).await;
     }
 
+    let app_data_image_cache = web::Data::from(image_cache.0.clone());
+    let app_data_metrics = web::Data::from(metrics_registry.0.clone());
+    let app_data_recordings = web::Data::from(recording_library.0.clone());
+    let app_data_streams = web::Data::from(stream_manager.0.clone());
+    let access = access_policy.0.clone();
+    let server_access = access.clone();
     let server = match HttpServer::new(move || {
         // Create reqwest::Client inside the closure for each worker thread
-        let app_data_reqwest_client = web::Data::new(build_proxy_http_client());
+        let app_data_reqwest_client = web::Data::new(build_proxy_http_client(&server_access, None));
         App::new()
             .app_data(app_data_reqwest_client)
+            .app_data(app_data_image_cache.clone())
+            .app_data(app_data_metrics.clone())
+            .app_data(app_data_recordings.clone())
+            .app_data(app_data_streams.clone())
+            .app_data(web::Data::from(server_access.clone()))
             .wrap(actix_cors::Cors::permissive())
-            .route("/live.flv", web::get().to(flv_proxy_handler))
-            .route("/image", web::get().to(image_proxy_handler))
+            .service(
+                web::scope("/{token}")
+                    .route("/live.flv", web::get().to(flv_proxy_handler))
+                    .route("/image", web::get().to(image_proxy_handler))
+                    .route("/metrics", web::get().to(metrics_handler))
+                    .route(
+                        "/recordings/{root}/{path:.*}",
+                        web::get().to(recordings::recording_file_handler),
+                    )
+                    .route(
+                        "/recordings/{root}/{path:.*}",
+                        web::head().to(recordings::recording_file_handler),
+                    ),
+            )
     })
     .keep_alive(Duration::from_secs(120))
     .bind(("127.0.0.1", port))
@@ -439,40 +1509,73 @@ pub async fn start_proxy(
         }
     });
 
-    let base_url = format!("http://127.0.0.1:{}", port);
+    // 令牌作为路径前缀，调用方仍按 `${base}/live.flv?url=...` 拼接
+    let base_url = format!("http://127.0.0.1:{}/{}", port, access.token());
     Ok(base_url)
 }
 
 #[tauri::command]
-pub async fn start_static_proxy_server(_app_handle: AppHandle) -> Result<String, String> {
+pub async fn start_static_proxy_server(app_handle: AppHandle) -> Result<String, String> {
+    ensure_static_proxy_server(&app_handle)
+}
+
+pub(crate) fn ensure_static_proxy_server(app_handle: &AppHandle) -> Result<String, String> {
+    let image_cache = app_handle.state::<ImageProxyCache>();
+    let access_policy = app_handle.state::<ProxyAccessPolicy>();
+    let metrics_registry = app_handle.state::<ProxyMetricsRegistry>();
+    let recording_library = app_handle.state::<RecordingFileLibrary>();
+    let stream_manager = app_handle.state::<StreamManager>();
+
     // Use a dedicated port for static image proxy to avoid interfering with FLV stream proxy
     let port: u16 = 34721;
 
-    // If the server is already running, just return the base URL (idempotent behavior)
-    if TcpStream::connect(("127.0.0.1", port)).is_ok() {
-        return Ok(format!("http://127.0.0.1:{}", port));
+    let access = access_policy.0.clone();
+    let base_url = format!("http://127.0.0.1:{}/{}", port, access.token());
+
+    // 端口上已经是本进程的 server 时直接复用；被其他程序占用时不能把令牌发给它
+    if static_proxy_alive(port, &access) {
+        return Ok(base_url);
     }
 
+    let app_data_image_cache = web::Data::from(image_cache.0.clone());
+    let app_data_metrics = web::Data::from(metrics_registry.0.clone());
+    let app_data_recordings = web::Data::from(recording_library.0.clone());
+    let app_data_streams = web::Data::from(stream_manager.0.clone());
+    let server_access = access.clone();
     let server = match HttpServer::new(move || {
-        let app_data_reqwest_client = web::Data::new(build_proxy_http_client());
+        let app_data_reqwest_client = web::Data::new(build_proxy_http_client(&server_access, None));
         App::new()
             .app_data(app_data_reqwest_client)
+            .app_data(app_data_image_cache.clone())
+            .app_data(app_data_metrics.clone())
+            .app_data(app_data_recordings.clone())
+            .app_data(app_data_streams.clone())
+            .app_data(web::Data::from(server_access.clone()))
             .wrap(actix_cors::Cors::permissive())
-            .route("/live.flv", web::get().to(flv_proxy_handler))
-            .route("/image", web::get().to(image_proxy_handler))
+            .route("/health/{nonce}", web::get().to(health_handler))
+            .service(
+                web::scope("/{token}")
+                    .route("/live.flv", web::get().to(flv_proxy_handler))
+                    .route("/image", web::get().to(image_proxy_handler))
+                    .route("/metrics", web::get().to(metrics_handler))
+                    .route(
+                        "/recordings/{root}/{path:.*}",
+                        web::get().to(recordings::recording_file_handler),
+                    )
+                    .route(
+                        "/recordings/{root}/{path:.*}",
+                        web::head().to(recordings::recording_file_handler),
+                    ),
+            )
     })
     .keep_alive(Duration::from_secs(120))
     .bind(("127.0.0.1", port))
     {
         Ok(srv) => srv,
         Err(e) => {
-            // If address already in use, assume server is running and return OK base URL
-            if e.kind() == ErrorKind::AddrInUse {
-                eprintln!(
-                    "[Rust/proxy.rs] Port {} already in use; assuming static proxy running.",
-                    port
-                );
-                return Ok(format!("http://127.0.0.1:{}", port));
+            // 并发启动时另一次调用可能刚刚绑定成功，确认是本进程的 server 后复用
+            if e.kind() == ErrorKind::AddrInUse && static_proxy_alive(port, &access) {
+                return Ok(base_url);
             }
             let err_msg = format!(
                 "[Rust/proxy.rs] Failed to bind server to port {}: {}",
@@ -494,7 +1597,7 @@ pub async fn start_static_proxy_server(_app_handle: AppHandle) -> Result<String,
         }
     });
 
-    Ok(format!("http://127.0.0.1:{}", port))
+    Ok(base_url)
 }
 
 #[tauri::command]
@@ -510,3 +1613,98 @@ pub async fn stop_proxy(server_handle_state: State<'_, ProxyServerHandle>) -> Re
     }
     Ok(())
 }
+
+#[tauri::command]
+pub async fn get_image_cache_stats(
+    image_cache: State<'_, ImageProxyCache>,
+) -> Result<ImageCacheStats, String> {
+    Ok(image_cache.0.stats())
+}
+
+#[tauri::command]
+pub async fn clear_image_cache(image_cache: State<'_, ImageProxyCache>) -> Result<(), String> {
+    image_cache.0.clear().await
+}
+
+#[tauri::command]
+pub async fn get_proxy_access_info(
+    access_policy: State<'_, ProxyAccessPolicy>,
+) -> Result<ProxyAccessInfo, String> {
+    Ok(access_policy.0.info())
+}
+
+#[tauri::command]
+pub async fn set_proxy_allowed_hosts(
+    access_policy: State<'_, ProxyAccessPolicy>,
+    patterns: Vec<String>,
+) -> Result<ProxyAccessInfo, String> {
+    access_policy.0.set_extra_allowed_hosts(patterns);
+    Ok(access_policy.0.info())
+}
+
+// 是否拒绝解析到 198.18.0.0/15 的上游；使用 Clash/Surge fake-ip 时需保持关闭
+#[tauri::command]
+pub async fn set_proxy_block_fake_ip_range(
+    access_policy: State<'_, ProxyAccessPolicy>,
+    enabled: bool,
+) -> Result<ProxyAccessInfo, String> {
+    access_policy.0.set_block_fake_ip_range(enabled);
+    Ok(access_policy.0.info())
+}
+
+// 只影响之后创建的 FLV 会话
+#[tauri::command]
+pub async fn set_proxy_lan_sharing(
+    access_policy: State<'_, ProxyAccessPolicy>,
+    enabled: bool,
+    interface_ip: Option<String>,
+) -> Result<ProxyAccessInfo, String> {
+    access_policy
+        .0
+        .set_lan_sharing(enabled, interface_ip.as_deref())?;
+    Ok(access_policy.0.info())
+}
+
+// 返回录制文件在静态代理上的回放地址；`output_dir` 只能是已知的录制目录（默认目录、历史目录或当前任务目录）
+#[tauri::command]
+pub async fn get_recording_playback_url(
+    app_handle: AppHandle,
+    recording_manager: State<'_, RecordingManager>,
+    recording_library: State<'_, RecordingFileLibrary>,
+    file_path: String,
+    output_dir: Option<String>,
+) -> Result<String, String> {
+    let roots = known_output_dirs(&recording_manager);
+    if let Some(dir) = output_dir.filter(|dir| !dir.trim().is_empty()) {
+        let dir = std::fs::canonicalize(dir.trim())
+            .map_err(|e| format!("Recording directory {} not found: {}", dir, e))?;
+        let known = roots
+            .iter()
+            .any(|root| std::fs::canonicalize(root).is_ok_and(|root| root == dir));
+        if !known {
+            return Err(format!(
+                "{} is not a known recording directory",
+                dir.display()
+            ));
+        }
+    }
+    let url_path = recording_library
+        .0
+        .url_path_for(std::path::Path::new(&file_path), &roots)?;
+    let base_url = ensure_static_proxy_server(&app_handle)?;
+    Ok(format!("{}/recordings/{}", base_url, url_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlvSessionQuery;
+
+    #[test]
+    fn time_shift_offset_must_be_behind_live() {
+        let seconds = |offset| FlvSessionQuery { offset }.time_shift_seconds();
+        assert_eq!(seconds(None), Ok(0));
+        assert_eq!(seconds(Some(0)), Ok(0));
+        assert_eq!(seconds(Some(-120)), Ok(120));
+        assert!(seconds(Some(120)).is_err());
+    }
+}
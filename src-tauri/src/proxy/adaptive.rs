@@ -0,0 +1,521 @@
+use super::flv::{FlvDemuxer, FlvEvent};
+use super::metrics::{SessionMetrics, UpstreamProbe};
+use super::security::ProxyAccess;
+use super::timeshift::{self, TimeShiftBuffer};
+use crate::platforms::common::FollowHttpClient;
+use crate::recording::resolve_stream_url;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+pub const QUALITY_SWITCH_EVENT: &str = "flv-proxy-quality-switch";
+
+// 从高到低的清晰度阶梯，与录制模块使用的清晰度名称一致
+const QUALITY_LADDER: [&str; 3] = ["原画", "高清", "标清"];
+
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(1);
+// 参与判断的采样个数（每秒一个）
+const HEALTH_WINDOW_TICKS: usize = 8;
+// 刚连上时 CDN 会突发推送缓存的 GOP，这段时间不采样
+const CONNECT_GRACE: Duration = Duration::from_secs(5);
+const SWITCH_COOLDOWN: Duration = Duration::from_secs(15);
+// 媒体时间推进速度 / 墙钟时间：低于该值说明下载跟不上码率
+const DEGRADE_RATIO: f64 = 0.85;
+const HEALTHY_RATIO: f64 = 0.97;
+const INITIAL_UPGRADE_DELAY: Duration = Duration::from_secs(60);
+const MAX_UPGRADE_DELAY: Duration = Duration::from_secs(10 * 60);
+const SWITCH_PREPARE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type UpstreamStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// 用于重新解析直播流地址的房间信息
+pub struct AdaptiveSource {
+    pub platform: String,
+    pub room_id: String,
+    pub cookie: Option<String>,
+    pub quality: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualitySwitchEvent {
+    pub session_id: String,
+    pub platform: String,
+    pub room_id: String,
+    pub from_quality: String,
+    pub to_quality: String,
+    // "degrade" 或 "recover"
+    pub reason: String,
+    pub download_kbps: f64,
+    pub stream_kbps: f64,
+}
+
+pub struct AdaptivePump {
+    pub app_handle: AppHandle,
+    pub client: Client,
+    pub follow_http: FollowHttpClient,
+    pub access: Arc<ProxyAccess>,
+    pub session_id: String,
+    pub source: AdaptiveSource,
+    pub initial_url: String,
+    pub buffer: Arc<TimeShiftBuffer>,
+    pub metrics: Arc<SessionMetrics>,
+    pub active_quality: Arc<StdMutex<String>>,
+}
+
+struct SwitchDecision {
+    level: usize,
+    reason: &'static str,
+    download_kbps: f64,
+    stream_kbps: f64,
+}
+
+/// 以媒体时间推进速度衡量下载是否跟得上码率，决定降档或回升
+struct QualityController {
+    level: usize,
+    // 每秒一个采样：(墙钟 ms, 媒体时间推进 ms, 字节数)
+    samples: VecDeque<(u64, u64, u64)>,
+    connected_at: Instant,
+    last_switch_at: Option<Instant>,
+    last_upgrade_at: Option<Instant>,
+    healthy_since: Option<Instant>,
+    upgrade_delay: Duration,
+}
+
+impl QualityController {
+    fn new(level: usize) -> Self {
+        Self {
+            level,
+            samples: VecDeque::new(),
+            connected_at: Instant::now(),
+            last_switch_at: None,
+            last_upgrade_at: None,
+            healthy_since: None,
+            upgrade_delay: INITIAL_UPGRADE_DELAY,
+        }
+    }
+
+    fn on_connected(&mut self) {
+        self.connected_at = Instant::now();
+        self.samples.clear();
+        self.healthy_since = None;
+    }
+
+    fn on_tick(&mut self, wall_ms: u64, media_ms: u64, bytes: u64) -> Option<SwitchDecision> {
+        let now = Instant::now();
+        if now.duration_since(self.connected_at) < CONNECT_GRACE {
+            return None;
+        }
+        self.samples.push_back((wall_ms, media_ms, bytes));
+        while self.samples.len() > HEALTH_WINDOW_TICKS {
+            self.samples.pop_front();
+        }
+        if self.samples.len() < HEALTH_WINDOW_TICKS {
+            return None;
+        }
+
+        let (wall, media, bytes) = self.samples.iter().fold((0u64, 0u64, 0u64), |acc, s| {
+            (acc.0 + s.0, acc.1 + s.1, acc.2 + s.2)
+        });
+        if wall == 0 {
+            return None;
+        }
+        let ratio = media as f64 / wall as f64;
+        let download_kbps = bytes as f64 * 8.0 / wall as f64;
+        let stream_kbps = if media > 0 {
+            bytes as f64 * 8.0 / media as f64
+        } else {
+            0.0
+        };
+
+        if ratio >= HEALTHY_RATIO {
+            self.healthy_since.get_or_insert(now);
+        } else {
+            self.healthy_since = None;
+        }
+
+        if self
+            .last_switch_at
+            .is_some_and(|at| now.duration_since(at) < SWITCH_COOLDOWN)
+        {
+            return None;
+        }
+
+        if ratio < DEGRADE_RATIO && self.level + 1 < QUALITY_LADDER.len() {
+            // 回升后很快又跟不上，说明带宽并未恢复，拉长下次尝试回升的间隔
+            if self
+                .last_upgrade_at
+                .is_some_and(|at| now.duration_since(at) < self.upgrade_delay)
+            {
+                self.upgrade_delay = (self.upgrade_delay * 2).min(MAX_UPGRADE_DELAY);
+            }
+            return Some(SwitchDecision {
+                level: self.level + 1,
+                reason: "degrade",
+                download_kbps,
+                stream_kbps,
+            });
+        }
+
+        if self.level > 0
+            && self
+                .healthy_since
+                .is_some_and(|since| now.duration_since(since) >= self.upgrade_delay)
+        {
+            return Some(SwitchDecision {
+                level: self.level - 1,
+                reason: "recover",
+                download_kbps,
+                stream_kbps,
+            });
+        }
+        None
+    }
+
+    fn commit(&mut self, level: usize, reason: &str) {
+        let now = Instant::now();
+        if reason == "recover" {
+            self.last_upgrade_at = Some(now);
+        }
+        self.level = level;
+        self.last_switch_at = Some(now);
+        self.on_connected();
+    }
+
+    fn switch_failed(&mut self) {
+        self.last_switch_at = Some(Instant::now());
+        self.healthy_since = None;
+    }
+}
+
+/// 累计当前连接的媒体时间推进量（音视频 tag 时间戳的最大值增量）
+#[derive(Default)]
+struct MediaClock {
+    last_ts: Option<u32>,
+    advanced_ms: u64,
+}
+
+impl MediaClock {
+    fn observe(&mut self, events: &[FlvEvent]) {
+        for event in events {
+            let FlvEvent::Tag(tag) = event else { continue };
+            if tag.is_script() {
+                continue;
+            }
+            match self.last_ts {
+                Some(last) if tag.timestamp > last => {
+                    self.advanced_ms += u64::from(tag.timestamp - last);
+                    self.last_ts = Some(tag.timestamp);
+                }
+                Some(_) => {}
+                None => self.last_ts = Some(tag.timestamp),
+            }
+        }
+    }
+
+    fn take(&mut self) -> u64 {
+        std::mem::take(&mut self.advanced_ms)
+    }
+}
+
+/// 预先连好目标清晰度并读到第一个关键帧，切换时可直接接入
+struct PreparedUpstream {
+    level: usize,
+    url: String,
+    stream: UpstreamStream,
+    demuxer: FlvDemuxer,
+    probe: UpstreamProbe,
+    events: Vec<FlvEvent>,
+    decision: SwitchDecision,
+}
+
+async fn open_upstream(
+    client: &Client,
+    url: &str,
+    metrics: &SessionMetrics,
+    reconnect: bool,
+) -> Result<(UpstreamStream, UpstreamProbe), String> {
+    let started = Instant::now();
+    match super::build_flv_upstream_request(client, Some(&metrics.platform), url)
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => {
+            metrics.record_upstream_connect(reconnect);
+            Ok((Box::pin(resp.bytes_stream()), UpstreamProbe::new(started)))
+        }
+        Ok(resp) => {
+            metrics.record_upstream_error();
+            Err(format!(
+                "Upstream {} responded with status {}",
+                url,
+                resp.status()
+            ))
+        }
+        Err(e) => {
+            metrics.record_upstream_error();
+            Err(format!("Failed to connect upstream {}: {}", url, e))
+        }
+    }
+}
+
+async fn resolve_level_url(
+    follow_http: &FollowHttpClient,
+    access: &ProxyAccess,
+    source: &AdaptiveSource,
+    level: usize,
+) -> Result<String, String> {
+    let url = resolve_stream_url(
+        &source.platform,
+        &source.room_id,
+        QUALITY_LADDER[level],
+        source.cookie.as_deref(),
+        &follow_http.client(Some(&source.platform))?.inner,
+    )
+    .await?;
+    access.check_upstream_url(&url)?;
+    Ok(url)
+}
+
+async fn prepare_upstream(
+    client: Client,
+    follow_http: FollowHttpClient,
+    access: Arc<ProxyAccess>,
+    source: Arc<AdaptiveSource>,
+    metrics: Arc<SessionMetrics>,
+    decision: SwitchDecision,
+) -> Result<PreparedUpstream, String> {
+    let level = decision.level;
+    let url = resolve_level_url(&follow_http, &access, &source, level).await?;
+    // 清晰度切换不算重连
+    let (mut stream, mut probe) = open_upstream(&client, &url, &metrics, false).await?;
+    let mut demuxer = FlvDemuxer::new();
+    let mut events = Vec::new();
+
+    let prefetch = async {
+        let mut has_video = true;
+        let mut keyframe_found = false;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("Upstream read error: {}", e))?;
+            probe.on_chunk(&metrics, chunk.len());
+            for event in demuxer.push(&chunk)? {
+                match &event {
+                    FlvEvent::Header { has_video: v, .. } => has_video = *v,
+                    FlvEvent::Tag(_) if keyframe_found => {}
+                    FlvEvent::Tag(tag) if tag.is_script() || tag.is_sequence_header() => {}
+                    FlvEvent::Tag(tag)
+                        if (has_video && tag.is_keyframe()) || (!has_video && tag.is_audio()) =>
+                    {
+                        keyframe_found = true;
+                    }
+                    // 关键帧之前的普通帧无法独立解码
+                    FlvEvent::Tag(_) => continue,
+                }
+                events.push(event);
+            }
+            if keyframe_found {
+                return Ok(());
+            }
+        }
+        Err("Upstream ended before the first keyframe".to_string())
+    };
+    tokio::time::timeout(SWITCH_PREPARE_TIMEOUT, prefetch)
+        .await
+        .map_err(|_| "Timed out waiting for the first keyframe".to_string())??;
+
+    Ok(PreparedUpstream {
+        level,
+        url,
+        stream,
+        demuxer,
+        probe,
+        events,
+        decision,
+    })
+}
+
+/// 自适应清晰度的会话拉流任务：与回看共用会话缓冲区，
+/// 在关键帧处无缝切换到重新解析的清晰度，每次切换都会发出事件
+pub async fn run_adaptive_pump(pump: AdaptivePump, mut stop_rx: watch::Receiver<bool>) {
+    let AdaptivePump {
+        app_handle,
+        client,
+        follow_http,
+        access,
+        session_id,
+        source,
+        initial_url,
+        buffer,
+        metrics,
+        active_quality,
+    } = pump;
+    let source = Arc::new(source);
+    let initial_level = QUALITY_LADDER
+        .iter()
+        .position(|q| *q == source.quality)
+        .unwrap_or(0);
+    let mut controller = QualityController::new(initial_level);
+    let mut url = initial_url;
+    let mut reconnect_attempts: u32 = 0;
+    let mut stopped = *stop_rx.borrow();
+    let mut first_attempt = true;
+
+    while !stopped {
+        let reconnect = !std::mem::replace(&mut first_attempt, false);
+        // 连续失败时地址多半已过期，重新解析当前清晰度
+        if reconnect_attempts >= 2 {
+            match resolve_level_url(&follow_http, &access, &source, controller.level).await {
+                Ok(fresh) => url = fresh,
+                Err(e) => eprintln!(
+                    "[Rust/proxy.rs adaptive] Failed to re-resolve stream url for session {}: {}",
+                    session_id, e
+                ),
+            }
+        }
+
+        let (mut stream, mut probe) = match open_upstream(&client, &url, &metrics, reconnect).await
+        {
+            Ok(opened) => opened,
+            Err(e) => {
+                eprintln!("[Rust/proxy.rs adaptive] {}", e);
+                reconnect_attempts += 1;
+                stopped = timeshift::wait_before_reconnect(reconnect_attempts, &mut stop_rx).await;
+                continue;
+            }
+        };
+        let mut demuxer = FlvDemuxer::new();
+        let mut clock = MediaClock::default();
+        let mut tick_bytes: u64 = 0;
+        let mut last_tick = Instant::now();
+        let mut ticker = tokio::time::interval(EVALUATE_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut pending: Option<
+            tauri::async_runtime::JoinHandle<Result<PreparedUpstream, String>>,
+        > = None;
+        controller.on_connected();
+
+        loop {
+            tokio::select! {
+                // 发送端只会发送停止信号；发送端被丢弃同样视为停止
+                _ = stop_rx.changed() => {
+                    stopped = true;
+                    break;
+                }
+                prepared = async {
+                    match pending.as_mut() {
+                        Some(handle) => handle.await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    pending = None;
+                    match prepared {
+                        Ok(Ok(prepared)) => {
+                            let from = QUALITY_LADDER[controller.level];
+                            let to = QUALITY_LADDER[prepared.level];
+                            println!(
+                                "[Rust/proxy.rs adaptive] Session {} switched {} -> {} ({})",
+                                session_id, from, to, prepared.decision.reason
+                            );
+                            let _ = app_handle.emit(
+                                QUALITY_SWITCH_EVENT,
+                                QualitySwitchEvent {
+                                    session_id: session_id.clone(),
+                                    platform: source.platform.clone(),
+                                    room_id: source.room_id.clone(),
+                                    from_quality: from.to_string(),
+                                    to_quality: to.to_string(),
+                                    reason: prepared.decision.reason.to_string(),
+                                    download_kbps: prepared.decision.download_kbps,
+                                    stream_kbps: prepared.decision.stream_kbps,
+                                },
+                            );
+                            controller.commit(prepared.level, prepared.decision.reason);
+                            *active_quality.lock().unwrap() = to.to_string();
+                            url = prepared.url;
+                            stream = prepared.stream;
+                            demuxer = prepared.demuxer;
+                            probe = prepared.probe;
+                            clock = MediaClock::default();
+                            tick_bytes = 0;
+                            buffer.ingest(prepared.events).await;
+                        }
+                        Ok(Err(e)) => {
+                            eprintln!(
+                                "[Rust/proxy.rs adaptive] Quality switch for session {} failed: {}",
+                                session_id, e
+                            );
+                            controller.switch_failed();
+                        }
+                        Err(e) => {
+                            eprintln!(
+                                "[Rust/proxy.rs adaptive] Quality switch task for session {} failed: {}",
+                                session_id, e
+                            );
+                            controller.switch_failed();
+                        }
+                    }
+                }
+                _ = ticker.tick() => {
+                    let wall_ms = last_tick.elapsed().as_millis() as u64;
+                    last_tick = Instant::now();
+                    let decision = controller.on_tick(
+                        wall_ms,
+                        clock.take(),
+                        std::mem::take(&mut tick_bytes),
+                    );
+                    if let (Some(decision), None) = (decision, pending.as_ref()) {
+                        pending = Some(tauri::async_runtime::spawn(prepare_upstream(
+                            client.clone(),
+                            follow_http.clone(),
+                            access.clone(),
+                            source.clone(),
+                            metrics.clone(),
+                            decision,
+                        )));
+                    }
+                }
+                chunk = stream.next() => {
+                    let chunk = match chunk {
+                        Some(Ok(chunk)) => chunk,
+                        Some(Err(e)) => {
+                            eprintln!("[Rust/proxy.rs adaptive] Upstream read error: {}", e);
+                            break;
+                        }
+                        None => break,
+                    };
+                    probe.on_chunk(&metrics, chunk.len());
+                    tick_bytes += chunk.len() as u64;
+                    let events = match demuxer.push(&chunk) {
+                        Ok(events) => events,
+                        Err(e) => {
+                            eprintln!("[Rust/proxy.rs adaptive] FLV parse error: {}", e);
+                            break;
+                        }
+                    };
+                    if events.iter().any(|event| matches!(event, FlvEvent::Header { .. })) {
+                        reconnect_attempts = 0;
+                    }
+                    clock.observe(&events);
+                    buffer.ingest(events).await;
+                }
+            }
+        }
+
+        if let Some(handle) = pending.take() {
+            handle.abort();
+        }
+        if !stopped {
+            reconnect_attempts += 1;
+            stopped = timeshift::wait_before_reconnect(reconnect_attempts, &mut stop_rx).await;
+        }
+    }
+
+    buffer.close();
+}
@@ -0,0 +1,194 @@
+use super::flv::{FlvDemuxer, FlvEvent, FlvTag};
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+
+// FLV 文件头 flags：仅音频
+const FLV_FLAG_AUDIO_ONLY: u8 = 0x04;
+const SOUND_FORMAT_AAC: u8 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioOutput {
+    // 只保留音频 tag 的 FLV
+    Flv,
+    // 裸 ADTS AAC
+    Adts,
+}
+
+impl AudioOutput {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioOutput::Flv => "video/x-flv",
+            AudioOutput::Adts => "audio/aac",
+        }
+    }
+}
+
+/// AudioSpecificConfig 中生成 ADTS 头所需的字段
+#[derive(Clone, Copy)]
+struct AdtsConfig {
+    profile: u8,
+    sampling_index: u8,
+    channels: u8,
+}
+
+impl AdtsConfig {
+    fn parse(asc: &[u8]) -> Result<Self, String> {
+        if asc.len() < 2 {
+            return Err("AudioSpecificConfig too short".to_string());
+        }
+        let object_type = asc[0] >> 3;
+        let sampling_index = ((asc[0] & 0x07) << 1) | (asc[1] >> 7);
+        let channels = (asc[1] >> 3) & 0x0f;
+        if sampling_index >= 13 {
+            return Err(format!("Unsupported AAC sampling index {}", sampling_index));
+        }
+        // ADTS 只能表达 Main/LC/SSR/LTP，HE-AAC 等按 LC 输出由解码器隐式识别 SBR
+        let profile = match object_type {
+            1..=4 => object_type - 1,
+            _ => 1,
+        };
+        Ok(Self {
+            profile,
+            sampling_index,
+            channels,
+        })
+    }
+
+    fn header(&self, payload_len: usize) -> [u8; 7] {
+        let frame_len = payload_len + 7;
+        [
+            0xff,
+            0xf1,
+            (self.profile << 6) | (self.sampling_index << 2) | (self.channels >> 2),
+            ((self.channels & 0x03) << 6) | ((frame_len >> 11) & 0x03) as u8,
+            ((frame_len >> 3) & 0xff) as u8,
+            (((frame_len & 0x07) << 5) as u8) | 0x1f,
+            0xfc,
+        ]
+    }
+}
+
+#[derive(Default)]
+struct AudioFilter {
+    adts: Option<AdtsConfig>,
+}
+
+impl AudioFilter {
+    fn apply(
+        &mut self,
+        event: FlvEvent,
+        output: AudioOutput,
+        out: &mut BytesMut,
+    ) -> Result<(), String> {
+        match (event, output) {
+            (FlvEvent::Header { raw, .. }, AudioOutput::Flv) => {
+                let start = out.len();
+                out.extend_from_slice(&raw);
+                out[start + 4] = FLV_FLAG_AUDIO_ONLY;
+            }
+            (FlvEvent::Header { .. }, AudioOutput::Adts) => {}
+            (FlvEvent::Tag(tag), AudioOutput::Flv) => {
+                if tag.is_audio() || tag.is_script() {
+                    out.extend_from_slice(&tag.raw);
+                }
+            }
+            (FlvEvent::Tag(tag), AudioOutput::Adts) => self.write_adts(&tag, out)?,
+        }
+        Ok(())
+    }
+
+    fn write_adts(&mut self, tag: &FlvTag, out: &mut BytesMut) -> Result<(), String> {
+        if !tag.is_audio() {
+            return Ok(());
+        }
+        let body = tag.body();
+        let Some(first) = body.first() else {
+            return Ok(());
+        };
+        if first >> 4 != SOUND_FORMAT_AAC {
+            return Err(format!("Audio codec {} is not AAC", first >> 4));
+        }
+        match body.get(1) {
+            Some(0) => self.adts = Some(AdtsConfig::parse(&body[2..])?),
+            Some(1) => {
+                // 序列头之前的帧无法封装
+                if let Some(config) = self.adts {
+                    let payload = &body[2..];
+                    out.extend_from_slice(&config.header(payload.len()));
+                    out.extend_from_slice(payload);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// 把 FLV 字节流过滤为纯音频输出；上游本身就是纯音频变体时同样适用
+pub fn audio_only_stream<S, E>(
+    source: S,
+    output: AudioOutput,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    struct State<S> {
+        source: S,
+        demuxer: FlvDemuxer,
+        filter: AudioFilter,
+        done: bool,
+    }
+
+    let state = State {
+        source,
+        demuxer: FlvDemuxer::new(),
+        filter: AudioFilter::default(),
+        done: false,
+    };
+
+    futures_util::stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+            let chunk = match state.source.next().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(std::io::Error::other(e.to_string())), state));
+                }
+                None => return None,
+            };
+            let result = state.demuxer.push(&chunk).and_then(|events| {
+                let mut out = BytesMut::new();
+                for event in events {
+                    state.filter.apply(event, output, &mut out)?;
+                }
+                Ok(out)
+            });
+            match result {
+                Ok(out) if !out.is_empty() => return Some((Ok(out.freeze()), state)),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("[Rust/proxy.rs audio] {}", e);
+                    state.done = true;
+                    return Some((Err(std::io::Error::other(e)), state));
+                }
+            }
+        }
+    })
+}
+
+/// 平台提供的纯音频拉流地址（抖音、虎牙的 FLV 支持 `only_audio=1`）
+pub fn audio_only_variant(platform: &str, url: &str) -> Option<String> {
+    if !matches!(platform.to_ascii_uppercase().as_str(), "DOUYIN" | "HUYA") {
+        return None;
+    }
+    let mut parsed = reqwest::Url::parse(url).ok()?;
+    if parsed.query_pairs().any(|(k, _)| k == "only_audio") {
+        return None;
+    }
+    parsed.query_pairs_mut().append_pair("only_audio", "1");
+    Some(parsed.to_string())
+}
@@ -0,0 +1,317 @@
+use bytes::{Bytes, BytesMut};
+
+pub const TAG_TYPE_AUDIO: u8 = 8;
+pub const TAG_TYPE_VIDEO: u8 = 9;
+pub const TAG_TYPE_SCRIPT: u8 = 18;
+
+const FLV_SIGNATURE: &[u8; 3] = b"FLV";
+const FLV_MIN_HEADER_LEN: usize = 9;
+const TAG_HEADER_LEN: usize = 11;
+const PREVIOUS_TAG_SIZE_LEN: usize = 4;
+
+/// 一个完整的 FLV tag（11 字节 tag 头 + body + 4 字节 PreviousTagSize），可原样转发
+#[derive(Clone, Debug)]
+pub struct FlvTag {
+    pub tag_type: u8,
+    pub timestamp: u32,
+    pub raw: Bytes,
+}
+
+impl FlvTag {
+    pub fn body(&self) -> &[u8] {
+        &self.raw[TAG_HEADER_LEN..self.raw.len() - PREVIOUS_TAG_SIZE_LEN]
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.tag_type == TAG_TYPE_AUDIO
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.tag_type == TAG_TYPE_VIDEO
+    }
+
+    pub fn is_script(&self) -> bool {
+        self.tag_type == TAG_TYPE_SCRIPT
+    }
+
+    pub fn is_keyframe(&self) -> bool {
+        if !self.is_video() {
+            return false;
+        }
+        match self.body().first() {
+            // Enhanced FLV: 高位为 IsExHeader，frame type 占 bit4..6
+            Some(b) if b & 0x80 != 0 => (b >> 4) & 0x07 == 1,
+            Some(b) => b >> 4 == 1,
+            None => false,
+        }
+    }
+
+    /// AVC/HEVC 的 decoder configuration 或 AAC 的 AudioSpecificConfig
+    pub fn is_sequence_header(&self) -> bool {
+        let body = self.body();
+        match self.tag_type {
+            TAG_TYPE_VIDEO => match body.first() {
+                Some(b) if b & 0x80 != 0 => b & 0x0f == 0,
+                Some(b) => matches!(b & 0x0f, 7 | 12) && body.get(1) == Some(&0),
+                None => false,
+            },
+            TAG_TYPE_AUDIO => body.first().map(|b| b >> 4) == Some(10) && body.get(1) == Some(&0),
+            _ => false,
+        }
+    }
+
+    /// 返回改写了时间戳的副本（用于在上游重连后保持时间线单调）
+    pub fn with_timestamp(&self, timestamp: u32) -> FlvTag {
+        let mut raw = BytesMut::from(&self.raw[..]);
+        raw[4] = ((timestamp >> 16) & 0xff) as u8;
+        raw[5] = ((timestamp >> 8) & 0xff) as u8;
+        raw[6] = (timestamp & 0xff) as u8;
+        raw[7] = ((timestamp >> 24) & 0xff) as u8;
+        FlvTag {
+            tag_type: self.tag_type,
+            timestamp,
+            raw: raw.freeze(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FlvEvent {
+    /// FLV 文件头（含 PreviousTagSize0），`has_video` 取自 flags
+    Header {
+        raw: Bytes,
+        has_video: bool,
+    },
+    Tag(FlvTag),
+}
+
+/// 增量 FLV 解析器：按任意边界喂入字节，吐出文件头与完整的 tag
+#[derive(Default)]
+pub struct FlvDemuxer {
+    buf: BytesMut,
+    header_done: bool,
+}
+
+impl FlvDemuxer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<FlvEvent>, String> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        if !self.header_done {
+            if self.buf.len() < FLV_MIN_HEADER_LEN {
+                return Ok(events);
+            }
+            if &self.buf[..3] != FLV_SIGNATURE {
+                return Err("invalid FLV signature".to_string());
+            }
+            let flags = self.buf[4];
+            let data_offset =
+                u32::from_be_bytes([self.buf[5], self.buf[6], self.buf[7], self.buf[8]]) as usize;
+            let data_offset = data_offset.max(FLV_MIN_HEADER_LEN);
+            let header_len = data_offset + PREVIOUS_TAG_SIZE_LEN;
+            if self.buf.len() < header_len {
+                return Ok(events);
+            }
+            let raw = self.buf.split_to(header_len).freeze();
+            events.push(FlvEvent::Header {
+                raw,
+                has_video: flags & 0x01 != 0,
+            });
+            self.header_done = true;
+        }
+
+        while self.buf.len() >= TAG_HEADER_LEN {
+            let tag_type = self.buf[0] & 0x1f;
+            if !matches!(tag_type, TAG_TYPE_AUDIO | TAG_TYPE_VIDEO | TAG_TYPE_SCRIPT) {
+                return Err(format!("unexpected FLV tag type {}", tag_type));
+            }
+            let data_size = ((self.buf[1] as usize) << 16)
+                | ((self.buf[2] as usize) << 8)
+                | self.buf[3] as usize;
+            let total = TAG_HEADER_LEN + data_size + PREVIOUS_TAG_SIZE_LEN;
+            if self.buf.len() < total {
+                break;
+            }
+            let timestamp = ((self.buf[7] as u32) << 24)
+                | ((self.buf[4] as u32) << 16)
+                | ((self.buf[5] as u32) << 8)
+                | self.buf[6] as u32;
+            let raw = self.buf.split_to(total).freeze();
+            events.push(FlvEvent::Tag(FlvTag {
+                tag_type,
+                timestamp,
+                raw,
+            }));
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::{PREVIOUS_TAG_SIZE_LEN, TAG_HEADER_LEN};
+
+    pub fn header(has_video: bool) -> Vec<u8> {
+        let flags = if has_video { 0x05 } else { 0x04 };
+        vec![b'F', b'L', b'V', 1, flags, 0, 0, 0, 9, 0, 0, 0, 0]
+    }
+
+    pub fn tag(tag_type: u8, timestamp: u32, body: &[u8]) -> Vec<u8> {
+        let size = body.len();
+        let mut raw = vec![
+            tag_type,
+            (size >> 16) as u8,
+            (size >> 8) as u8,
+            size as u8,
+            (timestamp >> 16) as u8,
+            (timestamp >> 8) as u8,
+            timestamp as u8,
+            (timestamp >> 24) as u8,
+            0,
+            0,
+            0,
+        ];
+        raw.extend_from_slice(body);
+        raw.extend_from_slice(&((TAG_HEADER_LEN + size) as u32).to_be_bytes());
+        debug_assert_eq!(raw.len(), TAG_HEADER_LEN + size + PREVIOUS_TAG_SIZE_LEN);
+        raw
+    }
+
+    pub fn metadata() -> Vec<u8> {
+        tag(super::TAG_TYPE_SCRIPT, 0, b"\x02\x00\x0aonMetaData")
+    }
+
+    pub fn avc_sequence_header() -> Vec<u8> {
+        tag(super::TAG_TYPE_VIDEO, 0, &[0x17, 0, 0, 0, 1])
+    }
+
+    pub fn aac_sequence_header() -> Vec<u8> {
+        tag(super::TAG_TYPE_AUDIO, 0, &[0xaf, 0, 0x12, 0x10])
+    }
+
+    pub fn video(timestamp: u32, keyframe: bool) -> Vec<u8> {
+        let frame = if keyframe { 0x17 } else { 0x27 };
+        tag(super::TAG_TYPE_VIDEO, timestamp, &[frame, 1, 0, 0, 0, 0xaa])
+    }
+
+    pub fn audio(timestamp: u32) -> Vec<u8> {
+        tag(super::TAG_TYPE_AUDIO, timestamp, &[0xaf, 1, 0xbb])
+    }
+
+    /// 解析完整的 FLV 字节，返回各 tag 的 (类型, 时间戳)
+    pub fn tag_summary(bytes: &[u8]) -> Vec<(u8, u32)> {
+        super::FlvDemuxer::new()
+            .push(bytes)
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| match event {
+                super::FlvEvent::Tag(tag) => Some((tag.tag_type, tag.timestamp)),
+                super::FlvEvent::Header { .. } => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_util::*;
+    use super::*;
+
+    #[test]
+    fn demuxes_across_arbitrary_chunk_boundaries() {
+        let mut stream = header(true);
+        stream.extend(metadata());
+        stream.extend(avc_sequence_header());
+        stream.extend(video(0, true));
+        stream.extend(audio(0x0100_0010));
+        stream.extend(video(40, false));
+
+        for chunk_size in [1, 3, 7, 64, stream.len()] {
+            let mut demuxer = FlvDemuxer::new();
+            let mut events = Vec::new();
+            for chunk in stream.chunks(chunk_size) {
+                events.extend(demuxer.push(chunk).unwrap());
+            }
+            assert!(matches!(
+                events.first(),
+                Some(FlvEvent::Header {
+                    has_video: true,
+                    ..
+                })
+            ));
+            let tags: Vec<_> = events
+                .iter()
+                .filter_map(|event| match event {
+                    FlvEvent::Tag(tag) => Some((tag.tag_type, tag.timestamp)),
+                    FlvEvent::Header { .. } => None,
+                })
+                .collect();
+            assert_eq!(
+                tags,
+                vec![
+                    (TAG_TYPE_SCRIPT, 0),
+                    (TAG_TYPE_VIDEO, 0),
+                    (TAG_TYPE_VIDEO, 0),
+                    (TAG_TYPE_AUDIO, 0x0100_0010),
+                    (TAG_TYPE_VIDEO, 40),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_non_flv_input() {
+        assert!(FlvDemuxer::new().push(b"HTTP/1.1 200 OK\r\n").is_err());
+        let mut demuxer = FlvDemuxer::new();
+        let mut stream = header(true);
+        stream.extend([0x07, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(demuxer.push(&stream).is_err());
+    }
+
+    #[test]
+    fn classifies_tags() {
+        let tag_of = |raw: Vec<u8>| match FlvDemuxer::new()
+            .push(&[header(true), raw].concat())
+            .unwrap()
+            .pop()
+        {
+            Some(FlvEvent::Tag(tag)) => tag,
+            _ => panic!("expected a tag"),
+        };
+
+        let sequence_header = tag_of(avc_sequence_header());
+        assert!(sequence_header.is_sequence_header());
+        let keyframe = tag_of(video(0, true));
+        assert!(keyframe.is_keyframe() && !keyframe.is_sequence_header());
+        assert!(!tag_of(video(0, false)).is_keyframe());
+        assert!(tag_of(aac_sequence_header()).is_sequence_header());
+        assert!(!tag_of(audio(0)).is_sequence_header());
+        // Enhanced FLV：IsExHeader + keyframe + PacketTypeSequenceStart
+        let enhanced = tag_of(tag(TAG_TYPE_VIDEO, 0, &[0x90, b'h', b'v', b'c', b'1']));
+        assert!(enhanced.is_keyframe() && enhanced.is_sequence_header());
+    }
+
+    #[test]
+    fn rewrites_timestamp_including_extended_byte() {
+        let tag = match FlvDemuxer::new()
+            .push(&[header(true), video(40, true)].concat())
+            .unwrap()
+            .pop()
+        {
+            Some(FlvEvent::Tag(tag)) => tag,
+            _ => panic!("expected a tag"),
+        };
+        let shifted = tag.with_timestamp(0x0123_4567);
+        assert_eq!(shifted.timestamp, 0x0123_4567);
+        assert_eq!(
+            tag_summary(&[header(true), shifted.raw.to_vec()].concat()),
+            vec![(TAG_TYPE_VIDEO, 0x0123_4567)]
+        );
+        assert_eq!(shifted.body(), tag.body());
+    }
+}
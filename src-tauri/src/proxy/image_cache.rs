@@ -0,0 +1,673 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, DATE, ETAG, EXPIRES, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex as StdMutex;
+
+pub const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+// 上游未给出任何缓存信息时的默认新鲜期
+const DEFAULT_FRESH_SECONDS: i64 = 60 * 60;
+const MAX_FRESH_SECONDS: i64 = 7 * 24 * 60 * 60;
+// 仅有 Last-Modified 时按启发式（距今时长的 10%）计算新鲜期的上限
+const HEURISTIC_MAX_FRESH_SECONDS: i64 = 24 * 60 * 60;
+const META_EXTENSION: &str = "json";
+const BODY_EXTENSION: &str = "bin";
+const TMP_EXTENSION: &str = "tmp";
+
+// 临时文件名后缀，保证同一 URL 的并发写入不会共用一个临时文件
+static NEXT_TMP_ID: AtomicU64 = AtomicU64::new(0);
+// 每次写入条目使用新的代号作为文件名的一部分，淘汰旧条目时不会误删同一 URL 新写入的文件。
+// 以启动时间为起点，避免与上次运行留下的文件重名
+static NEXT_GENERATION: Lazy<AtomicU64> =
+    Lazy::new(|| AtomicU64::new(Utc::now().timestamp_micros().max(0) as u64));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntryMeta {
+    url: String,
+    generation: u64,
+    content_type: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored_at: i64,
+    fresh_until: i64,
+    size: u64,
+    last_access: i64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntryMeta>,
+    total_bytes: u64,
+    hits: u64,
+    misses: u64,
+    revalidated: u64,
+    stale_served: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCacheStats {
+    pub dir: String,
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub revalidated: u64,
+    pub stale_served: u64,
+}
+
+pub struct CachedImage {
+    pub bytes: Bytes,
+    pub content_type: String,
+    // 剩余新鲜期（秒），过期条目为 0
+    pub fresh_for: i64,
+}
+
+/// 条件请求所需的校验信息
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum CacheLookup {
+    Fresh(CachedImage),
+    Stale(Validators),
+    Miss,
+}
+
+/// 图片代理的磁盘 LRU 缓存：每个 URL 对应一个 body 文件和一个元数据文件，文件名为 `{key}.{代号}.*`
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: StdMutex<CacheState>,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!(
+                "[Rust/proxy.rs image_cache] Failed to create cache dir {}: {}",
+                dir.display(),
+                e
+            );
+        }
+        let state = load_index(&dir);
+        Self {
+            dir,
+            max_bytes,
+            state: StdMutex::new(state),
+        }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("DTV")
+            .join("image-cache")
+    }
+
+    fn key(url: &str) -> String {
+        let mut hasher = Md5::new();
+        hasher.update(url.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    fn body_path(&self, key: &str, generation: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}.{}", key, generation, BODY_EXTENSION))
+    }
+
+    fn meta_path(&self, key: &str, generation: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}.{}", key, generation, META_EXTENSION))
+    }
+
+    async fn remove_files(&self, key: &str, generation: u64) {
+        let _ = tokio::fs::remove_file(self.body_path(key, generation)).await;
+        let _ = tokio::fs::remove_file(self.meta_path(key, generation)).await;
+    }
+
+    pub async fn lookup(&self, url: &str) -> CacheLookup {
+        let key = Self::key(url);
+        let now = now_secs();
+        let meta = {
+            let mut guard = self.state.lock().unwrap();
+            let Some(meta) = guard.entries.get_mut(&key) else {
+                guard.misses += 1;
+                return CacheLookup::Miss;
+            };
+            meta.last_access = now;
+            meta.clone()
+        };
+
+        if meta.fresh_until <= now {
+            return CacheLookup::Stale(Validators {
+                etag: meta.etag,
+                last_modified: meta.last_modified,
+            });
+        }
+
+        match tokio::fs::read(self.body_path(&key, meta.generation)).await {
+            Ok(body) => {
+                self.state.lock().unwrap().hits += 1;
+                CacheLookup::Fresh(CachedImage {
+                    bytes: Bytes::from(body),
+                    content_type: meta.content_type,
+                    fresh_for: meta.fresh_until - now,
+                })
+            }
+            Err(_) => {
+                // 文件被外部删除，按未命中处理
+                self.forget(&key, meta.generation);
+                self.state.lock().unwrap().misses += 1;
+                CacheLookup::Miss
+            }
+        }
+    }
+
+    /// 读取过期副本（用于 304 之后或上游不可用时）
+    async fn read_cached(&self, url: &str) -> Option<CachedImage> {
+        let key = Self::key(url);
+        let meta = self.state.lock().unwrap().entries.get(&key).cloned()?;
+        let body = tokio::fs::read(self.body_path(&key, meta.generation))
+            .await
+            .ok()?;
+        Some(CachedImage {
+            bytes: Bytes::from(body),
+            content_type: meta.content_type,
+            fresh_for: (meta.fresh_until - now_secs()).max(0),
+        })
+    }
+
+    /// 上游返回 304：刷新新鲜期后返回缓存内容
+    pub async fn revalidated(&self, url: &str, headers: &HeaderMap) -> Option<CachedImage> {
+        let key = Self::key(url);
+        let now = now_secs();
+        let meta = {
+            let mut guard = self.state.lock().unwrap();
+            guard.revalidated += 1;
+            let meta = guard.entries.get_mut(&key)?;
+            if let Some(fresh_until) = compute_fresh_until(headers, now) {
+                meta.fresh_until = fresh_until;
+            }
+            if let Some(etag) = header_string(headers, ETAG) {
+                meta.etag = Some(etag);
+            }
+            if let Some(last_modified) = header_string(headers, LAST_MODIFIED) {
+                meta.last_modified = Some(last_modified);
+            }
+            meta.last_access = now;
+            meta.clone()
+        };
+        self.write_meta(&key, &meta).await;
+        self.read_cached(url).await
+    }
+
+    /// 上游不可用时返回过期副本
+    pub async fn stale_fallback(&self, url: &str) -> Option<CachedImage> {
+        let cached = self.read_cached(url).await?;
+        self.state.lock().unwrap().stale_served += 1;
+        Some(cached)
+    }
+
+    /// 保存新的响应；上游声明 no-store 时不落盘，并删除之前缓存的副本。返回剩余新鲜期
+    pub async fn store(
+        &self,
+        url: &str,
+        content_type: &str,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> i64 {
+        let now = now_secs();
+        let Some(fresh_until) = compute_fresh_until(headers, now) else {
+            let key = Self::key(url);
+            let removed = self
+                .state
+                .lock()
+                .unwrap()
+                .entries
+                .get(&key)
+                .map(|m| m.generation);
+            if let Some(generation) = removed {
+                if self.forget(&key, generation) {
+                    self.remove_files(&key, generation).await;
+                }
+            }
+            return 0;
+        };
+        let meta = CacheEntryMeta {
+            url: url.to_string(),
+            generation: 0,
+            content_type: content_type.to_string(),
+            etag: header_string(headers, ETAG),
+            last_modified: header_string(headers, LAST_MODIFIED),
+            stored_at: now,
+            fresh_until,
+            size: body.len() as u64,
+            last_access: now,
+        };
+        self.write_entry(meta, body).await
+    }
+
+    /// 保存由原图派生的数据（如缩放/转码结果），新鲜期沿用原图
+    pub async fn store_derived(
+        &self,
+        key_url: &str,
+        content_type: &str,
+        fresh_for: i64,
+        body: &Bytes,
+    ) -> i64 {
+        if fresh_for <= 0 {
+            return 0;
+        }
+        let now = now_secs();
+        let meta = CacheEntryMeta {
+            url: key_url.to_string(),
+            generation: 0,
+            content_type: content_type.to_string(),
+            etag: None,
+            last_modified: None,
+            stored_at: now,
+            fresh_until: now + fresh_for,
+            size: body.len() as u64,
+            last_access: now,
+        };
+        self.write_entry(meta, body).await
+    }
+
+    async fn write_entry(&self, mut meta: CacheEntryMeta, body: &Bytes) -> i64 {
+        if meta.size > self.max_bytes {
+            return 0;
+        }
+        let fresh_for = (meta.fresh_until - meta.stored_at).max(0);
+        let key = Self::key(&meta.url);
+        meta.generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+
+        // 先写临时文件再重命名，避免读到写了一半的图片
+        let body_path = self.body_path(&key, meta.generation);
+        if let Err(e) = write_atomic(&body_path, body).await {
+            eprintln!(
+                "[Rust/proxy.rs image_cache] Failed to write {}: {}",
+                body_path.display(),
+                e
+            );
+            return 0;
+        }
+        self.write_meta(&key, &meta).await;
+
+        // 被替换或淘汰的条目各自有独立的文件名，释放锁后再删除也不会影响新写入的文件
+        let removed = {
+            let mut guard = self.state.lock().unwrap();
+            let size = meta.size;
+            let mut removed = Vec::new();
+            if let Some(previous) = guard.entries.insert(key.clone(), meta) {
+                guard.total_bytes = guard.total_bytes.saturating_sub(previous.size);
+                removed.push((key.clone(), previous.generation));
+            }
+            guard.total_bytes += size;
+            removed.extend(self.collect_evictions(&mut guard, &key));
+            removed
+        };
+        for (removed_key, generation) in removed {
+            self.remove_files(&removed_key, generation).await;
+        }
+
+        fresh_for
+    }
+
+    /// 按最近访问时间淘汰，直到总大小回到上限以内（刚写入的条目除外）
+    fn collect_evictions(&self, state: &mut CacheState, keep_key: &str) -> Vec<(String, u64)> {
+        let mut evicted = Vec::new();
+        while state.total_bytes > self.max_bytes {
+            let oldest = state
+                .entries
+                .iter()
+                .filter(|(key, _)| key.as_str() != keep_key)
+                .min_by_key(|(_, meta)| meta.last_access)
+                .map(|(key, _)| key.clone());
+            let Some(key) = oldest else {
+                break;
+            };
+            if let Some(meta) = state.entries.remove(&key) {
+                state.total_bytes = state.total_bytes.saturating_sub(meta.size);
+                evicted.push((key, meta.generation));
+            }
+        }
+        evicted
+    }
+
+    async fn write_meta(&self, key: &str, meta: &CacheEntryMeta) {
+        match serde_json::to_vec(meta) {
+            Ok(content) => {
+                if let Err(e) = write_atomic(&self.meta_path(key, meta.generation), &content).await
+                {
+                    eprintln!(
+                        "[Rust/proxy.rs image_cache] Failed to write meta for {}: {}",
+                        meta.url, e
+                    );
+                }
+            }
+            Err(e) => eprintln!(
+                "[Rust/proxy.rs image_cache] Failed to encode meta for {}: {}",
+                meta.url, e
+            ),
+        }
+    }
+
+    // 只移除指定代号的条目，期间已被新写入替换时保留新条目；返回是否移除
+    fn forget(&self, key: &str, generation: u64) -> bool {
+        let mut guard = self.state.lock().unwrap();
+        if guard.entries.get(key).map(|meta| meta.generation) != Some(generation) {
+            return false;
+        }
+        if let Some(meta) = guard.entries.remove(key) {
+            guard.total_bytes = guard.total_bytes.saturating_sub(meta.size);
+        }
+        true
+    }
+
+    pub fn stats(&self) -> ImageCacheStats {
+        let guard = self.state.lock().unwrap();
+        ImageCacheStats {
+            dir: self.dir.to_string_lossy().to_string(),
+            entries: guard.entries.len(),
+            total_bytes: guard.total_bytes,
+            max_bytes: self.max_bytes,
+            hits: guard.hits,
+            misses: guard.misses,
+            revalidated: guard.revalidated,
+            stale_served: guard.stale_served,
+        }
+    }
+
+    pub async fn clear(&self) -> Result<(), String> {
+        {
+            let mut guard = self.state.lock().unwrap();
+            *guard = CacheState::default();
+        }
+        let mut read_dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("Failed to read image cache dir: {}", e)),
+        };
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let path = entry.path();
+            if path.is_file() {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 每次写入使用独立的临时文件，rename 保证读者只会看到完整内容
+async fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let tmp_id = NEXT_TMP_ID.fetch_add(1, Ordering::Relaxed);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(
+        "{}.{}.{}.{}",
+        file_name,
+        std::process::id(),
+        tmp_id,
+        TMP_EXTENSION
+    ));
+    if let Err(e) = tokio::fs::write(&tmp_path, content).await {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+    if let Err(e) = tokio::fs::rename(&tmp_path, path).await {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn load_index(dir: &Path) -> CacheState {
+    let mut state = CacheState::default();
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return state;
+    };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        // 上次退出时残留的临时文件
+        if extension == Some(TMP_EXTENSION) {
+            let _ = std::fs::remove_file(&path);
+            continue;
+        }
+        if extension != Some(META_EXTENSION) {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let body_path = path.with_extension(BODY_EXTENSION);
+        let parsed = stem.split_once('.').and_then(|(key, generation)| {
+            let generation = generation.parse::<u64>().ok()?;
+            let meta = std::fs::read(&path)
+                .ok()
+                .and_then(|content| serde_json::from_slice::<CacheEntryMeta>(&content).ok())?;
+            (meta.generation == generation && body_path.is_file()).then(|| (key.to_string(), meta))
+        });
+        let Some((key, meta)) = parsed else {
+            // 旧格式、损坏或缺少 body 的条目
+            let _ = std::fs::remove_file(&path);
+            let _ = std::fs::remove_file(&body_path);
+            continue;
+        };
+        // 同一 URL 残留多代文件时保留最新的一代
+        let stale = match state.entries.get(&key) {
+            Some(existing) if existing.generation > meta.generation => Some(meta.generation),
+            _ => {
+                state.total_bytes += meta.size;
+                state.entries.insert(key.clone(), meta).map(|previous| {
+                    state.total_bytes = state.total_bytes.saturating_sub(previous.size);
+                    previous.generation
+                })
+            }
+        };
+        if let Some(generation) = stale {
+            let _ = std::fs::remove_file(
+                dir.join(format!("{}.{}.{}", key, generation, META_EXTENSION)),
+            );
+            let _ = std::fs::remove_file(
+                dir.join(format!("{}.{}.{}", key, generation, BODY_EXTENSION)),
+            );
+        }
+    }
+    state
+}
+
+fn header_string(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn parse_http_date(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc).timestamp())
+}
+
+/// 根据上游的 Cache-Control / Expires / Last-Modified 计算过期时间；返回 None 表示不可缓存
+fn compute_fresh_until(headers: &HeaderMap, now: i64) -> Option<i64> {
+    if let Some(cache_control) = header_string(headers, CACHE_CONTROL) {
+        let mut max_age = None;
+        for directive in cache_control.split(',') {
+            let directive = directive.trim().to_ascii_lowercase();
+            if directive == "no-store" {
+                return None;
+            }
+            if directive == "no-cache" {
+                return Some(now);
+            }
+            if let Some(value) = directive.strip_prefix("max-age=") {
+                max_age = value.trim_matches('"').parse::<i64>().ok();
+            }
+        }
+        if let Some(max_age) = max_age {
+            return Some(now + max_age.clamp(0, MAX_FRESH_SECONDS));
+        }
+    }
+
+    if let Some(expires) = header_string(headers, EXPIRES) {
+        let date = header_string(headers, DATE)
+            .and_then(|v| parse_http_date(&v))
+            .unwrap_or(now);
+        // 无法解析的 Expires（例如 "0"）视为已过期
+        let lifetime = parse_http_date(&expires)
+            .map(|expires_at| expires_at - date)
+            .unwrap_or(0);
+        return Some(now + lifetime.clamp(0, MAX_FRESH_SECONDS));
+    }
+
+    if let Some(last_modified) =
+        header_string(headers, LAST_MODIFIED).and_then(|v| parse_http_date(&v))
+    {
+        let age = (now - last_modified).max(0);
+        return Some(now + (age / 10).min(HEURISTIC_MAX_FRESH_SECONDS));
+    }
+
+    Some(now + DEFAULT_FRESH_SECONDS)
+}
+
+fn now_secs() -> i64 {
+    Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn temp_cache(name: &str, max_bytes: u64) -> ImageCache {
+        let dir = std::env::temp_dir().join(format!(
+            "dtv-image-cache-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        ImageCache::new(dir, max_bytes)
+    }
+
+    fn cache_control(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+        headers
+    }
+
+    fn file_count(cache: &ImageCache) -> usize {
+        std::fs::read_dir(&cache.dir).unwrap().count()
+    }
+
+    #[test]
+    fn computes_freshness_from_headers() {
+        let now = 1_000_000;
+        assert_eq!(compute_fresh_until(&cache_control("no-store"), now), None);
+        assert_eq!(
+            compute_fresh_until(&cache_control("no-cache"), now),
+            Some(now)
+        );
+        assert_eq!(
+            compute_fresh_until(&cache_control("public, max-age=60"), now),
+            Some(now + 60)
+        );
+        assert_eq!(
+            compute_fresh_until(&cache_control("max-age=999999999"), now),
+            Some(now + MAX_FRESH_SECONDS)
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(EXPIRES, HeaderValue::from_static("0"));
+        assert_eq!(compute_fresh_until(&headers, now), Some(now));
+        assert_eq!(
+            compute_fresh_until(&HeaderMap::new(), now),
+            Some(now + DEFAULT_FRESH_SECONDS)
+        );
+    }
+
+    #[tokio::test]
+    async fn no_store_drops_cached_copy() {
+        let cache = temp_cache("no-store", DEFAULT_MAX_BYTES);
+        let url = "https://example.com/a.png";
+        let body = Bytes::from_static(b"png");
+        assert!(
+            cache
+                .store(url, "image/png", &cache_control("max-age=60"), &body)
+                .await
+                > 0
+        );
+        assert!(matches!(cache.lookup(url).await, CacheLookup::Fresh(_)));
+
+        assert_eq!(
+            cache
+                .store(url, "image/png", &cache_control("no-store"), &body)
+                .await,
+            0
+        );
+        assert!(matches!(cache.lookup(url).await, CacheLookup::Miss));
+        assert_eq!(cache.stats().total_bytes, 0);
+        assert_eq!(file_count(&cache), 0);
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+
+    #[tokio::test]
+    async fn replacing_and_evicting_removes_only_old_generations() {
+        let cache = temp_cache("evict", 8);
+        let headers = cache_control("max-age=60");
+        cache
+            .store(
+                "https://example.com/a",
+                "image/png",
+                &headers,
+                &Bytes::from_static(b"aaaa"),
+            )
+            .await;
+        cache
+            .store(
+                "https://example.com/a",
+                "image/png",
+                &headers,
+                &Bytes::from_static(b"AAAA"),
+            )
+            .await;
+        assert_eq!(file_count(&cache), 2);
+        assert_eq!(cache.stats().total_bytes, 4);
+
+        cache
+            .store(
+                "https://example.com/b",
+                "image/png",
+                &headers,
+                &Bytes::from_static(b"bbbbbb"),
+            )
+            .await;
+        assert!(matches!(
+            cache.lookup("https://example.com/a").await,
+            CacheLookup::Miss
+        ));
+        match cache.lookup("https://example.com/b").await {
+            CacheLookup::Fresh(image) => assert_eq!(&image.bytes[..], b"bbbbbb"),
+            _ => panic!("expected a fresh entry"),
+        }
+        assert_eq!(file_count(&cache), 2);
+
+        // 重新加载索引得到同样的结果
+        let reloaded = ImageCache::new(cache.dir.clone(), 8);
+        assert_eq!(reloaded.stats().entries, 1);
+        assert_eq!(reloaded.stats().total_bytes, 6);
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+}
@@ -0,0 +1,156 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageFormat};
+use std::io::Cursor;
+
+const MAX_DIMENSION: u32 = 4096;
+const DEFAULT_JPEG_QUALITY: u8 = 82;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    WebP,
+    Png,
+    Jpeg,
+}
+
+impl OutputFormat {
+    fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "webp" => Ok(OutputFormat::WebP),
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            other => Err(format!("Unsupported image format: {}", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "webp",
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// `/image` 的 `w`/`h`/`format`/`q` 参数：按边界等比缩小（不放大）并重新编码
+#[derive(Clone, Debug)]
+pub struct ImageTransform {
+    width: Option<u32>,
+    height: Option<u32>,
+    format: Option<OutputFormat>,
+    quality: u8,
+}
+
+impl ImageTransform {
+    /// 没有任何变换参数时返回 None
+    pub fn from_query(
+        width: Option<u32>,
+        height: Option<u32>,
+        format: Option<&str>,
+        quality: Option<u8>,
+    ) -> Result<Option<Self>, String> {
+        let width = width.filter(|v| *v > 0).map(|v| v.min(MAX_DIMENSION));
+        let height = height.filter(|v| *v > 0).map(|v| v.min(MAX_DIMENSION));
+        let format = match format.map(str::trim).filter(|v| !v.is_empty()) {
+            Some(raw) => Some(OutputFormat::parse(raw)?),
+            None => None,
+        };
+        if width.is_none() && height.is_none() && format.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            width,
+            height,
+            format,
+            quality: quality
+                .filter(|v| (1..=100).contains(v))
+                .unwrap_or(DEFAULT_JPEG_QUALITY),
+        }))
+    }
+
+    /// 是否显式要求了输出格式（此时不能把原图原样返回）
+    pub fn has_format(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// 缓存中变换结果的键，与原图 URL 区分开
+    pub fn cache_key(&self, url: &str) -> String {
+        format!(
+            "{}#w={}&h={}&format={}&q={}",
+            url,
+            self.width.unwrap_or(0),
+            self.height.unwrap_or(0),
+            self.format.map(|f| f.as_str()).unwrap_or("auto"),
+            self.quality
+        )
+    }
+
+    /// 解码、缩放并编码，返回新的图片数据和 Content-Type
+    pub fn apply(&self, input: &[u8]) -> Result<(Vec<u8>, &'static str), String> {
+        let source_format = image::guess_format(input).ok();
+        let img =
+            image::load_from_memory(input).map_err(|e| format!("Failed to decode image: {}", e))?;
+        let img = self.resize(img);
+
+        // 未指定格式时尽量保留原格式，其余（gif 等）统一转为 png
+        let output_format = self.format.unwrap_or(match source_format {
+            Some(ImageFormat::Jpeg) => OutputFormat::Jpeg,
+            Some(ImageFormat::WebP) => OutputFormat::WebP,
+            _ => OutputFormat::Png,
+        });
+
+        let mut out = Vec::new();
+        match output_format {
+            OutputFormat::Png => img
+                .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+                .map_err(|e| format!("Failed to encode png: {}", e))?,
+            OutputFormat::Jpeg => JpegEncoder::new_with_quality(&mut out, self.quality)
+                .encode_image(&img.to_rgb8())
+                .map_err(|e| format!("Failed to encode jpeg: {}", e))?,
+            OutputFormat::WebP => {
+                // image 只提供无损 WebP 编码，q 参数对 WebP 无效
+                let rgba = img.to_rgba8();
+                WebPEncoder::new_lossless(&mut out)
+                    .encode(
+                        rgba.as_raw(),
+                        rgba.width(),
+                        rgba.height(),
+                        ExtendedColorType::Rgba8,
+                    )
+                    .map_err(|e| format!("Failed to encode webp: {}", e))?
+            }
+        }
+        Ok((out, output_format.content_type()))
+    }
+
+    fn resize(&self, img: DynamicImage) -> DynamicImage {
+        let (src_w, src_h) = (img.width(), img.height());
+        let max_w = self.width.unwrap_or(src_w);
+        let max_h = self.height.unwrap_or(src_h);
+        if src_w <= max_w && src_h <= max_h {
+            return img;
+        }
+        img.resize(max_w, max_h, FilterType::Triangle)
+    }
+}
+
+/// 本地解码器不支持 AVIF；按 Content-Type 或 ftyp 品牌识别
+pub fn is_avif(content_type: &str, data: &[u8]) -> bool {
+    if content_type
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with("image/avif")
+    {
+        return true;
+    }
+    data.len() >= 12 && &data[4..8] == b"ftyp" && matches!(&data[8..12], b"avif" | b"avis")
+}
@@ -0,0 +1,376 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+
+use crate::streams::ByteCounter;
+
+// 两个上游数据块之间超过该间隔即记为一次卡顿
+const STALL_THRESHOLD: Duration = Duration::from_secs(2);
+// 当前码率的统计窗口
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+const RATE_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 单个 FLV 代理会话的运行指标，全部为原子计数，可在各个 worker 间共享
+pub struct SessionMetrics {
+    pub session_id: String,
+    pub platform: String,
+    pub room_id: Option<String>,
+    started_at: Instant,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    clients: AtomicU64,
+    total_clients: AtomicU64,
+    upstream_connects: AtomicU64,
+    // 仅统计拉流任务在上游断开/失败后为同一消费者重新建立的连接
+    upstream_reconnects: AtomicU64,
+    upstream_errors: AtomicU64,
+    last_ttfb_ms: AtomicU64,
+    ttfb_total_ms: AtomicU64,
+    ttfb_samples: AtomicU64,
+    stalls: AtomicU64,
+    stall_ms: AtomicU64,
+    // (采样时间, 累计入站字节)
+    rate_samples: StdMutex<VecDeque<(Instant, u64)>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionMetricsSnapshot {
+    pub uptime_seconds: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub current_bitrate_kbps: f64,
+    pub average_bitrate_kbps: f64,
+    pub clients: u64,
+    pub total_clients: u64,
+    pub upstream_connects: u64,
+    pub upstream_reconnects: u64,
+    pub upstream_errors: u64,
+    pub last_ttfb_ms: Option<u64>,
+    pub average_ttfb_ms: Option<u64>,
+    pub stalls: u64,
+    pub stall_ms: u64,
+}
+
+// 会话缓冲区只有一条上游连接，带宽调度直接采样会话的入站字节数
+impl ByteCounter for SessionMetrics {
+    fn total_bytes(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+}
+
+impl SessionMetrics {
+    pub fn new(session_id: &str, platform: &str, room_id: Option<&str>) -> Arc<Self> {
+        Arc::new(Self {
+            session_id: session_id.to_string(),
+            platform: platform.to_string(),
+            room_id: room_id.map(str::to_string),
+            started_at: Instant::now(),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            clients: AtomicU64::new(0),
+            total_clients: AtomicU64::new(0),
+            upstream_connects: AtomicU64::new(0),
+            upstream_reconnects: AtomicU64::new(0),
+            upstream_errors: AtomicU64::new(0),
+            last_ttfb_ms: AtomicU64::new(0),
+            ttfb_total_ms: AtomicU64::new(0),
+            ttfb_samples: AtomicU64::new(0),
+            stalls: AtomicU64::new(0),
+            stall_ms: AtomicU64::new(0),
+            rate_samples: StdMutex::new(VecDeque::new()),
+        })
+    }
+
+    /// `reconnect` 为 true 表示拉流任务在上一条上游结束或失败后重连；
+    /// 新客户端各自打开的上游、清晰度切换都不算重连
+    pub fn record_upstream_connect(&self, reconnect: bool) {
+        self.upstream_connects.fetch_add(1, Ordering::Relaxed);
+        if reconnect {
+            self.upstream_reconnects.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_upstream_error(&self) {
+        self.upstream_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 新客户端接入；返回值被丢弃时客户端计数自动减一
+    pub fn client_meter(self: &Arc<Self>, upstream: Option<UpstreamProbe>) -> ClientMeter {
+        self.clients.fetch_add(1, Ordering::Relaxed);
+        self.total_clients.fetch_add(1, Ordering::Relaxed);
+        ClientMeter {
+            metrics: self.clone(),
+            upstream,
+        }
+    }
+
+    fn record_bytes_in(&self, len: usize) {
+        let total = self.bytes_in.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        let now = Instant::now();
+        let mut samples = self.rate_samples.lock().unwrap();
+        let due = !matches!(
+            samples.back(),
+            Some((at, _)) if now.duration_since(*at) < RATE_SAMPLE_INTERVAL
+        );
+        if due {
+            samples.push_back((now, total));
+        }
+        while samples.len() > 1
+            && samples
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW)
+        {
+            samples.pop_front();
+        }
+    }
+
+    fn current_bitrate_kbps(&self, bytes_in: u64) -> f64 {
+        let now = Instant::now();
+        let samples = self.rate_samples.lock().unwrap();
+        match samples.front() {
+            // 窗口内没有新数据时码率视为 0
+            Some((at, _)) if now.duration_since(*at) > RATE_WINDOW * 2 => 0.0,
+            Some((at, base)) => {
+                let elapsed = now.duration_since(*at).as_secs_f64();
+                if elapsed < 0.2 {
+                    0.0
+                } else {
+                    (bytes_in.saturating_sub(*base) as f64 * 8.0) / elapsed / 1000.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn snapshot(&self) -> SessionMetricsSnapshot {
+        let uptime = self.started_at.elapsed();
+        let bytes_in = self.bytes_in.load(Ordering::Relaxed);
+        let ttfb_samples = self.ttfb_samples.load(Ordering::Relaxed);
+        let uptime_secs = uptime.as_secs_f64();
+        SessionMetricsSnapshot {
+            uptime_seconds: uptime.as_secs(),
+            bytes_in,
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            current_bitrate_kbps: self.current_bitrate_kbps(bytes_in),
+            average_bitrate_kbps: if uptime_secs > 0.0 {
+                bytes_in as f64 * 8.0 / uptime_secs / 1000.0
+            } else {
+                0.0
+            },
+            clients: self.clients.load(Ordering::Relaxed),
+            total_clients: self.total_clients.load(Ordering::Relaxed),
+            upstream_connects: self.upstream_connects.load(Ordering::Relaxed),
+            upstream_reconnects: self.upstream_reconnects.load(Ordering::Relaxed),
+            upstream_errors: self.upstream_errors.load(Ordering::Relaxed),
+            last_ttfb_ms: (ttfb_samples > 0).then(|| self.last_ttfb_ms.load(Ordering::Relaxed)),
+            average_ttfb_ms: (ttfb_samples > 0)
+                .then(|| self.ttfb_total_ms.load(Ordering::Relaxed) / ttfb_samples),
+            stalls: self.stalls.load(Ordering::Relaxed),
+            stall_ms: self.stall_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 统计上游首字节时间与数据块间隔（卡顿）
+pub struct UpstreamProbe {
+    started: Instant,
+    last_chunk: Option<Instant>,
+}
+
+impl UpstreamProbe {
+    /// `started` 为发出上游请求的时刻
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            last_chunk: None,
+        }
+    }
+
+    pub fn on_chunk(&mut self, metrics: &SessionMetrics, len: usize) {
+        let now = Instant::now();
+        match self.last_chunk {
+            None => {
+                let ttfb = now.duration_since(self.started).as_millis() as u64;
+                metrics.last_ttfb_ms.store(ttfb, Ordering::Relaxed);
+                metrics.ttfb_total_ms.fetch_add(ttfb, Ordering::Relaxed);
+                metrics.ttfb_samples.fetch_add(1, Ordering::Relaxed);
+            }
+            Some(last) => {
+                let gap = now.duration_since(last);
+                if gap >= STALL_THRESHOLD {
+                    metrics.stalls.fetch_add(1, Ordering::Relaxed);
+                    metrics
+                        .stall_ms
+                        .fetch_add(gap.as_millis() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+        self.last_chunk = Some(now);
+        metrics.record_bytes_in(len);
+    }
+}
+
+/// 一个下游客户端的计量；直连上游时同时携带上游探针
+pub struct ClientMeter {
+    metrics: Arc<SessionMetrics>,
+    upstream: Option<UpstreamProbe>,
+}
+
+impl ClientMeter {
+    pub fn on_chunk(&mut self, len: usize) {
+        if let Some(probe) = self.upstream.as_mut() {
+            probe.on_chunk(&self.metrics, len);
+        }
+        self.metrics
+            .bytes_out
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ClientMeter {
+    fn drop(&mut self) {
+        self.metrics.clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 所有会话指标的登记表；只保存弱引用，会话结束后自动消失
+#[derive(Default)]
+pub struct MetricsRegistry {
+    sessions: StdMutex<Vec<Weak<SessionMetrics>>>,
+}
+
+impl MetricsRegistry {
+    pub fn register(&self, metrics: &Arc<SessionMetrics>) {
+        self.sessions.lock().unwrap().push(Arc::downgrade(metrics));
+    }
+
+    pub fn live(&self) -> Vec<Arc<SessionMetrics>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|weak| weak.strong_count() > 0);
+        sessions.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Prometheus 文本格式（text/plain; version=0.0.4）
+    pub fn render_prometheus(&self) -> String {
+        let sessions: Vec<(Arc<SessionMetrics>, SessionMetricsSnapshot)> = self
+            .live()
+            .into_iter()
+            .map(|m| {
+                let snapshot = m.snapshot();
+                (m, snapshot)
+            })
+            .collect();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP dtv_proxy_sessions Active FLV proxy sessions.");
+        let _ = writeln!(out, "# TYPE dtv_proxy_sessions gauge");
+        let _ = writeln!(out, "dtv_proxy_sessions {}", sessions.len());
+
+        type Getter = fn(&SessionMetricsSnapshot) -> f64;
+        let families: &[(&str, &str, &str, Getter)] = &[
+            (
+                "dtv_proxy_bytes_in_total",
+                "counter",
+                "Bytes received from upstream.",
+                |s| s.bytes_in as f64,
+            ),
+            (
+                "dtv_proxy_bytes_out_total",
+                "counter",
+                "Bytes sent to clients.",
+                |s| s.bytes_out as f64,
+            ),
+            (
+                "dtv_proxy_bitrate_kbps",
+                "gauge",
+                "Upstream bitrate over the last few seconds.",
+                |s| s.current_bitrate_kbps,
+            ),
+            (
+                "dtv_proxy_average_bitrate_kbps",
+                "gauge",
+                "Upstream bitrate averaged over the session.",
+                |s| s.average_bitrate_kbps,
+            ),
+            ("dtv_proxy_clients", "gauge", "Connected clients.", |s| {
+                s.clients as f64
+            }),
+            (
+                "dtv_proxy_clients_total",
+                "counter",
+                "Clients served since the session started.",
+                |s| s.total_clients as f64,
+            ),
+            (
+                "dtv_proxy_upstream_connects_total",
+                "counter",
+                "Successful upstream connections.",
+                |s| s.upstream_connects as f64,
+            ),
+            (
+                "dtv_proxy_upstream_reconnects_total",
+                "counter",
+                "Upstream connections re-established by the session pump after the previous one dropped or failed.",
+                |s| s.upstream_reconnects as f64,
+            ),
+            (
+                "dtv_proxy_upstream_errors_total",
+                "counter",
+                "Failed upstream requests.",
+                |s| s.upstream_errors as f64,
+            ),
+            (
+                "dtv_proxy_ttfb_ms",
+                "gauge",
+                "Time to first byte of the latest upstream connection.",
+                |s| s.last_ttfb_ms.unwrap_or(0) as f64,
+            ),
+            (
+                "dtv_proxy_stalls_total",
+                "counter",
+                "Gaps between upstream chunks longer than 2s.",
+                |s| s.stalls as f64,
+            ),
+            (
+                "dtv_proxy_stall_ms_total",
+                "counter",
+                "Total duration of upstream stalls.",
+                |s| s.stall_ms as f64,
+            ),
+            (
+                "dtv_proxy_uptime_seconds",
+                "gauge",
+                "Session uptime.",
+                |s| s.uptime_seconds as f64,
+            ),
+        ];
+
+        for (name, kind, help, getter) in families {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (metrics, snapshot) in &sessions {
+                let _ = writeln!(
+                    out,
+                    "{}{{session=\"{}\",platform=\"{}\",room=\"{}\"}} {}",
+                    name,
+                    escape_label(&metrics.session_id),
+                    escape_label(&metrics.platform),
+                    escape_label(metrics.room_id.as_deref().unwrap_or("")),
+                    getter(snapshot)
+                );
+            }
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,219 @@
+use super::security::ProxyToken;
+use actix_web::{web, HttpRequest, HttpResponse};
+use bytes::Bytes;
+use futures_util::Stream;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const READ_CHUNK_SIZE: usize = 256 * 1024;
+
+// 路径段编码时保留 RFC 3986 的非保留字符
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn content_type_for(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "flv" => Some("video/x-flv"),
+        "mp4" | "m4v" => Some("video/mp4"),
+        "ts" => Some("video/mp2t"),
+        _ => None,
+    }
+}
+
+/// 可通过 `/{token}/recordings/{root}/{path}` 访问的录制目录；只追加不删除，序号在本次运行内稳定
+#[derive(Default)]
+pub struct RecordingLibrary {
+    roots: StdMutex<Vec<PathBuf>>,
+}
+
+impl RecordingLibrary {
+    fn register_root(&self, dir: &Path) -> Option<usize> {
+        let dir = std::fs::canonicalize(dir).ok()?;
+        let mut roots = self.roots.lock().unwrap();
+        if let Some(index) = roots.iter().position(|root| *root == dir) {
+            return Some(index);
+        }
+        roots.push(dir);
+        Some(roots.len() - 1)
+    }
+
+    /// 校验文件位于某个录制目录之内，返回 URL 中使用的 `{root}/{path}` 部分
+    pub fn url_path_for(&self, file: &Path, candidate_roots: &[PathBuf]) -> Result<String, String> {
+        let file = std::fs::canonicalize(file)
+            .map_err(|e| format!("Recording file {} not found: {}", file.display(), e))?;
+        if !file.is_file() || content_type_for(&file).is_none() {
+            return Err(format!("{} is not a playable recording", file.display()));
+        }
+        for root in candidate_roots {
+            let Some(index) = self.register_root(root) else {
+                continue;
+            };
+            let canonical_root = self.roots.lock().unwrap()[index].clone();
+            if let Ok(relative) = file.strip_prefix(&canonical_root) {
+                let segments: Vec<String> = relative
+                    .components()
+                    .map(|c| {
+                        utf8_percent_encode(&c.as_os_str().to_string_lossy(), PATH_SEGMENT)
+                            .to_string()
+                    })
+                    .collect();
+                return Ok(format!("{}/{}", index, segments.join("/")));
+            }
+        }
+        Err(format!(
+            "{} is not inside a recording output directory",
+            file.display()
+        ))
+    }
+
+    /// 把请求路径映射回磁盘文件，拒绝 `..`、绝对路径以及通过符号链接逃出目录的情况
+    fn resolve(&self, root_index: usize, encoded_path: &str) -> Option<PathBuf> {
+        let root = self.roots.lock().unwrap().get(root_index)?.clone();
+        let decoded = percent_decode_str(encoded_path).decode_utf8().ok()?;
+        let mut path = root.clone();
+        for segment in decoded.split('/') {
+            if segment.is_empty() || segment.contains(['\\', ':', '\0']) {
+                return None;
+            }
+            let mut components = Path::new(segment).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(part)), None) => path.push(part),
+                _ => return None,
+            }
+        }
+        let path = std::fs::canonicalize(path).ok()?;
+        (path.starts_with(&root) && path.is_file()).then_some(path)
+    }
+}
+
+enum RangeRequest {
+    Full,
+    // 闭区间 [start, end]
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// 只支持单个 `bytes=` 区间；多区间请求按完整文件返回
+fn parse_range(header: Option<&str>, len: u64) -> RangeRequest {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+    let range = match (start.trim(), end.trim()) {
+        ("", "") => return RangeRequest::Full,
+        // bytes=-N：最后 N 个字节
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(n) => (len.saturating_sub(n), len.saturating_sub(1)),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(s) => (s, len.saturating_sub(1)),
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(s), Ok(e)) if s <= e => (s, e.min(len.saturating_sub(1))),
+            _ => return RangeRequest::Full,
+        },
+    };
+    if len == 0 || range.0 >= len {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Partial(range.0, range.1)
+}
+
+fn file_stream(
+    file: tokio::fs::File,
+    remaining: u64,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    futures_util::stream::unfold((file, remaining), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return None;
+        }
+        let mut buf = vec![0u8; remaining.min(READ_CHUNK_SIZE as u64) as usize];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), (file, remaining - n as u64)))
+            }
+            Err(e) => Some((Err(e), (file, 0))),
+        }
+    })
+}
+
+// 录制文件回放：`/{token}/recordings/{root}/{path}`，支持 Range 以便播放器拖动
+pub async fn recording_file_handler(
+    _token: ProxyToken,
+    req: HttpRequest,
+    library: web::Data<RecordingLibrary>,
+) -> HttpResponse {
+    let root_index = req
+        .match_info()
+        .get("root")
+        .and_then(|v| v.parse::<usize>().ok());
+    let encoded_path = req.match_info().get("path").unwrap_or_default();
+    let Some(path) = root_index.and_then(|index| library.resolve(index, encoded_path)) else {
+        return HttpResponse::NotFound().body("Recording not found");
+    };
+    let Some(content_type) = content_type_for(&path) else {
+        return HttpResponse::Forbidden().body("Unsupported recording type");
+    };
+
+    let mut file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!(
+                "[Rust/proxy.rs recordings] Failed to open {}: {}",
+                path.display(),
+                e
+            );
+            return HttpResponse::NotFound().body("Recording not found");
+        }
+    };
+    // 录制中的文件仍在增长，以打开时的长度为准
+    let len = match file.metadata().await {
+        Ok(meta) => meta.len(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let range_header = req.headers().get("Range").and_then(|v| v.to_str().ok());
+    let (mut builder, start, end) = match parse_range(range_header, len) {
+        RangeRequest::Full => (HttpResponse::Ok(), 0, len.saturating_sub(1)),
+        RangeRequest::Partial(start, end) => {
+            let mut builder = HttpResponse::PartialContent();
+            builder.insert_header(("Content-Range", format!("bytes {}-{}/{}", start, end, len)));
+            (builder, start, end)
+        }
+        RangeRequest::Unsatisfiable => {
+            return HttpResponse::RangeNotSatisfiable()
+                .insert_header(("Content-Range", format!("bytes */{}", len)))
+                .finish();
+        }
+    };
+
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+    if start > 0 {
+        if let Err(e) = file.seek(SeekFrom::Start(start)).await {
+            return HttpResponse::InternalServerError().body(e.to_string());
+        }
+    }
+    builder
+        .content_type(content_type)
+        .insert_header(("Accept-Ranges", "bytes"))
+        .insert_header(("Cache-Control", "no-cache"))
+        .no_chunking(body_len)
+        .streaming(file_stream(file, body_len))
+}
@@ -0,0 +1,495 @@
+use crate::platforms::common::header_rules::host_matches;
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::Serialize;
+use std::future::{ready, Ready};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+/// 代理允许访问的上游主机；`*.example.com` 同时匹配 `example.com` 本身
+const DEFAULT_ALLOWED_HOSTS: &[&str] = &[
+    // Bilibili
+    "*.bilibili.com",
+    "*.hdslb.com",
+    "*.biliimg.com",
+    "*.bilivideo.com",
+    "*.bilivideo.cn",
+    // 海外 CDN 只放行 B 站自己的 Akamai 镜像，不能整体放行 akamaized.net
+    "upos-hz-mirrorakam.akamaized.net",
+    "upos-sz-mirrorakam.akamaized.net",
+    "upos-bstar-mirrorakam.akamaized.net",
+    "upos-bstar1-mirrorakam.akamaized.net",
+    // Douyu
+    "*.douyu.com",
+    "*.douyucdn.cn",
+    "*.douyucdn2.cn",
+    // Huya
+    "*.huya.com",
+    "*.hy-cdn.com",
+    "*.huyaimg.com",
+    "*.msstatic.com",
+    // Douyin
+    "*.douyin.com",
+    "*.douyinpic.com",
+    "*.douyincdn.com",
+    "*.douyinliving.com",
+    "*.douyinvod.com",
+    "*.byteimg.com",
+    "*.pstatp.com",
+    "*.bytecdn.cn",
+    "*.amemv.com",
+];
+
+/// 本地代理的访问控制：每次启动随机生成的访问令牌、上游主机白名单与局域网共享设置
+pub struct ProxyAccess {
+    token: String,
+    extra_allowed_hosts: StdMutex<Vec<String>>,
+    lan_bind_ip: StdMutex<Option<IpAddr>>,
+    // 198.18.0.0/15 是 Clash/Surge 等 TUN 模式的 fake-ip 网段，默认放行
+    block_fake_ip_range: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyAccessInfo {
+    pub token: String,
+    pub allowed_hosts: Vec<String>,
+    pub lan_bind_ip: Option<String>,
+    pub block_fake_ip_range: bool,
+}
+
+impl Default for ProxyAccess {
+    fn default() -> Self {
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self {
+            token: hex::encode(bytes),
+            extra_allowed_hosts: StdMutex::new(Vec::new()),
+            lan_bind_ip: StdMutex::new(None),
+            block_fake_ip_range: AtomicBool::new(false),
+        }
+    }
+}
+
+impl ProxyAccess {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn info(&self) -> ProxyAccessInfo {
+        let mut allowed_hosts: Vec<String> = DEFAULT_ALLOWED_HOSTS
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        allowed_hosts.extend(self.extra_allowed_hosts.lock().unwrap().iter().cloned());
+        ProxyAccessInfo {
+            token: self.token.clone(),
+            allowed_hosts,
+            lan_bind_ip: self.lan_bind_ip().map(|ip| ip.to_string()),
+            block_fake_ip_range: self.block_fake_ip_range.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn set_block_fake_ip_range(&self, enabled: bool) {
+        self.block_fake_ip_range.store(enabled, Ordering::Relaxed);
+    }
+
+    fn ip_allowed(&self, ip: IpAddr) -> bool {
+        is_public_ip(ip, self.block_fake_ip_range.load(Ordering::Relaxed))
+    }
+
+    pub fn set_extra_allowed_hosts(&self, patterns: Vec<String>) {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| p.trim().trim_end_matches('.').to_ascii_lowercase())
+            .filter(|p| !p.is_empty())
+            .collect();
+        *self.extra_allowed_hosts.lock().unwrap() = patterns;
+    }
+
+    pub fn lan_bind_ip(&self) -> Option<IpAddr> {
+        *self.lan_bind_ip.lock().unwrap()
+    }
+
+    /// 开启/关闭局域网共享；未指定网卡地址时自动探测默认出口地址
+    pub fn set_lan_sharing(&self, enabled: bool, interface_ip: Option<&str>) -> Result<(), String> {
+        let ip = if !enabled {
+            None
+        } else {
+            match interface_ip.map(str::trim).filter(|v| !v.is_empty()) {
+                Some(raw) => Some(
+                    raw.parse::<IpAddr>()
+                        .map_err(|e| format!("Invalid interface ip {}: {}", raw, e))?,
+                ),
+                None => Some(
+                    detect_lan_ip()
+                        .ok_or_else(|| "Failed to detect LAN interface address".to_string())?,
+                ),
+            }
+        };
+        *self.lan_bind_ip.lock().unwrap() = ip;
+        Ok(())
+    }
+
+    /// 健康检查的应答：md5(令牌:随机数)，不泄露令牌
+    pub fn health_proof(&self, nonce: &str) -> String {
+        use md5::{Digest, Md5};
+
+        let mut hasher = Md5::new();
+        hasher.update(self.token.as_bytes());
+        hasher.update(b":");
+        hasher.update(nonce.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    fn token_matches(&self, candidate: &str) -> bool {
+        constant_time_eq(self.token.as_bytes(), candidate.as_bytes())
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let extra = self.extra_allowed_hosts.lock().unwrap();
+        DEFAULT_ALLOWED_HOSTS
+            .iter()
+            .copied()
+            .chain(extra.iter().map(String::as_str))
+            .any(|pattern| host_matches(pattern, &host))
+    }
+
+    /// 不涉及 DNS 的检查：协议、白名单与 IP 字面量地址；也用于重定向目标。
+    /// 配置了出站代理时域名由代理解析、`PublicOnlyResolver` 不生效，这里是唯一的地址检查
+    pub fn check_url_static(&self, url: &reqwest::Url) -> Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Unsupported upstream scheme: {}", url.scheme()));
+        }
+        let host = match url.host() {
+            Some(url::Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                // 只在本机解析的名字，经代理时会指向代理自身
+                if domain == "localhost" || domain.ends_with(".localhost") {
+                    return Err(format!("Upstream address {} is not allowed", domain));
+                }
+                domain
+            }
+            Some(url::Host::Ipv4(ip)) => {
+                if !self.ip_allowed(IpAddr::V4(ip)) {
+                    return Err(format!("Upstream address {} is not allowed", ip));
+                }
+                ip.to_string()
+            }
+            Some(url::Host::Ipv6(ip)) => {
+                if !self.ip_allowed(IpAddr::V6(ip)) {
+                    return Err(format!("Upstream address {} is not allowed", ip));
+                }
+                ip.to_string()
+            }
+            None => return Err("Upstream url has no host".to_string()),
+        };
+        if !self.host_allowed(&host) {
+            return Err(format!("Upstream host {} is not in the allowlist", host));
+        }
+        Ok(())
+    }
+
+    /// 发起上游请求前的检查；域名解析结果由客户端的 `PublicOnlyResolver` 在连接时过滤
+    pub fn check_upstream_url(&self, raw_url: &str) -> Result<reqwest::Url, String> {
+        let url = reqwest::Url::parse(raw_url)
+            .map_err(|e| format!("Invalid upstream url {}: {}", raw_url, e))?;
+        self.check_url_static(&url)?;
+        Ok(url)
+    }
+}
+
+/// 只返回公网地址的 DNS 解析器。挂在代理用客户端上，使检查作用于真正用来连接的地址，
+/// 不存在先检查、后解析之间的 DNS rebinding 窗口；重定向的每一跳同样经过这里
+pub struct PublicOnlyResolver {
+    access: Arc<ProxyAccess>,
+}
+
+impl PublicOnlyResolver {
+    pub fn new(access: Arc<ProxyAccess>) -> Self {
+        Self { access }
+    }
+}
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let access = self.access.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let resolved: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let allowed: Vec<SocketAddr> = resolved
+                .iter()
+                .copied()
+                .filter(|addr| access.ip_allowed(addr.ip()))
+                .collect();
+            if allowed.is_empty() {
+                let rejected = resolved
+                    .first()
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or_else(|| "no address".to_string());
+                return Err(format!(
+                    "Upstream host {} resolves to non-public address {}",
+                    host, rejected
+                )
+                .into());
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 构建代理用 reqwest 客户端的重定向策略：每一跳都要重新过白名单检查
+pub fn redirect_policy(access: Arc<ProxyAccess>) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 {
+            return attempt.error("too many redirects");
+        }
+        match access.check_url_static(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    })
+}
+
+fn is_public_ip(ip: IpAddr, block_fake_ip_range: bool) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4, block_fake_ip_range),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ipv4(v4, block_fake_ip_range);
+            }
+            is_public_ipv6(v6)
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr, block_fake_ip_range: bool) -> bool {
+    let octets = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || octets[0] == 0
+        // 100.64.0.0/10 运营商级 NAT
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        // 198.18.0.0/15 基准测试网段，同时也是代理软件 fake-ip 的默认网段
+        || (block_fake_ip_range && octets[0] == 198 && (octets[1] & 0xfe) == 18)
+        // 240.0.0.0/4 保留地址
+        || octets[0] >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // fc00::/7 唯一本地地址
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 链路本地地址
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// 通过 UDP "连接" 公网地址获取默认出口网卡的地址（不会真正发包）
+fn detect_lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("223.5.5.5", 53)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_unspecified() || ip.is_loopback() {
+        None
+    } else {
+        Some(ip)
+    }
+}
+
+fn constant_time_eq(expected: &[u8], candidate: &[u8]) -> bool {
+    if expected.len() != candidate.len() {
+        return false;
+    }
+    expected
+        .iter()
+        .zip(candidate)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// 会话级的投屏令牌：只在签发它的会话的 `/live.flv` 上有效，会话停止时吊销。
+/// 发给局域网设备（电视、DLNA 渲染器）的地址使用它而不是全局令牌
+pub struct SessionCastToken(StdMutex<Option<String>>);
+
+impl SessionCastToken {
+    pub fn issue() -> Self {
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(StdMutex::new(Some(hex::encode(bytes))))
+    }
+
+    /// 已吊销时返回 None
+    pub fn token(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn revoke(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    fn matches(&self, candidate: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_deref()
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), candidate.as_bytes()))
+    }
+}
+
+/// 代理路由统一挂在 `/{token}` 之下；令牌不匹配的请求直接 403
+pub struct ProxyToken;
+
+impl FromRequest for ProxyToken {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let Some(access) = req.app_data::<web::Data<ProxyAccess>>() else {
+            return ready(Err(actix_web::error::ErrorInternalServerError(
+                "proxy access policy missing",
+            )));
+        };
+        let token = req.match_info().get("token").unwrap_or_default();
+        if access.token_matches(token) {
+            ready(Ok(ProxyToken))
+        } else {
+            ready(Err(actix_web::error::ErrorForbidden("invalid proxy token")))
+        }
+    }
+}
+
+/// 会话 `/live.flv` 的鉴权：接受全局令牌，或本会话（且未吊销）的投屏令牌
+pub struct LiveFlvToken;
+
+impl FromRequest for LiveFlvToken {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let Some(access) = req.app_data::<web::Data<ProxyAccess>>() else {
+            return ready(Err(actix_web::error::ErrorInternalServerError(
+                "proxy access policy missing",
+            )));
+        };
+        let token = req.match_info().get("token").unwrap_or_default();
+        let cast_token_matches = req
+            .app_data::<web::Data<SessionCastToken>>()
+            .is_some_and(|cast| cast.matches(token));
+        if access.token_matches(token) || cast_token_matches {
+            ready(Ok(LiveFlvToken))
+        } else {
+            ready(Err(actix_web::error::ErrorForbidden("invalid proxy token")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(access: &ProxyAccess, url: &str) -> Result<reqwest::Url, String> {
+        access.check_upstream_url(url)
+    }
+
+    #[test]
+    fn allowlist_matches_platform_hosts_only() {
+        let access = ProxyAccess::default();
+        assert!(check(&access, "https://cn-gotcha01.bilivideo.com/live/x.flv").is_ok());
+        assert!(check(&access, "https://upos-sz-mirrorakam.akamaized.net/a.flv").is_ok());
+        assert!(check(&access, "https://evil.akamaized.net/a.flv").is_err());
+        assert!(check(&access, "https://douyu.com.evil.com/").is_err());
+        assert!(check(&access, "ftp://www.douyu.com/").is_err());
+
+        access.set_extra_allowed_hosts(vec![" *.Example.COM. ".to_string()]);
+        assert!(check(&access, "https://img.example.com/a.png").is_ok());
+    }
+
+    #[test]
+    fn rejects_private_and_local_targets_without_dns() {
+        let access = ProxyAccess::default();
+        access.set_extra_allowed_hosts(vec!["localhost".to_string(), "*.localhost".to_string()]);
+        for url in [
+            "http://127.0.0.1:34719/",
+            "http://10.0.0.1/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://localhost/",
+            "http://api.localhost/",
+        ] {
+            assert!(check(&access, url).is_err(), "{} should be rejected", url);
+        }
+    }
+
+    #[test]
+    fn classifies_addresses() {
+        assert!(is_public_ip("223.5.5.5".parse().unwrap(), false));
+        assert!(is_public_ip("2400:3200::1".parse().unwrap(), false));
+        assert!(!is_public_ip("100.64.0.1".parse().unwrap(), false));
+        assert!(!is_public_ip("fe80::1".parse().unwrap(), false));
+        // fake-ip 网段默认放行
+        assert!(is_public_ip("198.18.0.1".parse().unwrap(), false));
+        assert!(!is_public_ip("198.19.255.1".parse().unwrap(), true));
+    }
+
+    #[test]
+    fn health_proof_depends_on_token_and_nonce() {
+        let access = ProxyAccess::default();
+        let other = ProxyAccess::default();
+        assert_eq!(access.health_proof("a"), access.health_proof("a"));
+        assert_ne!(access.health_proof("a"), access.health_proof("b"));
+        assert_ne!(access.health_proof("a"), other.health_proof("a"));
+        assert!(!access.health_proof("a").contains(access.token()));
+    }
+
+    #[actix_web::test]
+    async fn cast_token_is_scoped_and_revocable() {
+        let access = web::Data::new(ProxyAccess::default());
+        let cast = web::Data::new(SessionCastToken::issue());
+        let cast_token = cast.token().unwrap();
+        let other_session = SessionCastToken::issue();
+
+        let live_flv = |token: &str, with_cast: bool| {
+            let mut req = actix_web::test::TestRequest::default()
+                .param("token", token.to_string())
+                .app_data(access.clone());
+            if with_cast {
+                req = req.app_data(cast.clone());
+            }
+            req.to_http_request()
+        };
+        let accepts = |req: HttpRequest| async move { LiveFlvToken::extract(&req).await.is_ok() };
+        let proxy_accepts =
+            |req: HttpRequest| async move { ProxyToken::extract(&req).await.is_ok() };
+
+        assert!(accepts(live_flv(access.token(), true)).await);
+        assert!(accepts(live_flv(&cast_token, true)).await);
+        // 其他会话或其他路由（ProxyToken）不接受投屏令牌
+        assert!(!accepts(live_flv(&other_session.token().unwrap(), true)).await);
+        assert!(!accepts(live_flv(&cast_token, false)).await);
+        assert!(!proxy_accepts(live_flv(&cast_token, true)).await);
+
+        cast.revoke();
+        assert!(cast.token().is_none());
+        assert!(!accepts(live_flv(&cast_token, true)).await);
+        assert!(accepts(live_flv(access.token(), true)).await);
+    }
+}
@@ -0,0 +1,714 @@
+use super::flv::{FlvDemuxer, FlvEvent, FlvTag};
+use super::metrics::{SessionMetrics, UpstreamProbe};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify};
+
+const MIN_WINDOW_SECONDS: u32 = 10;
+const MAX_WINDOW_SECONDS: u32 = 6 * 60 * 60;
+const DEFAULT_MEMORY_MAX_MB: u64 = 512;
+const DEFAULT_DISK_MAX_MB: u64 = 4096;
+// 纯音频流没有关键帧，按固定时长切分可寻址的片段
+const AUDIO_ONLY_GOP_MS: u64 = 1000;
+// 上游重连后新时间线与旧时间线之间留出的间隔
+const RECONNECT_TIMESTAMP_GAP_MS: u64 = 40;
+const READER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeShiftStorage {
+    Memory,
+    Disk,
+}
+
+impl TimeShiftStorage {
+    pub fn parse(input: Option<&str>) -> Result<Self, String> {
+        match input.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("memory") => Ok(TimeShiftStorage::Memory),
+            Some("disk") => Ok(TimeShiftStorage::Disk),
+            Some(other) => Err(format!("Unsupported time-shift storage: {}", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimeShiftStorage::Memory => "memory",
+            TimeShiftStorage::Disk => "disk",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TimeShiftConfig {
+    pub window_seconds: u32,
+    pub storage: TimeShiftStorage,
+    pub max_bytes: u64,
+}
+
+impl TimeShiftConfig {
+    pub fn new(window_seconds: u32, storage: TimeShiftStorage, max_mb: Option<u64>) -> Self {
+        let default_mb = match storage {
+            TimeShiftStorage::Memory => DEFAULT_MEMORY_MAX_MB,
+            TimeShiftStorage::Disk => DEFAULT_DISK_MAX_MB,
+        };
+        let max_mb = max_mb.filter(|v| *v > 0).unwrap_or(default_mb);
+        Self {
+            window_seconds: window_seconds.clamp(MIN_WINDOW_SECONDS, MAX_WINDOW_SECONDS),
+            storage,
+            max_bytes: max_mb.saturating_mul(1024 * 1024),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeShiftStatus {
+    pub storage: String,
+    pub window_seconds: u32,
+    pub available_seconds: u64,
+    pub live_timestamp_ms: u64,
+    pub buffered_bytes: u64,
+    pub keyframe_count: usize,
+}
+
+enum GopData {
+    Memory(Vec<Bytes>),
+    // 已落盘：文件内按顺序存放的各 tag 的 (offset, len)
+    Disk {
+        path: PathBuf,
+        offsets: Vec<(usize, usize)>,
+    },
+}
+
+/// 以关键帧起始的一组 tag，是回看定位的最小单位
+struct Gop {
+    first_seq: u64,
+    start_ms: u64,
+    end_ms: u64,
+    bytes: u64,
+    tag_count: usize,
+    // 读者从该 GOP 开始播放时需要先拿到的 metadata 与解码配置
+    metadata: Option<Bytes>,
+    video_sequence_header: Option<Bytes>,
+    audio_sequence_header: Option<Bytes>,
+    data: GopData,
+}
+
+struct SealedGop {
+    first_seq: u64,
+    tags: Vec<Bytes>,
+}
+
+#[derive(Default)]
+struct BufferInner {
+    header: Option<Bytes>,
+    has_video: bool,
+    metadata: Option<Bytes>,
+    video_sequence_header: Option<Bytes>,
+    audio_sequence_header: Option<Bytes>,
+    gops: VecDeque<Gop>,
+    next_seq: u64,
+    total_bytes: u64,
+    // 当前上游连接的时间线映射：session_ms = base + (raw_ts - first_raw_ts)
+    connection_base_ms: u64,
+    connection_first_ts: Option<u32>,
+    live_ms: u64,
+    closed: bool,
+}
+
+enum ReadOutcome {
+    Tags(Vec<Bytes>),
+    DiskTags {
+        path: PathBuf,
+        offsets: Vec<(usize, usize)>,
+    },
+    Pending,
+    Closed,
+}
+
+/// 每个 FLV 代理会话独立的回看缓冲区，按关键帧时间戳建立索引
+pub struct TimeShiftBuffer {
+    config: TimeShiftConfig,
+    dir: Option<PathBuf>,
+    inner: StdMutex<BufferInner>,
+    notify: Notify,
+}
+
+impl TimeShiftBuffer {
+    pub fn new(session_id: &str, config: TimeShiftConfig) -> Result<Arc<Self>, String> {
+        let dir = match config.storage {
+            TimeShiftStorage::Memory => None,
+            TimeShiftStorage::Disk => {
+                let dir = std::env::temp_dir().join("dtv-timeshift").join(session_id);
+                std::fs::create_dir_all(&dir)
+                    .map_err(|e| format!("Failed to create time-shift dir: {}", e))?;
+                Some(dir)
+            }
+        };
+        Ok(Arc::new(Self {
+            config,
+            dir,
+            inner: StdMutex::new(BufferInner::default()),
+            notify: Notify::new(),
+        }))
+    }
+
+    pub fn status(&self) -> TimeShiftStatus {
+        let guard = self.inner.lock().unwrap();
+        let available_seconds = guard
+            .gops
+            .front()
+            .map(|gop| guard.live_ms.saturating_sub(gop.start_ms) / 1000)
+            .unwrap_or(0);
+        TimeShiftStatus {
+            storage: self.config.storage.as_str().to_string(),
+            window_seconds: self.config.window_seconds,
+            available_seconds,
+            live_timestamp_ms: guard.live_ms,
+            buffered_bytes: guard.total_bytes,
+            keyframe_count: guard.gops.len(),
+        }
+    }
+
+    /// 新的上游连接开始：后续 tag 的时间戳接在已有时间线之后
+    fn begin_connection(&self, header: Bytes, has_video: bool) {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.header.is_none() {
+            guard.header = Some(header);
+        }
+        guard.has_video = has_video;
+        guard.connection_base_ms = if guard.gops.is_empty() {
+            guard.live_ms
+        } else {
+            guard.live_ms + RECONNECT_TIMESTAMP_GAP_MS
+        };
+        guard.connection_first_ts = None;
+    }
+
+    /// 追加一个 tag；若因此封存了上一个 GOP 且需要落盘，返回它
+    fn push_tag(&self, tag: FlvTag) -> Option<SealedGop> {
+        let mut guard = self.inner.lock().unwrap();
+        let first_ts = *guard.connection_first_ts.get_or_insert(tag.timestamp);
+        let session_ms =
+            guard.connection_base_ms + u64::from(tag.timestamp.saturating_sub(first_ts));
+        let tag = tag.with_timestamp(session_ms as u32);
+
+        if tag.is_script() {
+            guard.metadata = Some(tag.raw);
+            return None;
+        }
+        if tag.is_sequence_header() {
+            if tag.is_video() {
+                guard.video_sequence_header = Some(tag.raw.clone());
+            } else {
+                guard.audio_sequence_header = Some(tag.raw.clone());
+            }
+        }
+
+        guard.live_ms = guard.live_ms.max(session_ms);
+
+        let starts_gop = if guard.has_video {
+            tag.is_keyframe()
+        } else {
+            tag.is_audio()
+                && guard
+                    .gops
+                    .back()
+                    .map(|gop| session_ms.saturating_sub(gop.start_ms) >= AUDIO_ONLY_GOP_MS)
+                    .unwrap_or(true)
+        };
+
+        let mut sealed = None;
+        if starts_gop {
+            if self.config.storage == TimeShiftStorage::Disk {
+                if let Some(Gop {
+                    first_seq,
+                    data: GopData::Memory(tags),
+                    ..
+                }) = guard.gops.back()
+                {
+                    sealed = Some(SealedGop {
+                        first_seq: *first_seq,
+                        tags: tags.clone(),
+                    });
+                }
+            }
+            let gop = Gop {
+                first_seq: guard.next_seq,
+                start_ms: session_ms,
+                end_ms: session_ms,
+                bytes: 0,
+                tag_count: 0,
+                metadata: guard.metadata.clone(),
+                video_sequence_header: guard.video_sequence_header.clone(),
+                audio_sequence_header: guard.audio_sequence_header.clone(),
+                data: GopData::Memory(Vec::new()),
+            };
+            guard.gops.push_back(gop);
+        }
+
+        // 第一个关键帧之前的数据无法独立解码，直接丢弃
+        let size = tag.raw.len() as u64;
+        let gop = guard.gops.back_mut()?;
+        if let GopData::Memory(tags) = &mut gop.data {
+            tags.push(tag.raw);
+        }
+        gop.tag_count += 1;
+        gop.bytes += size;
+        gop.end_ms = gop.end_ms.max(session_ms);
+        guard.next_seq += 1;
+        guard.total_bytes += size;
+        drop(guard);
+
+        self.notify.notify_waiters();
+        sealed
+    }
+
+    fn mark_persisted(&self, first_seq: u64, path: PathBuf, offsets: Vec<(usize, usize)>) {
+        let mut guard = self.inner.lock().unwrap();
+        if let Some(gop) = guard.gops.iter_mut().find(|gop| gop.first_seq == first_seq) {
+            gop.data = GopData::Disk { path, offsets };
+            return;
+        }
+        drop(guard);
+        // 落盘期间该 GOP 已被淘汰
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// 淘汰超出时长窗口或容量上限的 GOP，返回需要删除的磁盘文件
+    fn evict(&self) -> Vec<PathBuf> {
+        let window_ms = u64::from(self.config.window_seconds) * 1000;
+        let mut removed_files = Vec::new();
+        let mut guard = self.inner.lock().unwrap();
+        while guard.gops.len() > 1 {
+            let live_ms = guard.live_ms;
+            let total_bytes = guard.total_bytes;
+            let front = guard.gops.front().unwrap();
+            let too_old = live_ms.saturating_sub(front.end_ms) > window_ms;
+            let too_big = total_bytes > self.config.max_bytes;
+            if !too_old && !too_big {
+                break;
+            }
+            let gop = guard.gops.pop_front().unwrap();
+            guard.total_bytes = guard.total_bytes.saturating_sub(gop.bytes);
+            if let GopData::Disk { path, .. } = gop.data {
+                removed_files.push(path);
+            }
+        }
+        removed_files
+    }
+
+    /// 找到 live 往前 `offset_ms` 处最近的关键帧，返回其序号与起播前导数据
+    fn locate(&self, offset_ms: u64) -> Option<(u64, Vec<Bytes>)> {
+        let guard = self.inner.lock().unwrap();
+        let target_ms = guard.live_ms.saturating_sub(offset_ms);
+        let gop = guard
+            .gops
+            .iter()
+            .rev()
+            .find(|gop| gop.start_ms <= target_ms)
+            .or_else(|| guard.gops.front())?;
+        let mut prelude = Vec::with_capacity(4);
+        prelude.extend(guard.header.clone());
+        prelude.extend(gop.metadata.clone());
+        prelude.extend(gop.video_sequence_header.clone());
+        prelude.extend(gop.audio_sequence_header.clone());
+        Some((gop.first_seq, prelude))
+    }
+
+    /// 读取从 `seq` 开始、同一 GOP 内已到达的 tag，返回结果与下一次读取的序号
+    fn read_from(&self, seq: u64) -> (ReadOutcome, u64) {
+        let guard = self.inner.lock().unwrap();
+        if guard.closed {
+            return (ReadOutcome::Closed, seq);
+        }
+        // 读者落后到已被淘汰的位置时，跳到最早仍可用的关键帧
+        let seq = match guard.gops.front() {
+            Some(front) if seq < front.first_seq => front.first_seq,
+            _ => seq,
+        };
+        let Some(gop) = guard
+            .gops
+            .iter()
+            .find(|gop| seq >= gop.first_seq && seq < gop.first_seq + gop.tag_count as u64)
+        else {
+            return (ReadOutcome::Pending, seq);
+        };
+        let skip = (seq - gop.first_seq) as usize;
+        let outcome = match &gop.data {
+            GopData::Memory(tags) => ReadOutcome::Tags(tags[skip..].to_vec()),
+            GopData::Disk { path, offsets } => ReadOutcome::DiskTags {
+                path: path.clone(),
+                offsets: offsets[skip..].to_vec(),
+            },
+        };
+        (outcome, gop.first_seq + gop.tag_count as u64)
+    }
+
+    /// 停止缓冲：唤醒所有读者并清理磁盘文件
+    pub fn close(&self) {
+        {
+            let mut guard = self.inner.lock().unwrap();
+            guard.closed = true;
+            guard.gops.clear();
+            guard.total_bytes = 0;
+        }
+        self.notify.notify_waiters();
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    /// 写入一批解析结果：新连接的文件头会接续时间线，封存的 GOP 按需落盘，最后执行淘汰
+    pub(super) async fn ingest(&self, events: Vec<FlvEvent>) {
+        for event in events {
+            match event {
+                FlvEvent::Header { raw, has_video } => self.begin_connection(raw, has_video),
+                FlvEvent::Tag(tag) => {
+                    if let Some(sealed) = self.push_tag(tag) {
+                        if let Some(dir) = &self.dir {
+                            let first_seq = sealed.first_seq;
+                            match persist_gop(dir, sealed).await {
+                                Ok((path, offsets)) => {
+                                    self.mark_persisted(first_seq, path, offsets)
+                                }
+                                Err(e) => eprintln!("[Rust/proxy.rs timeshift] {}", e),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for path in self.evict() {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+
+    /// 从 live 往前 `offset_seconds` 秒处开始输出 FLV 字节流，之后持续跟随直播
+    pub fn subscribe(
+        self: &Arc<Self>,
+        offset_seconds: u64,
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + 'static {
+        struct ReaderState {
+            buffer: Arc<TimeShiftBuffer>,
+            offset_ms: u64,
+            next_seq: Option<u64>,
+            queue: VecDeque<Bytes>,
+        }
+
+        let state = ReaderState {
+            buffer: self.clone(),
+            offset_ms: offset_seconds.saturating_mul(1000),
+            next_seq: None,
+            queue: VecDeque::new(),
+        };
+
+        futures_util::stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(bytes) = state.queue.pop_front() {
+                    return Some((Ok(bytes), state));
+                }
+
+                let notified = state.buffer.notify.notified();
+                let outcome = match state.next_seq {
+                    None => match state.buffer.locate(state.offset_ms) {
+                        Some((seq, prelude)) => {
+                            state.next_seq = Some(seq);
+                            state.queue.extend(prelude);
+                            continue;
+                        }
+                        None if state.buffer.inner.lock().unwrap().closed => ReadOutcome::Closed,
+                        None => ReadOutcome::Pending,
+                    },
+                    Some(seq) => {
+                        let (outcome, next_seq) = state.buffer.read_from(seq);
+                        state.next_seq = Some(next_seq);
+                        outcome
+                    }
+                };
+
+                match outcome {
+                    ReadOutcome::Tags(tags) => state.queue.extend(tags),
+                    ReadOutcome::DiskTags { path, offsets } => {
+                        match tokio::fs::read(&path).await {
+                            Ok(content) => {
+                                let content = Bytes::from(content);
+                                state.queue.extend(
+                                    offsets
+                                        .into_iter()
+                                        .filter(|(start, len)| start + len <= content.len())
+                                        .map(|(start, len)| content.slice(start..start + len)),
+                                );
+                            }
+                            // 文件在读取前被淘汰，下一轮会跳到最早可用的关键帧
+                            Err(e) => {
+                                eprintln!(
+                                    "[Rust/proxy.rs timeshift] Failed to read {}: {}",
+                                    path.display(),
+                                    e
+                                );
+                            }
+                        }
+                    }
+                    ReadOutcome::Pending => {
+                        let _ = tokio::time::timeout(READER_POLL_INTERVAL, notified).await;
+                    }
+                    ReadOutcome::Closed => return None,
+                }
+            }
+        })
+    }
+}
+
+async fn persist_gop(
+    dir: &Path,
+    sealed: SealedGop,
+) -> Result<(PathBuf, Vec<(usize, usize)>), String> {
+    let mut content = Vec::with_capacity(sealed.tags.iter().map(|t| t.len()).sum());
+    let mut offsets = Vec::with_capacity(sealed.tags.len());
+    for tag in &sealed.tags {
+        offsets.push((content.len(), tag.len()));
+        content.extend_from_slice(tag);
+    }
+    let path = dir.join(format!("{:012}.gop", sealed.first_seq));
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok((path, offsets))
+}
+
+/// 会话级上游拉流任务：持续写入回看缓冲区，断流后自动重连，直到收到停止信号
+pub async fn run_time_shift_pump(
+    client: Client,
+    upstream_url: String,
+    buffer: Arc<TimeShiftBuffer>,
+    metrics: Arc<SessionMetrics>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let mut reconnect_attempts: u32 = 0;
+    let mut stopped = *stop_rx.borrow();
+    // 第一次之后的每次连接都发生在上一条上游结束或失败之后
+    let mut first_attempt = true;
+
+    while !stopped {
+        let reconnect = !std::mem::replace(&mut first_attempt, false);
+        let started = Instant::now();
+        let response = match super::build_flv_upstream_request(
+            &client,
+            Some(&metrics.platform),
+            &upstream_url,
+        )
+        .send()
+        .await
+        {
+            Ok(resp) if resp.status().is_success() => resp,
+            Ok(resp) => {
+                metrics.record_upstream_error();
+                eprintln!(
+                    "[Rust/proxy.rs timeshift] Upstream {} responded with status {}",
+                    upstream_url,
+                    resp.status()
+                );
+                reconnect_attempts += 1;
+                stopped = wait_before_reconnect(reconnect_attempts, &mut stop_rx).await;
+                continue;
+            }
+            Err(e) => {
+                metrics.record_upstream_error();
+                eprintln!(
+                    "[Rust/proxy.rs timeshift] Failed to connect upstream {}: {}",
+                    upstream_url, e
+                );
+                reconnect_attempts += 1;
+                stopped = wait_before_reconnect(reconnect_attempts, &mut stop_rx).await;
+                continue;
+            }
+        };
+
+        metrics.record_upstream_connect(reconnect);
+        let mut probe = UpstreamProbe::new(started);
+        let mut demuxer = FlvDemuxer::new();
+        let mut stream = response.bytes_stream();
+        loop {
+            let chunk = tokio::select! {
+                // 发送端只会发送停止信号；发送端被丢弃同样视为停止
+                _ = stop_rx.changed() => {
+                    stopped = true;
+                    break;
+                }
+                chunk = stream.next() => chunk,
+            };
+            let chunk = match chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    eprintln!("[Rust/proxy.rs timeshift] Upstream read error: {}", e);
+                    break;
+                }
+                None => break,
+            };
+            probe.on_chunk(&metrics, chunk.len());
+            let events = match demuxer.push(&chunk) {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("[Rust/proxy.rs timeshift] FLV parse error: {}", e);
+                    break;
+                }
+            };
+            if events
+                .iter()
+                .any(|event| matches!(event, FlvEvent::Header { .. }))
+            {
+                reconnect_attempts = 0;
+            }
+            buffer.ingest(events).await;
+        }
+
+        if !stopped {
+            reconnect_attempts += 1;
+            stopped = wait_before_reconnect(reconnect_attempts, &mut stop_rx).await;
+        }
+    }
+
+    buffer.close();
+}
+
+/// 按重连次数退避等待；期间收到停止信号时返回 true
+pub(super) async fn wait_before_reconnect(
+    attempt: u32,
+    stop_rx: &mut watch::Receiver<bool>,
+) -> bool {
+    let delay = match attempt {
+        0 | 1 => Duration::from_secs(1),
+        2 => Duration::from_secs(3),
+        3 => Duration::from_secs(5),
+        _ => Duration::from_secs(10),
+    };
+    tokio::select! {
+        _ = stop_rx.changed() => true,
+        _ = tokio::time::sleep(delay) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::flv::test_util::*;
+    use super::super::flv::{TAG_TYPE_SCRIPT, TAG_TYPE_VIDEO};
+    use super::*;
+
+    fn memory_buffer() -> Arc<TimeShiftBuffer> {
+        TimeShiftBuffer::new(
+            "test",
+            TimeShiftConfig::new(60, TimeShiftStorage::Memory, None),
+        )
+        .unwrap()
+    }
+
+    async fn ingest(buffer: &TimeShiftBuffer, demuxer: &mut FlvDemuxer, bytes: Vec<u8>) {
+        buffer.ingest(demuxer.push(&bytes).unwrap()).await;
+    }
+
+    // 关键帧位于 0、2000、4000 ms，live 为 4040 ms
+    async fn filled_buffer() -> Arc<TimeShiftBuffer> {
+        let buffer = memory_buffer();
+        let mut demuxer = FlvDemuxer::new();
+        let mut stream = header(true);
+        stream.extend(metadata());
+        stream.extend(avc_sequence_header());
+        for start in [0, 2000, 4000] {
+            stream.extend(video(start, true));
+            stream.extend(video(start + 40, false));
+        }
+        ingest(&buffer, &mut demuxer, stream).await;
+        buffer
+    }
+
+    async fn read(buffer: &Arc<TimeShiftBuffer>, offset_seconds: u64, items: usize) -> Vec<u8> {
+        let chunks: Vec<_> = buffer.subscribe(offset_seconds).take(items).collect().await;
+        chunks
+            .into_iter()
+            .flat_map(|c| c.unwrap().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn config_clamps_window_and_defaults_capacity() {
+        let config = TimeShiftConfig::new(1, TimeShiftStorage::Disk, None);
+        assert_eq!(config.window_seconds, MIN_WINDOW_SECONDS);
+        assert_eq!(config.max_bytes, DEFAULT_DISK_MAX_MB * 1024 * 1024);
+        let config = TimeShiftConfig::new(u32::MAX, TimeShiftStorage::Memory, Some(0));
+        assert_eq!(config.window_seconds, MAX_WINDOW_SECONDS);
+        assert_eq!(config.max_bytes, DEFAULT_MEMORY_MAX_MB * 1024 * 1024);
+        assert!(TimeShiftStorage::parse(Some("tape")).is_err());
+    }
+
+    #[tokio::test]
+    async fn live_subscriber_starts_at_latest_keyframe() {
+        let buffer = filled_buffer().await;
+        // 文件头、metadata、视频序列头，然后是最后一个 GOP 的两个 tag
+        let bytes = read(&buffer, 0, 5).await;
+        assert_eq!(
+            tag_summary(&bytes),
+            vec![
+                (TAG_TYPE_SCRIPT, 0),
+                (TAG_TYPE_VIDEO, 0),
+                (TAG_TYPE_VIDEO, 4000),
+                (TAG_TYPE_VIDEO, 4040),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn offset_seeks_to_keyframe_before_target() {
+        let buffer = filled_buffer().await;
+        // live 4040 - 3000 = 1040，之前最近的关键帧为 0
+        let bytes = read(&buffer, 3, 9).await;
+        let timestamps: Vec<_> = tag_summary(&bytes).into_iter().map(|(_, ts)| ts).collect();
+        assert_eq!(timestamps, vec![0, 0, 0, 40, 2000, 2040, 4000, 4040]);
+
+        // 超出已缓冲的范围时从最早的关键帧开始
+        let bytes = read(&buffer, 600, 4).await;
+        assert_eq!(tag_summary(&bytes).last(), Some(&(TAG_TYPE_VIDEO, 0)));
+        assert_eq!(buffer.status().available_seconds, 4);
+    }
+
+    #[tokio::test]
+    async fn reconnect_continues_timeline() {
+        let buffer = filled_buffer().await;
+        let mut demuxer = FlvDemuxer::new();
+        let mut stream = header(true);
+        stream.extend(video(90_000, true));
+        ingest(&buffer, &mut demuxer, stream).await;
+        assert_eq!(
+            buffer.status().live_timestamp_ms,
+            4040 + RECONNECT_TIMESTAMP_GAP_MS
+        );
+    }
+
+    #[tokio::test]
+    async fn evicts_gops_outside_window() {
+        let buffer = memory_buffer();
+        let mut demuxer = FlvDemuxer::new();
+        let mut stream = header(true);
+        for start in (0..=120_000).step_by(10_000) {
+            stream.extend(video(start, true));
+        }
+        ingest(&buffer, &mut demuxer, stream).await;
+        let status = buffer.status();
+        assert_eq!(status.live_timestamp_ms, 120_000);
+        assert!(status.available_seconds <= 70, "{:?}", status);
+    }
+
+    #[tokio::test]
+    async fn close_ends_subscribers() {
+        let buffer = filled_buffer().await;
+        let reader = buffer.subscribe(0);
+        buffer.close();
+        let chunks: Vec<_> = reader.collect().await;
+        assert!(chunks.is_empty());
+    }
+}
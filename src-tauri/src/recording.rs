@@ -1,9 +1,12 @@
 use crate::platforms::bilibili::stream_url::get_bilibili_live_stream_url_with_quality;
+use crate::platforms::common::header_rules::UpstreamHeadersExt;
 use crate::platforms::common::types::{GetStreamUrlArgs, GetStreamUrlPayload};
 use crate::platforms::common::FollowHttpClient;
 use crate::platforms::douyin::douyin_streamer_detail::fetch_douyin_live_stream_info_by_quality;
 use crate::platforms::douyu::get_stream_url_with_quality;
 use crate::platforms::huya::stream_url::get_huya_unified_with_client;
+use crate::recording::segmenter::{FlvSegmenter, SegmentAction};
+use crate::streams::{StreamBudget, StreamKind, StreamLease, StreamManager, StreamRequest};
 use chrono::Local;
 use futures_util::StreamExt;
 use rand::RngCore;
@@ -11,11 +14,15 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex as StdMutex};
-use tauri::{async_runtime::JoinHandle, AppHandle, Emitter, State};
+use tauri::{async_runtime::JoinHandle, AppHandle, Emitter, Manager, State};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::watch;
 use tokio::time::{sleep, Duration, Instant};
 
+mod finalize;
+mod output_dirs;
+mod segmenter;
+
 const RECORDING_EVENT_NAME: &str = "recording-status";
 const DEFAULT_SEGMENT_MINUTES: u32 = 30;
 const MIN_SEGMENT_MINUTES: u32 = 1;
@@ -57,6 +64,8 @@ pub struct RecordingTaskSnapshot {
     pub started_at: i64,
     pub updated_at: i64,
     pub message: Option<String>,
+    // 最近一次分段收尾失败的原因，不随状态变化清除
+    pub finalize_error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -70,6 +79,7 @@ pub struct RecordingStatusEvent {
     pub segment_index: u32,
     pub bytes_written: u64,
     pub message: Option<String>,
+    pub finalize_error: Option<String>,
     pub timestamp: i64,
 }
 
@@ -128,6 +138,7 @@ pub async fn start_live_recording(
     tokio::fs::create_dir_all(&output_dir)
         .await
         .map_err(|e| format!("Failed to create output dir: {}", e))?;
+    output_dirs::remember(&output_root);
 
     let existing_task_id = {
         let guard = manager
@@ -154,13 +165,12 @@ pub async fn start_live_recording(
         ));
     }
 
-    let follow_client = follow_http.0.inner.clone();
     let initial_url = resolve_stream_url(
         platform,
         &room_id,
         &quality,
         cookie.as_deref(),
-        &follow_client,
+        &follow_http.client(Some(platform))?.inner,
     )
     .await?;
 
@@ -179,6 +189,7 @@ pub async fn start_live_recording(
         started_at,
         updated_at: started_at,
         message: None,
+        finalize_error: None,
     };
     let state = Arc::new(StdMutex::new(snapshot));
     emit_status_event(&app_handle, &state);
@@ -191,7 +202,7 @@ pub async fn start_live_recording(
     let worker_cookie = cookie.clone();
     let worker_output_dir = output_dir.clone();
     let worker_state = state.clone();
-    let worker_follow_client = follow_client.clone();
+    let worker_follow_http = follow_http.inner().clone();
     let worker_initial_url = initial_url.clone();
     let worker_join = tauri::async_runtime::spawn(async move {
         run_recording_worker(
@@ -204,7 +215,7 @@ pub async fn start_live_recording(
             segment_minutes,
             worker_state,
             stop_rx,
-            worker_follow_client,
+            worker_follow_http,
             worker_initial_url,
         )
         .await;
@@ -277,6 +288,55 @@ pub async fn stop_all_live_recordings(manager: State<'_, RecordingManager>) -> R
     Ok(())
 }
 
+pub(crate) fn active_recording_count(manager: &RecordingManager) -> usize {
+    manager
+        .0
+        .lock()
+        .map(|guard| {
+            guard
+                .values()
+                .filter_map(|runtime| snapshot_clone(&runtime.state))
+                .filter(|snap| is_active_status(&snap.status))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// 退出前停止所有录制，在 `timeout` 内等待各任务刷盘并收尾当前分段及轮转后仍在收尾的分段；返回停止前仍在进行的任务
+pub(crate) async fn shutdown_all(
+    manager: &RecordingManager,
+    timeout: Duration,
+) -> Vec<RecordingTaskSnapshot> {
+    let runtimes = match manager.0.lock() {
+        Ok(mut guard) => guard
+            .drain()
+            .map(|(_, runtime)| runtime)
+            .collect::<Vec<_>>(),
+        Err(_) => return Vec::new(),
+    };
+    let active: Vec<RecordingTaskSnapshot> = runtimes
+        .iter()
+        .filter_map(|runtime| snapshot_clone(&runtime.state))
+        .filter(|snap| is_active_status(&snap.status))
+        .collect();
+
+    let mut join_handles = Vec::with_capacity(runtimes.len());
+    for runtime in runtimes {
+        let _ = runtime.stop_tx.send(true);
+        join_handles.push(runtime.join_handle);
+    }
+    if tokio::time::timeout(timeout, futures_util::future::join_all(join_handles))
+        .await
+        .is_err()
+    {
+        eprintln!(
+            "[Rust/recording.rs] Some recordings did not finish within {:?}",
+            timeout
+        );
+    }
+    active
+}
+
 async fn run_recording_worker(
     app_handle: AppHandle,
     platform: String,
@@ -287,7 +347,7 @@ async fn run_recording_worker(
     segment_minutes: u32,
     state: Arc<StdMutex<RecordingTaskSnapshot>>,
     mut stop_rx: watch::Receiver<bool>,
-    follow_client: reqwest::Client,
+    follow_http: FollowHttpClient,
     mut current_stream_url: String,
 ) {
     let mut segment_index: u32 = 0;
@@ -295,6 +355,10 @@ async fn run_recording_worker(
     let mut reconnect_attempts: usize = 0;
     let mut offline_attempts: usize = 0;
     let mut should_stop = false;
+    // 轮转后上一分段的收尾在后台进行，退出前必须等它们完成，否则元数据没有修正
+    let mut finalizing = tokio::task::JoinSet::new();
+
+    let budget = app_handle.state::<StreamManager>().0.clone();
 
     while !should_stop {
         if *stop_rx.borrow() {
@@ -303,6 +367,32 @@ async fn run_recording_worker(
             break;
         }
 
+        // 名额只在连接期间占用，等待重连或轮询离线房间时释放
+        let Some(lease) = acquire_recording_lease(
+            &app_handle,
+            &state,
+            &budget,
+            &platform,
+            &room_id,
+            &mut stop_rx,
+        )
+        .await
+        else {
+            mark_status(&state, "stopped", Some("stopped_by_user".to_string()));
+            emit_status_event(&app_handle, &state);
+            break;
+        };
+
+        // 出站代理设置可能在录制期间修改，每次连接前按当前设置取客户端
+        let follow_client = match follow_http.client(Some(&platform)) {
+            Ok(client) => client.inner,
+            Err(e) => {
+                mark_status(&state, "failed", Some(e));
+                emit_status_event(&app_handle, &state);
+                break;
+            }
+        };
+
         let request_builder = build_stream_request(
             &follow_client,
             &platform,
@@ -312,6 +402,7 @@ async fn run_recording_worker(
         let response = match request_builder.send().await {
             Ok(resp) => resp,
             Err(e) => {
+                drop(lease);
                 reconnect_attempts += 1;
                 mark_status(
                     &state,
@@ -355,6 +446,7 @@ async fn run_recording_worker(
         };
 
         if !response.status().is_success() {
+            drop(lease);
             reconnect_attempts += 1;
             let code = response.status();
             let message = format!("upstream_status={}", code);
@@ -387,12 +479,22 @@ async fn run_recording_worker(
         set_current_file(&state, file.1.clone(), segment_index);
         emit_status_event(&app_handle, &state);
 
+        // 每条连接从 FLV 头开始，分段只在 tag 边界切换
+        let mut segmenter = FlvSegmenter::new();
         let mut stream = response.bytes_stream();
-        while let Some(chunk_result) = stream.next().await {
+        'stream: loop {
+            // 停止信号不必等下一个数据块，上游卡住时也能及时收尾
+            let chunk_result = tokio::select! {
+                _ = stop_rx.changed() => None,
+                chunk = stream.next() => chunk,
+            };
             if *stop_rx.borrow() {
                 should_stop = true;
                 break;
             }
+            let Some(chunk_result) = chunk_result else {
+                break;
+            };
 
             let chunk = match chunk_result {
                 Ok(c) => c,
@@ -407,53 +509,92 @@ async fn run_recording_worker(
                 }
             };
 
+            lease.record_bytes(chunk.len());
             if segment_started.elapsed() >= segment_duration {
-                if let Err(e) = file.0.flush().await {
-                    mark_status(&state, "failed", Some(format!("flush_failed: {}", e)));
+                segmenter.request_rotation();
+            }
+            let actions = match segmenter.push(&chunk) {
+                Ok(actions) => actions,
+                Err(e) => {
+                    mark_status(
+                        &state,
+                        "reconnecting",
+                        Some(format!("invalid_flv_stream: {}", e)),
+                    );
                     emit_status_event(&app_handle, &state);
-                    should_stop = true;
                     break;
                 }
+            };
 
-                segment_index += 1;
-                match open_segment_file(&output_dir, &platform, &room_id, segment_index).await {
-                    Ok(next_file) => {
-                        file = next_file;
-                        segment_started = Instant::now();
-                        set_current_file(&state, file.1.clone(), segment_index);
-                        emit_status_event(&app_handle, &state);
-                    }
-                    Err(e) => {
-                        mark_status(
-                            &state,
-                            "failed",
-                            Some(format!("failed_to_open_next_segment: {}", e)),
-                        );
-                        emit_status_event(&app_handle, &state);
-                        should_stop = true;
-                        break;
+            for action in actions {
+                let data = match action {
+                    SegmentAction::Write(data) => data,
+                    SegmentAction::Rotate(preamble) => {
+                        if let Err(e) = file.0.flush().await {
+                            mark_status(&state, "failed", Some(format!("flush_failed: {}", e)));
+                            emit_status_event(&app_handle, &state);
+                            should_stop = true;
+                            break 'stream;
+                        }
+
+                        segment_index += 1;
+                        match open_segment_file(&output_dir, &platform, &room_id, segment_index)
+                            .await
+                        {
+                            Ok(next_file) => {
+                                let finished = std::mem::replace(&mut file, next_file);
+                                drop(finished.0);
+                                while finalizing.try_join_next().is_some() {}
+                                finalizing.spawn(finalize_segment(
+                                    app_handle.clone(),
+                                    state.clone(),
+                                    finished.1,
+                                ));
+                                segment_started = Instant::now();
+                                set_current_file(&state, file.1.clone(), segment_index);
+                                emit_status_event(&app_handle, &state);
+                            }
+                            Err(e) => {
+                                mark_status(
+                                    &state,
+                                    "failed",
+                                    Some(format!("failed_to_open_next_segment: {}", e)),
+                                );
+                                emit_status_event(&app_handle, &state);
+                                should_stop = true;
+                                break 'stream;
+                            }
+                        }
+                        preamble
                     }
-                }
-            }
+                };
 
-            if let Err(e) = file.0.write_all(&chunk).await {
-                mark_status(&state, "failed", Some(format!("write_failed: {}", e)));
-                emit_status_event(&app_handle, &state);
-                should_stop = true;
-                break;
+                if let Err(e) = file.0.write_all(&data).await {
+                    mark_status(&state, "failed", Some(format!("write_failed: {}", e)));
+                    emit_status_event(&app_handle, &state);
+                    should_stop = true;
+                    break 'stream;
+                }
+                increment_bytes_written(&state, data.len() as u64);
             }
-            increment_bytes_written(&state, chunk.len() as u64);
         }
 
-        if should_stop {
-            break;
-        }
+        drop(lease);
 
-        if let Err(e) = file.0.flush().await {
+        // 停止或断流时都要刷盘并修正当前分段，否则最后一段的元数据是坏的
+        let flushed = file.0.flush().await;
+        let (segment, segment_path) = file;
+        drop(segment);
+        if let Err(e) = flushed {
             mark_status(&state, "failed", Some(format!("flush_failed: {}", e)));
             emit_status_event(&app_handle, &state);
             break;
         }
+        finalize_segment(app_handle.clone(), state.clone(), segment_path).await;
+
+        if should_stop {
+            break;
+        }
 
         match resolve_stream_url(
             &platform,
@@ -490,6 +631,9 @@ async fn run_recording_worker(
         }
     }
 
+    // shutdown_all 对整个 worker 限时等待，这里的收尾也在该时限内
+    while finalizing.join_next().await.is_some() {}
+
     if *stop_rx.borrow() {
         mark_status(&state, "stopped", Some("stopped_by_user".to_string()));
         emit_status_event(&app_handle, &state);
@@ -501,6 +645,31 @@ async fn run_recording_worker(
     }
 }
 
+/// 录制优先于预览，放不下时会抢占预览的名额；名额被占满时先标记为排队
+async fn acquire_recording_lease(
+    app_handle: &AppHandle,
+    state: &Arc<StdMutex<RecordingTaskSnapshot>>,
+    budget: &Arc<StreamBudget>,
+    platform: &str,
+    room_id: &str,
+    stop_rx: &mut watch::Receiver<bool>,
+) -> Option<StreamLease> {
+    let task_id = state.lock().unwrap().task_id.clone();
+    let request =
+        StreamRequest::new(StreamKind::Recording, platform, Some(room_id)).owner(&task_id);
+    let acquire = budget.acquire_until_stopped(request, stop_rx);
+    tokio::pin!(acquire);
+    tokio::select! {
+        biased;
+        lease = &mut acquire => lease,
+        _ = std::future::ready(()) => {
+            mark_status(state, "queued", Some("waiting_for_stream_slot".to_string()));
+            emit_status_event(app_handle, state);
+            acquire.await
+        }
+    }
+}
+
 fn resolve_output_root(custom_output_dir: Option<&str>) -> Result<PathBuf, String> {
     if let Some(raw) = custom_output_dir {
         let trimmed = raw.trim();
@@ -511,7 +680,7 @@ fn resolve_output_root(custom_output_dir: Option<&str>) -> Result<PathBuf, Strin
     Ok(default_output_dir())
 }
 
-fn default_output_dir() -> PathBuf {
+pub(crate) fn default_output_dir() -> PathBuf {
     if let Some(video_dir) = dirs::video_dir() {
         return video_dir.join("DTV");
     }
@@ -521,6 +690,21 @@ fn default_output_dir() -> PathBuf {
     PathBuf::from("./recordings")
 }
 
+/// 默认录制目录、以前使用过的录制目录以及当前各录制任务使用的目录
+pub(crate) fn known_output_dirs(manager: &RecordingManager) -> Vec<PathBuf> {
+    let mut dirs = vec![default_output_dir()];
+    dirs.extend(output_dirs::history());
+    if let Ok(guard) = manager.0.lock() {
+        dirs.extend(
+            guard
+                .values()
+                .filter_map(|runtime| snapshot_clone(&runtime.state))
+                .map(|snapshot| PathBuf::from(snapshot.output_dir)),
+        );
+    }
+    dirs
+}
+
 fn build_platform_room_output_dir(root: &Path, platform: &str, room_id: &str) -> PathBuf {
     root.join(platform).join(sanitize_token(room_id))
 }
@@ -531,7 +715,7 @@ fn generate_task_id() -> String {
     hex::encode(bytes)
 }
 
-fn normalize_platform(input: &str) -> Option<&'static str> {
+pub(crate) fn normalize_platform(input: &str) -> Option<&'static str> {
     match input.trim().to_ascii_uppercase().as_str() {
         "DOUYU" => Some("DOUYU"),
         "DOUYIN" => Some("DOUYIN"),
@@ -541,7 +725,7 @@ fn normalize_platform(input: &str) -> Option<&'static str> {
     }
 }
 
-fn normalize_quality(input: Option<&str>) -> String {
+pub(crate) fn normalize_quality(input: Option<&str>) -> String {
     match input.unwrap_or("原画").trim() {
         "高清" => "高清".to_string(),
         "标清" => "标清".to_string(),
@@ -580,7 +764,7 @@ fn now_millis() -> i64 {
 }
 
 fn is_active_status(status: &str) -> bool {
-    matches!(status, "starting" | "recording" | "reconnecting")
+    matches!(status, "starting" | "queued" | "recording" | "reconnecting")
 }
 
 fn is_room_offline_error(message: &str) -> bool {
@@ -599,28 +783,11 @@ fn build_stream_request(
 ) -> reqwest::RequestBuilder {
     let mut request = client
         .get(url)
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36",
-        )
+        .upstream_headers(Some(platform), url)
         .header("Accept", "video/x-flv,application/octet-stream,*/*")
         .header("Connection", "keep-alive")
         .header("Range", "bytes=0-");
 
-    request = match platform {
-        "HUYA" => request
-            .header("Referer", "https://www.huya.com/")
-            .header("Origin", "https://www.huya.com"),
-        "BILIBILI" => request
-            .header("Referer", "https://live.bilibili.com/")
-            .header("Origin", "https://live.bilibili.com"),
-        "DOUYIN" => request
-            .header("Referer", "https://live.douyin.com/")
-            .header("Origin", "https://live.douyin.com"),
-        "DOUYU" => request.header("Referer", "https://www.douyu.com/"),
-        _ => request,
-    };
-
     if let Some(raw_cookie) = cookie {
         let trimmed = raw_cookie.trim();
         if !trimmed.is_empty() {
@@ -640,7 +807,7 @@ fn backoff_duration(attempt: usize) -> Duration {
     }
 }
 
-async fn resolve_stream_url(
+pub(crate) async fn resolve_stream_url(
     platform: &str,
     room_id: &str,
     quality: &str,
@@ -736,6 +903,35 @@ async fn open_segment_file(
     Ok((file, path.to_string_lossy().to_string()))
 }
 
+/// 收尾失败时不改变任务状态，原因记在 `finalize_error` 并通知前端
+async fn finalize_segment(
+    app_handle: AppHandle,
+    state: Arc<StdMutex<RecordingTaskSnapshot>>,
+    path: String,
+) {
+    let result = tokio::task::spawn_blocking(move || {
+        finalize::finalize_flv_segment(Path::new(&path)).map(|r| (path, r))
+    })
+    .await;
+    let error = match result {
+        Ok(Ok((path, report))) => {
+            println!(
+                "[Rust/recording.rs] Finalized {} (duration={:?}s, truncated={}B, patched={:?})",
+                path, report.duration_seconds, report.truncated_bytes, report.patched_fields
+            );
+            return;
+        }
+        Ok(Err(e)) => e,
+        Err(e) => format!("finalize task failed: {}", e),
+    };
+    eprintln!("[Rust/recording.rs] Failed to finalize segment: {}", error);
+    if let Ok(mut snapshot) = state.lock() {
+        snapshot.finalize_error = Some(error);
+        snapshot.updated_at = now_millis();
+    }
+    emit_status_event(&app_handle, &state);
+}
+
 fn mark_status(
     state: &Arc<StdMutex<RecordingTaskSnapshot>>,
     status: &str,
@@ -782,6 +978,7 @@ fn emit_status_event(app_handle: &AppHandle, state: &Arc<StdMutex<RecordingTaskS
             segment_index: snapshot.segment_index,
             bytes_written: snapshot.bytes_written,
             message: snapshot.message.clone(),
+            finalize_error: snapshot.finalize_error.clone(),
             timestamp: now_millis(),
         };
         let _ = app_handle.emit(RECORDING_EVENT_NAME, payload);
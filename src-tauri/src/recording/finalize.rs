@@ -0,0 +1,277 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const FLV_HEADER_LEN: u64 = 9;
+const TAG_HEADER_LEN: usize = 11;
+const TAG_TYPE_AUDIO: u8 = 8;
+const TAG_TYPE_VIDEO: u8 = 9;
+const TAG_TYPE_SCRIPT: u8 = 18;
+// onMetaData 一般位于文件开头几 KB 内
+const HEAD_SCAN_BYTES: usize = 256 * 1024;
+// 从文件末尾向前寻找最后一个完整 tag 的范围
+const TAIL_SCAN_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct FinalizeReport {
+    pub truncated_bytes: u64,
+    pub duration_seconds: Option<f64>,
+    pub patched_fields: Vec<&'static str>,
+}
+
+fn u24(b: &[u8]) -> u32 {
+    (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2])
+}
+
+fn tag_timestamp(header: &[u8]) -> u32 {
+    u24(&header[4..7]) | (u32::from(header[7]) << 24)
+}
+
+fn is_tag_header(header: &[u8], expected_data_len: Option<u32>) -> bool {
+    let tag_type = header[0] & 0x1f;
+    matches!(tag_type, TAG_TYPE_AUDIO | TAG_TYPE_VIDEO | TAG_TYPE_SCRIPT)
+        && header[8..11] == [0, 0, 0]
+        && expected_data_len.is_none_or(|len| u24(&header[1..4]) == len)
+}
+
+/// 在 `buf`（文件偏移 `base` 起的尾部数据）中找最后一个完整 tag 的结束位置与时间戳；
+/// 结束位置指 PreviousTagSize 之后，用前一个 tag 的 PreviousTagSize 再校验一次以排除误判
+fn find_last_complete_tag(buf: &[u8], base: u64, data_start: u64) -> Option<(u64, u32)> {
+    let mut end = buf.len();
+    while end >= 4 + TAG_HEADER_LEN {
+        let size = u32::from_be_bytes(buf[end - 4..end].try_into().unwrap()) as usize;
+        if size > TAG_HEADER_LEN && size + 4 <= end {
+            let start = end - 4 - size;
+            let header = &buf[start..start + TAG_HEADER_LEN];
+            if is_tag_header(header, Some((size - TAG_HEADER_LEN) as u32)) {
+                let absolute_start = base + start as u64;
+                let chained = absolute_start == data_start
+                    || (start >= 4 && {
+                        let prev =
+                            u32::from_be_bytes(buf[start - 4..start].try_into().unwrap()) as usize;
+                        prev == 0
+                            || (prev > TAG_HEADER_LEN
+                                && prev + 4 <= start
+                                && is_tag_header(
+                                    &buf[start - 4 - prev..start - 4 - prev + TAG_HEADER_LEN],
+                                    Some((prev - TAG_HEADER_LEN) as u32),
+                                ))
+                    });
+                if chained {
+                    return Some((base + end as u64, tag_timestamp(header)));
+                }
+            }
+        }
+        end -= 1;
+    }
+    None
+}
+
+/// 最小的 AMF0 读取器，只用于定位 onMetaData 中数值字段的位置
+struct Amf0Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Amf0Cursor<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.buf.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        self.take(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    fn u32(&mut self) -> Option<usize> {
+        self.take(4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+    }
+
+    fn short_string(&mut self) -> Option<&'a [u8]> {
+        let len = self.u16()?;
+        self.take(len)
+    }
+
+    // 跳过一个值；遇到数值时返回其 8 字节的偏移
+    fn skip_value(&mut self, depth: usize) -> Option<Option<usize>> {
+        if depth > 8 {
+            return None;
+        }
+        match self.u8()? {
+            0x00 => {
+                let at = self.pos;
+                self.take(8)?;
+                Some(Some(at))
+            }
+            0x01 => self.take(1).map(|_| None),
+            0x02 => self.short_string().map(|_| None),
+            0x03 => self
+                .skip_properties(depth + 1, &mut |_, _| {})
+                .map(|_| None),
+            0x05 | 0x06 => Some(None),
+            0x08 => {
+                self.u32()?;
+                self.skip_properties(depth + 1, &mut |_, _| {})
+                    .map(|_| None)
+            }
+            0x0A => {
+                let count = self.u32()?;
+                for _ in 0..count {
+                    self.skip_value(depth + 1)?;
+                }
+                Some(None)
+            }
+            0x0B => self.take(10).map(|_| None),
+            0x0C => {
+                let len = self.u32()?;
+                self.take(len).map(|_| None)
+            }
+            _ => None,
+        }
+    }
+
+    // 读取对象属性直到 0x00 0x00 0x09 结束符；数组长度不可信，以结束符为准
+    fn skip_properties(
+        &mut self,
+        depth: usize,
+        on_number: &mut dyn FnMut(&[u8], usize),
+    ) -> Option<()> {
+        loop {
+            let key = self.short_string()?;
+            if key.is_empty() {
+                return (self.u8()? == 0x09).then_some(());
+            }
+            if let Some(at) = self.skip_value(depth)? {
+                on_number(key, at);
+            }
+        }
+    }
+}
+
+/// onMetaData 中 `duration`/`filesize` 数值在 tag body 内的偏移
+fn locate_metadata_numbers(body: &[u8]) -> Vec<(&'static str, usize)> {
+    let mut cursor = Amf0Cursor { buf: body, pos: 0 };
+    let name = match (cursor.u8(), cursor.short_string()) {
+        (Some(0x02), Some(name)) => name,
+        _ => return Vec::new(),
+    };
+    if name != b"onMetaData" {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    let mut collect = |key: &[u8], at: usize| match key {
+        b"duration" => found.push(("duration", at)),
+        b"filesize" => found.push(("filesize", at)),
+        _ => {}
+    };
+    match cursor.u8() {
+        Some(0x08) => {
+            if cursor.u32().is_some() {
+                let _ = cursor.skip_properties(0, &mut collect);
+            }
+        }
+        Some(0x03) => {
+            let _ = cursor.skip_properties(0, &mut collect);
+        }
+        _ => {}
+    }
+    found
+}
+
+/// 收尾录制分段：截掉末尾不完整的 tag，并把 onMetaData 的 duration/filesize 改为实际值。
+/// 直播流的 onMetaData 通常带有值为 0 的这两个字段，原地改写；字段不存在时不改动文件结构
+pub fn finalize_flv_segment(path: &Path) -> Result<FinalizeReport, String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("open {}: {}", path.display(), e))?;
+    let file_len = file
+        .metadata()
+        .map_err(|e| format!("stat {}: {}", path.display(), e))?
+        .len();
+    // 刚创建就停止的分段没有内容
+    if file_len == 0 {
+        return Ok(FinalizeReport::default());
+    }
+
+    let mut head = vec![0u8; (HEAD_SCAN_BYTES as u64).min(file_len) as usize];
+    file.read_exact(&mut head)
+        .map_err(|e| format!("read {}: {}", path.display(), e))?;
+    if head.len() < FLV_HEADER_LEN as usize + 4 || &head[..3] != b"FLV" {
+        return Err(format!("{} is not an FLV file", path.display()));
+    }
+    let header_len = u32::from_be_bytes(head[5..9].try_into().unwrap()) as u64;
+    // 第一个 tag 从 FLV 头与 PreviousTagSize0 之后开始
+    let data_start = header_len.max(FLV_HEADER_LEN) + 4;
+
+    let tail_base = file_len.saturating_sub(TAIL_SCAN_BYTES).max(data_start);
+    let mut tail = Vec::with_capacity((file_len - tail_base.min(file_len)) as usize);
+    file.seek(SeekFrom::Start(tail_base))
+        .and_then(|_| file.read_to_end(&mut tail))
+        .map_err(|e| format!("read {}: {}", path.display(), e))?;
+    let mut report = FinalizeReport::default();
+    let Some((complete_len, last_ts)) = find_last_complete_tag(&tail, tail_base, data_start) else {
+        return Ok(report);
+    };
+    if complete_len < file_len {
+        file.set_len(complete_len)
+            .map_err(|e| format!("truncate {}: {}", path.display(), e))?;
+        report.truncated_bytes = file_len - complete_len;
+    }
+
+    // 从头部找第一个音视频 tag 的时间戳作为起点，并记下 onMetaData 的位置
+    let mut pos = data_start as usize;
+    let mut first_media_ts = None;
+    let mut metadata_fields = Vec::new();
+    while pos + TAG_HEADER_LEN <= head.len() && (pos as u64) < complete_len {
+        let header = &head[pos..pos + TAG_HEADER_LEN];
+        if !is_tag_header(header, None) {
+            break;
+        }
+        let data_len = u24(&header[1..4]) as usize;
+        let body_start = pos + TAG_HEADER_LEN;
+        match header[0] & 0x1f {
+            TAG_TYPE_SCRIPT if metadata_fields.is_empty() => {
+                if let Some(body) = head.get(body_start..body_start + data_len) {
+                    metadata_fields = locate_metadata_numbers(body)
+                        .into_iter()
+                        .map(|(name, at)| (name, (body_start + at) as u64))
+                        .collect();
+                }
+            }
+            TAG_TYPE_AUDIO | TAG_TYPE_VIDEO => {
+                first_media_ts = Some(tag_timestamp(header));
+                break;
+            }
+            _ => {}
+        }
+        pos = body_start + data_len + 4;
+    }
+
+    let duration = first_media_ts.map(|first| f64::from(last_ts.saturating_sub(first)) / 1000.0);
+    report.duration_seconds = duration;
+    for (name, offset) in metadata_fields {
+        let value = match name {
+            "duration" => match duration {
+                Some(duration) => duration,
+                None => continue,
+            },
+            _ => complete_len as f64,
+        };
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.write_all(&value.to_be_bytes()))
+            .map_err(|e| format!("patch {} in {}: {}", name, path.display(), e))?;
+        report.patched_fields.push(name);
+    }
+    file.sync_all()
+        .map_err(|e| format!("sync {}: {}", path.display(), e))?;
+    Ok(report)
+}
@@ -0,0 +1,65 @@
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const HISTORY_FILE_NAME: &str = "recording-output-dirs.json";
+// 只保留最近使用的若干个自定义目录
+const MAX_HISTORY: usize = 32;
+
+static HISTORY: Lazy<RwLock<Vec<PathBuf>>> = Lazy::new(|| RwLock::new(load_history()));
+
+fn history_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(HISTORY_FILE_NAME)
+}
+
+fn load_history() -> Vec<PathBuf> {
+    let path = history_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<Vec<PathBuf>>(&raw) {
+        Ok(dirs) => dirs,
+        Err(e) => {
+            eprintln!(
+                "[Recording] Ignoring invalid output dir history in {}: {}",
+                path.display(),
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+fn save_history(dirs: &[PathBuf]) -> Result<(), String> {
+    let path = history_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(dirs)
+        .map_err(|e| format!("Failed to serialize output dir history: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 记录一个录制根目录，供之后的运行中回放旧录像时校验
+pub fn remember(root: &Path) {
+    let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut history = HISTORY.write().unwrap();
+    if history.first() == Some(&root) {
+        return;
+    }
+    history.retain(|dir| *dir != root);
+    history.insert(0, root);
+    history.truncate(MAX_HISTORY);
+    if let Err(e) = save_history(&history) {
+        eprintln!("[Recording] {}", e);
+    }
+}
+
+/// 以前运行中使用过的录制根目录（最近的在前）
+pub fn history() -> Vec<PathBuf> {
+    HISTORY.read().unwrap().clone()
+}
@@ -0,0 +1,263 @@
+use crate::proxy::flv::{FlvDemuxer, FlvEvent, FlvTag};
+use bytes::{Bytes, BytesMut};
+
+/// 分段写入动作，按顺序执行
+pub enum SegmentAction {
+    /// 收尾当前分段，新分段以给定的 FLV 头、onMetaData 与序列头开始
+    Rotate(Bytes),
+    Write(Bytes),
+}
+
+/// 按 tag 边界切分录制的 FLV 流：请求切分后在下一个视频关键帧（纯音频流为下一个音频 tag）处开新分段，
+/// 分段里不会出现半个 tag，每个分段都可以单独播放。时间戳按分段从 0 开始
+#[derive(Default)]
+pub struct FlvSegmenter {
+    demuxer: FlvDemuxer,
+    header: Option<Bytes>,
+    metadata: Option<FlvTag>,
+    video_sequence_header: Option<FlvTag>,
+    audio_sequence_header: Option<FlvTag>,
+    seen_video: bool,
+    rotation_requested: bool,
+    // 当前分段第一个音视频 tag 的原始时间戳
+    base_timestamp: Option<u32>,
+}
+
+impl FlvSegmenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request_rotation(&mut self) {
+        self.rotation_requested = true;
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<SegmentAction>, String> {
+        let mut actions = Vec::new();
+        for event in self.demuxer.push(chunk)? {
+            match event {
+                FlvEvent::Header { raw, .. } => {
+                    self.header = Some(raw.clone());
+                    actions.push(SegmentAction::Write(raw));
+                }
+                FlvEvent::Tag(tag) => self.push_tag(tag, &mut actions),
+            }
+        }
+        Ok(actions)
+    }
+
+    fn push_tag(&mut self, tag: FlvTag, actions: &mut Vec<SegmentAction>) {
+        self.seen_video |= tag.is_video();
+        if tag.is_script() && self.metadata.is_none() {
+            self.metadata = Some(tag.clone());
+        }
+        if tag.is_sequence_header() {
+            if tag.is_video() {
+                self.video_sequence_header = Some(tag.clone());
+            } else {
+                self.audio_sequence_header = Some(tag.clone());
+            }
+        } else if self.rotation_requested
+            // 当前分段还没有音视频时不切分，避免产生只有文件头的空分段
+            && self.base_timestamp.is_some()
+            && self.is_rotation_point(&tag)
+        {
+            if let Some(preamble) = self.preamble() {
+                self.rotation_requested = false;
+                self.base_timestamp = Some(tag.timestamp);
+                actions.push(SegmentAction::Rotate(preamble));
+            }
+        }
+        actions.push(SegmentAction::Write(self.rebase(&tag)));
+    }
+
+    fn is_rotation_point(&self, tag: &FlvTag) -> bool {
+        if self.seen_video {
+            tag.is_keyframe()
+        } else {
+            tag.is_audio()
+        }
+    }
+
+    fn preamble(&self) -> Option<Bytes> {
+        let header = self.header.as_ref()?;
+        let mut preamble = BytesMut::from(&header[..]);
+        for tag in [
+            &self.metadata,
+            &self.video_sequence_header,
+            &self.audio_sequence_header,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            preamble.extend_from_slice(&tag.with_timestamp(0).raw);
+        }
+        Some(preamble.freeze())
+    }
+
+    fn rebase(&mut self, tag: &FlvTag) -> Bytes {
+        let base = match self.base_timestamp {
+            Some(base) => base,
+            // 第一个分段以第一个音视频 tag 为起点，之前的脚本/序列头原样写入
+            None if tag.is_script() || tag.is_sequence_header() => return tag.raw.clone(),
+            None => *self.base_timestamp.insert(tag.timestamp),
+        };
+        // 关键帧前后的音频可能略早于起点
+        let timestamp = tag.timestamp.saturating_sub(base);
+        if timestamp == tag.timestamp {
+            tag.raw.clone()
+        } else {
+            tag.with_timestamp(timestamp).raw
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::flv::test_util::*;
+    use crate::proxy::flv::{TAG_TYPE_AUDIO, TAG_TYPE_SCRIPT, TAG_TYPE_VIDEO};
+
+    /// 按动作拼出各分段的完整字节
+    fn apply(segments: &mut Vec<Vec<u8>>, actions: Vec<SegmentAction>) {
+        for action in actions {
+            match action {
+                SegmentAction::Rotate(preamble) => segments.push(preamble.to_vec()),
+                SegmentAction::Write(data) => {
+                    if segments.is_empty() {
+                        segments.push(Vec::new());
+                    }
+                    segments.last_mut().unwrap().extend_from_slice(&data);
+                }
+            }
+        }
+    }
+
+    fn stream_prefix() -> Vec<u8> {
+        [
+            header(true),
+            metadata(),
+            avc_sequence_header(),
+            aac_sequence_header(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn rotates_at_the_next_keyframe_with_a_fresh_preamble() {
+        let mut segmenter = FlvSegmenter::new();
+        let mut segments = Vec::new();
+        let first = [
+            stream_prefix(),
+            video(1000, true),
+            audio(1010),
+            video(1040, false),
+        ]
+        .concat();
+        apply(&mut segments, segmenter.push(&first).unwrap());
+
+        segmenter.request_rotation();
+        // 非关键帧与音频不触发切分
+        apply(
+            &mut segments,
+            segmenter
+                .push(&[video(1080, false), audio(1090)].concat())
+                .unwrap(),
+        );
+        assert_eq!(segments.len(), 1);
+        apply(
+            &mut segments,
+            segmenter
+                .push(&[video(2000, true), audio(1995), video(2040, false)].concat())
+                .unwrap(),
+        );
+        assert_eq!(segments.len(), 2);
+
+        // 第一个分段以第一个音视频 tag 为 0 点
+        assert_eq!(
+            tag_summary(&segments[0]),
+            vec![
+                (TAG_TYPE_SCRIPT, 0),
+                (TAG_TYPE_VIDEO, 0),
+                (TAG_TYPE_AUDIO, 0),
+                (TAG_TYPE_VIDEO, 0),
+                (TAG_TYPE_AUDIO, 10),
+                (TAG_TYPE_VIDEO, 40),
+                (TAG_TYPE_VIDEO, 80),
+                (TAG_TYPE_AUDIO, 90),
+            ]
+        );
+        // 新分段带 FLV 头、onMetaData 与两个序列头，关键帧之前略早的音频钳到 0
+        assert!(segments[1].starts_with(&header(true)));
+        assert_eq!(
+            tag_summary(&segments[1]),
+            vec![
+                (TAG_TYPE_SCRIPT, 0),
+                (TAG_TYPE_VIDEO, 0),
+                (TAG_TYPE_AUDIO, 0),
+                (TAG_TYPE_VIDEO, 0),
+                (TAG_TYPE_AUDIO, 0),
+                (TAG_TYPE_VIDEO, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn output_does_not_depend_on_chunk_boundaries() {
+        let stream = [
+            stream_prefix(),
+            video(0, true),
+            audio(20),
+            video(40, false),
+            video(80, true),
+            audio(90),
+        ]
+        .concat();
+        let run = |chunk_size: usize| {
+            let mut segmenter = FlvSegmenter::new();
+            segmenter.request_rotation();
+            let mut segments = Vec::new();
+            for chunk in stream.chunks(chunk_size) {
+                apply(&mut segments, segmenter.push(chunk).unwrap());
+            }
+            segments
+        };
+        let expected = run(stream.len());
+        for chunk_size in [1, 5, 17, 64] {
+            assert_eq!(run(chunk_size), expected, "chunk size {}", chunk_size);
+        }
+        // 开头还没有音视频时不切分，第一个分段不会是空的
+        assert_eq!(expected.len(), 2);
+        assert_eq!(
+            tag_summary(&expected[0])
+                .into_iter()
+                .filter(|(_, ts)| *ts > 0)
+                .count(),
+            2
+        );
+        assert_eq!(
+            tag_summary(&expected[1])[3..],
+            [(TAG_TYPE_VIDEO, 0), (TAG_TYPE_AUDIO, 10)]
+        );
+    }
+
+    #[test]
+    fn audio_only_streams_rotate_on_audio_tags() {
+        let mut segmenter = FlvSegmenter::new();
+        let mut segments = Vec::new();
+        let prefix = [header(false), metadata(), aac_sequence_header(), audio(500)].concat();
+        apply(&mut segments, segmenter.push(&prefix).unwrap());
+        segmenter.request_rotation();
+        apply(&mut segments, segmenter.push(&audio(523)).unwrap());
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            tag_summary(&segments[1]),
+            vec![
+                (TAG_TYPE_SCRIPT, 0),
+                (TAG_TYPE_AUDIO, 0),
+                (TAG_TYPE_AUDIO, 0)
+            ]
+        );
+    }
+}
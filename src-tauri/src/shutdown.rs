@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, Window, WindowEvent};
+
+use crate::platforms::common::danmaku_listener::DanmakuRegistry;
+use crate::recording::{self, RecordingManager, RecordingTaskSnapshot};
+
+pub const EXIT_CONFIRM_EVENT: &str = "app-exit-confirm";
+const STATE_FILE_NAME: &str = "last-session.json";
+// 等待录制刷盘、收尾的上限，超时后直接退出
+const RECORDING_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+// 等待弹幕监听断开、写完存档的上限
+const DANMAKU_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 退出流程的状态：`confirmed` 为用户已确认中断录制，`finished` 为收尾完成、允许进程退出
+#[derive(Default)]
+pub struct ShutdownState {
+    confirmed: AtomicBool,
+    started: AtomicBool,
+    finished: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningRoom {
+    pub platform: String,
+    pub room_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedRecording {
+    pub platform: String,
+    pub room_id: String,
+    pub quality: String,
+    pub output_dir: String,
+    pub last_file: Option<String>,
+}
+
+/// 退出时仍在运行的任务，下次启动时可据此提示恢复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastSessionState {
+    pub saved_at: i64,
+    pub recordings: Vec<InterruptedRecording>,
+    pub proxy_sessions: Vec<RunningRoom>,
+    pub danmaku_listeners: Vec<RunningRoom>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExitConfirmPayload {
+    active_recordings: usize,
+}
+
+fn state_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(STATE_FILE_NAME)
+}
+
+fn save_last_session(state: &LastSessionState) -> Result<(), String> {
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize last session: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// 通知各平台弹幕监听断开，并等待它们写完存档结尾和会话信息
+async fn stop_danmaku_listeners(app_handle: &AppHandle) -> Vec<RunningRoom> {
+    app_handle
+        .state::<DanmakuRegistry>()
+        .shutdown_all(DANMAKU_SHUTDOWN_TIMEOUT)
+        .await
+        .into_iter()
+        .map(|(platform, room_id)| RunningRoom {
+            platform: platform.as_str().to_string(),
+            room_id: Some(room_id),
+        })
+        .collect()
+}
+
+/// 通知所有录制、弹幕监听与代理会话退出，等待录制和弹幕存档收尾后记录退出时的运行状态
+pub async fn graceful_shutdown(app_handle: &AppHandle) {
+    println!("[Rust/shutdown.rs] Shutting down...");
+    let recording_manager = app_handle.state::<RecordingManager>();
+    let (danmaku_listeners, recordings): (Vec<RunningRoom>, Vec<RecordingTaskSnapshot>) = tokio::join!(
+        stop_danmaku_listeners(app_handle),
+        recording::shutdown_all(&recording_manager, RECORDING_SHUTDOWN_TIMEOUT),
+    );
+    let proxy_sessions = crate::proxy::shutdown_all(app_handle).await;
+
+    let state = LastSessionState {
+        saved_at: chrono::Local::now().timestamp_millis(),
+        recordings: recordings
+            .into_iter()
+            .map(|snap| InterruptedRecording {
+                platform: snap.platform,
+                room_id: snap.room_id,
+                quality: snap.quality,
+                output_dir: snap.output_dir,
+                last_file: snap.current_file,
+            })
+            .collect(),
+        proxy_sessions,
+        danmaku_listeners,
+    };
+    println!(
+        "[Rust/shutdown.rs] Stopped {} recording(s), {} proxy session(s), {} danmaku listener(s)",
+        state.recordings.len(),
+        state.proxy_sessions.len(),
+        state.danmaku_listeners.len()
+    );
+    if let Err(e) = save_last_session(&state) {
+        eprintln!("[Rust/shutdown.rs] {}", e);
+    }
+}
+
+/// 关闭最后一个窗口时如有录制在进行，先阻止关闭并请前端确认
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return;
+    };
+    let app_handle = window.app_handle();
+    if app_handle.webview_windows().len() > 1
+        || app_handle
+            .state::<ShutdownState>()
+            .confirmed
+            .load(Ordering::SeqCst)
+    {
+        return;
+    }
+    let active_recordings =
+        recording::active_recording_count(&app_handle.state::<RecordingManager>());
+    if active_recordings == 0 {
+        return;
+    }
+    api.prevent_close();
+    let _ = window.emit(EXIT_CONFIRM_EVENT, ExitConfirmPayload { active_recordings });
+}
+
+/// 拦截进程退出，先完成收尾再真正退出
+pub fn handle_run_event(app_handle: &AppHandle, event: RunEvent) {
+    let RunEvent::ExitRequested { api, .. } = event else {
+        return;
+    };
+    let state = app_handle.state::<ShutdownState>();
+    if state.finished.load(Ordering::SeqCst) {
+        return;
+    }
+    api.prevent_exit();
+    if state.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        graceful_shutdown(&app_handle).await;
+        app_handle
+            .state::<ShutdownState>()
+            .finished
+            .store(true, Ordering::SeqCst);
+        app_handle.exit(0);
+    });
+}
+
+/// 前端确认中断录制后调用，走正常的收尾流程退出
+#[tauri::command]
+pub fn confirm_app_exit(app_handle: AppHandle, shutdown: State<'_, ShutdownState>) {
+    shutdown.confirmed.store(true, Ordering::SeqCst);
+    app_handle.exit(0);
+}
+
+/// 读取并清除上次退出时记录的运行状态
+#[tauri::command]
+pub fn take_last_session_state() -> Option<LastSessionState> {
+    let path = state_path();
+    let raw = std::fs::read_to_string(&path).ok()?;
+    let _ = std::fs::remove_file(&path);
+    match serde_json::from_str::<LastSessionState>(&raw) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            eprintln!(
+                "[Rust/shutdown.rs] Ignoring invalid {}: {}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
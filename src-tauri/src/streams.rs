@@ -0,0 +1,635 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+use tokio::sync::{oneshot, watch};
+
+const SETTINGS_FILE_NAME: &str = "stream-limits.json";
+// 还没有实测码率时按该值占用带宽预算
+const DEFAULT_STREAM_KBPS: u64 = 4000;
+// 排队期间定期重新评估，实测码率下降后可能腾出预算
+const REBALANCE_INTERVAL: Duration = Duration::from_secs(2);
+const RATE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 上游连接的来源。
+///
+/// 需求中提到的片段缓冲（clip buffer）在本仓库中还没有对应功能、也没有拉流的调用方，
+/// 这里不预留变体；以后加入剪辑功能时应新增一个变体并在拉流前申请租约
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamKind {
+    Recording,
+    // 代理会话的常驻拉流（回看/自适应缓冲区）
+    SessionBuffer,
+    // 播放器直连代理时每个客户端一条上游连接
+    Preview,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamPriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl StreamKind {
+    /// 录制优先于缓冲区，缓冲区优先于预览
+    pub fn priority(self) -> StreamPriority {
+        match self {
+            StreamKind::Recording => StreamPriority::High,
+            StreamKind::SessionBuffer => StreamPriority::Normal,
+            StreamKind::Preview => StreamPriority::Low,
+        }
+    }
+
+    /// 预览是长连接，只靠排队顺序录制可能一直等下去，因此放不下录制时直接断开最新的预览
+    fn preemptible_by(self, other: StreamKind) -> bool {
+        self == StreamKind::Preview && other == StreamKind::Recording
+    }
+}
+
+/// 并发与带宽上限；0 表示不限制
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamLimits {
+    #[serde(default)]
+    pub max_concurrent: u32,
+    #[serde(default)]
+    pub bandwidth_budget_kbps: u64,
+    #[serde(default = "default_stream_kbps")]
+    pub default_stream_kbps: u64,
+    // 预览排队超过该时长直接返回 503，避免播放器一直挂起
+    #[serde(default = "default_preview_queue_seconds")]
+    pub preview_queue_seconds: u64,
+}
+
+fn default_stream_kbps() -> u64 {
+    DEFAULT_STREAM_KBPS
+}
+
+fn default_preview_queue_seconds() -> u64 {
+    15
+}
+
+impl Default for StreamLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 0,
+            bandwidth_budget_kbps: 0,
+            default_stream_kbps: DEFAULT_STREAM_KBPS,
+            preview_queue_seconds: default_preview_queue_seconds(),
+        }
+    }
+}
+
+impl StreamLimits {
+    fn normalized(mut self) -> Self {
+        if self.default_stream_kbps == 0 {
+            self.default_stream_kbps = DEFAULT_STREAM_KBPS;
+        }
+        self.preview_queue_seconds = self.preview_queue_seconds.clamp(1, 600);
+        self
+    }
+}
+
+/// 可被采样的累计字节数，带宽按两次采样的差值计算
+pub trait ByteCounter: Send + Sync {
+    fn total_bytes(&self) -> u64;
+}
+
+impl ByteCounter for AtomicU64 {
+    fn total_bytes(&self) -> u64 {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+/// 申请一条上游连接时的描述
+pub struct StreamRequest {
+    pub kind: StreamKind,
+    pub platform: String,
+    pub room_id: Option<String>,
+    // 录制任务 id 或代理会话 id
+    pub owner_id: Option<String>,
+    // 已有自己的字节计数（例如会话指标）时复用，否则由租约自行计数
+    pub counter: Option<Arc<dyn ByteCounter>>,
+}
+
+impl StreamRequest {
+    pub fn new(kind: StreamKind, platform: &str, room_id: Option<&str>) -> Self {
+        Self {
+            kind,
+            platform: platform.to_string(),
+            room_id: room_id.map(str::to_string),
+            owner_id: None,
+            counter: None,
+        }
+    }
+
+    pub fn owner(mut self, owner_id: &str) -> Self {
+        self.owner_id = Some(owner_id.to_string());
+        self
+    }
+
+    pub fn counter(mut self, counter: Arc<dyn ByteCounter>) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+}
+
+struct RateSample {
+    at: Instant,
+    bytes: u64,
+    kbps: Option<f64>,
+}
+
+struct ActiveStream {
+    kind: StreamKind,
+    platform: String,
+    room_id: Option<String>,
+    owner_id: Option<String>,
+    counter: Arc<dyn ByteCounter>,
+    started_at: Instant,
+    estimate_kbps: u64,
+    rate: RateSample,
+    // 被抢占时置为 true，持有租约的一方据此断开
+    preempt_tx: watch::Sender<bool>,
+}
+
+impl ActiveStream {
+    // 有实测值后按实测码率占用预算
+    fn budget_kbps(&self) -> u64 {
+        self.rate
+            .kbps
+            .map(|kbps| kbps.ceil() as u64)
+            .unwrap_or(self.estimate_kbps)
+    }
+
+    fn sample_rate(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.rate.at);
+        if elapsed < RATE_SAMPLE_INTERVAL {
+            return;
+        }
+        let bytes = self.counter.total_bytes();
+        let kbps =
+            bytes.saturating_sub(self.rate.bytes) as f64 * 8.0 / elapsed.as_secs_f64() / 1000.0;
+        self.rate = RateSample {
+            at: now,
+            bytes,
+            kbps: Some(kbps),
+        };
+    }
+}
+
+struct QueuedStream {
+    request: StreamRequest,
+    enqueued_at: Instant,
+    grant_tx: oneshot::Sender<()>,
+    preempt_tx: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    next_id: u64,
+    active: HashMap<u64, ActiveStream>,
+    // 按优先级从高到低、同级先来先得的顺序放行
+    queue: Vec<(u64, QueuedStream)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamEntryInfo {
+    pub id: u64,
+    pub kind: StreamKind,
+    pub priority: StreamPriority,
+    pub platform: String,
+    pub room_id: Option<String>,
+    pub owner_id: Option<String>,
+    // 运行秒数；排队中的条目为已排队秒数
+    pub elapsed_seconds: u64,
+    pub bytes: Option<u64>,
+    pub current_kbps: Option<f64>,
+    pub budget_kbps: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamsOverview {
+    pub limits: StreamLimits,
+    pub used_kbps: u64,
+    pub active: Vec<StreamEntryInfo>,
+    pub queued: Vec<StreamEntryInfo>,
+}
+
+/// 全局上游连接调度：限制并发数与总带宽，超出时按优先级排队
+pub struct StreamBudget {
+    limits: StdMutex<StreamLimits>,
+    state: StdMutex<BudgetState>,
+}
+
+impl Default for StreamBudget {
+    fn default() -> Self {
+        Self {
+            limits: StdMutex::new(load_limits()),
+            state: StdMutex::new(BudgetState::default()),
+        }
+    }
+}
+
+impl BudgetState {
+    fn used_kbps(&self) -> u64 {
+        self.active.values().map(ActiveStream::budget_kbps).sum()
+    }
+
+    // 至少放行一条，单条码率超过预算时也不会永远排队
+    fn fits(&self, limits: &StreamLimits) -> bool {
+        if limits.max_concurrent > 0 && self.active.len() >= limits.max_concurrent as usize {
+            return false;
+        }
+        limits.bandwidth_budget_kbps == 0
+            || self.active.is_empty()
+            || self.used_kbps() + limits.default_stream_kbps <= limits.bandwidth_budget_kbps
+    }
+
+    fn activate(
+        &mut self,
+        id: u64,
+        request: StreamRequest,
+        preempt_tx: watch::Sender<bool>,
+        limits: &StreamLimits,
+    ) {
+        let counter = request
+            .counter
+            .unwrap_or_else(|| Arc::new(AtomicU64::new(0)));
+        let now = Instant::now();
+        self.active.insert(
+            id,
+            ActiveStream {
+                kind: request.kind,
+                platform: request.platform,
+                room_id: request.room_id,
+                owner_id: request.owner_id,
+                rate: RateSample {
+                    at: now,
+                    bytes: counter.total_bytes(),
+                    kbps: None,
+                },
+                counter,
+                started_at: now,
+                estimate_kbps: limits.default_stream_kbps,
+                preempt_tx,
+            },
+        );
+    }
+
+    // 断开最新的一条可被 `kind` 抢占的连接，名额立即归还
+    fn preempt_one(&mut self, kind: StreamKind) -> bool {
+        let Some(id) = self
+            .active
+            .iter()
+            .filter(|(_, stream)| stream.kind.preemptible_by(kind))
+            .map(|(id, _)| *id)
+            .max()
+        else {
+            return false;
+        };
+        if let Some(stream) = self.active.remove(&id) {
+            let _ = stream.preempt_tx.send(true);
+            println!(
+                "[Rust/streams.rs] Preempted {:?} stream #{} ({}) for {:?}",
+                stream.kind, id, stream.platform, kind
+            );
+        }
+        true
+    }
+
+    // 放不下时先尝试抢占，返回最终是否放得下
+    fn make_room(&mut self, kind: StreamKind, limits: &StreamLimits) -> bool {
+        while !self.fits(limits) {
+            if !self.preempt_one(kind) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn enqueue(&mut self, id: u64, queued: QueuedStream) {
+        let priority = queued.request.kind.priority();
+        let position = self
+            .queue
+            .iter()
+            .position(|(_, q)| q.request.kind.priority() < priority)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(position, (id, queued));
+    }
+
+    // 队首放不下时停止，低优先级不能插队
+    fn drain_queue(&mut self, limits: &StreamLimits) {
+        let now = Instant::now();
+        for stream in self.active.values_mut() {
+            stream.sample_rate(now);
+        }
+        // 等待方已放弃（取消或超时）的条目直接丢弃
+        self.queue.retain(|(_, q)| !q.grant_tx.is_closed());
+        while let Some(kind) = self.queue.first().map(|(_, q)| q.request.kind) {
+            if !self.make_room(kind, limits) {
+                break;
+            }
+            let (id, queued) = self.queue.remove(0);
+            let QueuedStream {
+                request,
+                grant_tx,
+                preempt_tx,
+                ..
+            } = queued;
+            let platform = request.platform.clone();
+            self.activate(id, request, preempt_tx, limits);
+            if grant_tx.send(()).is_err() {
+                self.active.remove(&id);
+                continue;
+            }
+            println!(
+                "[Rust/streams.rs] Granted queued {:?} stream #{} ({})",
+                kind, id, platform
+            );
+        }
+    }
+}
+
+impl StreamBudget {
+    pub fn limits(&self) -> StreamLimits {
+        self.limits.lock().unwrap().clone()
+    }
+
+    pub fn set_limits(&self, limits: StreamLimits) {
+        let limits = limits.normalized();
+        *self.limits.lock().unwrap() = limits.clone();
+        self.state.lock().unwrap().drain_queue(&limits);
+    }
+
+    fn release(&self, id: u64) {
+        let limits = self.limits();
+        let mut state = self.state.lock().unwrap();
+        state.active.remove(&id);
+        state.queue.retain(|(queued_id, _)| *queued_id != id);
+        state.drain_queue(&limits);
+    }
+
+    fn rebalance(&self) {
+        let limits = self.limits();
+        self.state.lock().unwrap().drain_queue(&limits);
+    }
+
+    /// 申请一条上游连接；超出限制时排队等待，直到放行或调用方放弃（future 被丢弃）。
+    /// 录制放不下时会抢占预览的名额，被抢占的租约通过 `StreamLease::preempted` 得知
+    pub async fn acquire(self: &Arc<Self>, request: StreamRequest) -> StreamLease {
+        let limits = self.limits();
+        let own_counter = request
+            .counter
+            .is_none()
+            .then(|| Arc::new(AtomicU64::new(0)));
+        let request = StreamRequest {
+            counter: request.counter.clone().or_else(|| {
+                own_counter
+                    .clone()
+                    .map(|counter| counter as Arc<dyn ByteCounter>)
+            }),
+            ..request
+        };
+        let (preempt_tx, preempt_rx) = watch::channel(false);
+        let (id, grant_rx) = {
+            let mut state = self.state.lock().unwrap();
+            state.next_id += 1;
+            let id = state.next_id;
+            let kind = request.kind;
+            // 同级及以上已有排队时不能插队
+            let queued_ahead = state
+                .queue
+                .iter()
+                .any(|(_, q)| q.request.kind.priority() >= kind.priority());
+            if !queued_ahead && state.make_room(kind, &limits) {
+                state.activate(id, request, preempt_tx, &limits);
+                (id, None)
+            } else {
+                println!(
+                    "[Rust/streams.rs] Queueing {:?} stream #{} ({}) active={} used={}kbps",
+                    kind,
+                    id,
+                    request.platform,
+                    state.active.len(),
+                    state.used_kbps()
+                );
+                let (grant_tx, grant_rx) = oneshot::channel();
+                state.enqueue(
+                    id,
+                    QueuedStream {
+                        request,
+                        enqueued_at: Instant::now(),
+                        grant_tx,
+                        preempt_tx,
+                    },
+                );
+                (id, Some(grant_rx))
+            }
+        };
+
+        // 先构造租约：排队期间被取消时同样会在 Drop 中释放
+        let lease = StreamLease {
+            budget: self.clone(),
+            id,
+            own_counter,
+            preempt_rx,
+        };
+        if let Some(mut grant_rx) = grant_rx {
+            loop {
+                match tokio::time::timeout(REBALANCE_INTERVAL, &mut grant_rx).await {
+                    Ok(_) => break,
+                    Err(_) => self.rebalance(),
+                }
+            }
+        }
+        lease
+    }
+
+    /// 最多排队 `wait`，超时返回 None
+    pub async fn acquire_within(
+        self: &Arc<Self>,
+        request: StreamRequest,
+        wait: Duration,
+    ) -> Option<StreamLease> {
+        tokio::time::timeout(wait, self.acquire(request)).await.ok()
+    }
+
+    /// 排队期间收到停止信号时返回 None
+    pub async fn acquire_until_stopped(
+        self: &Arc<Self>,
+        request: StreamRequest,
+        stop_rx: &mut watch::Receiver<bool>,
+    ) -> Option<StreamLease> {
+        if *stop_rx.borrow() {
+            return None;
+        }
+        tokio::select! {
+            lease = self.acquire(request) => Some(lease),
+            _ = stop_rx.changed() => None,
+        }
+    }
+
+    /// 预览连接的排队上限
+    pub fn preview_wait(&self) -> Duration {
+        Duration::from_secs(self.limits.lock().unwrap().preview_queue_seconds)
+    }
+
+    pub fn overview(&self) -> StreamsOverview {
+        let limits = self.limits();
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        for stream in state.active.values_mut() {
+            stream.sample_rate(now);
+        }
+        let mut active: Vec<StreamEntryInfo> = state
+            .active
+            .iter()
+            .map(|(id, stream)| StreamEntryInfo {
+                id: *id,
+                kind: stream.kind,
+                priority: stream.kind.priority(),
+                platform: stream.platform.clone(),
+                room_id: stream.room_id.clone(),
+                owner_id: stream.owner_id.clone(),
+                elapsed_seconds: now.duration_since(stream.started_at).as_secs(),
+                bytes: Some(stream.counter.total_bytes()),
+                current_kbps: stream.rate.kbps,
+                budget_kbps: Some(stream.budget_kbps()),
+            })
+            .collect();
+        active.sort_by_key(|entry| (std::cmp::Reverse(entry.priority), entry.id));
+        let queued = state
+            .queue
+            .iter()
+            .map(|(id, queued)| StreamEntryInfo {
+                id: *id,
+                kind: queued.request.kind,
+                priority: queued.request.kind.priority(),
+                platform: queued.request.platform.clone(),
+                room_id: queued.request.room_id.clone(),
+                owner_id: queued.request.owner_id.clone(),
+                elapsed_seconds: now.duration_since(queued.enqueued_at).as_secs(),
+                bytes: None,
+                current_kbps: None,
+                budget_kbps: None,
+            })
+            .collect();
+        StreamsOverview {
+            used_kbps: state.used_kbps(),
+            limits,
+            active,
+            queued,
+        }
+    }
+}
+
+/// 持有期间占用一个并发名额与相应带宽；丢弃时释放并放行排队的连接
+pub struct StreamLease {
+    budget: Arc<StreamBudget>,
+    id: u64,
+    own_counter: Option<Arc<AtomicU64>>,
+    preempt_rx: watch::Receiver<bool>,
+}
+
+impl StreamLease {
+    /// 名额被更高优先级的连接抢占时完成；不会被抢占的连接永远不会完成
+    pub fn preempted(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut preempt_rx = self.preempt_rx.clone();
+        async move {
+            while !*preempt_rx.borrow_and_update() {
+                if preempt_rx.changed().await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            }
+        }
+    }
+
+    /// 记录本连接读到的字节；申请时传入了外部计数的连接由外部负责累加
+    pub fn record_bytes(&self, len: usize) {
+        if let Some(counter) = &self.own_counter {
+            counter.fetch_add(len as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for StreamLease {
+    fn drop(&mut self) {
+        self.budget.release(self.id);
+    }
+}
+
+#[derive(Default)]
+pub struct StreamManager(pub Arc<StreamBudget>);
+
+fn settings_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("DTV")
+        .join(SETTINGS_FILE_NAME)
+}
+
+fn load_limits() -> StreamLimits {
+    let path = settings_path();
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return StreamLimits::default();
+    };
+    match serde_json::from_str::<StreamLimits>(&raw) {
+        Ok(limits) => limits.normalized(),
+        Err(e) => {
+            eprintln!(
+                "[Rust/streams.rs] Ignoring invalid limits in {}: {}",
+                path.display(),
+                e
+            );
+            StreamLimits::default()
+        }
+    }
+}
+
+fn save_limits(limits: &StreamLimits) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(limits)
+        .map_err(|e| format!("Failed to serialize stream limits: {}", e))?;
+    std::fs::write(&path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 所有录制、代理会话与预览的上游连接，以及排队中的申请
+#[tauri::command]
+pub fn list_active_streams(streams: State<'_, StreamManager>) -> StreamsOverview {
+    streams.0.overview()
+}
+
+#[tauri::command]
+pub fn get_stream_limits(streams: State<'_, StreamManager>) -> StreamLimits {
+    streams.0.limits()
+}
+
+/// 保存后立即生效：放宽限制时会放行排队的连接，收紧时不会中断已在进行的连接
+#[tauri::command]
+pub fn set_stream_limits(
+    streams: State<'_, StreamManager>,
+    limits: StreamLimits,
+) -> Result<StreamsOverview, String> {
+    let limits = limits.normalized();
+    save_limits(&limits)?;
+    streams.0.set_limits(limits.clone());
+    println!(
+        "[Rust/streams.rs] Limits updated: max_concurrent={} budget={}kbps",
+        limits.max_concurrent, limits.bandwidth_budget_kbps
+    );
+    Ok(streams.0.overview())
+}
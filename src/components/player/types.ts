@@ -1,8 +1,7 @@
 import { Platform } from '../../platforms/common/types';
 
 // This interface represents the Rust struct: crate::platforms::common::GetStreamUrlPayload
-// It is used as the type for the value of the 'payload' key when invoking 'start_douyin_danmu_listener'
-// It might also be useful for other platform-specific player helpers if they use a similar payload structure.
+// It might be useful for platform-specific player helpers that use this payload structure.
 export interface RustGetStreamUrlPayload {
   args: {
     room_id_str: string;
@@ -6,6 +6,7 @@ import { useFollowStore } from './store/followStore';
 import { useThemeStore } from './stores/theme';
 import { useRecordingStore } from './stores/recording';
 import { check } from '@tauri-apps/plugin-updater';
+import { confirmAppExit, onExitConfirmRequested } from './platforms/common/lifecycle';
 
 const app = createApp(App);
 const pinia = createPinia();
@@ -48,4 +49,12 @@ const maybeCheckForUpdates = async () => {
 
 void maybeCheckForUpdates();
 
+void onExitConfirmRequested(({ activeRecordings }) => {
+  const shouldExit = window.confirm(`有 ${activeRecordings} 个录制正在进行，退出将停止录制。确定要退出吗？`);
+  if (!shouldExit) return;
+  void confirmAppExit().catch((error) => {
+    console.error('[main.ts] Failed to exit:', error);
+  });
+});
+
 app.mount('#app');
@@ -1,9 +1,8 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
 import type { LiveStreamInfo, StreamVariant } from '../common/types';
 import type { Ref } from 'vue';
 import type { DanmakuMessage, DanmuOverlayInstance, DanmuRenderOptions } from '../../components/player/types';
-import { v4 as uuidv4 } from 'uuid';
+import { listenRoomDanmaku, listenRoomDanmakuErrors, startDanmaku, stopDanmaku, toDanmakuMessage } from '../common/danmaku';
 import { startFlvProxySession } from '../common/flvProxySession';
 import { Platform } from '../common/types';
 
@@ -120,15 +119,6 @@ export async function getBilibiliStreamConfig(
   return { streamUrl: result.stream_url, streamType, proxySessionId: undefined };
 }
 
-// 统一的 Rust 弹幕事件负载（与 Douyin/Douyu/Huya 保持一致）
-interface UnifiedRustDanmakuPayload {
-  room_id: string;
-  user: string;
-  content: string;
-  user_level: number;
-  fans_club_level: number;
-}
-
 export async function startBilibiliDanmakuListener(
   roomId: string,
   danmuOverlay: DanmuOverlayInstance | null,
@@ -138,27 +128,15 @@ export async function startBilibiliDanmakuListener(
 ): Promise<() => void> {
   // 启动后端 B 站弹幕监听（cookie 可选）；若未传，则从 localStorage 兜底读取
   const effectiveCookie = cookie ?? (typeof localStorage !== 'undefined' ? (localStorage.getItem('bilibili_cookie') || undefined) : undefined);
-  await invoke('start_bilibili_danmaku_listener', {
-    payload: { args: { room_id_str: roomId } },
-    cookie: effectiveCookie || null,
-  });
-
-  const eventName = 'danmaku-message';
-  const unlisten = await listen<UnifiedRustDanmakuPayload>(eventName, (event: TauriEvent<UnifiedRustDanmakuPayload>) => {
-    if (!event.payload || event.payload.room_id !== roomId) return;
+  await startDanmaku('BILIBILI', roomId, { cookie: effectiveCookie || null });
 
-    const frontendDanmaku: DanmakuMessage = {
-      id: uuidv4(),
-      nickname: event.payload.user || '未知用户',
-      content: event.payload.content,
-      level: String(event.payload.user_level ?? 0),
-      badgeLevel: event.payload.fans_club_level != null ? String(event.payload.fans_club_level) : undefined,
-      room_id: roomId,
-    };
+  const unlisten = await listenRoomDanmaku(roomId, (event) => {
+    const frontendDanmaku = toDanmakuMessage(event);
+    if (!frontendDanmaku) return;
 
     const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
 
-    if (shouldDisplay && danmuOverlay?.sendComment) {
+    if (shouldDisplay && !frontendDanmaku.isSystem && danmuOverlay?.sendComment) {
       try {
         const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
         const styleFromOptions = commentOptions.style ?? {};
@@ -186,7 +164,13 @@ export async function startBilibiliDanmakuListener(
       }
     }
   });
-  return unlisten;
+  const unlistenErrors = await listenRoomDanmakuErrors(roomId, (error) => {
+    console.warn(`[BilibiliPlayerHelper] Danmaku connection error (retry=${error.willRetry}):`, error.message);
+  });
+  return () => {
+    unlisten();
+    unlistenErrors();
+  };
 }
 
 export async function stopBilibiliDanmaku(roomId: string, currentUnlistenFn: (() => void) | null): Promise<void> {
@@ -194,6 +178,6 @@ export async function stopBilibiliDanmaku(roomId: string, currentUnlistenFn: (()
     try { currentUnlistenFn(); } catch {}
   }
   try {
-    await invoke('stop_bilibili_danmaku_listener', { roomId: roomId || '' });
+    await stopDanmaku('BILIBILI', roomId || '');
   } catch {}
 }
@@ -0,0 +1,160 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { v4 as uuidv4 } from 'uuid';
+import type { DanmakuMessage } from '../../components/player/types';
+import type {
+  DanmakuErrorPayload,
+  DanmakuEvent,
+  DanmakuFilterRules,
+  DanmakuFilterSettings,
+  DanmakuListenerInfo,
+  DanmakuPlatform,
+  DanmakuStatePayload,
+} from './danmakuBindings';
+
+// 类型由后端 specta 生成（开发构建启动时刷新 danmakuBindings.ts）
+export type {
+  DanmakuErrorPayload,
+  DanmakuEvent,
+  DanmakuFilterRules,
+  DanmakuFilterSettings,
+  DanmakuListenerInfo,
+  DanmakuMedal,
+  DanmakuMessageType,
+  DanmakuNoble,
+  DanmakuPlatform,
+  DanmakuRoomFilter,
+  DanmakuStatePayload,
+  DanmakuUser,
+} from './danmakuBindings';
+
+export const DANMAKU_EVENT = 'danmaku-event';
+export const DANMAKU_ERROR_EVENT = 'danmaku-error';
+export const DANMAKU_STATE_EVENT = 'danmaku-state';
+
+export interface DanmakuStartOptions {
+  /** B 站登录 Cookie */
+  cookie?: string | null;
+}
+
+/**
+ * 订阅房间弹幕。同一房间的多个订阅共用一条后端连接；
+ * 事件里的 roomId 以返回值为准（例如抖音会把链接规范成 web_rid）
+ */
+export function startDanmaku(
+  platform: DanmakuPlatform,
+  roomId: string,
+  options?: DanmakuStartOptions,
+): Promise<DanmakuListenerInfo> {
+  return invoke<DanmakuListenerInfo>('start_danmaku', { platform, roomId, options: options ?? null });
+}
+
+/** 取消订阅，返回该房间剩余的订阅数（为 0 时后端已断开） */
+export function stopDanmaku(platform: DanmakuPlatform, roomId: string): Promise<number> {
+  return invoke<number>('stop_danmaku', { platform, roomId });
+}
+
+export function listDanmakuListeners(): Promise<DanmakuListenerInfo[]> {
+  return invoke<DanmakuListenerInfo[]>('list_danmaku_listeners');
+}
+
+/** 只回调指定房间的弹幕事件 */
+export function listenRoomDanmaku(roomId: string, handler: (event: DanmakuEvent) => void): Promise<UnlistenFn> {
+  return listen<DanmakuEvent>(DANMAKU_EVENT, (event) => {
+    if (event.payload && event.payload.roomId === roomId) {
+      handler(event.payload);
+    }
+  });
+}
+
+/** 连接出错时回调；willRetry 为 false 表示后端监听已退出 */
+export function listenRoomDanmakuErrors(
+  roomId: string,
+  handler: (error: DanmakuErrorPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<DanmakuErrorPayload>(DANMAKU_ERROR_EVENT, (event) => {
+    if (event.payload && event.payload.roomId === roomId) {
+      handler(event.payload);
+    }
+  });
+}
+
+export function getDanmakuFilterSettings(): Promise<DanmakuFilterSettings> {
+  return invoke<DanmakuFilterSettings>('get_danmaku_filter_settings');
+}
+
+/** 保存全部过滤规则；正则不合法时返回错误，运行中的监听在下一条弹幕时生效 */
+export function setDanmakuFilterSettings(settings: DanmakuFilterSettings): Promise<DanmakuFilterSettings> {
+  return invoke<DanmakuFilterSettings>('set_danmaku_filter_settings', { settings });
+}
+
+/** 设置单个房间的规则，传 null 删除 */
+export function setRoomDanmakuFilter(
+  platform: DanmakuPlatform,
+  roomId: string,
+  rules: DanmakuFilterRules | null,
+): Promise<DanmakuFilterSettings> {
+  return invoke<DanmakuFilterSettings>('set_room_danmaku_filter', { platform, roomId, rules });
+}
+
+/** 连接状态变化：connecting / connected / reconnecting / closed */
+export function listenRoomDanmakuState(
+  platform: DanmakuPlatform,
+  roomId: string,
+  handler: (state: DanmakuStatePayload) => void,
+): Promise<UnlistenFn> {
+  return listen<DanmakuStatePayload>(DANMAKU_STATE_EVENT, (event) => {
+    if (event.payload && event.payload.platform === platform && event.payload.roomId === roomId) {
+      handler(event.payload);
+    }
+  });
+}
+
+function describeEvent(event: DanmakuEvent): string | null {
+  switch (event.type) {
+    case 'chat':
+      return event.content;
+    case 'gift':
+      // count 为 0 表示连击汇总，单次礼物已经逐条展示过
+      if (event.count === 0) {
+        return event.comboTotal ? `连击 ${event.giftName} 共 ×${event.comboTotal}` : null;
+      }
+      return `送出 ${event.giftName} ×${event.count}`;
+    case 'superChat':
+      return `[醒目留言 ¥${event.valueCny}] ${event.content}`;
+    case 'guard':
+      return event.months && event.months > 1
+        ? `开通了${event.levelName} ×${event.months}个月`
+        : `开通了${event.levelName}`;
+    case 'follow':
+      return '关注了主播';
+    case 'notice':
+      return `[系统] ${event.message}`;
+    default:
+      // 进场、点赞、房间统计、房间信息和开关播不进入弹幕列表
+      return null;
+  }
+}
+
+/**
+ * 转成弹幕列表使用的结构；不需要展示的事件返回 null。
+ * 聊天和醒目留言之外的消息标记为 isSystem，只进入列表不上屏
+ */
+export function toDanmakuMessage(event: DanmakuEvent): DanmakuMessage | null {
+  const content = describeEvent(event);
+  if (content === null) return null;
+  const medal = event.user?.medal;
+  return {
+    id: uuidv4(),
+    type: event.type,
+    isSystem: event.type !== 'chat' && event.type !== 'superChat',
+    uid: event.user?.id ?? undefined,
+    nickname: event.user?.nickname || '未知用户',
+    level: String(event.user?.level ?? 0),
+    content,
+    badgeName: medal?.name,
+    badgeLevel: medal ? String(medal.level) : undefined,
+    color: event.color ?? undefined,
+    room_id: event.roomId,
+  };
+}
@@ -0,0 +1,134 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+import type {
+  DanmakuArchiveInfo,
+  DanmakuArchiveSettings,
+  DanmakuExportFormat,
+  DanmakuExportOptions,
+  DanmakuExportResult,
+  DanmakuPlatform,
+  DanmakuReplayInfo,
+  DanmakuReplayPayload,
+  DanmakuReplayState,
+} from './danmakuBindings';
+
+export type {
+  DanmakuArchiveInfo,
+  DanmakuArchiveSettings,
+  DanmakuExportFormat,
+  DanmakuExportOptions,
+  DanmakuExportResult,
+  DanmakuReplayInfo,
+  DanmakuReplayPayload,
+  DanmakuReplayState,
+  DanmakuReplayStatus,
+} from './danmakuBindings';
+
+export const DANMAKU_REPLAY_EVENT = 'danmaku-replay';
+export const DANMAKU_REPLAY_STATE_EVENT = 'danmaku-replay-state';
+
+/** 列出弹幕存档（开始时间倒序），可按平台、房间筛选 */
+export function listDanmakuArchives(platform?: DanmakuPlatform | null, roomId?: string | null): Promise<DanmakuArchiveInfo[]> {
+  return invoke<DanmakuArchiveInfo[]>('list_danmaku_archives', {
+    platform: platform ?? null,
+    roomId: roomId ?? null,
+  });
+}
+
+export function deleteDanmakuArchive(archive: Pick<DanmakuArchiveInfo, 'platform' | 'roomId' | 'sessionId'>): Promise<void> {
+  return invoke<void>('delete_danmaku_archive', {
+    platform: archive.platform,
+    roomId: archive.roomId,
+    sessionId: archive.sessionId,
+  });
+}
+
+export function getDanmakuArchiveSettings(): Promise<DanmakuArchiveSettings> {
+  return invoke<DanmakuArchiveSettings>('get_danmaku_archive_settings');
+}
+
+/** 保存后立即按新的保留策略清理；开关对之后开始的监听生效 */
+export function setDanmakuArchiveSettings(settings: DanmakuArchiveSettings): Promise<DanmakuArchiveSettings> {
+  return invoke<DanmakuArchiveSettings>('set_danmaku_archive_settings', { settings });
+}
+
+/** 导出为 B 站 XML 或 ASS 字幕；不指定 outputPath 时写到录像默认目录 */
+export function exportDanmakuArchive(
+  archive: Pick<DanmakuArchiveInfo, 'platform' | 'roomId' | 'sessionId'>,
+  format: DanmakuExportFormat,
+  options?: Partial<DanmakuExportOptions> | null,
+  outputPath?: string | null,
+): Promise<DanmakuExportResult> {
+  return invoke<DanmakuExportResult>('export_danmaku_archive', {
+    platform: archive.platform,
+    roomId: archive.roomId,
+    sessionId: archive.sessionId,
+    format,
+    options: options ?? null,
+    outputPath: outputPath ?? null,
+  });
+}
+
+export interface StartDanmakuReplayOptions {
+  speed?: number;
+  positionMs?: number;
+}
+
+/** 开始回放；播完后停在末尾，不用时需要调用 stopDanmakuReplay 释放 */
+export function startDanmakuReplay(
+  archive: Pick<DanmakuArchiveInfo, 'platform' | 'roomId' | 'sessionId'>,
+  options: StartDanmakuReplayOptions = {},
+): Promise<DanmakuReplayInfo> {
+  return invoke<DanmakuReplayInfo>('start_danmaku_replay', {
+    platform: archive.platform,
+    roomId: archive.roomId,
+    sessionId: archive.sessionId,
+    speed: options.speed ?? null,
+    positionMs: options.positionMs ?? null,
+  });
+}
+
+export function pauseDanmakuReplay(replayId: number): Promise<void> {
+  return invoke<void>('pause_danmaku_replay', { replayId });
+}
+
+export function resumeDanmakuReplay(replayId: number): Promise<void> {
+  return invoke<void>('resume_danmaku_replay', { replayId });
+}
+
+export function seekDanmakuReplay(replayId: number, positionMs: number): Promise<void> {
+  return invoke<void>('seek_danmaku_replay', { replayId, positionMs: Math.max(0, Math.floor(positionMs)) });
+}
+
+/** 倍速范围 0.1 ~ 16 */
+export function setDanmakuReplaySpeed(replayId: number, speed: number): Promise<void> {
+  return invoke<void>('set_danmaku_replay_speed', { replayId, speed });
+}
+
+export function stopDanmakuReplay(replayId: number): Promise<void> {
+  return invoke<void>('stop_danmaku_replay', { replayId });
+}
+
+/** 只回调指定回放的弹幕 */
+export function listenDanmakuReplay(
+  replayId: number,
+  handler: (payload: DanmakuReplayPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<DanmakuReplayPayload>(DANMAKU_REPLAY_EVENT, (event) => {
+    if (event.payload && event.payload.replayId === replayId) {
+      handler(event.payload);
+    }
+  });
+}
+
+export function listenDanmakuReplayState(
+  replayId: number,
+  handler: (state: DanmakuReplayState) => void,
+): Promise<UnlistenFn> {
+  return listen<DanmakuReplayState>(DANMAKU_REPLAY_STATE_EVENT, (event) => {
+    if (event.payload && event.payload.replayId === replayId) {
+      handler(event.payload);
+    }
+  });
+}
@@ -0,0 +1,197 @@
+// This file has been generated by Specta. DO NOT EDIT.
+
+export type DanmakuArchiveInfo = { platform: DanmakuPlatform; roomId: string; sessionId: string; 
+/**
+ * 毫秒时间戳
+ */
+startedAt: number; 
+/**
+ * 会话未正常结束（或仍在写入）时为空
+ */
+durationMs: number | null; eventCount: number | null; sizeBytes: number; 
+/**
+ * 正在写入
+ */
+active: boolean }
+
+/**
+ * 存档开关与保留策略；上限为空表示不限制
+ */
+export type DanmakuArchiveSettings = { 
+/**
+ * 关闭后新开始的监听不再写存档，已有存档保留
+ */
+enabled: boolean; 
+/**
+ * 超过天数的会话被删除
+ */
+maxAgeDays: number | null; 
+/**
+ * 所有存档的总大小上限（MB），超出时从最旧的会话开始删除
+ */
+maxTotalMb: number | null }
+
+export type DanmakuErrorPayload = { platform: DanmakuPlatform; roomId: string; message: string; 
+/**
+ * false 表示监听已退出，需要重新启动
+ */
+willRetry: boolean }
+
+export type DanmakuEvent = ({ type: "chat"; content: string } | { type: "gift"; giftId: string | null; giftName: string; count: number; valueCny: number | null; comboId: string | null; comboTotal: number | null } | { type: "enter" } | { type: "follow" } | { type: "like"; count: number } | 
+/**
+ * B 站醒目留言、斗鱼超级弹幕等付费消息
+ */
+{ type: "superChat"; content: string; valueCny: number; durationSeconds: number | null } | 
+/**
+ * 舰长 / 贵族 / 会员等开通与续费
+ */
+{ type: "guard"; level: number; levelName: string; months: number | null } | { type: "roomStats"; online: number | null; watched: number | null; likes: number | null; display: string | null } | { type: "liveStatus"; live: boolean; reason: string | null } | 
+/**
+ * 直播间标题、分区变更
+ */
+{ type: "roomInfo"; title: string | null; area: string | null } | 
+/**
+ * 平台的房间通知、超管警告等
+ */
+{ type: "notice"; message: string }) & { platform: DanmakuPlatform; roomId: string; 
+/**
+ * 毫秒时间戳
+ */
+timestamp: number; 
+/**
+ * 房间统计、开关播等消息没有用户
+ */
+user: DanmakuUser | null; 
+/**
+ * `#RRGGBB`
+ */
+color: string | null }
+
+export type DanmakuExportFormat = "xml" | "ass"
+
+export type DanmakuExportOptions = { 
+/**
+ * 整体平移，正数让弹幕晚出现；用于对齐录像的开头
+ */
+timeOffsetMs: number; width: number; height: number; fontName: string; fontSize: number; 
+/**
+ * 0~1
+ */
+opacity: number; outline: number; 
+/**
+ * 滚动弹幕从右到左穿过屏幕的时间
+ */
+scrollDurationMs: number; 
+/**
+ * 顶部固定弹幕的停留时间
+ */
+fixedDurationMs: number; 
+/**
+ * 滚动弹幕可以使用的屏幕高度比例，0~1；从顶部固定弹幕的轨道下方开始计算
+ */
+displayArea: number }
+
+export type DanmakuExportResult = { path: string; 
+/**
+ * 写入的弹幕条数
+ */
+count: number }
+
+export type DanmakuFilterRules = { enabled: boolean; 
+/**
+ * 不区分大小写的子串匹配
+ */
+keywords: string[]; regexes: string[]; 
+/**
+ * 用户 id 或昵称，完全匹配
+ */
+blockedUsers: string[]; 
+/**
+ * 白名单用户的消息跳过所有规则
+ */
+allowedUsers: string[]; 
+/**
+ * 只作用于聊天弹幕；平台没给出等级（0）时不拦截
+ */
+minUserLevel: number | null; 
+/**
+ * 窗口内相同的聊天只显示第一条，窗口结束后补一条 "×N"
+ */
+dedupeWindowSecs: number | null; hiddenTypes: DanmakuMessageType[] }
+
+export type DanmakuFilterSettings = { global: DanmakuFilterRules; rooms: DanmakuRoomFilter[] }
+
+export type DanmakuListenerInfo = ({ status: "connecting" } | { status: "connected" } | 
+/**
+ * 第 `attempt` 次重连，`delayMs` 后开始
+ */
+{ status: "reconnecting"; attempt: number; delayMs: number } | 
+/**
+ * 监听已退出；主动停止时 reason 为 null
+ */
+{ status: "closed"; reason: string | null }) & { platform: DanmakuPlatform; roomId: string; 
+/**
+ * 共享这条连接的订阅数
+ */
+subscribers: number; 
+/**
+ * 毫秒时间戳
+ */
+startedAt: number }
+
+/**
+ * 粉丝牌 / 徽章
+ */
+export type DanmakuMedal = { name: string; level: number; 
+/**
+ * 粉丝牌所属主播，B 站等平台会给出
+ */
+anchorName: string | null; anchorRoomId: string | null }
+
+/**
+ * 可以单独隐藏的消息类型；房间统计、开关播、房间信息不受过滤影响
+ */
+export type DanmakuMessageType = "chat" | "gift" | "enter" | "follow" | "like" | "superChat" | "guard" | "notice"
+
+export type DanmakuNoble = { level: number; name: string }
+
+export type DanmakuPlatform = "DOUYU" | "DOUYIN" | "HUYA" | "BILIBILI"
+
+export type DanmakuReplayInfo = { replayId: number; durationMs: number; eventCount: number }
+
+export type DanmakuReplayPayload = { replayId: number; 
+/**
+ * 该条消息在会话中的位置
+ */
+positionMs: number; event: DanmakuEvent }
+
+export type DanmakuReplayState = { replayId: number; status: DanmakuReplayStatus; positionMs: number; durationMs: number; speed: number; eventCount: number }
+
+export type DanmakuReplayStatus = "playing" | "paused" | 
+/**
+ * 已推送到最后一条，可以跳转回去继续
+ */
+"finished" | "stopped"
+
+export type DanmakuRoomFilter = { platform: DanmakuPlatform; roomId: string; rules: DanmakuFilterRules }
+
+export type DanmakuStatePayload = ({ status: "connecting" } | { status: "connected" } | 
+/**
+ * 第 `attempt` 次重连，`delayMs` 后开始
+ */
+{ status: "reconnecting"; attempt: number; delayMs: number } | 
+/**
+ * 监听已退出；主动停止时 reason 为 null
+ */
+{ status: "closed"; reason: string | null }) & { platform: DanmakuPlatform; roomId: string }
+
+export type DanmakuUser = { id: string | null; nickname: string; 
+/**
+ * 平台的用户 / 财富等级，0 表示未知
+ */
+level: number; medal: DanmakuMedal | null; 
+/**
+ * 贵族身份（虎牙贵族等），没有时为 null
+ */
+noble: DanmakuNoble | null }
+
@@ -0,0 +1,65 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface DlnaRenderer {
+  id: string;
+  name: string;
+  location: string;
+  manufacturer?: string | null;
+  model?: string | null;
+  supportsVolume: boolean;
+}
+
+export interface DlnaRendererState {
+  rendererId: string;
+  sessionId?: string | null;
+  /** PLAYING / STOPPED / PAUSED_PLAYBACK / TRANSITIONING / NO_MEDIA_PRESENT；渲染器离线时为 UNREACHABLE */
+  transportState: string;
+  transportStatus: string;
+  volume?: number | null;
+  error?: string | null;
+}
+
+export const DLNA_STATE_EVENT = 'dlna-renderer-state';
+
+export async function discoverDlnaRenderers(timeoutMs?: number): Promise<DlnaRenderer[]> {
+  return invoke<DlnaRenderer[]>('discover_dlna_renderers', { timeoutMs: timeoutMs ?? null });
+}
+
+/** location 为设备描述地址，例如本机软件渲染器的 http://127.0.0.1:49494/description.xml */
+export async function addDlnaRenderer(location: string): Promise<DlnaRenderer> {
+  return invoke<DlnaRenderer>('add_dlna_renderer', { location });
+}
+
+export async function listDlnaRenderers(): Promise<DlnaRenderer[]> {
+  return invoke<DlnaRenderer[]>('list_dlna_renderers');
+}
+
+/** 需先开启局域网共享再启动代理会话，渲染器才能访问会话地址 */
+export async function castSessionToDlna(
+  rendererId: string,
+  sessionId: string,
+  title?: string,
+): Promise<DlnaRendererState> {
+  return invoke<DlnaRendererState>('dlna_cast_session', { rendererId, sessionId, title: title ?? null });
+}
+
+export async function dlnaPlay(rendererId: string): Promise<DlnaRendererState> {
+  return invoke<DlnaRendererState>('dlna_play', { rendererId });
+}
+
+export async function dlnaStop(rendererId: string): Promise<DlnaRendererState> {
+  return invoke<DlnaRendererState>('dlna_stop', { rendererId });
+}
+
+export async function dlnaSetVolume(rendererId: string, volume: number): Promise<DlnaRendererState> {
+  return invoke<DlnaRendererState>('dlna_set_volume', { rendererId, volume: Math.round(volume) });
+}
+
+export async function getDlnaState(rendererId: string): Promise<DlnaRendererState> {
+  return invoke<DlnaRendererState>('dlna_get_state', { rendererId });
+}
+
+export function onDlnaStateChange(handler: (state: DlnaRendererState) => void): Promise<UnlistenFn> {
+  return listen<DlnaRendererState>(DLNA_STATE_EVENT, (event) => handler(event.payload));
+}
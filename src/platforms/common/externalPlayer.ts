@@ -0,0 +1,55 @@
+import { invoke } from '@tauri-apps/api/core';
+
+import { Platform } from './types';
+
+export type ExternalPlayer = 'mpv' | 'vlc' | 'potplayer';
+
+export interface ExternalPlayerArgs {
+  platform: Platform;
+  roomId: string;
+  quality?: string | null;
+  cookie?: string | null;
+  player: ExternalPlayer;
+  /** 未指定时按常见安装位置/PATH 查找 */
+  playerPath?: string | null;
+  /** 即使播放器能设置请求头也经本地代理播放 */
+  forceProxy?: boolean;
+}
+
+export interface LaunchExternalPlayerResponse {
+  program: string;
+  streamUrl: string;
+  viaProxy: boolean;
+  title?: string | null;
+}
+
+export async function launchExternalPlayer(args: ExternalPlayerArgs): Promise<LaunchExternalPlayerResponse> {
+  const payload = {
+    platform: String(args.platform),
+    roomId: args.roomId,
+    quality: args.quality ?? null,
+    cookie: args.cookie ?? null,
+    player: args.player,
+    playerPath: args.playerPath ?? null,
+    forceProxy: args.forceProxy ?? false,
+  };
+  return invoke<LaunchExternalPlayerResponse>('launch_external_player', { payload });
+}
+
+/** outputPath 的扩展名决定格式（.m3u / .m3u8 / .strm）；导出的 CDN 地址有时效 */
+export async function exportStreamPlaylist(args: {
+  platform: Platform;
+  roomId: string;
+  quality?: string | null;
+  cookie?: string | null;
+  outputPath: string;
+}): Promise<string> {
+  const payload = {
+    platform: String(args.platform),
+    roomId: args.roomId,
+    quality: args.quality ?? null,
+    cookie: args.cookie ?? null,
+    outputPath: args.outputPath,
+  };
+  return invoke<string>('export_stream_playlist', { payload });
+}
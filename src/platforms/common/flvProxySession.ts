@@ -6,17 +6,43 @@ export interface StartFlvProxySessionArgs {
   upstreamUrl: string;
   platform: Platform;
   roomId?: string | null;
+  /** 带宽不足时自动降档、恢复后回升，需要 roomId */
+  adaptiveQuality?: boolean;
+  /** upstreamUrl 对应的清晰度（原画/高清/标清） */
+  quality?: string | null;
+  /** 后台收听：只输出音频，抖音/虎牙优先拉取纯音频流 */
+  audioOnly?: boolean;
 }
 
 interface StartFlvProxySessionPayload {
   upstreamUrl: string;
   platform: string;
   roomId?: string | null;
+  adaptiveQuality?: boolean;
+  quality?: string | null;
+  audioOnly?: boolean;
 }
 
+export interface FlvProxyQualitySwitchEvent {
+  sessionId: string;
+  platform: string;
+  roomId: string;
+  fromQuality: string;
+  toQuality: string;
+  reason: 'degrade' | 'recover';
+  downloadKbps: number;
+  streamKbps: number;
+}
+
+export const FLV_PROXY_QUALITY_SWITCH_EVENT = 'flv-proxy-quality-switch';
+
 interface StartFlvProxySessionResponse {
   sessionId: string;
   proxyUrl: string;
+  /** 只含音频 tag 的 FLV */
+  audioProxyUrl?: string;
+  /** ADTS AAC */
+  aacProxyUrl?: string;
 }
 
 export async function startFlvProxySession(args: StartFlvProxySessionArgs): Promise<StartFlvProxySessionResponse> {
@@ -24,6 +50,9 @@ export async function startFlvProxySession(args: StartFlvProxySessionArgs): Prom
     upstreamUrl: args.upstreamUrl,
     platform: String(args.platform),
     roomId: args.roomId ?? null,
+    adaptiveQuality: args.adaptiveQuality ?? false,
+    quality: args.quality ?? null,
+    audioOnly: args.audioOnly ?? false,
   };
   const result = await invoke<StartFlvProxySessionResponse>('start_flv_proxy_session', { payload });
   if (!result?.sessionId || !result?.proxyUrl) {
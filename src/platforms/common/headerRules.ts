@@ -0,0 +1,23 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** platform 与 hosts 任一命中即生效；后面的规则覆盖前面的同名请求头，值为空字符串表示删除 */
+export interface UpstreamHeaderRule {
+  name?: string | null;
+  platform?: string | null;
+  hosts: string[];
+  headers: Record<string, string>;
+}
+
+export interface UpstreamHeaderRulesInfo {
+  /** 用户规则文件：{ "replaceDefaults": false, "rules": [...] } */
+  configPath: string;
+  rules: UpstreamHeaderRule[];
+}
+
+export async function getUpstreamHeaderRules(): Promise<UpstreamHeaderRulesInfo> {
+  return invoke<UpstreamHeaderRulesInfo>('get_upstream_header_rules');
+}
+
+export async function reloadUpstreamHeaderRules(): Promise<UpstreamHeaderRulesInfo> {
+  return invoke<UpstreamHeaderRulesInfo>('reload_upstream_header_rules');
+}
@@ -0,0 +1,44 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export const EXIT_CONFIRM_EVENT = 'app-exit-confirm';
+
+export interface ExitConfirmPayload {
+  activeRecordings: number;
+}
+
+export interface RunningRoom {
+  platform: string;
+  roomId?: string | null;
+}
+
+export interface InterruptedRecording {
+  platform: string;
+  roomId: string;
+  quality: string;
+  outputDir: string;
+  lastFile?: string | null;
+}
+
+/** 上次退出时仍在运行的任务 */
+export interface LastSessionState {
+  savedAt: number;
+  recordings: InterruptedRecording[];
+  proxySessions: RunningRoom[];
+  danmakuListeners: RunningRoom[];
+}
+
+/** 用户确认后停止录制并退出；录制会先刷盘、修正最后一个分段 */
+export async function confirmAppExit(): Promise<void> {
+  await invoke('confirm_app_exit');
+}
+
+/** 读取后即清除，只会返回一次 */
+export async function takeLastSessionState(): Promise<LastSessionState | null> {
+  return invoke<LastSessionState | null>('take_last_session_state');
+}
+
+/** 关闭窗口时仍有录制在进行，后端会阻止关闭并发出该事件 */
+export function onExitConfirmRequested(handler: (payload: ExitConfirmPayload) => void): Promise<UnlistenFn> {
+  return listen<ExitConfirmPayload>(EXIT_CONFIRM_EVENT, (event) => handler(event.payload));
+}
@@ -0,0 +1,40 @@
+import { invoke } from '@tauri-apps/api/core';
+
+import { Platform } from './types';
+
+export type OutboundProxyScheme = 'http' | 'https' | 'socks5';
+
+export interface OutboundProxy {
+  scheme: OutboundProxyScheme;
+  host: string;
+  port: number;
+  username?: string | null;
+  /**
+   * 读取时已保存的密码以 PROXY_PASSWORD_MASK 返回，原样回传即保留原密码。
+   * 密码不写入 network-proxy.json，单独保存在同目录的 network-proxy-credentials.json（仅当前用户可读写）
+   */
+  password?: string | null;
+}
+
+export const PROXY_PASSWORD_MASK = '********';
+
+/** 平台覆盖：direct 为强制直连，custom 为单独的代理；未设置的平台沿用全局代理 */
+export type PlatformProxyOverride =
+  | { mode: 'direct' }
+  | ({ mode: 'custom' } & OutboundProxy);
+
+export interface NetworkProxySettings {
+  global?: OutboundProxy | null;
+  platforms: Partial<Record<Platform, PlatformProxyOverride>>;
+}
+
+export async function getNetworkProxySettings(): Promise<NetworkProxySettings> {
+  return invoke<NetworkProxySettings>('get_network_proxy_settings');
+}
+
+/** 保存后立即生效，共享的关注/取流客户端会在下次请求时按新设置重建 */
+export async function setNetworkProxySettings(
+  settings: NetworkProxySettings,
+): Promise<NetworkProxySettings> {
+  return invoke<NetworkProxySettings>('set_network_proxy_settings', { settings });
+}
@@ -1,7 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import { Platform } from './types';
 
-export type RecordingStatus = 'starting' | 'recording' | 'reconnecting' | 'stopped' | 'failed';
+export type RecordingStatus = 'starting' | 'queued' | 'recording' | 'reconnecting' | 'stopped' | 'failed';
 
 export interface StartLiveRecordingPayload {
   platform: Platform;
@@ -32,6 +32,8 @@ export interface RecordingTaskSnapshot {
   startedAt: number;
   updatedAt: number;
   message?: string | null;
+  /** 最近一次分段收尾失败的原因，不随状态变化清除 */
+  finalizeError?: string | null;
 }
 
 export interface RecordingStatusEventPayload {
@@ -43,6 +45,7 @@ export interface RecordingStatusEventPayload {
   segmentIndex: number;
   bytesWritten: number;
   message?: string | null;
+  finalizeError?: string | null;
   timestamp: number;
 }
 
@@ -75,3 +78,7 @@ export async function stopLiveRecording(taskId: string): Promise<void> {
 export async function stopAllLiveRecordings(): Promise<void> {
   await invoke('stop_all_live_recordings');
 }
+
+export async function getRecordingPlaybackUrl(filePath: string, outputDir?: string | null): Promise<string> {
+  return invoke<string>('get_recording_playback_url', { filePath, outputDir: outputDir ?? null });
+}
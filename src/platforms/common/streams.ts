@@ -0,0 +1,53 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * recording：录制；sessionBuffer：代理会话的回看/自适应拉流；preview：播放器直连代理的上游连接。
+ * 片段缓冲（clip buffer）尚无对应功能，暂不在此列
+ */
+export type StreamKind = 'recording' | 'sessionBuffer' | 'preview';
+export type StreamPriority = 'low' | 'normal' | 'high';
+
+/** 0 表示不限制 */
+export interface StreamLimits {
+  maxConcurrent: number;
+  bandwidthBudgetKbps: number;
+  /** 尚无实测码率时每条连接按该值占用预算 */
+  defaultStreamKbps: number;
+  /** 预览排队超过该时长返回 503 */
+  previewQueueSeconds: number;
+}
+
+export interface StreamEntry {
+  id: number;
+  kind: StreamKind;
+  priority: StreamPriority;
+  platform: string;
+  roomId?: string | null;
+  /** 录制任务 id 或代理会话 id */
+  ownerId?: string | null;
+  /** 运行秒数；排队中的条目为已排队秒数 */
+  elapsedSeconds: number;
+  bytes?: number | null;
+  currentKbps?: number | null;
+  budgetKbps?: number | null;
+}
+
+export interface StreamsOverview {
+  limits: StreamLimits;
+  usedKbps: number;
+  active: StreamEntry[];
+  queued: StreamEntry[];
+}
+
+export async function listActiveStreams(): Promise<StreamsOverview> {
+  return invoke<StreamsOverview>('list_active_streams');
+}
+
+export async function getStreamLimits(): Promise<StreamLimits> {
+  return invoke<StreamLimits>('get_stream_limits');
+}
+
+/** 立即生效；收紧限制不会中断已在进行的连接 */
+export async function setStreamLimits(limits: StreamLimits): Promise<StreamsOverview> {
+  return invoke<StreamsOverview>('set_stream_limits', { limits });
+}
@@ -1,19 +1,10 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
 import { Ref } from 'vue';
-import { Platform } from '../common/types';
-import type { DanmakuMessage, DanmuOverlayInstance, DanmuRenderOptions, RustGetStreamUrlPayload } from '../../components/player/types';
+import type { DanmakuMessage, DanmuOverlayInstance, DanmuRenderOptions } from '../../components/player/types';
 import type { LiveStreamInfo } from '../common/types';
-import { v4 as uuidv4 } from 'uuid';
+import { listenRoomDanmaku, startDanmaku, stopDanmaku, toDanmakuMessage } from '../common/danmaku';
 
 
-export interface DouyinRustDanmakuPayload {
-  room_id?: string; 
-  user: string;      // Nickname from Rust's DanmakuFrontendPayload
-  content: string;
-  user_level: number; // from Rust's i64
-  fans_club_level: number; // from Rust's i32
-}
 
 export async function fetchAndPrepareDouyinStreamConfig(roomId: string, quality: string = '原画'): Promise<{ 
   streamUrl: string | null;
@@ -117,57 +108,39 @@ export async function startDouyinDanmakuListener(
   renderOptions?: DanmuRenderOptions
 ): Promise<() => void> {
   
-  const rustPayload: RustGetStreamUrlPayload = { 
-    args: { room_id_str: roomId }, 
-    platform: Platform.DOUYIN, 
-  };
-  await invoke('start_douyin_danmu_listener', { payload: rustPayload });
+  // 后端会把直播间链接等规范成 web_rid，事件按规范后的房间号推送
+  const listener = await startDanmaku('DOUYIN', roomId);
   
-  const eventName = 'danmaku-message';
-
-  const unlisten = await listen<DouyinRustDanmakuPayload>(eventName, (event: TauriEvent<DouyinRustDanmakuPayload>) => {
-    if (event.payload) {
-      const rustP = event.payload;
-
-      // 仅处理当前 roomId 的消息，避免跨房间干扰
-      if (rustP.room_id && rustP.room_id !== roomId) return;
-
-      const frontendDanmaku: DanmakuMessage = {
-        id: uuidv4(),
-        nickname: rustP.user || '未知用户',
-        content: rustP.content || '',
-        level: String(rustP.user_level || 0),
-        badgeLevel: rustP.fans_club_level > 0 ? String(rustP.fans_club_level) : undefined,
-        room_id: rustP.room_id || roomId, // Ensure room_id is present
-      };
-
-      const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
-
-      if (shouldDisplay && danmuOverlay?.sendComment) {
-        try {
-          const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
-          const styleFromOptions = commentOptions.style ?? {};
-          const preferredColor = styleFromOptions.color || frontendDanmaku.color || '#FFFFFF';
-          danmuOverlay.sendComment({
-            id: frontendDanmaku.id,
-            txt: frontendDanmaku.content,
-            duration: commentOptions.duration ?? 12000,
-            mode: commentOptions.mode ?? 'scroll',
-            style: {
-              ...styleFromOptions,
-              color: preferredColor,
-            },
-          });
-        } catch (emitError) {
-          console.warn('[DouyinPlayerHelper] Failed emitting danmu.js comment:', emitError);
-        }
+  const unlisten = await listenRoomDanmaku(listener.roomId, (event) => {
+    const frontendDanmaku = toDanmakuMessage(event);
+    if (!frontendDanmaku) return;
+
+    const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
+
+    if (shouldDisplay && !frontendDanmaku.isSystem && danmuOverlay?.sendComment) {
+      try {
+        const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
+        const styleFromOptions = commentOptions.style ?? {};
+        const preferredColor = styleFromOptions.color || frontendDanmaku.color || '#FFFFFF';
+        danmuOverlay.sendComment({
+          id: frontendDanmaku.id,
+          txt: frontendDanmaku.content,
+          duration: commentOptions.duration ?? 12000,
+          mode: commentOptions.mode ?? 'scroll',
+          style: {
+            ...styleFromOptions,
+            color: preferredColor,
+          },
+        });
+      } catch (emitError) {
+        console.warn('[DouyinPlayerHelper] Failed emitting danmu.js comment:', emitError);
       }
-      const shouldAppend = renderOptions?.shouldAppendToList ? renderOptions.shouldAppendToList(frontendDanmaku) : true;
-      if (shouldAppend) {
-        danmakuMessagesRef.value.push(frontendDanmaku);
-        if (danmakuMessagesRef.value.length > 200) { // Manage danmaku array size
-          danmakuMessagesRef.value.splice(0, danmakuMessagesRef.value.length - 200);
-        }
+    }
+    const shouldAppend = renderOptions?.shouldAppendToList ? renderOptions.shouldAppendToList(frontendDanmaku) : true;
+    if (shouldAppend) {
+      danmakuMessagesRef.value.push(frontendDanmaku);
+      if (danmakuMessagesRef.value.length > 200) { // Manage danmaku array size
+        danmakuMessagesRef.value.splice(0, danmakuMessagesRef.value.length - 200);
       }
     }
   });
@@ -180,7 +153,7 @@ export async function stopDouyinDanmaku(roomId: string, currentUnlistenFn: (() =
   }
   try {
     if (roomId) {
-      await invoke('stop_douyin_danmu_listener', { roomId });
+      await stopDanmaku('DOUYIN', roomId);
     }
   } catch (error) {
     console.error('[DouyinPlayerHelper] Error stopping Douyin danmaku listener:', error);
@@ -1,5 +1,6 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { DouyuRoomInfo, DouyuRawCategoriesResponseData } from './types';
+import { startDanmaku } from '../common/danmaku';
 
 export async function fetchDouyuRoomInfo(roomId: string): Promise<DouyuRoomInfo> {
   if (!roomId) {
@@ -68,7 +69,7 @@ export async function startDouyuDanmakuListener(roomId: string): Promise<void> {
     return Promise.reject('Room ID is required for Danmaku listener.');
   }
   try {
-    await invoke<void>('start_danmaku_listener', { roomId });
+    await startDanmaku('DOUYU', roomId);
   } catch (error) {
     console.error(`Error starting Douyu danmaku listener for ${roomId}:`, error);
     throw error; // Re-throw to be handled by the caller
@@ -1,19 +1,10 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
 import { Ref } from 'vue';
 import type { DanmakuMessage, DanmuOverlayInstance, DanmuRenderOptions } from '../../components/player/types';
-import { v4 as uuidv4 } from 'uuid';
+import { listenRoomDanmaku, startDanmaku, stopDanmaku, toDanmakuMessage } from '../common/danmaku';
 import { startFlvProxySession } from '../common/flvProxySession';
 import { Platform } from '../common/types';
 
-// 统一的 Rust 弹幕事件负载（与 Douyin/Huya 保持一致）
-export interface UnifiedRustDanmakuPayload {
-  room_id?: string;
-  user: string;
-  content: string;
-  user_level: number;
-  fans_club_level: number;
-}
 
 export async function getDouyuStreamConfig(
   roomId: string,
@@ -88,54 +79,39 @@ export async function startDouyuDanmakuListener(
   renderOptions?: DanmuRenderOptions
 ): Promise<() => void> {
 
-  await invoke('start_danmaku_listener', { roomId });
+  await startDanmaku('DOUYU', roomId);
   
-  const eventName = 'danmaku-message';
-
-  const unlisten = await listen<UnifiedRustDanmakuPayload>(eventName, (event: TauriEvent<UnifiedRustDanmakuPayload>) => {
-    if (event.payload) {
-      const rustP = event.payload;
-
-      // 仅处理当前 roomId 的消息，避免跨房间干扰
-      if (rustP.room_id && rustP.room_id !== roomId) return;
-
-      const frontendDanmaku: DanmakuMessage = {
-        id: uuidv4(),
-        nickname: rustP.user || '未知用户',
-        content: rustP.content || '',
-        level: String(rustP.user_level || 0),
-        badgeLevel: rustP.fans_club_level > 0 ? String(rustP.fans_club_level) : undefined,
-        room_id: rustP.room_id || roomId,
-      };
-
-      const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
-
-      if (shouldDisplay && danmuOverlay?.sendComment) {
-        try {
-          const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
-          const styleFromOptions = commentOptions.style ?? {};
-          const preferredColor = styleFromOptions.color || frontendDanmaku.color || '#FFFFFF';
-
-          danmuOverlay.sendComment({
-            id: frontendDanmaku.id,
-            txt: frontendDanmaku.content,
-            duration: commentOptions.duration ?? 12000,
-            mode: commentOptions.mode ?? 'scroll',
-            style: {
-              ...styleFromOptions,
-              color: preferredColor,
-            },
-          });
-        } catch (emitError) {
-          console.warn('[DouyuPlayerHelper] Failed emitting danmu.js comment:', emitError);
-        }
+  const unlisten = await listenRoomDanmaku(roomId, (event) => {
+    const frontendDanmaku = toDanmakuMessage(event);
+    if (!frontendDanmaku) return;
+
+    const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
+
+    if (shouldDisplay && !frontendDanmaku.isSystem && danmuOverlay?.sendComment) {
+      try {
+        const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
+        const styleFromOptions = commentOptions.style ?? {};
+        const preferredColor = styleFromOptions.color || frontendDanmaku.color || '#FFFFFF';
+
+        danmuOverlay.sendComment({
+          id: frontendDanmaku.id,
+          txt: frontendDanmaku.content,
+          duration: commentOptions.duration ?? 12000,
+          mode: commentOptions.mode ?? 'scroll',
+          style: {
+            ...styleFromOptions,
+            color: preferredColor,
+          },
+        });
+      } catch (emitError) {
+        console.warn('[DouyuPlayerHelper] Failed emitting danmu.js comment:', emitError);
       }
-      const shouldAppend = renderOptions?.shouldAppendToList ? renderOptions.shouldAppendToList(frontendDanmaku) : true;
-      if (shouldAppend) {
-        danmakuMessagesRef.value.push(frontendDanmaku);
-        if (danmakuMessagesRef.value.length > 200) {
-          danmakuMessagesRef.value.splice(0, danmakuMessagesRef.value.length - 200);
-        }
+    }
+    const shouldAppend = renderOptions?.shouldAppendToList ? renderOptions.shouldAppendToList(frontendDanmaku) : true;
+    if (shouldAppend) {
+      danmakuMessagesRef.value.push(frontendDanmaku);
+      if (danmakuMessagesRef.value.length > 200) {
+        danmakuMessagesRef.value.splice(0, danmakuMessagesRef.value.length - 200);
       }
     }
   });
@@ -149,10 +125,10 @@ export async function stopDouyuDanmaku(roomId: string, currentUnlistenFn: (() =>
   }
   try {
     if (roomId) { 
-        await invoke('stop_danmaku_listener', { roomId: roomId });
+        await stopDanmaku('DOUYU', roomId);
     }
   } catch (error) {
-    console.error('[DouyuPlayerHelper] Error invoking stop_danmaku_listener for Douyu:', error);
+    console.error('[DouyuPlayerHelper] Error stopping Douyu danmaku listener:', error);
   }
 }
 
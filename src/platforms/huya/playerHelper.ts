@@ -1,8 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen, type Event as TauriEvent } from '@tauri-apps/api/event';
 import { Ref } from 'vue';
 import type { DanmakuMessage, DanmuOverlayInstance, DanmuRenderOptions } from '../../components/player/types';
-import { v4 as uuidv4 } from 'uuid';
+import { listenRoomDanmaku, startDanmaku, stopDanmaku, toDanmakuMessage } from '../common/danmaku';
 
 export interface HuyaUnifiedEntry { quality: string; bitRate: number; url: string; }
 
@@ -40,14 +39,6 @@ export async function getHuyaStreamConfig(
   }
 }
 
-// 统一的 Rust 弹幕事件负载（与 Douyin/Douyu 保持一致）
-interface UnifiedRustDanmakuPayload {
-  room_id: string;
-  user: string;
-  content: string;
-  user_level: number;
-  fans_club_level: number;
-}
 let currentHuyaRoomId: string | null = null;
 
 export async function startHuyaDanmakuListener(
@@ -61,7 +52,7 @@ export async function startHuyaDanmakuListener(
   
   try {
     // 调用后端虎牙弹幕监听命令
-    await invoke('start_huya_danmaku_listener', { payload: { args: { room_id_str: roomId } } });
+    await startDanmaku('HUYA', roomId);
     console.log('[HuyaPlayerHelper] Backend Huya danmaku listener started');
   } catch (error) {
     console.error('[HuyaPlayerHelper] Failed to start backend Huya danmaku listener:', error);
@@ -69,28 +60,13 @@ export async function startHuyaDanmakuListener(
   }
 
   // 监听弹幕事件
-  const eventName = 'danmaku-message';
-  
-  const unlisten = await listen<UnifiedRustDanmakuPayload>(eventName, (event: TauriEvent<UnifiedRustDanmakuPayload>) => {
-    console.log('[HuyaPlayerHelper] Received danmaku event:', event.payload);
-    
-    // 只处理当前房间的弹幕（后端 payload 字段为 room_id/user/content/...）
-    if (!event.payload || event.payload.room_id !== roomId) {
-      return;
-    }
-
-    const frontendDanmaku: DanmakuMessage = {
-      id: uuidv4(),
-      nickname: event.payload.user || '未知用户',
-      content: event.payload.content,
-      level: String(event.payload.user_level ?? 0),
-      badgeLevel: event.payload.fans_club_level != null ? String(event.payload.fans_club_level) : undefined,
-      room_id: roomId,
-    };
+  const unlisten = await listenRoomDanmaku(roomId, (event) => {
+    const frontendDanmaku = toDanmakuMessage(event);
+    if (!frontendDanmaku) return;
 
     const shouldDisplay = renderOptions?.shouldDisplay ? renderOptions.shouldDisplay(frontendDanmaku) : true;
 
-    if (shouldDisplay && danmuOverlay?.sendComment) {
+    if (shouldDisplay && !frontendDanmaku.isSystem && danmuOverlay?.sendComment) {
       try {
         const commentOptions = renderOptions?.buildCommentOptions?.(frontendDanmaku) ?? {};
         const styleFromOptions = commentOptions.style ?? {};
@@ -120,7 +96,7 @@ export async function startHuyaDanmakuListener(
     }
   });
 
-  console.log('[HuyaPlayerHelper] Event listener registered for:', eventName);
+  console.log('[HuyaPlayerHelper] Event listener registered for room:', roomId);
   
   return unlisten;
 }
@@ -138,7 +114,7 @@ export async function stopHuyaDanmaku(roomId: string, currentUnlistenFn: (() =>
   // 停止后端虎牙弹幕监听
   try {
     const roomIdToStop = roomId || currentHuyaRoomId || '';
-    await invoke('stop_huya_danmaku_listener', { roomId: roomIdToStop });
+    await stopDanmaku('HUYA', roomIdToStop);
   } catch (e) {
     console.warn('[HuyaPlayerHelper] stopHuyaDanmaku: backend stop encountered error (ignored):', e);
   }
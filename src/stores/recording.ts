@@ -16,7 +16,7 @@ import {
 
 let unlistenRecordingStatus: UnlistenFn | null = null;
 
-const activeStatuses = new Set(['starting', 'recording', 'reconnecting']);
+const activeStatuses = new Set(['starting', 'queued', 'recording', 'reconnecting']);
 
 const roomKeyOf = (platform: string | Platform, roomId: string): string => {
   return `${String(platform).toUpperCase()}:${roomId}`;